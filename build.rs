@@ -0,0 +1,8 @@
+//! Generates the gRPC service code from `proto/` for builds with `--features grpc`
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/face_detection.proto")?;
+    Ok(())
+}
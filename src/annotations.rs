@@ -0,0 +1,199 @@
+//! Detector training annotations for whole source images (`--annotations`)
+//!
+//! Alongside the crops, source images are saved together with a label file in
+//! one or more training formats. The saved image is the decoded
+//! (orientation-corrected) one the boxes were found on, so labels always line
+//! up. Files are named after the source's path below `--input`.
+//!
+//! - `yolo`: `yolo/images/*.jpg` and `yolo/labels/*.txt` with one normalized
+//!   `class cx cy w h` line per accepted face, plus `yolo/dataset.yaml`.
+//! - `voc`: `voc/JPEGImages/*.jpg` and `voc/Annotations/*.xml` with an
+//!   `<object>` per detected face. Faces the size/score filters reject are
+//!   kept as `difficult`, and boxes cut off by the image border as `truncated`.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::detector::{BBox, Detection};
+use crate::orientation::{self, Facing};
+
+pub const YOLO_DIR: &str = "yolo";
+pub const VOC_DIR: &str = "voc";
+const FACE_CLASS: usize = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationFormat {
+    /// YOLO txt labels and dataset.yaml
+    Yolo,
+    /// Pascal VOC XML
+    Voc,
+}
+
+/// Subdirectories of the output directory the requested formats write into
+pub fn subdirs(formats: &[AnnotationFormat]) -> Vec<PathBuf> {
+    formats
+        .iter()
+        .flat_map(|format| match format {
+            AnnotationFormat::Yolo => [Path::new(YOLO_DIR).join("images"), Path::new(YOLO_DIR).join("labels")],
+            AnnotationFormat::Voc => [Path::new(VOC_DIR).join("JPEGImages"), Path::new(VOC_DIR).join("Annotations")],
+        })
+        .collect()
+}
+
+/// Write the dataset-level files of the requested formats
+pub fn write_dataset_files(output: &Path, formats: &[AnnotationFormat]) -> Result<()> {
+    if formats.contains(&AnnotationFormat::Yolo) {
+        write_yolo_dataset(output)?;
+    }
+    Ok(())
+}
+
+/// Write `yolo/dataset.yaml`; the image folder doubles as the validation split
+fn write_yolo_dataset(output: &Path) -> Result<()> {
+    let root = output.join(YOLO_DIR);
+    let root = fs::canonicalize(&root).unwrap_or(root);
+    let yaml = format!(
+        "# Generated by face_dataset_generator --annotations yolo\npath: {}\ntrain: images\nval: images\nnames:\n  {}: face\n",
+        root.display(),
+        FACE_CLASS
+    );
+    let path = output.join(YOLO_DIR).join("dataset.yaml");
+    fs::write(&path, yaml).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Save `image` with annotations for `faces`, of which `accepted` passed the filters
+pub fn write(
+    formats: &[AnnotationFormat],
+    output: &Path,
+    input: &Path,
+    source: &Path,
+    image: &DynamicImage,
+    faces: &[Detection],
+    accepted: &[&Detection],
+) -> Result<()> {
+    let name = flat_name(input, source);
+    for format in formats {
+        match format {
+            AnnotationFormat::Yolo if !accepted.is_empty() => write_yolo(&output.join(YOLO_DIR), &name, image, accepted)?,
+            AnnotationFormat::Voc if !faces.is_empty() => write_voc(&output.join(VOC_DIR), &name, source, image, faces, accepted)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn write_yolo(root: &Path, name: &str, image: &DynamicImage, faces: &[&Detection]) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as f64, height as f64);
+    let mut labels = String::new();
+    for face in faces {
+        let (x0, y0, x1, y1) = clipped(&face.bbox, width, height);
+        let (x0, y0, x1, y1) = (x0 as f64, y0 as f64, x1 as f64, y1 as f64);
+        let _ = writeln!(
+            labels,
+            "{} {:.6} {:.6} {:.6} {:.6}",
+            FACE_CLASS,
+            (x0 + x1) / 2.0 / w,
+            (y0 + y1) / 2.0 / h,
+            (x1 - x0) / w,
+            (y1 - y0) / h
+        );
+    }
+
+    image
+        .to_rgb8()
+        .save(root.join("images").join(format!("{}.jpg", name)))
+        .context("Failed to save YOLO training image")?;
+    fs::write(root.join("labels").join(format!("{}.txt", name)), labels).context("Failed to write YOLO label file")
+}
+
+fn write_voc(
+    root: &Path,
+    name: &str,
+    source: &Path,
+    image: &DynamicImage,
+    faces: &[Detection],
+    accepted: &[&Detection],
+) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let gray = image.to_luma8();
+    let file = format!("{}.jpg", name);
+
+    let mut xml = String::new();
+    let _ = writeln!(xml, "<annotation>");
+    let _ = writeln!(xml, "  <folder>JPEGImages</folder>");
+    let _ = writeln!(xml, "  <filename>{}</filename>", escape(&file));
+    let _ = writeln!(xml, "  <path>{}</path>", escape(&source.display().to_string()));
+    let _ = writeln!(xml, "  <source><database>face_dataset_generator</database></source>");
+    let _ = writeln!(xml, "  <size><width>{}</width><height>{}</height><depth>3</depth></size>", width, height);
+    let _ = writeln!(xml, "  <segmented>0</segmented>");
+    for face in faces {
+        let bbox = &face.bbox;
+        let (x0, y0, x1, y1) = clipped(bbox, width, height);
+        if x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+        let truncated = (x0, y0, x1, y1) != (bbox.x, bbox.y, bbox.x + bbox.width as i32, bbox.y + bbox.height as i32);
+        let difficult = !accepted.iter().any(|a| std::ptr::eq(*a, face));
+        let pose = match orientation::estimate_facing(&gray, bbox) {
+            Facing::Left => "Left",
+            Facing::Right => "Right",
+            Facing::Frontal => "Frontal",
+        };
+
+        let _ = writeln!(xml, "  <object>");
+        let _ = writeln!(xml, "    <name>face</name>");
+        let _ = writeln!(xml, "    <pose>{}</pose>", pose);
+        let _ = writeln!(xml, "    <truncated>{}</truncated>", truncated as u8);
+        let _ = writeln!(xml, "    <difficult>{}</difficult>", difficult as u8);
+        // VOC boxes are 1-based and inclusive
+        let _ = writeln!(
+            xml,
+            "    <bndbox><xmin>{}</xmin><ymin>{}</ymin><xmax>{}</xmax><ymax>{}</ymax></bndbox>",
+            x0 + 1,
+            y0 + 1,
+            x1,
+            y1
+        );
+        let _ = writeln!(xml, "  </object>");
+    }
+    let _ = writeln!(xml, "</annotation>");
+
+    image
+        .to_rgb8()
+        .save(root.join("JPEGImages").join(&file))
+        .context("Failed to save VOC image")?;
+    fs::write(root.join("Annotations").join(format!("{}.xml", name)), xml).context("Failed to write VOC annotation")
+}
+
+/// Box corners clipped to the image, as (x0, y0, x1, y1) with exclusive ends
+fn clipped(bbox: &BBox, width: u32, height: u32) -> (i32, i32, i32, i32) {
+    let (w, h) = (width as i32, height as i32);
+    (
+        bbox.x.clamp(0, w),
+        bbox.y.clamp(0, h),
+        (bbox.x + bbox.width as i32).clamp(0, w),
+        (bbox.y + bbox.height as i32).clamp(0, h),
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// `a/b/photo.png` below the input folder becomes `a_b_photo`, so equal file names in different folders never collide
+pub fn flat_name(input: &Path, source: &Path) -> String {
+    let relative = source.strip_prefix(input).unwrap_or(source).with_extension("");
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .filter(|c| c != "/" && c != "." && c != "..")
+        .collect::<Vec<_>>()
+        .join("_")
+}
@@ -0,0 +1,316 @@
+//! Archives as input (`--input photos.zip`, `.tar`, `.tar.gz`, `.tgz`)
+//!
+//! Members are listed once up front and show up in the run as virtual paths
+//! below the archive (`photos.zip/2019/img_001.jpg`), so manifests, resume and
+//! annotation names work as for a folder. Nothing is extracted to disk: each
+//! image is read from the archive when it is decoded. Zip members are found
+//! through the central directory and inflated one at a time. A tar stream can
+//! only be read front to back, so one reader thread per archive walks it
+//! forward as the run asks for members in listing order, and only starts over
+//! when a member before its position is requested. Zip64 archives are not
+//! supported.
+
+use anyhow::{bail, Context, Result};
+use flate2::read::{DeflateDecoder, GzDecoder};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+use crate::decoders::{Registry, SNIFF_LEN};
+
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP_DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Kind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Kind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Kind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `path` is an archive file this module reads
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && Kind::of(path).is_some()
+}
+
+/// Virtual paths of the members of `archive` that some decoder recognizes, in archive order
+pub fn list(archive: &Path, decoders: &Registry) -> Result<Vec<PathBuf>> {
+    let mut members = Vec::new();
+    let mut consider = |name: &str, header: &[u8]| {
+        if decoders.find(header).is_some() {
+            members.push(archive.join(name));
+        }
+    };
+    match Kind::of(archive) {
+        Some(Kind::Zip) => {
+            let index = zip_index(archive)?;
+            let mut file = File::open(archive)?;
+            for (name, entry) in &index.entries {
+                let mut header = Vec::with_capacity(SNIFF_LEN);
+                entry.open(&mut file)?.take(SNIFF_LEN as u64).read_to_end(&mut header)?;
+                consider(name, &header);
+            }
+        }
+        Some(kind) => {
+            let mut tar = tar::Archive::new(open_tar(archive, kind)?);
+            for entry in tar.entries()? {
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path()?.to_string_lossy().into_owned();
+                let mut header = Vec::with_capacity(SNIFF_LEN);
+                entry.take(SNIFF_LEN as u64).read_to_end(&mut header)?;
+                consider(&name, &header);
+            }
+        }
+        None => bail!("{} is not a zip or tar archive", archive.display()),
+    }
+    Ok(members)
+}
+
+/// Bytes of `path`: a plain file, or a member given by its virtual path below an archive
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    if path.exists() {
+        return fs::read(path).with_context(|| format!("Failed to read {}", path.display()));
+    }
+    let Some((archive, kind)) = path.ancestors().skip(1).find_map(|a| Some((a, Kind::of(a)?)).filter(|(a, _)| a.is_file())) else {
+        return fs::read(path).with_context(|| format!("Failed to read {}", path.display()));
+    };
+    let member = member_name(path.strip_prefix(archive)?);
+    match kind {
+        Kind::Zip => {
+            let index = zip_index(archive)?;
+            let entry = index
+                .entries
+                .iter()
+                .find(|(name, _)| *name == member)
+                .map(|(_, entry)| entry)
+                .with_context(|| format!("{} has no member {}", archive.display(), member))?;
+            let mut bytes = Vec::with_capacity(entry.size as usize);
+            entry.open(&mut File::open(archive)?)?.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }
+        kind => tar_reader(archive, kind).read(member),
+    }
+}
+
+/// Member name with `/` separators, as archives store them
+fn member_name(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn open_tar(path: &Path, kind: Kind) -> Result<Box<dyn Read + Send>> {
+    let file = BufReader::new(File::open(path).with_context(|| format!("Failed to open {}", path.display()))?);
+    Ok(match kind {
+        Kind::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+/// Where one member's data sits in a zip file
+struct ZipEntry {
+    method: u16,
+    compressed: u64,
+    size: u64,
+    header_offset: u64,
+}
+
+struct ZipIndex {
+    /// Files in central-directory order, directories left out
+    entries: Vec<(String, ZipEntry)>,
+}
+
+impl ZipEntry {
+    /// Reader over the uncompressed member
+    fn open<'a>(&self, file: &'a mut File) -> Result<Box<dyn Read + 'a>> {
+        file.seek(SeekFrom::Start(self.header_offset))?;
+        let mut header = [0u8; 30];
+        file.read_exact(&mut header)?;
+        if u32_le(&header, 0) != ZIP_LOCAL_HEADER {
+            bail!("Corrupt zip: no local header at offset {}", self.header_offset);
+        }
+        let skip = u16_le(&header, 26) as i64 + u16_le(&header, 28) as i64;
+        file.seek(SeekFrom::Current(skip))?;
+        let data = file.take(self.compressed);
+        Ok(match self.method {
+            0 => Box::new(data),
+            8 => Box::new(DeflateDecoder::new(data)),
+            method => bail!("Unsupported zip compression method {}", method),
+        })
+    }
+}
+
+/// Central directory of `path`, parsed once per run
+fn zip_index(path: &Path) -> Result<Arc<ZipIndex>> {
+    static INDEXES: OnceLock<Mutex<HashMap<PathBuf, Arc<ZipIndex>>>> = OnceLock::new();
+    let mut indexes = INDEXES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(index) = indexes.get(path) {
+        return Ok(index.clone());
+    }
+    let index = Arc::new(read_zip_index(path).with_context(|| format!("Failed to read zip {}", path.display()))?);
+    indexes.insert(path.to_path_buf(), index.clone());
+    Ok(index)
+}
+
+fn read_zip_index(path: &Path) -> Result<ZipIndex> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    // The end record is 22 bytes plus a comment of up to 64 KiB
+    let tail_len = len.min(22 + 65535);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_le(&tail, i) == ZIP_END_OF_DIRECTORY)
+        .context("No end of central directory record")?;
+    let count = u16_le(&tail, end + 10) as usize;
+    let directory_size = u32_le(&tail, end + 12);
+    let directory_offset = u32_le(&tail, end + 16);
+    if count == 0xFFFF || directory_offset == u32::MAX {
+        bail!("Zip64 archives are not supported");
+    }
+
+    file.seek(SeekFrom::Start(directory_offset as u64))?;
+    let mut directory = vec![0u8; directory_size as usize];
+    file.read_exact(&mut directory)?;
+
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 0;
+    for _ in 0..count {
+        if directory.len() < at + 46 || u32_le(&directory, at) != ZIP_DIRECTORY_ENTRY {
+            bail!("Corrupt central directory");
+        }
+        let name_len = u16_le(&directory, at + 28) as usize;
+        let extra_len = u16_le(&directory, at + 30) as usize;
+        let comment_len = u16_le(&directory, at + 32) as usize;
+        let name = String::from_utf8_lossy(directory.get(at + 46..at + 46 + name_len).context("Corrupt central directory")?).into_owned();
+        let entry = ZipEntry {
+            method: u16_le(&directory, at + 10),
+            compressed: u32_le(&directory, at + 20) as u64,
+            size: u32_le(&directory, at + 24) as u64,
+            header_offset: u32_le(&directory, at + 42) as u64,
+        };
+        if !name.ends_with('/') {
+            entries.push((name, entry));
+        }
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(ZipIndex { entries })
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+type TarRequest = (String, Sender<Result<Vec<u8>>>);
+
+/// Handle on the thread that streams one tar archive
+#[derive(Clone)]
+struct TarReader {
+    requests: Sender<TarRequest>,
+}
+
+impl TarReader {
+    fn read(&self, member: String) -> Result<Vec<u8>> {
+        let (reply, response) = mpsc::channel();
+        self.requests.send((member, reply)).context("Tar reader stopped")?;
+        response.recv().context("Tar reader stopped")?
+    }
+}
+
+fn tar_reader(path: &Path, kind: Kind) -> TarReader {
+    static READERS: OnceLock<Mutex<HashMap<PathBuf, TarReader>>> = OnceLock::new();
+    let mut readers = READERS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    readers
+        .entry(path.to_path_buf())
+        .or_insert_with(|| {
+            let (requests, incoming) = mpsc::channel::<TarRequest>();
+            let path = path.to_path_buf();
+            thread::spawn(move || {
+                let mut pending = incoming.recv().ok();
+                while let Some(request) = pending.take() {
+                    // One pass from the top; a request behind the position starts another
+                    match stream_tar(&path, kind, request, &incoming) {
+                        Ok(next) => pending = next,
+                        Err(((member, reply), e)) => {
+                            let _ = reply.send(Err(e.context(format!("{} has no readable member {}", path.display(), member))));
+                            pending = incoming.recv().ok();
+                        }
+                    }
+                }
+            });
+            TarReader { requests }
+        })
+        .clone()
+}
+
+/// Serve requests in stream order; returns the request that lies behind the current position
+fn stream_tar(
+    path: &Path,
+    kind: Kind,
+    request: TarRequest,
+    incoming: &mpsc::Receiver<TarRequest>,
+) -> std::result::Result<Option<TarRequest>, (TarRequest, anyhow::Error)> {
+    let reader = match open_tar(path, kind) {
+        Ok(reader) => reader,
+        Err(e) => return Err((request, e)),
+    };
+    let mut tar = tar::Archive::new(reader);
+    let entries = match tar.entries() {
+        Ok(entries) => entries,
+        Err(e) => return Err((request, e.into())),
+    };
+    let (mut member, mut reply) = request;
+    let mut from_start = true;
+    for entry in entries {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => return Err(((member, reply), e.into())),
+        };
+        if entry.path().is_ok_and(|p| p.to_string_lossy() == member.as_str()) {
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            let result = entry.read_to_end(&mut bytes).map(|_| bytes).map_err(anyhow::Error::from);
+            let _ = reply.send(result);
+            from_start = false;
+            match incoming.recv() {
+                Ok(next) => (member, reply) = next,
+                Err(_) => return Ok(None),
+            }
+        }
+    }
+    if from_start {
+        return Err(((member, reply), anyhow::anyhow!("member not found")));
+    }
+    Ok(Some((member, reply)))
+}
@@ -0,0 +1,151 @@
+//! `backfill` subcommand: run newer analysis stages over an existing output
+//!
+//! Outputs built by older versions lack fields that later stages fill in.
+//! Instead of regenerating the dataset, `backfill` re-opens each source image,
+//! takes the recorded detector box and computes the missing fields in place:
+//! `quality` (the quality score), `landmarks` (eye centers and head pose) and,
+//! in builds with the `embeddings` feature, `embeddings` (rows appended to the
+//! run's `embeddings.npy`). Fields a record already has are left alone, all
+//! variants of one detection share the work, and the manifest is rewritten
+//! atomically at the end. Crops cut from video frames are skipped, since their
+//! sources can't be re-read as still images.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::custody::SEAL_FILE;
+use crate::manifest::{self, ManifestRecord};
+use crate::{crop, decode, landmarks, quality, say, say_err, Args};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Stage {
+    /// Combined quality score (`quality`)
+    Quality,
+    /// Eye centers and head pose (`eyes`, `pose`)
+    Landmarks,
+    /// Recognition embeddings (`embedding`, needs `--embeddings MODEL`)
+    Embeddings,
+}
+
+impl Stage {
+    /// Whether `record` still lacks what this stage produces
+    fn missing(self, record: &ManifestRecord) -> bool {
+        match self {
+            Stage::Quality => record.quality.is_none(),
+            Stage::Landmarks => record.eyes.is_none() || record.pose.is_none(),
+            Stage::Embeddings => record.embedding.is_none(),
+        }
+    }
+}
+
+pub fn run(manifest_path: &Path, stages: &[Stage], args: &Args) -> Result<()> {
+    if cfg!(not(feature = "embeddings")) && stages.contains(&Stage::Embeddings) {
+        anyhow::bail!("This build has no embedding support; rebuild with `cargo build --release --features embeddings`");
+    }
+    if stages.contains(&Stage::Embeddings) && args.embeddings.is_none() {
+        anyhow::bail!("The embeddings stage needs the recognition model; pass --embeddings MODEL");
+    }
+
+    let mut records = manifest::read(manifest_path)?;
+    #[cfg(feature = "embeddings")]
+    let mut embedder = match &args.embeddings {
+        Some(model) if stages.contains(&Stage::Embeddings) => {
+            let output = manifest_path.parent().unwrap_or(Path::new("."));
+            Some(crate::embeddings::Embedder::load(model, output, args.skip_checksum, true)?)
+        }
+        _ => None,
+    };
+
+    // Rows needing work, grouped by source so every image is decoded once
+    let mut by_source: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    let mut skipped = 0;
+    for (i, record) in records.iter().enumerate() {
+        if !stages.iter().any(|stage| stage.missing(record)) {
+            continue;
+        }
+        if record.frame.is_some() {
+            skipped += 1;
+            continue;
+        }
+        by_source.entry(record.source.clone()).or_default().push(i);
+    }
+    say!(BackfillStarting, by_source.values().map(Vec::len).sum::<usize>(), by_source.len());
+
+    let mut updated = 0;
+    let mut errors = 0;
+    for (source, rows) in &by_source {
+        let image = match decode::open(source, args) {
+            Ok(image) => image,
+            Err(e) => {
+                say_err!(BackfillFailed, source.display(), format!("{:#}", e));
+                errors += rows.len();
+                continue;
+            }
+        };
+        let gray = image.to_luma8();
+
+        // Variants of a detection share its box, so each detection is analyzed once
+        let mut done: HashMap<usize, ManifestRecord> = HashMap::new();
+        for &i in rows {
+            let record = &records[i];
+            if let Entry::Vacant(slot) = done.entry(record.detection_id) {
+                let mut fields = ManifestRecord::default();
+                let bbox = &record.bbox;
+                let points = landmarks::estimate_landmarks(&gray, bbox);
+                for stage in stages {
+                    match stage {
+                        Stage::Quality => {
+                            let confidence = args.backend.confidence(record.score);
+                            fields.quality = Some(quality::score(&crop::padded_crop(&image, bbox, 0), confidence));
+                        }
+                        Stage::Landmarks => {
+                            fields.eyes = points.map(|p| [p.left_eye, p.right_eye]);
+                            fields.pose = points.map(|p| landmarks::head_pose(&p, bbox, false));
+                        }
+                        #[cfg(feature = "embeddings")]
+                        Stage::Embeddings => {
+                            if let Some(embedder) = &mut embedder {
+                                let aligned = crop::crop_variant(&image, bbox, points.as_ref(), crop::Variant::Arcface, crop::Framing::of(args)).0;
+                                fields.embedding = Some(embedder.embed(&aligned)?);
+                            }
+                        }
+                        #[cfg(not(feature = "embeddings"))]
+                        Stage::Embeddings => {}
+                    }
+                }
+                slot.insert(fields);
+            }
+
+            let fields = &done[&records[i].detection_id];
+            let record = &mut records[i];
+            let before = (record.quality, record.eyes.is_some(), record.pose.is_some(), record.embedding);
+            record.quality = record.quality.or(fields.quality);
+            record.eyes = record.eyes.or(fields.eyes);
+            record.pose = record.pose.or(fields.pose);
+            record.embedding = record.embedding.or(fields.embedding);
+            if before != (record.quality, record.eyes.is_some(), record.pose.is_some(), record.embedding) {
+                updated += 1;
+            }
+        }
+    }
+
+    if updated > 0 {
+        manifest::rewrite(manifest_path, &records).context("Failed to rewrite manifest")?;
+    }
+    say!(Backfilled, updated, records.len(), manifest_path.display());
+    if skipped > 0 {
+        say!(BackfillVideoSkipped, skipped);
+    }
+    if errors > 0 {
+        say!(BackfillUnreadable, errors);
+    }
+
+    let seal = manifest_path.with_file_name(SEAL_FILE);
+    if updated > 0 && seal.exists() {
+        say!(SealStale, seal.display());
+    }
+    Ok(())
+}
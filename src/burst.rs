@@ -0,0 +1,146 @@
+//! Burst-group detection for phone photo corpora
+//!
+//! A burst is a run of neighbouring shots of the same scene: sequential
+//! filenames whose EXIF capture times are within a couple of seconds,
+//! confirmed by matching perceptual hashes. Only the sharpest frame of each
+//! burst is kept for extraction, since the rest would mostly add near-duplicate
+//! faces. Both name and capture time must match before anything is decoded:
+//! numbered datasets and freshly copied folders, whose files have no capture
+//! time or only close modification times, are left alone instead of being
+//! decoded twice.
+
+use std::path::{Path, PathBuf};
+
+use crate::quality::sharpness;
+use crate::{decode, metadata, phash, Args};
+
+/// Shots taken this close together (in seconds, by EXIF capture time) are burst candidates
+const BURST_WINDOW: f64 = 2.0;
+
+/// Largest gap between trailing filename numbers still treated as sequential
+const MAX_SEQUENCE_GAP: u64 = 2;
+
+/// Frames within this many pHash bits of their neighbour belong to the same burst
+const MAX_PHASH_DISTANCE: u32 = 10;
+
+#[derive(Debug, Default)]
+pub struct BurstSummary {
+    pub groups: usize,
+    pub dropped: usize,
+}
+
+/// Drop all but the sharpest frame of every burst, keeping the input order otherwise
+pub fn select(paths: Vec<PathBuf>, args: &Args) -> (Vec<PathBuf>, BurstSummary) {
+    let mut order: Vec<usize> = (0..paths.len()).collect();
+    order.sort_by(|&a, &b| paths[a].cmp(&paths[b]));
+
+    // Capture times are read only for files with a sequential neighbour
+    let mut taken: Vec<Option<Option<f64>>> = vec![None; paths.len()];
+    let mut capture_time = |i: usize| *taken[i].get_or_insert_with(|| metadata::capture_time(&paths[i]));
+
+    let mut keep = vec![true; paths.len()];
+    let mut summary = BurstSummary::default();
+
+    // Runs of candidates by name/time first, so only those get decoded and hashed
+    let mut run: Vec<usize> = order.first().copied().into_iter().collect();
+    for pair in order.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if is_sequential(&paths[a], &paths[b]) && close_in_time(capture_time(a), capture_time(b)) {
+            run.push(b);
+            continue;
+        }
+        if run.len() > 1 {
+            resolve_run(&run, &paths, args, &mut keep, &mut summary);
+        }
+        run = vec![b];
+    }
+    if run.len() > 1 {
+        resolve_run(&run, &paths, args, &mut keep, &mut summary);
+    }
+
+    let selected = paths
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(path, keep)| keep.then_some(path))
+        .collect();
+    (selected, summary)
+}
+
+/// Cheapest test first: same folder and sequential names
+fn is_sequential(a: &Path, b: &Path) -> bool {
+    if a.parent() != b.parent() {
+        return false;
+    }
+    match (sequence_number(a), sequence_number(b)) {
+        (Some((prefix_a, n_a)), Some((prefix_b, n_b))) => prefix_a == prefix_b && n_a.abs_diff(n_b) <= MAX_SEQUENCE_GAP,
+        _ => false,
+    }
+}
+
+/// Both shots have a capture time and were taken within the burst window
+fn close_in_time(a: Option<f64>, b: Option<f64>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if (a - b).abs() <= BURST_WINDOW)
+}
+
+/// Split `IMG_0042` into (`IMG_`, 42)
+fn sequence_number(path: &Path) -> Option<(String, u64)> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let (prefix, number) = stem.split_at(stem.len() - digits);
+    Some((prefix.to_string(), number.parse().ok()?))
+}
+
+/// Confirm a candidate run visually and keep the sharpest frame of each burst inside it
+fn resolve_run(run: &[usize], paths: &[PathBuf], args: &Args, keep: &mut [bool], summary: &mut BurstSummary) {
+    // Frames that fail to decode are left for the main loop to report
+    let frames: Vec<Option<(u64, f64)>> = run
+        .iter()
+        .map(|&i| {
+            let image = decode::open(&paths[i], args).ok()?;
+            Some((phash::phash(&image), sharpness(&image)))
+        })
+        .collect();
+
+    let mut burst: Vec<usize> = Vec::new();
+    for (pos, frame) in frames.iter().enumerate() {
+        let continues = match (burst.last(), frame) {
+            (Some(&prev), Some((hash, _))) => {
+                frames[prev].is_some_and(|(prev_hash, _)| phash::distance(prev_hash, *hash) <= MAX_PHASH_DISTANCE)
+            }
+            _ => false,
+        };
+        if !continues {
+            finish_burst(&burst, run, &frames, keep, summary);
+            burst.clear();
+        }
+        if frame.is_some() {
+            burst.push(pos);
+        }
+    }
+    finish_burst(&burst, run, &frames, keep, summary);
+}
+
+fn finish_burst(burst: &[usize], run: &[usize], frames: &[Option<(u64, f64)>], keep: &mut [bool], summary: &mut BurstSummary) {
+    if burst.len() < 2 {
+        return;
+    }
+    let sharpest = burst
+        .iter()
+        .copied()
+        .max_by(|&a, &b| {
+            let score = |pos: usize| frames[pos].map_or(0.0, |(_, s)| s);
+            score(a).total_cmp(&score(b))
+        })
+        .unwrap_or(burst[0]);
+
+    summary.groups += 1;
+    for &pos in burst {
+        if pos != sharpest {
+            keep[run[pos]] = false;
+            summary.dropped += 1;
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Crash-safe progress checkpoint for plain runs (`--resume`)
+//!
+//! Finished and failed images are committed to the run store (`run.redb`, see
+//! `store`) every `--checkpoint-every` images, together with the face count at
+//! that point. Output directories from older versions keep their progress in
+//! the append-only `checkpoint.jsonl`; resuming such a run reads it and moves
+//! it into the store.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::store::{self, FileStatus};
+
+/// Progress log written by older versions
+pub const LEGACY_CHECKPOINT_FILE: &str = "checkpoint.jsonl";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    faces_extracted: usize,
+    processed: Vec<PathBuf>,
+}
+
+/// What a previous run got through before it stopped
+#[derive(Debug, Default)]
+pub struct Progress {
+    pub processed: HashSet<PathBuf>,
+    pub faces_extracted: usize,
+}
+
+/// Read the progress in `output_dir`, if a previous run left any
+pub fn load(output_dir: &Path) -> Result<Option<Progress>> {
+    if store::exists(output_dir) {
+        let (processed, faces_extracted) = store::progress(output_dir)?;
+        return Ok(Some(Progress { processed: processed.into_iter().collect(), faces_extracted }));
+    }
+    load_legacy(output_dir)
+}
+
+fn load_legacy(output_dir: &Path) -> Result<Option<Progress>> {
+    let path = output_dir.join(LEGACY_CHECKPOINT_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+
+    let mut progress = Progress::default();
+    for entry in text.lines().filter_map(|line| serde_json::from_str::<Entry>(line).ok()) {
+        progress.faces_extracted = entry.faces_extracted;
+        progress.processed.extend(entry.processed);
+    }
+    Ok(Some(progress))
+}
+
+pub struct CheckpointWriter {
+    output_dir: PathBuf,
+    every: usize,
+    pending: Vec<(PathBuf, FileStatus)>,
+}
+
+impl CheckpointWriter {
+    /// Open the checkpoint in `output_dir`, starting a new one unless `append` is set
+    pub fn open(output_dir: &Path, append: bool, every: usize) -> Result<Self> {
+        let mut meta = vec![("version", env!("CARGO_PKG_VERSION").to_string())];
+        if append {
+            meta.push(("resumed_at_ms", store::now_ms()));
+        } else {
+            store::reset(output_dir)?;
+            meta.push(("started_at_ms", store::now_ms()));
+        }
+
+        // Carry an older run's log over so the store holds the whole history
+        let legacy = output_dir.join(LEGACY_CHECKPOINT_FILE);
+        if legacy.exists() {
+            if let Some(progress) = load_legacy(output_dir)?.filter(|_| append && !store::exists(output_dir)) {
+                let files: Vec<_> = progress
+                    .processed
+                    .into_iter()
+                    .map(|path| (path, FileStatus::Done { faces: 0 }))
+                    .collect();
+                store::commit(output_dir, &files, Some(progress.faces_extracted), &[])?;
+            }
+            fs::remove_file(&legacy).with_context(|| format!("Failed to remove {}", legacy.display()))?;
+        }
+        store::commit(output_dir, &[], None, &meta)?;
+        Ok(CheckpointWriter {
+            output_dir: output_dir.to_path_buf(),
+            every: every.max(1),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Note a finished image and the faces it yielded; commits every `every` images
+    pub fn record(&mut self, path: &Path, faces: usize, faces_extracted: usize) -> Result<()> {
+        self.push(path, FileStatus::Done { faces }, faces_extracted)
+    }
+
+    /// Note an image that failed, so `status` can list it; a resumed run retries it
+    pub fn record_failure(&mut self, path: &Path, error: String, faces_extracted: usize) -> Result<()> {
+        self.push(path, FileStatus::Failed { error }, faces_extracted)
+    }
+
+    fn push(&mut self, path: &Path, status: FileStatus, faces_extracted: usize) -> Result<()> {
+        self.pending.push((path.to_path_buf(), status));
+        if self.pending.len() >= self.every {
+            self.flush(faces_extracted)?;
+        }
+        Ok(())
+    }
+
+    /// Commit whatever has been recorded since the last commit
+    pub fn flush(&mut self, faces_extracted: usize) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let files = std::mem::take(&mut self.pending);
+        store::commit(&self.output_dir, &files, Some(faces_extracted), &[])
+    }
+}
@@ -0,0 +1,194 @@
+//! Face-centered video clips cut from tracks (`--export-clips`)
+//!
+//! Each track from a video source is split into back-to-back segments of the
+//! requested length. Every segment is re-encoded by `ffmpeg` with a fixed
+//! square crop around the face's smoothed path (audio kept), which is the raw
+//! material lip-reading and talking-head datasets need. Clips are indexed in
+//! `clips/clips.jsonl`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::tracking::{Sequence, Track};
+
+pub const CLIPS_DIR: &str = "clips";
+const CLIPS_INDEX: &str = "clips.jsonl";
+
+/// Crop side relative to the largest face box in the segment
+const CONTEXT: f32 = 1.5;
+
+/// Clip length such as `2s`, `1.5s` or `800ms`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ClipLength(pub Duration);
+
+impl FromStr for ClipLength {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+            (ms, 0.001)
+        } else {
+            (s.strip_suffix('s').unwrap_or(s), 1.0)
+        };
+        match number.trim().parse::<f64>() {
+            Ok(value) if value > 0.0 && value.is_finite() => Ok(ClipLength(Duration::from_secs_f64(value * scale))),
+            _ => Err(format!("'{}' is not a clip length (e.g. 2s, 1.5s, 800ms)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ClipLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}s", self.0.as_secs_f64())
+    }
+}
+
+impl TryFrom<String> for ClipLength {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ClipLength> for String {
+    fn from(length: ClipLength) -> Self {
+        length.to_string()
+    }
+}
+
+/// One line of `clips.jsonl`
+#[derive(Debug, Serialize)]
+struct ClipRecord<'a> {
+    clip: String,
+    source: &'a Path,
+    track: usize,
+    start_frame: usize,
+    start_secs: f64,
+    duration_secs: f64,
+    crop: CropWindow,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct CropWindow {
+    x: u32,
+    y: u32,
+    side: u32,
+}
+
+pub struct ClipExporter {
+    dir: PathBuf,
+    index: BufWriter<File>,
+    length: ClipLength,
+}
+
+impl ClipExporter {
+    pub fn open(output_dir: &Path, length: ClipLength) -> Result<Self> {
+        let dir = output_dir.join(CLIPS_DIR);
+        fs::create_dir_all(&dir).context("Failed to create clips directory")?;
+        let index = File::create(dir.join(CLIPS_INDEX)).context("Failed to create clips index")?;
+        Ok(ClipExporter {
+            dir,
+            index: BufWriter::new(index),
+            length,
+        })
+    }
+
+    /// Cut every full-length segment of `track`; frame folders have no video to cut from
+    pub fn export(&mut self, sequence: &Sequence, track: &Track) -> Result<usize> {
+        let (Some(fps), false) = (sequence.fps, sequence.source.is_dir()) else {
+            return Ok(0);
+        };
+        let frames_per_clip = (self.length.0.as_secs_f64() * fps).round().max(1.0) as usize;
+        let boxes = track.stabilized();
+        let Some((width, height)) = sequence
+            .frames
+            .first()
+            .and_then(|f| image::image_dimensions(f).ok())
+        else {
+            return Ok(0);
+        };
+
+        let mut written = 0;
+        for (n, segment) in boxes.chunks_exact(frames_per_clip).enumerate() {
+            let count = segment.len() as f32;
+            let cx = segment.iter().map(|(_, b)| b.cx).sum::<f32>() / count;
+            let cy = segment.iter().map(|(_, b)| b.cy).sum::<f32>() / count;
+            let largest = segment.iter().map(|(_, b)| b.side).fold(0.0, f32::max);
+
+            // libx264 needs even dimensions
+            let side = ((largest * CONTEXT).min(width as f32).min(height as f32) as u32) & !1;
+            if side < 2 {
+                continue;
+            }
+            let window = CropWindow {
+                x: (cx - side as f32 / 2.0).clamp(0.0, (width - side) as f32) as u32,
+                y: (cy - side as f32 / 2.0).clamp(0.0, (height - side) as f32) as u32,
+                side,
+            };
+
+            let start_frame = segment[0].0;
+            let start_secs = start_frame as f64 / fps;
+            let duration_secs = frames_per_clip as f64 / fps;
+            let name = format!("{}_track_{:04}_{:03}.mp4", sequence.name, track.id, n + 1);
+            cut_clip(&sequence.source, &self.dir.join(&name), start_secs, duration_secs, window)?;
+
+            serde_json::to_writer(
+                &mut self.index,
+                &ClipRecord {
+                    clip: format!("{}/{}", CLIPS_DIR, name),
+                    source: &sequence.source,
+                    track: track.id,
+                    start_frame,
+                    start_secs,
+                    duration_secs,
+                    crop: window,
+                },
+            )?;
+            self.index.write_all(b"\n")?;
+            self.index.flush().context("Failed to write clips index")?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+}
+
+fn cut_clip(video: &Path, out: &Path, start_secs: f64, duration_secs: f64, window: CropWindow) -> Result<()> {
+    let status = Command::new("ffmpeg")
+        .args(["-v", "error", "-nostdin", "-y", "-ss", &format!("{:.3}", start_secs), "-i"])
+        .arg(video)
+        .args([
+            "-t",
+            &format!("{:.3}", duration_secs),
+            "-vf",
+            &format!("crop={0}:{0}:{1}:{2}", window.side, window.x, window.y),
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-crf",
+            "20",
+            "-pix_fmt",
+            "yuv420p",
+            "-c:a",
+            "aac",
+        ])
+        .arg(out)
+        .status()
+        .context("Failed to run ffmpeg")?;
+
+    if !status.success() {
+        anyhow::bail!("ffmpeg failed to cut {}", out.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,478 @@
+//! S3 and Google Cloud Storage as input and output (`s3://bucket/prefix`, `gs://bucket/prefix`)
+//!
+//! An input URI is listed like a folder: every object under the prefix whose
+//! first bytes some decoder recognizes becomes an input, and is fetched only
+//! when it is decoded. An output URI is staged in a local directory named after
+//! it, which the run writes as usual. Each crop is uploaded below the prefix as
+//! soon as it is written; when the run ends the remaining files follow, the
+//! manifest last so it never lists a crop that isn't there yet, and the staging
+//! directory is removed. A failed run keeps it, so `--resume` with the same URI
+//! continues where it stopped.
+//!
+//! Requests go through the AWS SDK and the `google-cloud-storage` client, on a
+//! Tokio runtime of their own. S3 takes credentials and region from the SDK's
+//! default chain (environment, shared profiles, SSO, instance and container
+//! roles); `AWS_ENDPOINT_URL` points it at an S3-compatible store such as
+//! MinIO. GCS uses Application Default Credentials, refreshing access tokens
+//! before they expire, and honours `STORAGE_EMULATOR_HOST`.
+//! Everything network-facing needs the `cloud` build feature.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use walkdir::WalkDir;
+
+use crate::decoders::{Registry, SNIFF_LEN};
+use crate::{say, Args};
+
+/// Concurrent requests when listing and uploading
+const CONNECTIONS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Provider {
+    S3,
+    Gcs,
+}
+
+/// A bucket and the key (or key prefix) inside it
+#[derive(Debug, Clone)]
+struct Location {
+    provider: Provider,
+    bucket: String,
+    key: String,
+}
+
+impl Location {
+    fn parse(path: &Path) -> Option<Self> {
+        let uri = path.to_str()?;
+        let (provider, rest) = if let Some(rest) = uri.strip_prefix("s3://") {
+            (Provider::S3, rest)
+        } else if let Some(rest) = uri.strip_prefix("gs://") {
+            (Provider::Gcs, rest)
+        } else {
+            return None;
+        };
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        Some(Location { provider, bucket: bucket.to_string(), key: key.to_string() })
+    }
+
+    /// Key prefix of everything below this location
+    fn prefix(&self) -> String {
+        match self.key.trim_end_matches('/') {
+            "" => String::new(),
+            key => format!("{}/", key),
+        }
+    }
+
+    fn uri(&self, key: &str) -> PathBuf {
+        let scheme = match self.provider {
+            Provider::S3 => "s3",
+            Provider::Gcs => "gs",
+        };
+        PathBuf::from(format!("{}://{}/{}", scheme, self.bucket, key))
+    }
+}
+
+/// Whether `path` is an `s3://` or `gs://` URI
+pub fn is_uri(path: &Path) -> bool {
+    Location::parse(path).is_some()
+}
+
+/// Objects below `input` that some decoder recognizes, as URIs
+pub fn list(input: &Path, decoders: &Registry) -> Result<Vec<PathBuf>> {
+    let location = Location::parse(input).context("Not an s3:// or gs:// URI")?;
+    let keys = client::list(&location, &location.prefix())
+        .with_context(|| format!("Failed to list {}", input.display()))?;
+    let keys: Vec<String> = keys.into_iter().filter(|key| !key.ends_with('/')).collect();
+
+    // Sniff each object's first bytes with ranged reads, a few at a time
+    let next = AtomicUsize::new(0);
+    let recognized = Mutex::new(vec![false; keys.len()]);
+    thread::scope(|scope| {
+        for _ in 0..CONNECTIONS.min(keys.len()) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(key) = keys.get(i) else { break };
+                    let known = client::get(&location, key, Some(SNIFF_LEN)).is_ok_and(|header| decoders.find(&header).is_some());
+                    recognized.lock().unwrap_or_else(|e| e.into_inner())[i] = known;
+                }
+            });
+        }
+    });
+    let recognized = recognized.into_inner().unwrap_or_else(|e| e.into_inner());
+    Ok(keys.iter().zip(recognized).filter(|(_, known)| *known).map(|(key, _)| location.uri(key)).collect())
+}
+
+/// Contents of the object at `uri`
+pub fn read(uri: &Path) -> Result<Vec<u8>> {
+    let location = Location::parse(uri).context("Not an s3:// or gs:// URI")?;
+    client::get(&location, &location.key, None).with_context(|| format!("Failed to fetch {}", uri.display()))
+}
+
+/// A cloud `--output` and the local directory standing in for it during the run
+pub struct Staged {
+    location: Location,
+    dir: PathBuf,
+}
+
+/// Uploads of crops as they are written, while the run goes on
+struct Live {
+    dir: PathBuf,
+    queue: mpsc::Sender<(PathBuf, Vec<u8>)>,
+    workers: Vec<thread::JoinHandle<()>>,
+    /// Staged files uploaded so far and the SHA-256 of what was sent
+    sent: Arc<Mutex<HashMap<PathBuf, [u8; 32]>>>,
+}
+
+static LIVE: Mutex<Option<Live>> = Mutex::new(None);
+
+/// Point a cloud `--output` at its local staging directory; the returned handle uploads it
+pub fn stage_output(args: &mut Args) -> Result<Option<Staged>> {
+    let Some(location) = Location::parse(&args.output) else {
+        return Ok(None);
+    };
+    client::available()?;
+    let hash = hex::encode(&Sha256::digest(args.output.to_string_lossy().as_bytes())[..8]);
+    let dir = std::env::temp_dir().join("face_dataset_generator").join(hash);
+    fs::create_dir_all(&dir).context("Failed to create staging directory")?;
+    say!(CloudStaging, dir.display(), args.output.display());
+    args.output = dir.clone();
+    *LIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(start_live(&location, &dir));
+    Ok(Some(Staged { location, dir }))
+}
+
+/// Queue a file just written for upload, if it is part of a cloud `--output`
+pub fn written(path: &Path, bytes: &[u8]) {
+    let live = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(live) = live.as_ref().filter(|live| path.starts_with(&live.dir)) {
+        let _ = live.queue.send((path.to_path_buf(), bytes.to_vec()));
+    }
+}
+
+fn start_live(location: &Location, dir: &Path) -> Live {
+    let (queue, jobs) = mpsc::channel::<(PathBuf, Vec<u8>)>();
+    let jobs = Arc::new(Mutex::new(jobs));
+    let sent = Arc::new(Mutex::new(HashMap::new()));
+    let workers = (0..CONNECTIONS)
+        .map(|_| {
+            let (jobs, sent) = (Arc::clone(&jobs), Arc::clone(&sent));
+            let (location, dir) = (location.clone(), dir.to_path_buf());
+            thread::spawn(move || loop {
+                let job = jobs.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                let Ok((path, bytes)) = job else { break };
+                let digest: [u8; 32] = Sha256::digest(&bytes).into();
+                // A failed upload is retried with the rest of the output when the run ends
+                if client::put(&location, &object_key(&location, &dir, &path), bytes).is_ok() {
+                    sent.lock().unwrap_or_else(|e| e.into_inner()).insert(path, digest);
+                }
+            })
+        })
+        .collect();
+    Live { dir: dir.to_path_buf(), queue, workers, sent }
+}
+
+/// Key of the object a staged file is uploaded to
+fn object_key(location: &Location, dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    format!("{}{}", location.prefix(), relative.to_string_lossy().replace('\\', "/"))
+}
+
+impl Staged {
+    /// Upload every staged file not sent yet below the output prefix, then remove the staging directory
+    pub fn upload(self) -> Result<()> {
+        // Let the crop uploads in flight finish first
+        let sent = match LIVE.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(Live { queue, workers, sent, .. }) => {
+                drop(queue);
+                for worker in workers {
+                    let _ = worker.join();
+                }
+                std::mem::take(&mut *sent.lock().unwrap_or_else(|e| e.into_inner()))
+            }
+            None => HashMap::new(),
+        };
+
+        let mut files: Vec<PathBuf> = WalkDir::new(&self.dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect();
+        // The manifest and its CSV and seal go last, after the crops they list
+        let is_manifest = |path: &PathBuf| {
+            path.parent() == Some(self.dir.as_path()) && path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("manifest."))
+        };
+        files.sort_by_key(is_manifest);
+        let split = files.iter().position(is_manifest).unwrap_or(files.len());
+
+        for batch in [&files[..split], &files[split..]] {
+            let next = AtomicUsize::new(0);
+            let failure = Mutex::new(None);
+            thread::scope(|scope| {
+                for _ in 0..CONNECTIONS.min(batch.len()) {
+                    scope.spawn(|| {
+                        while let Some(path) = batch.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            let key = object_key(&self.location, &self.dir, path);
+                            let result = fs::read(path).map_err(anyhow::Error::from).and_then(|bytes| {
+                                // Crops uploaded as they were written are only sent again if they changed since
+                                let digest: [u8; 32] = Sha256::digest(&bytes).into();
+                                match sent.get(path) {
+                                    Some(sent) if *sent == digest => Ok(()),
+                                    _ => client::put(&self.location, &key, bytes),
+                                }
+                            });
+                            if let Err(e) = result {
+                                failure.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert(e.context(format!("Failed to upload {}", key)));
+                            }
+                        }
+                    });
+                }
+            });
+            if let Some(e) = failure.into_inner().unwrap_or_else(|e| e.into_inner()) {
+                return Err(e.context(format!("Staged output kept in {}", self.dir.display())));
+            }
+        }
+
+        // Crops the run removed again after uploading them (e.g. duplicates) leave the bucket too
+        for path in sent.keys().filter(|path| !path.exists()) {
+            let key = object_key(&self.location, &self.dir, path);
+            client::delete(&self.location, &key)
+                .with_context(|| format!("Failed to remove {}; staged output kept in {}", key, self.dir.display()))?;
+        }
+        say!(CloudUploaded, files.len(), self.location.uri(&self.location.prefix()).display());
+        fs::remove_dir_all(&self.dir).context("Failed to remove staging directory")?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "cloud"))]
+mod client {
+    use anyhow::Result;
+
+    use super::Location;
+
+    fn unavailable<T>() -> Result<T> {
+        anyhow::bail!("This build has no S3/GCS support; rebuild with `cargo build --release --features cloud`")
+    }
+
+    pub fn available() -> Result<()> {
+        unavailable()
+    }
+
+    pub fn list(_location: &Location, _prefix: &str) -> Result<Vec<String>> {
+        unavailable()
+    }
+
+    pub fn get(_location: &Location, _key: &str, _first: Option<usize>) -> Result<Vec<u8>> {
+        unavailable()
+    }
+
+    pub fn put(_location: &Location, _key: &str, _bytes: Vec<u8>) -> Result<()> {
+        unavailable()
+    }
+
+    pub fn delete(_location: &Location, _key: &str) -> Result<()> {
+        unavailable()
+    }
+}
+
+#[cfg(feature = "cloud")]
+mod client {
+    use anyhow::Result;
+    use aws_config::meta::region::RegionProviderChain;
+    use aws_config::BehaviorVersion;
+    use aws_sdk_s3::error::DisplayErrorContext;
+    use aws_sdk_s3::primitives::ByteStream;
+    use google_cloud_storage::client::{Client as GcsClient, ClientConfig};
+    use google_cloud_storage::http::objects::delete::DeleteObjectRequest;
+    use google_cloud_storage::http::objects::download::Range;
+    use google_cloud_storage::http::objects::get::GetObjectRequest;
+    use google_cloud_storage::http::objects::list::ListObjectsRequest;
+    use google_cloud_storage::http::objects::upload::{Media, UploadObjectRequest, UploadType};
+    use std::env;
+    use std::sync::OnceLock;
+    use tokio::runtime::Runtime;
+
+    use super::{Location, Provider};
+
+    /// Runtime the SDK clients run on; callers block on it from their own threads
+    fn runtime() -> &'static Runtime {
+        static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+        RUNTIME.get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to start the cloud storage runtime")
+        })
+    }
+
+    /// S3 client with the SDK's credential chain (environment, profiles, SSO, instance roles)
+    fn s3() -> &'static aws_sdk_s3::Client {
+        static CLIENT: OnceLock<aws_sdk_s3::Client> = OnceLock::new();
+        CLIENT.get_or_init(|| {
+            runtime().block_on(async {
+                let region = RegionProviderChain::default_provider().or_else("us-east-1");
+                let shared = aws_config::defaults(BehaviorVersion::latest()).region(region).load().await;
+                // S3-compatible stores such as MinIO take path-style requests
+                let config = aws_sdk_s3::config::Builder::from(&shared)
+                    .force_path_style(env::var_os("AWS_ENDPOINT_URL").is_some())
+                    .build();
+                aws_sdk_s3::Client::from_conf(config)
+            })
+        })
+    }
+
+    /// GCS client with Application Default Credentials, whose tokens are refreshed before they expire;
+    /// emulators take none
+    fn gcs() -> Result<&'static GcsClient> {
+        static CLIENT: OnceLock<Result<GcsClient, String>> = OnceLock::new();
+        let client = CLIENT.get_or_init(|| {
+            runtime().block_on(async {
+                let config = match env::var("STORAGE_EMULATOR_HOST") {
+                    Ok(host) => ClientConfig {
+                        storage_endpoint: host.trim_end_matches('/').to_string(),
+                        ..Default::default()
+                    }
+                    .anonymous(),
+                    Err(_) => ClientConfig::default()
+                        .with_auth()
+                        .await
+                        .map_err(|e| format!("No GCS credentials ({}); run `gcloud auth application-default login`", e))?,
+                };
+                Ok(GcsClient::new(config))
+            })
+        });
+        client.as_ref().map_err(|e| anyhow::anyhow!("{}", e))
+    }
+
+    fn s3_error<E: std::error::Error>(e: E) -> anyhow::Error {
+        anyhow::anyhow!("S3 request failed: {}", DisplayErrorContext(e))
+    }
+
+    pub fn available() -> Result<()> {
+        Ok(())
+    }
+
+    pub fn list(location: &Location, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        match location.provider {
+            Provider::S3 => {
+                let client = s3();
+                runtime().block_on(async {
+                    let mut pages = client.list_objects_v2().bucket(&location.bucket).prefix(prefix).into_paginator().send();
+                    while let Some(page) = pages.next().await {
+                        let page = page.map_err(s3_error)?;
+                        keys.extend(page.contents().iter().filter_map(|object| object.key().map(String::from)));
+                    }
+                    anyhow::Ok(())
+                })?;
+            }
+            Provider::Gcs => {
+                let client = gcs()?;
+                runtime().block_on(async {
+                    let mut request = ListObjectsRequest {
+                        bucket: location.bucket.clone(),
+                        prefix: Some(prefix.to_string()),
+                        ..Default::default()
+                    };
+                    loop {
+                        let page = client.list_objects(&request).await?;
+                        keys.extend(page.items.into_iter().flatten().map(|object| object.name));
+                        match page.next_page_token {
+                            Some(token) => request.page_token = Some(token),
+                            None => return anyhow::Ok(()),
+                        }
+                    }
+                })?;
+            }
+        }
+        Ok(keys)
+    }
+
+    /// The object at `key`, or only its first `first` bytes
+    pub fn get(location: &Location, key: &str, first: Option<usize>) -> Result<Vec<u8>> {
+        let last = first.map(|n| n.saturating_sub(1));
+        match location.provider {
+            Provider::S3 => {
+                let client = s3();
+                runtime().block_on(async {
+                    let response = client
+                        .get_object()
+                        .bucket(&location.bucket)
+                        .key(key)
+                        .set_range(last.map(|last| format!("bytes=0-{}", last)))
+                        .send()
+                        .await
+                        .map_err(s3_error)?;
+                    Ok(response.body.collect().await?.into_bytes().to_vec())
+                })
+            }
+            Provider::Gcs => {
+                let client = gcs()?;
+                runtime().block_on(async {
+                    let request = GetObjectRequest {
+                        bucket: location.bucket.clone(),
+                        object: key.to_string(),
+                        ..Default::default()
+                    };
+                    let range = Range(last.map(|_| 0), last.map(|last| last as u64));
+                    Ok(client.download_object(&request, &range).await?)
+                })
+            }
+        }
+    }
+
+    pub fn put(location: &Location, key: &str, bytes: Vec<u8>) -> Result<()> {
+        match location.provider {
+            Provider::S3 => {
+                let client = s3();
+                runtime().block_on(async {
+                    let request = client.put_object().bucket(&location.bucket).key(key).body(ByteStream::from(bytes));
+                    request.send().await.map_err(s3_error)?;
+                    Ok(())
+                })
+            }
+            Provider::Gcs => {
+                let client = gcs()?;
+                runtime().block_on(async {
+                    let request = UploadObjectRequest {
+                        bucket: location.bucket.clone(),
+                        ..Default::default()
+                    };
+                    client.upload_object(&request, bytes, &UploadType::Simple(Media::new(key.to_string()))).await?;
+                    Ok(())
+                })
+            }
+        }
+    }
+
+    pub fn delete(location: &Location, key: &str) -> Result<()> {
+        match location.provider {
+            Provider::S3 => {
+                let client = s3();
+                runtime().block_on(async {
+                    client.delete_object().bucket(&location.bucket).key(key).send().await.map_err(s3_error)?;
+                    Ok(())
+                })
+            }
+            Provider::Gcs => {
+                let client = gcs()?;
+                runtime().block_on(async {
+                    let request = DeleteObjectRequest {
+                        bucket: location.bucket.clone(),
+                        object: key.to_string(),
+                        ..Default::default()
+                    };
+                    client.delete_object(&request).await?;
+                    Ok(())
+                })
+            }
+        }
+    }
+}
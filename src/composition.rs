@@ -0,0 +1,229 @@
+//! Target composition specs (`--composition target.yaml`)
+//!
+//! A spec assigns shares of `--target-faces` to face categories and optionally
+//! caps how many faces one identity may contribute:
+//!
+//! ```yaml
+//! quotas:
+//!   frontal: 40%
+//!   profile: 30%
+//!   low_light: 20%
+//!   occluded: 10%
+//! max_per_identity: 3
+//! ```
+//!
+//! Faces are admitted greedily as they are found: a face is kept if it belongs to
+//! a category whose quota is still open and is charged to the first such category
+//! in spec order. Shares that add up to less than 100% leave the remainder open
+//! to any face. Categories are heuristic (RustFace reports no pose or attributes)
+//! and identities are approximated by the source image's parent folder.
+
+use anyhow::{Context, Result};
+use image::GrayImage;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::detector::BBox;
+use crate::i18n::{self, Msg};
+use crate::landmarks;
+use crate::say;
+use crate::orientation::Facing;
+
+/// Mean face luma below which a face counts as low-light
+const LOW_LIGHT_LUMA: f64 = 70.0;
+
+/// Quota that soaks up the share a spec leaves unassigned
+const ANY: &str = "any";
+
+const CATEGORIES: &[&str] = &["frontal", "profile", "low_light", "occluded"];
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    /// Kept as a mapping so quotas stay in the order they were written
+    #[serde(default)]
+    quotas: serde_yaml::Mapping,
+    max_per_identity: Option<usize>,
+}
+
+/// A share written as `40%`, `"40%"` or `40`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Share {
+    Number(f64),
+    Text(String),
+}
+
+impl Share {
+    fn percent(&self) -> Result<f64> {
+        let value = match self {
+            Share::Number(n) => *n,
+            Share::Text(text) => text
+                .trim()
+                .trim_end_matches('%')
+                .trim()
+                .parse()
+                .with_context(|| format!("'{}' is not a percentage", text))?,
+        };
+        anyhow::ensure!((0.0..=100.0).contains(&value), "quota {}% is outside 0-100%", value);
+        Ok(value)
+    }
+}
+
+/// What the selection logic knows about one detected face
+pub struct FaceTraits {
+    pub facing: Facing,
+    pub low_light: bool,
+    pub occluded: bool,
+}
+
+impl FaceTraits {
+    pub fn measure(gray: &GrayImage, bbox: &BBox, facing: Facing) -> Self {
+        FaceTraits {
+            facing,
+            low_light: mean_luma(gray, bbox) < LOW_LIGHT_LUMA,
+            // No eye pair usually means sunglasses, a hand, hair or a mask over the eyes
+            occluded: landmarks::estimate_eyes(gray, bbox).is_none(),
+        }
+    }
+
+    fn is(&self, category: &str) -> bool {
+        match category {
+            "frontal" => self.facing == Facing::Frontal,
+            "profile" => self.facing != Facing::Frontal,
+            "low_light" => self.low_light,
+            "occluded" => self.occluded,
+            _ => category == ANY,
+        }
+    }
+}
+
+struct Quota {
+    category: String,
+    wanted: usize,
+    filled: usize,
+}
+
+pub struct Composition {
+    quotas: Vec<Quota>,
+    max_per_identity: Option<usize>,
+    per_identity: HashMap<String, usize>,
+    capped: usize,
+}
+
+impl Composition {
+    /// Read a YAML spec and size its quotas for `target` faces
+    pub fn load(path: &Path, target: usize) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read composition spec {}", path.display()))?;
+        let spec: Spec =
+            serde_yaml::from_str(&text).with_context(|| format!("Invalid composition spec {}", path.display()))?;
+
+        let mut quotas = Vec::new();
+        let mut total = 0.0;
+        for (key, value) in spec.quotas {
+            let category = key.as_str().context("Composition categories must be strings")?.to_string();
+            let share: Share = serde_yaml::from_value(value)
+                .with_context(|| format!("Invalid share for composition category '{}'", category))?;
+            anyhow::ensure!(
+                CATEGORIES.contains(&category.as_str()),
+                "Unknown composition category '{}' (known: {})",
+                category,
+                CATEGORIES.join(", ")
+            );
+            let percent = share.percent()?;
+            total += percent;
+            quotas.push(Quota {
+                category,
+                wanted: (target as f64 * percent / 100.0).round() as usize,
+                filled: 0,
+            });
+        }
+        anyhow::ensure!(total <= 100.0 + 1e-6, "Composition quotas add up to {}%, more than 100%", total);
+
+        let assigned: usize = quotas.iter().map(|q| q.wanted).sum();
+        if assigned < target {
+            quotas.push(Quota {
+                category: ANY.to_string(),
+                wanted: target - assigned,
+                filled: 0,
+            });
+        }
+
+        Ok(Composition {
+            quotas,
+            max_per_identity: spec.max_per_identity,
+            per_identity: HashMap::new(),
+            capped: 0,
+        })
+    }
+
+    /// The open quota a face from `source` would fill, or `None` to skip it; nothing is claimed yet
+    pub fn admit(&mut self, traits: &FaceTraits, source: &Path) -> Option<usize> {
+        let seen = self.per_identity.get(&identity_of(source)).copied().unwrap_or(0);
+        if self.max_per_identity.is_some_and(|max| seen >= max) {
+            self.capped += 1;
+            return None;
+        }
+        self.quotas.iter().position(|q| q.filled < q.wanted && traits.is(&q.category))
+    }
+
+    /// Charge a face from `source` that was saved to the quota `admit` returned; returns its category
+    pub fn claim(&mut self, quota: usize, source: &Path) -> String {
+        let quota = &mut self.quotas[quota];
+        quota.filled += 1;
+        *self.per_identity.entry(identity_of(source)).or_default() += 1;
+        quota.category.clone()
+    }
+
+    /// Print how far each quota got and flag the ones left unmet
+    pub fn report(&self) {
+        say!(CompositionHeader);
+        for q in &self.quotas {
+            say!(QuotaProgress, q.category, q.filled, q.wanted);
+        }
+        if self.capped > 0 {
+            say!(CappedByIdentity, self.capped);
+        }
+
+        let unmet: Vec<String> = self
+            .quotas
+            .iter()
+            .filter(|q| q.filled < q.wanted)
+            .map(|q| i18n::text(Msg::QuotaShort, &[&q.category, &(q.wanted - q.filled)]))
+            .collect();
+        if !unmet.is_empty() {
+            say!(UnmetQuotas, unmet.join(", "));
+        }
+    }
+}
+
+/// Faces are grouped by the folder their source image sits in
+fn identity_of(source: &Path) -> String {
+    source
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn mean_luma(gray: &GrayImage, bbox: &BBox) -> f64 {
+    let x0 = bbox.x.max(0) as u32;
+    let y0 = bbox.y.max(0) as u32;
+    let x1 = (bbox.x + bbox.width as i32).clamp(0, gray.width() as i32) as u32;
+    let y1 = (bbox.y + bbox.height as i32).clamp(0, gray.height() as i32) as u32;
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            sum += gray.get_pixel(x, y)[0] as u64;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return 255.0;
+    }
+    sum as f64 / count as f64
+}
@@ -0,0 +1,91 @@
+//! TOML configuration files
+//!
+//! Config keys are the long flag names in snake_case (`min_face_size = 60`).
+//! Values from the file only apply to flags that were not given on the command
+//! line, so a shared config can still be overridden per run. A `--profile`
+//! ranks below both and only fills in what neither set. `--dump-config`
+//! prints the merged result, which can be saved and loaded back as a config.
+
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory};
+use std::fs;
+use std::path::Path;
+
+use crate::profile::Profile;
+use crate::Args;
+
+/// What a config file contributed, reported once the output language is set
+#[derive(Debug, Default)]
+pub struct FileSettings {
+    /// Keys the file set
+    pub keys: Vec<String>,
+    /// Keys that are not settings and were ignored
+    pub unknown: Vec<String>,
+}
+
+/// Merge the settings in `path` into `args`, keeping anything set explicitly on the command line
+pub fn apply_file(args: &mut Args, matches: &ArgMatches, path: &Path) -> Result<FileSettings> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let file: toml::Table = text
+        .parse()
+        .with_context(|| format!("Invalid TOML in {}", path.display()))?;
+
+    let cli = Args::command();
+    let mut settings = toml::Table::new();
+    let mut unknown = Vec::new();
+    for (key, value) in file {
+        if !cli.get_arguments().any(|arg| arg.get_id() == key.as_str()) {
+            unknown.push(key);
+            continue;
+        }
+        if matches.value_source(&key) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        settings.insert(key, value);
+    }
+
+    let keys: Vec<String> = settings.keys().cloned().collect();
+    merge(args, settings).with_context(|| format!("Invalid value in config file {}", path.display()))?;
+    Ok(FileSettings { keys, unknown })
+}
+
+/// Fill in the settings of `profile` that neither the command line nor the config file (`from_file`) set.
+/// Returns the settings applied, as `key=value`.
+pub fn apply_profile(args: &mut Args, matches: &ArgMatches, profile: Profile, from_file: &[String]) -> Result<Vec<String>> {
+    let settings: toml::Table = profile
+        .settings()
+        .into_iter()
+        .filter(|(key, _)| matches.value_source(key) != Some(ValueSource::CommandLine) && !from_file.contains(key))
+        .collect();
+
+    let applied: Vec<String> = settings.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+    merge(args, settings).with_context(|| format!("Invalid value in profile {}", profile.name()))?;
+    Ok(applied)
+}
+
+/// Every setting of `args` as a config file
+pub fn dump(args: &Args) -> Result<String> {
+    let settings = toml::to_string_pretty(args).context("Failed to serialize settings")?;
+    Ok(format!("# Effective settings; load with --config\n{}", settings))
+}
+
+/// Overwrite the fields of `args` named in `settings`
+fn merge(args: &mut Args, settings: toml::Table) -> Result<()> {
+    // Through JSON, as TOML integers stop at i64::MAX while flags like --seed take any u64
+    let mut effective = serde_json::to_value(&*args).context("Failed to serialize settings")?;
+    let fields = effective.as_object_mut().context("Failed to serialize settings")?;
+    for (key, value) in settings {
+        fields.insert(key, serde_json::to_value(value)?);
+    }
+
+    let command = args.command.take();
+    let config = args.config.take();
+    let dump_config = args.dump_config;
+    *args = serde_json::from_value(effective)?;
+    args.command = command;
+    args.config = config;
+    args.dump_config = dump_config;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Contact sheets of saved faces (`--contact-sheet`)
+//!
+//! Every saved face is shrunk to a thumbnail and laid out on a grid of
+//! COLUMNS x ROWS cells (10x10 by default), so a thousand crops fit on ten
+//! PNG pages that can be flicked through for junk detections. Each cell is
+//! stamped with its number on the sheet, and `contact_sheets/index.csv` maps
+//! sheet and number back to the crop. A sheet is written as soon as it fills
+//! up, so memory stays flat however long the run; resumed runs add new sheets
+//! after the ones already there.
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::manifest::csv_escape;
+use crate::{say, watermark};
+
+pub const CONTACT_SHEET_DIR: &str = "contact_sheets";
+
+const INDEX_FILE: &str = "index.csv";
+
+/// Side of each thumbnail cell
+const CELL_SIDE: u32 = 128;
+
+/// Cells per sheet, as COLUMNSxROWS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Grid {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s
+            .trim()
+            .split_once(['x', 'X'])
+            .and_then(|(c, r)| Some((c.trim().parse().ok()?, r.trim().parse().ok()?)));
+        match parsed {
+            Some((columns, rows)) if columns > 0 && rows > 0 => Ok(Grid { columns, rows }),
+            _ => Err(format!("'{}' is not a grid (use COLUMNSxROWS, e.g. 10x10)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.columns, self.rows)
+    }
+}
+
+impl TryFrom<String> for Grid {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Grid> for String {
+    fn from(grid: Grid) -> Self {
+        grid.to_string()
+    }
+}
+
+pub struct ContactSheets {
+    grid: Grid,
+    dir: PathBuf,
+    /// `index.csv`, appended to as faces are added
+    index: File,
+    /// Thumbnails waiting for the current sheet to fill up
+    cells: Vec<DynamicImage>,
+    /// Number of the sheet being filled
+    sheet: usize,
+    /// Sheets and faces written in this run
+    written: usize,
+    faces: usize,
+}
+
+impl ContactSheets {
+    pub fn open(output: &Path, grid: Grid) -> Result<Self> {
+        let dir = output.join(CONTACT_SHEET_DIR);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let mut sheet = 1;
+        while dir.join(sheet_name(sheet)).exists() {
+            sheet += 1;
+        }
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(INDEX_FILE))
+            .context("Failed to open contact sheet index")?;
+        if index.metadata()?.len() == 0 {
+            writeln!(index, "sheet,number,crop")?;
+        }
+        Ok(ContactSheets { grid, dir, index, cells: Vec::new(), sheet, written: 0, faces: 0 })
+    }
+
+    /// Thumbnail of a crop, small enough to hold a full sheet of them in memory
+    pub fn thumbnail(crop: &DynamicImage) -> DynamicImage {
+        crop.resize_to_fill(CELL_SIDE, CELL_SIDE, FilterType::Triangle)
+    }
+
+    /// Add a face to the current sheet, writing the sheet once it is full
+    pub fn add(&mut self, thumbnail: DynamicImage, crop: &str) -> Result<()> {
+        writeln!(self.index, "{},{},{}", sheet_name(self.sheet), self.cells.len() + 1, csv_escape(crop))
+            .context("Failed to write contact sheet index")?;
+
+        self.cells.push(thumbnail);
+        self.faces += 1;
+        if self.cells.len() == (self.grid.columns * self.grid.rows) as usize {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the last, partly filled sheet and report what was written
+    pub fn finish(&mut self) -> Result<()> {
+        self.flush()?;
+        say!(ContactSheets, self.written, self.faces, self.dir.display());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.cells.is_empty() {
+            return Ok(());
+        }
+        // A partial sheet keeps the full width so pages line up, but only the rows it needs
+        let rows = (self.cells.len() as u32).div_ceil(self.grid.columns);
+        let mut canvas = RgbaImage::from_pixel(self.grid.columns * CELL_SIDE, rows * CELL_SIDE, Rgba([32, 32, 32, 255]));
+        for (i, cell) in self.cells.iter().enumerate() {
+            let x = (i as u32 % self.grid.columns) * CELL_SIDE;
+            let y = (i as u32 / self.grid.columns) * CELL_SIDE;
+            canvas.copy_from(&cell.to_rgba8(), x, y)?;
+            watermark::draw_line(&mut canvas, &(i + 1).to_string(), x + 4, y + 4, 2);
+        }
+        let path = self.dir.join(sheet_name(self.sheet));
+        DynamicImage::ImageRgba8(canvas)
+            .to_rgb8()
+            .save(&path)
+            .with_context(|| format!("Failed to save {}", path.display()))?;
+        self.cells.clear();
+        self.sheet += 1;
+        self.written += 1;
+        Ok(())
+    }
+}
+
+fn sheet_name(sheet: usize) -> String {
+    format!("sheet_{:04}.png", sheet)
+}
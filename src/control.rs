@@ -0,0 +1,75 @@
+//! Operator control files, polled while a run is in progress
+//!
+//! `<output>/control/prioritize.txt` lists folders, one per line (relative to
+//! `--input` or absolute, `#` starts a comment). Whenever the file changes, the
+//! images still waiting in the queue are reordered so those folders come first,
+//! in the order listed. A chunked run reorders within the current chunk.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::say;
+
+pub const CONTROL_DIR: &str = "control";
+const PRIORITIZE_FILE: &str = "prioritize.txt";
+
+pub struct PriorityControl {
+    file: PathBuf,
+    input: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl PriorityControl {
+    pub fn new(output: &Path, input: &Path) -> Self {
+        PriorityControl {
+            file: output.join(CONTROL_DIR).join(PRIORITIZE_FILE),
+            input: input.to_path_buf(),
+            modified: None,
+        }
+    }
+
+    /// Reorder `remaining` if the control file changed since the last poll
+    pub fn poll(&mut self, remaining: &mut [PathBuf]) {
+        let Ok(modified) = fs::metadata(&self.file).and_then(|m| m.modified()) else {
+            return;
+        };
+        if self.modified == Some(modified) {
+            return;
+        }
+        self.modified = Some(modified);
+
+        let folders = self.read_folders();
+        if folders.is_empty() {
+            return;
+        }
+        let rank = |path: &PathBuf| {
+            folders
+                .iter()
+                .position(|folder| path.starts_with(folder))
+                .unwrap_or(folders.len())
+        };
+        remaining.sort_by_key(rank);
+
+        let bumped = remaining.iter().filter(|path| rank(path) < folders.len()).count();
+        say!(Prioritized, bumped, folders.len(), self.file.display());
+    }
+
+    fn read_folders(&self) -> Vec<PathBuf> {
+        let Ok(text) = fs::read_to_string(&self.file) else {
+            return Vec::new();
+        };
+        text.lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let folder = Path::new(line);
+                if folder.is_absolute() || folder.starts_with(&self.input) {
+                    folder.to_path_buf()
+                } else {
+                    self.input.join(folder)
+                }
+            })
+            .collect()
+    }
+}
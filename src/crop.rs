@@ -0,0 +1,432 @@
+//! Crop geometry: how much context is kept around each detected face
+
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::{imageops, DynamicImage, GenericImageView, Pixel, Rgb, RgbImage};
+use imageproc::geometric_transformations::{rotate, warp_into, Interpolation, Projection};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::detector::{BBox, Landmarks};
+use crate::landmarks::Point;
+use crate::Args;
+
+/// Side of the canonical ArcFace crop
+pub const ARCFACE_SIZE: u32 = 112;
+
+/// ArcFace reference positions in a 112x112 crop: eyes, nose tip, mouth corners
+const ARCFACE_TEMPLATE: [(f32, f32); 5] = [
+    (38.2946, 51.6963),
+    (73.5318, 51.5014),
+    (56.0252, 71.7366),
+    (41.5493, 92.3655),
+    (70.7299, 92.2041),
+];
+
+/// Framing of a saved crop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    /// Detector box plus `--padding` (12.5% by default, the classic output)
+    #[default]
+    Standard,
+    /// Exactly the detector box
+    Tight,
+    /// Detector box plus twice `--padding`, keeping hair and chin
+    Loose,
+    /// Square crop rotated so the eyes are level
+    Aligned,
+    /// 112x112 crop warped onto the ArcFace five-point landmark template
+    Arcface,
+}
+
+impl Variant {
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::Tight => "tight",
+            Variant::Loose => "loose",
+            Variant::Aligned => "aligned",
+            Variant::Arcface => "arcface",
+        }
+    }
+}
+
+/// Fixed crop resolution such as `112x112` or `160x200`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct OutputSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for OutputSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s
+            .trim()
+            .split_once(['x', 'X'])
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+        match parsed {
+            Some((width, height)) if width > 0 && height > 0 => Ok(OutputSize { width, height }),
+            _ => Err(format!("'{}' is not an output size (use WIDTHxHEIGHT, e.g. 224x224)", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl TryFrom<String> for OutputSize {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<OutputSize> for String {
+    fn from(size: OutputSize) -> Self {
+        size.to_string()
+    }
+}
+
+/// Resampling used when crops are scaled to an export size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFilter {
+    /// Fastest; blocky when upscaling
+    Nearest,
+    /// Smooth, slightly soft
+    Bilinear,
+    /// Sharpest (the default)
+    #[default]
+    Lanczos,
+}
+
+impl ResizeFilter {
+    pub fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Bilinear => FilterType::Triangle,
+            ResizeFilter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// What happens where a padded crop reaches past the edge of the source image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PadMode {
+    /// Cut the crop off at the image border, so faces near it get less context on that side
+    #[default]
+    Clip,
+    /// Keep the full framing and mirror the image across the border
+    Reflect,
+    /// Keep the full framing and fill the part outside the image (see `--pad-fill`)
+    #[value(alias = "pad")]
+    Constant,
+}
+
+/// Fill for the part of a crop outside the source image under `--pad-mode constant`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PadFill {
+    #[default]
+    Black,
+    /// Average color of the part inside the image
+    Mean,
+    /// The part inside the image stretched over the whole crop and blurred
+    Blur,
+}
+
+/// Crop settings shared by every variant
+#[derive(Debug, Clone, Copy)]
+pub struct Framing {
+    /// Standard margin as a fraction of the box width plus height
+    pub padding: f64,
+    /// Grow the shorter side of padded crops to make them square
+    pub square: bool,
+    pub mode: PadMode,
+    pub fill: PadFill,
+}
+
+impl Framing {
+    pub fn of(args: &Args) -> Self {
+        Framing { padding: args.padding, square: args.square_crop, mode: args.pad_mode, fill: args.pad_fill }
+    }
+}
+
+/// Part of the source image a padded crop was cut from
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// Margin added on each side of the detector box, before squaring
+    pub padding: u32,
+    /// The grown box in source pixels; it may reach past the image
+    pub region: BBox,
+}
+
+impl Frame {
+    /// `bbox` grown by `padding` on every side, then along its shorter side if `square`
+    fn around(bbox: &BBox, padding: u32, square: bool) -> Self {
+        let (width, height) = (bbox.width + 2 * padding, bbox.height + 2 * padding);
+        let (side_w, side_h) = if square { (width.max(height), width.max(height)) } else { (width, height) };
+        let region = BBox {
+            x: bbox.x - padding as i32 - ((side_w - width) / 2) as i32,
+            y: bbox.y - padding as i32 - ((side_h - height) / 2) as i32,
+            width: side_w,
+            height: side_h,
+        };
+        Frame { padding, region }
+    }
+}
+
+/// Pixels of a crop that lie outside the source image and were filled, per side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeFill {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// Cut the face in `bbox` out of `image` using the requested framing; `landmarks` drive the aligned crops.
+/// Also returns where the crop was cut from, or `None` for a rotated or warped crop.
+pub fn crop_variant(
+    image: &DynamicImage,
+    bbox: &BBox,
+    landmarks: Option<&Landmarks>,
+    variant: Variant,
+    framing: Framing,
+) -> (DynamicImage, Option<Frame>) {
+    let standard = ((bbox.width + bbox.height) as f64 * framing.padding) as u32;
+    let padding = match variant {
+        Variant::Standard => standard,
+        Variant::Tight => 0,
+        Variant::Loose => standard * 2,
+        Variant::Aligned => match landmarks.and_then(|l| aligned_crop(image, bbox, [l.left_eye, l.right_eye])) {
+            Some(aligned) => return (aligned, None),
+            None => standard,
+        },
+        Variant::Arcface => {
+            // Without landmarks, the standard framing scaled to the template size is the best available
+            let crop = match landmarks.and_then(|l| template_crop(image, l)) {
+                Some(warped) => warped,
+                None => {
+                    let frame = Frame::around(bbox, standard, framing.square);
+                    framed_crop(image, &frame.region, framing).resize_to_fill(ARCFACE_SIZE, ARCFACE_SIZE, FilterType::Lanczos3)
+                }
+            };
+            return (crop, None);
+        }
+    };
+    let frame = Frame::around(bbox, padding, framing.square);
+    (framed_crop(image, &frame.region, framing), Some(frame))
+}
+
+/// Cut `region` out of `image`, handling the part past the border as `framing.mode` says
+fn framed_crop(image: &DynamicImage, region: &BBox, framing: Framing) -> DynamicImage {
+    let fill = match edge_fill(image, region) {
+        Some(fill) if framing.mode != PadMode::Clip => fill,
+        _ => return clipped_crop(image, region),
+    };
+    let inside_w = region.width.saturating_sub(fill.left + fill.right);
+    let inside_h = region.height.saturating_sub(fill.top + fill.bottom);
+    if inside_w == 0 || inside_h == 0 {
+        return clipped_crop(image, region);
+    }
+
+    let canvas = if framing.mode == PadMode::Reflect {
+        RgbImage::from_fn(region.width, region.height, |u, v| {
+            let x = reflect(region.x as i64 + u as i64, image.width());
+            let y = reflect(region.y as i64 + v as i64, image.height());
+            image.get_pixel(x, y).to_rgb()
+        })
+    } else {
+        let inside = image
+            .crop_imm(region.x.max(0) as u32, region.y.max(0) as u32, inside_w, inside_h)
+            .to_rgb8();
+        let (width, height) = (region.width, region.height);
+        let mut canvas = match framing.fill {
+            PadFill::Black => RgbImage::new(width, height),
+            PadFill::Mean => RgbImage::from_pixel(width, height, mean_color(&inside)),
+            PadFill::Blur => {
+                let stretched = imageops::resize(&inside, width, height, FilterType::Triangle);
+                imageops::blur(&stretched, (width.max(height) as f32 / 20.0).max(2.0))
+            }
+        };
+        imageops::replace(&mut canvas, &inside, fill.left as i64, fill.top as i64);
+        canvas
+    };
+    let canvas = DynamicImage::ImageRgb8(canvas);
+    if image.color().has_color() { canvas } else { canvas.grayscale() }
+}
+
+/// Mirror coordinate `i` back into `0..n`, repeating the image as often as needed
+fn reflect(i: i64, n: u32) -> u32 {
+    let n = n as i64;
+    let m = i.rem_euclid(2 * n);
+    (if m < n { m } else { 2 * n - 1 - m }) as u32
+}
+
+/// How far `region` reaches past each edge of `image`, or `None` if it fits
+pub fn edge_fill(image: &DynamicImage, region: &BBox) -> Option<EdgeFill> {
+    let (x0, y0) = (region.x as i64, region.y as i64);
+    let x1 = x0 + region.width as i64;
+    let y1 = y0 + region.height as i64;
+    let fill = EdgeFill {
+        left: (-x0).max(0) as u32,
+        top: (-y0).max(0) as u32,
+        right: (x1 - image.width() as i64).max(0) as u32,
+        bottom: (y1 - image.height() as i64).max(0) as u32,
+    };
+    (fill != EdgeFill { left: 0, top: 0, right: 0, bottom: 0 }).then_some(fill)
+}
+
+fn mean_color(image: &RgbImage) -> Rgb<u8> {
+    let mut sum = [0u64; 3];
+    for pixel in image.pixels() {
+        for (total, &value) in sum.iter_mut().zip(&pixel.0) {
+            *total += value as u64;
+        }
+    }
+    let n = (image.width() as u64 * image.height() as u64).max(1);
+    Rgb(sum.map(|total| (total / n) as u8))
+}
+
+/// Crop `bbox` grown by `padding` pixels on every side, clipped to the image
+pub fn padded_crop(image: &DynamicImage, bbox: &BBox, padding: i32) -> DynamicImage {
+    clipped_crop(image, &Frame::around(bbox, padding.max(0) as u32, false).region)
+}
+
+/// The part of `region` inside the image
+fn clipped_crop(image: &DynamicImage, region: &BBox) -> DynamicImage {
+    let (x, y, width, height) = clipped(image, region);
+    image.crop_imm(x, y, width, height)
+}
+
+/// Origin and size of `region` clipped to the image
+fn clipped(image: &DynamicImage, region: &BBox) -> (u32, u32, u32, u32) {
+    let x = region.x.max(0) as u32;
+    let y = region.y.max(0) as u32;
+    let width = region.width.min(image.width().saturating_sub(x));
+    let height = region.height.min(image.height().saturating_sub(y));
+    (x, y, width, height)
+}
+
+/// Face box inside a saved crop, as fractions of the crop's width and height
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FaceBox {
+    pub cx: f32,
+    pub cy: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Where `bbox` lands in the crop of `region` under `mode`, after an optional
+/// `resize_to_fill` to `fill` and an optional horizontal flip
+pub fn face_box(
+    image: &DynamicImage,
+    bbox: &BBox,
+    region: &BBox,
+    mode: PadMode,
+    fill: Option<(u32, u32)>,
+    mirrored: bool,
+) -> FaceBox {
+    let (origin_x, origin_y, crop_w, crop_h) = if mode != PadMode::Clip && edge_fill(image, region).is_some() {
+        (region.x as f32, region.y as f32, region.width as f32, region.height as f32)
+    } else {
+        let (x, y, width, height) = clipped(image, region);
+        (x as f32, y as f32, width as f32, height as f32)
+    };
+    let mut x = bbox.x as f32 - origin_x;
+    let mut y = bbox.y as f32 - origin_y;
+    let (mut w, mut h) = (bbox.width as f32, bbox.height as f32);
+    let (mut out_w, mut out_h) = (crop_w, crop_h);
+
+    // resize_to_fill scales to cover the target, then trims the overflow evenly from both sides
+    if let Some((fill_w, fill_h)) = fill {
+        let scale = (fill_w as f32 / crop_w).max(fill_h as f32 / crop_h);
+        x = x * scale - (crop_w * scale - fill_w as f32) / 2.0;
+        y = y * scale - (crop_h * scale - fill_h as f32) / 2.0;
+        (w, h) = (w * scale, h * scale);
+        (out_w, out_h) = (fill_w as f32, fill_h as f32);
+    }
+
+    let cx = (x + w / 2.0) / out_w;
+    FaceBox {
+        cx: if mirrored { 1.0 - cx } else { cx },
+        cy: (y + h / 2.0) / out_h,
+        width: w / out_w,
+        height: h / out_h,
+    }
+}
+
+/// Similarity transform (rotation, uniform scale, shift) that best maps `landmarks` onto the
+/// ArcFace template in the least-squares sense, applied to produce the canonical crop
+fn template_crop(image: &DynamicImage, landmarks: &Landmarks) -> Option<DynamicImage> {
+    let source = [
+        landmarks.left_eye,
+        landmarks.right_eye,
+        landmarks.nose,
+        landmarks.mouth_left,
+        landmarks.mouth_right,
+    ];
+    let n = source.len() as f32;
+    let (sx, sy) = source.iter().fold((0.0, 0.0), |(x, y), p| (x + p.x / n, y + p.y / n));
+    let (tx, ty) = ARCFACE_TEMPLATE.iter().fold((0.0, 0.0), |(x, y), p| (x + p.0 / n, y + p.1 / n));
+
+    // Closed-form 2D Procrustes on the centered point sets
+    let (mut dot, mut cross, mut norm) = (0.0, 0.0, 0.0);
+    for (p, q) in source.iter().zip(ARCFACE_TEMPLATE.iter()) {
+        let (px, py) = (p.x - sx, p.y - sy);
+        let (qx, qy) = (q.0 - tx, q.1 - ty);
+        dot += px * qx + py * qy;
+        cross += px * qy - py * qx;
+        norm += px * px + py * py;
+    }
+    if norm <= f32::EPSILON {
+        return None;
+    }
+    let (a, b) = (dot / norm, cross / norm);
+    let projection = Projection::from_matrix([a, -b, tx - (a * sx - b * sy), b, a, ty - (b * sx + a * sy), 0.0, 0.0, 1.0])?;
+
+    let mut out = RgbImage::new(ARCFACE_SIZE, ARCFACE_SIZE);
+    warp_into(&image.to_rgb8(), &projection, Interpolation::Bilinear, Rgb([0, 0, 0]), &mut out);
+    Some(DynamicImage::ImageRgb8(out))
+}
+
+/// Square crop centered on the face with the eye line rotated to horizontal
+fn aligned_crop(image: &DynamicImage, bbox: &BBox, [left, right]: [Point; 2]) -> Option<DynamicImage> {
+    let angle = (right.y - left.y).atan2(right.x - left.x);
+
+    // Work on a generous region so the rotation never pulls in undefined corners
+    let side = bbox.width.max(bbox.height) as f32 * 1.25;
+    let margin = side.ceil() as i32;
+    let x0 = (bbox.x - margin).max(0) as u32;
+    let y0 = (bbox.y - margin).max(0) as u32;
+    let x1 = ((bbox.x + bbox.width as i32 + margin) as u32).min(image.width());
+    let y1 = ((bbox.y + bbox.height as i32 + margin) as u32).min(image.height());
+    let region = image.crop_imm(x0, y0, x1 - x0, y1 - y0).to_rgb8();
+
+    let center = ((left.x + right.x) / 2.0 - x0 as f32, (left.y + right.y) / 2.0 - y0 as f32);
+    let leveled = rotate(&region, center, -angle, Interpolation::Bilinear, Rgb([0, 0, 0]));
+
+    // The eyes sit a little above the middle of a well-framed face
+    let cx = center.0;
+    let cy = center.1 + 0.15 * side;
+    let sx = (cx - side / 2.0).max(0.0) as u32;
+    let sy = (cy - side / 2.0).max(0.0) as u32;
+    let sw = (side as u32).min(leveled.width().saturating_sub(sx));
+    let sh = (side as u32).min(leveled.height().saturating_sub(sy));
+    if sw == 0 || sh == 0 {
+        return None;
+    }
+
+    Some(DynamicImage::ImageRgb8(leveled).crop_imm(sx, sy, sw, sh))
+}
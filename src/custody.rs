@@ -0,0 +1,238 @@
+//! Chain of custody: the run seal and `verify --manifest`
+//!
+//! Every manifest record carries the SHA-256 of its crop. When a run finishes,
+//! `manifest.seal.json` is written next to the manifest with the effective
+//! settings, their fingerprint and the manifest's own digest. If
+//! `FDG_SIGNING_KEY` is set the seal is also signed with HMAC-SHA256, so a
+//! recipient holding the same key can tell the seal was not rewritten.
+//!
+//! `verify` never writes into the dataset: it recomputes every digest, checks
+//! the seal and prints (or saves) a verification certificate.
+
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::manifest::{self, MANIFEST_FILE};
+use crate::annotations::{VOC_DIR, YOLO_DIR};
+use crate::contact_sheet::CONTACT_SHEET_DIR;
+use crate::heatmap::HEATMAP_FILE;
+use crate::redact::REDACTED_DIR;
+use crate::remote::DOWNLOADS_DIR;
+use crate::{collect_images, say, Args};
+
+pub const SEAL_FILE: &str = "manifest.seal.json";
+
+/// Environment variable holding the HMAC key for signing and checking seals
+pub const SIGNING_KEY_VAR: &str = "FDG_SIGNING_KEY";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Seal {
+    version: u32,
+    /// SHA-256 of the canonical JSON of `config`
+    config_fingerprint: String,
+    config: serde_json::Value,
+    manifest_sha256: String,
+    records: usize,
+    /// Hex HMAC-SHA256 over `config_fingerprint:manifest_sha256`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+/// Hex SHA-256 of a file's contents
+pub fn file_sha256(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+fn fingerprint(config: &serde_json::Value) -> String {
+    hex::encode(Sha256::digest(config.to_string().as_bytes()))
+}
+
+fn sign(key: &str, config_fingerprint: &str, manifest_sha256: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}:{}", config_fingerprint, manifest_sha256).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn signing_key() -> Option<String> {
+    std::env::var(SIGNING_KEY_VAR).ok().filter(|key| !key.is_empty())
+}
+
+/// Write the seal for the manifest in `args.output`; called once the manifest is complete
+pub fn write_seal(args: &Args) -> Result<()> {
+    let manifest_path = args.output.join(MANIFEST_FILE);
+    let config = serde_json::to_value(args).context("Failed to serialize settings")?;
+    let config_fingerprint = fingerprint(&config);
+    let manifest_sha256 = file_sha256(&manifest_path)?;
+    let signature = signing_key().map(|key| sign(&key, &config_fingerprint, &manifest_sha256));
+
+    let seal = Seal {
+        version: 1,
+        config_fingerprint,
+        config,
+        records: manifest::read(&manifest_path)?.len(),
+        manifest_sha256,
+        signature,
+    };
+    let path = args.output.join(SEAL_FILE);
+    fs::write(&path, serde_json::to_string_pretty(&seal)?)
+        .with_context(|| format!("Failed to write seal {}", path.display()))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SignatureStatus {
+    Valid,
+    Invalid,
+    /// The seal carries no signature
+    Unsigned,
+    /// Signed, but no key was available to check it
+    Unchecked,
+}
+
+#[derive(Debug, Serialize)]
+struct Certificate {
+    version: u32,
+    verified_at_ms: u128,
+    manifest: PathBuf,
+    manifest_sha256: String,
+    strict: bool,
+    records: usize,
+    crops_verified: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_fingerprint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<SignatureStatus>,
+    passed: bool,
+    failures: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Certificate {
+    /// A strict verification turns every warning into a failure
+    fn flag(&mut self, problem: String, fatal: bool) {
+        if fatal || self.strict {
+            self.failures.push(problem);
+        } else {
+            self.warnings.push(problem);
+        }
+    }
+}
+
+/// Check the crops and seal behind `manifest_path` and report a certificate
+pub fn verify(manifest_path: &Path, strict: bool, certificate_path: Option<&Path>) -> Result<()> {
+    say!(VerifyStarting, manifest_path.display());
+    let records = manifest::read(manifest_path)?;
+    let root = manifest_path.parent().unwrap_or(Path::new("."));
+
+    let mut cert = Certificate {
+        version: 1,
+        verified_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis()),
+        manifest: manifest_path.to_path_buf(),
+        manifest_sha256: file_sha256(manifest_path)?,
+        strict,
+        records: records.len(),
+        crops_verified: 0,
+        config_fingerprint: None,
+        signature: None,
+        passed: false,
+        failures: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let mut listed = HashSet::new();
+    for record in &records {
+        let path = root.join(&record.crop);
+        listed.insert(path.clone());
+        match (&record.sha256, file_sha256(&path)) {
+            (_, Err(_)) => cert.flag(format!("{}: crop is missing", record.crop), true),
+            (None, Ok(_)) => cert.flag(format!("{}: manifest records no hash", record.crop), false),
+            (Some(expected), Ok(actual)) if *expected != actual => {
+                cert.flag(format!("{}: hash mismatch", record.crop), true)
+            }
+            (Some(_), Ok(_)) => cert.crops_verified += 1,
+        }
+    }
+
+    // Whole-image training exports, redacted originals, downloaded inputs, the heatmap and contact sheets are not crops
+    let exports = [
+        root.join(YOLO_DIR),
+        root.join(VOC_DIR),
+        root.join(REDACTED_DIR),
+        root.join(DOWNLOADS_DIR),
+        root.join(HEATMAP_FILE),
+        root.join(CONTACT_SHEET_DIR),
+    ];
+    for path in collect_images(root) {
+        if !listed.contains(&path) && !exports.iter().any(|dir| path.starts_with(dir)) {
+            cert.flag(format!("{}: crop is not in the manifest", path.display()), false);
+        }
+    }
+
+    check_seal(&mut cert, &root.join(SEAL_FILE), records.len());
+
+    cert.passed = cert.failures.is_empty();
+    let json = serde_json::to_string_pretty(&cert)?;
+    match certificate_path {
+        Some(path) => {
+            fs::write(path, &json).with_context(|| format!("Failed to write certificate {}", path.display()))?;
+            say!(CertificateWritten, path.display());
+        }
+        None => println!("{}", json),
+    }
+
+    if cert.passed {
+        say!(VerifyPassed, cert.crops_verified, cert.records, cert.failures.len(), cert.warnings.len());
+    } else {
+        say!(VerifyFailed, cert.crops_verified, cert.records, cert.failures.len(), cert.warnings.len());
+    }
+    if !cert.passed {
+        anyhow::bail!("Verification failed");
+    }
+    Ok(())
+}
+
+fn check_seal(cert: &mut Certificate, seal_path: &Path, records: usize) {
+    let seal: Seal = match fs::read_to_string(seal_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| Ok(serde_json::from_str(&text)?))
+    {
+        Ok(seal) => seal,
+        Err(e) => {
+            cert.flag(format!("{}: no usable seal ({})", seal_path.display(), e), false);
+            return;
+        }
+    };
+
+    cert.config_fingerprint = Some(seal.config_fingerprint.clone());
+    if fingerprint(&seal.config) != seal.config_fingerprint {
+        cert.flag("seal: config does not match its fingerprint".to_string(), true);
+    }
+    if seal.manifest_sha256 != cert.manifest_sha256 || seal.records != records {
+        cert.flag("seal: manifest changed after the run was sealed".to_string(), true);
+    }
+
+    let status = match (&seal.signature, signing_key()) {
+        (None, _) => SignatureStatus::Unsigned,
+        (Some(_), None) => SignatureStatus::Unchecked,
+        (Some(signature), Some(key)) if *signature == sign(&key, &seal.config_fingerprint, &seal.manifest_sha256) => {
+            SignatureStatus::Valid
+        }
+        (Some(_), Some(_)) => SignatureStatus::Invalid,
+    };
+    match status {
+        SignatureStatus::Valid => {}
+        SignatureStatus::Invalid => cert.flag("seal: signature does not match".to_string(), true),
+        SignatureStatus::Unsigned => cert.flag("seal: not signed".to_string(), false),
+        SignatureStatus::Unchecked => {
+            cert.flag(format!("seal: signature not checked ({} is not set)", SIGNING_KEY_VAR), false)
+        }
+    }
+    cert.signature = Some(status);
+}
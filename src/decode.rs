@@ -0,0 +1,168 @@
+//! Image decoding shared by extraction, calibration and previews
+//!
+//! Everything that feeds pixels to the detector goes through [`open`], so
+//! decode-time normalization (alpha flattening and, with `--enhance-scans`,
+//! scan clean-up) is applied the same way whether an image is being extracted,
+//! timed for a plan or previewed.
+
+use anyhow::{Context, Result};
+use image::{ColorType, DynamicImage, Rgb, RgbImage};
+use imageproc::contrast::stretch_contrast;
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::stats::{histogram, percentile};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{decoders, Args};
+
+/// An opaque RGB color given as `#rrggbb`, `rrggbb` or a basic color name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub [u8; 3]);
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let named = match s.to_ascii_lowercase().as_str() {
+            "white" => Some([255, 255, 255]),
+            "black" => Some([0, 0, 0]),
+            "gray" | "grey" => Some([128, 128, 128]),
+            _ => None,
+        };
+        if let Some(rgb) = named {
+            return Ok(Color(rgb));
+        }
+
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("'{}' is not a color (use #rrggbb, white, black or gray)", s));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+        Ok(Color([channel(0), channel(2), channel(4)]))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+/// Sources with this many gray levels or fewer are treated as bilevel/dithered
+const MAX_BILEVEL_LEVELS: usize = 4;
+
+/// Sources whose 1st-99th percentile luma range is narrower than this get stretched
+const LOW_CONTRAST_RANGE: u8 = 160;
+
+/// Mean per-pixel channel spread below which an RGB image is really grayscale
+const GRAY_CHANNEL_SPREAD: f64 = 3.0;
+
+/// Decode `path` and apply the decode-time normalization configured in `args`
+pub fn open(path: &Path, args: &Args) -> Result<DynamicImage> {
+    let image = open_raw(path, args)?;
+    if args.enhance_scans {
+        if let Some(enhanced) = enhance_scan(&image) {
+            return Ok(enhanced);
+        }
+    }
+    Ok(image)
+}
+
+/// Decode `path` with alpha flattening only, leaving scan enhancement to the caller
+pub fn open_raw(path: &Path, args: &Args) -> Result<DynamicImage> {
+    let decoders = decoders::registry().read().unwrap_or_else(|e| e.into_inner());
+    let image = decoders.decode(path).context("Failed to open image")?;
+    Ok(flatten_alpha(image, args.alpha_background))
+}
+
+/// Clean up a scanned-archive image, or `None` when `image` does not look like one
+///
+/// Only grayscale sources are touched (including RGB files holding gray
+/// pixels). They are reduced to 8-bit luma; bilevel or dithered scans are
+/// blurred back into continuous tone, and a narrow tonal range is stretched to
+/// the full 0-255 scale.
+pub fn enhance_scan(image: &DynamicImage) -> Option<DynamicImage> {
+    if !is_grayscale(image) {
+        return None;
+    }
+
+    let mut gray = image.to_luma8();
+    let mut changed = !matches!(image.color(), ColorType::L8);
+
+    let levels = histogram(&gray).channels[0].iter().filter(|&&n| n > 0).count();
+    if levels <= MAX_BILEVEL_LEVELS {
+        gray = gaussian_blur_f32(&gray, 1.2);
+        changed = true;
+    }
+
+    let (lo, hi) = (percentile(&gray, 1), percentile(&gray, 99));
+    if hi > lo && hi - lo < LOW_CONTRAST_RANGE {
+        gray = stretch_contrast(&gray, lo, hi);
+        changed = true;
+    }
+
+    changed.then_some(DynamicImage::ImageLuma8(gray))
+}
+
+fn is_grayscale(image: &DynamicImage) -> bool {
+    match image.color() {
+        ColorType::L8 | ColorType::L16 | ColorType::La8 | ColorType::La16 => true,
+        ColorType::Rgb8 | ColorType::Rgba8 | ColorType::Rgb16 | ColorType::Rgba16 => {
+            mean_channel_spread(&image.to_rgb8()) < GRAY_CHANNEL_SPREAD
+        }
+        _ => false,
+    }
+}
+
+/// Average of max-min across the RGB channels, sampled on a sparse grid
+fn mean_channel_spread(rgb: &RgbImage) -> f64 {
+    let step = ((rgb.width() * rgb.height()) as f64 / 10_000.0).sqrt().max(1.0) as usize;
+    let mut total = 0u64;
+    let mut samples = 0u64;
+    for y in (0..rgb.height()).step_by(step) {
+        for x in (0..rgb.width()).step_by(step) {
+            let [r, g, b] = rgb.get_pixel(x, y).0;
+            total += (r.max(g).max(b) - r.min(g).min(b)) as u64;
+            samples += 1;
+        }
+    }
+    total as f64 / samples.max(1) as f64
+}
+
+/// Composite transparent pixels over `background`; opaque images pass through untouched
+///
+/// Transparent regions otherwise keep whatever color values the encoder left
+/// behind, which is often black and turns cut-out faces into dark blobs after
+/// luma conversion.
+fn flatten_alpha(image: DynamicImage, background: Color) -> DynamicImage {
+    if !image.color().has_alpha() {
+        return image;
+    }
+
+    let rgba = image.to_rgba8();
+    let [br, bg, bb] = background.0.map(u32::from);
+    let flat = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0.map(u32::from);
+        let blend = |c: u32, back: u32| ((c * a + back * (255 - a) + 127) / 255) as u8;
+        Rgb([blend(r, br), blend(g, bg), blend(b, bb)])
+    });
+    DynamicImage::ImageRgb8(flat)
+}
@@ -0,0 +1,179 @@
+//! Image decoders picked by file content rather than by file name
+//!
+//! Scraped corpora are full of misnamed files (PNG bytes saved as `.jpg`,
+//! JPEGs without any extension), so inputs are matched against a registry by
+//! their leading bytes. Each [`ImageDecoder`] says which signatures it reads.
+//! A new format is added by implementing the trait and registering it in
+//! `Registry::default`; decoders registered later are tried before earlier
+//! ones, so they can also take over a built-in format.
+//!
+//! JPEG, PNG, BMP, WebP and TIFF are always available. AVIF (`--features avif`,
+//! through libdav1d) and HEIC (`--features heic`, through libheif) link native
+//! libraries, so they are opt-in.
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageFormat};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use crate::{archive, cloud};
+
+/// Leading bytes read to recognize a format
+pub const SNIFF_LEN: usize = 16;
+
+pub trait ImageDecoder: Send + Sync {
+    /// Short format name for messages, e.g. `png`
+    fn name(&self) -> &'static str;
+
+    /// Whether a file starting with `header` (up to `SNIFF_LEN` bytes) is in this format
+    fn sniff(&self, header: &[u8]) -> bool;
+
+    fn decode(&self, bytes: &[u8]) -> Result<DynamicImage>;
+}
+
+/// A format the `image` crate decodes, recognized by fixed magic bytes at a fixed offset
+struct Builtin {
+    name: &'static str,
+    format: ImageFormat,
+    magic: &'static [u8],
+    offset: usize,
+}
+
+impl ImageDecoder for Builtin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        header.get(self.offset..).is_some_and(|rest| rest.starts_with(self.magic))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DynamicImage> {
+        Ok(image::load_from_memory_with_format(bytes, self.format)?)
+    }
+}
+
+pub struct Registry {
+    decoders: Vec<Box<dyn ImageDecoder>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry { decoders: Vec::new() };
+        for (name, format, magic, offset) in [
+            ("bmp", ImageFormat::Bmp, &b"BM"[..], 0),
+            ("png", ImageFormat::Png, &b"\x89PNG\r\n\x1a\n"[..], 0),
+            ("jpeg", ImageFormat::Jpeg, &b"\xff\xd8\xff"[..], 0),
+            // RIFF container; other RIFF types (AVI, WAV) differ at byte 8
+            ("webp", ImageFormat::WebP, &b"WEBP"[..], 8),
+            ("tiff", ImageFormat::Tiff, &b"II*\0"[..], 0),
+            ("tiff", ImageFormat::Tiff, &b"MM\0*"[..], 0),
+        ] {
+            registry.register(Builtin { name, format, magic, offset });
+        }
+        #[cfg(feature = "avif")]
+        for brand in [&b"avif"[..], &b"avis"[..]] {
+            registry.register(Builtin { name: "avif", format: ImageFormat::Avif, magic: brand, offset: 8 });
+        }
+        #[cfg(feature = "heic")]
+        registry.register(heic::Heic);
+        registry
+    }
+}
+
+impl Registry {
+    /// Add a decoder, ahead of every decoder registered before it
+    fn register(&mut self, decoder: impl ImageDecoder + 'static) {
+        self.decoders.insert(0, Box::new(decoder));
+    }
+
+    /// The decoder for a file starting with `header`
+    pub fn find(&self, header: &[u8]) -> Option<&dyn ImageDecoder> {
+        self.decoders.iter().find(|d| d.sniff(header)).map(|d| d.as_ref())
+    }
+
+    /// Whether some decoder reads the file at `path`
+    pub fn recognizes(&self, path: &Path) -> bool {
+        read_header(path).is_ok_and(|header| self.find(&header).is_some())
+    }
+
+    /// Decode `path` with the decoder its content calls for, whatever its extension
+    pub fn decode(&self, path: &Path) -> Result<DynamicImage> {
+        let bytes = read_input(path).context("Failed to read image")?;
+        self.decode_bytes(&bytes)
+    }
+
+    /// Decode an image already in memory
+    pub fn decode_bytes(&self, bytes: &[u8]) -> Result<DynamicImage> {
+        let decoder = self
+            .find(&bytes[..bytes.len().min(SNIFF_LEN)])
+            .context("Unrecognized image format")?;
+        decoder
+            .decode(bytes)
+            .with_context(|| format!("Failed to decode {} data", decoder.name()))
+    }
+}
+
+/// The process-wide registry every input goes through
+pub fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(Registry::default()))
+}
+
+/// Bytes of an input: a file, an archive member or a cloud object
+pub fn read_input(path: &Path) -> Result<Vec<u8>> {
+    if cloud::is_uri(path) {
+        cloud::read(path)
+    } else {
+        archive::read(path)
+    }
+}
+
+fn read_header(path: &Path) -> Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?.take(SNIFF_LEN as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// HEIC/HEIF stills (iPhone photos), decoded through libheif
+#[cfg(feature = "heic")]
+mod heic {
+    use anyhow::{Context, Result};
+    use image::{DynamicImage, RgbImage};
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    use super::ImageDecoder;
+
+    /// ISO-BMFF brands of HEIF stills, found at byte 8 after the `ftyp` box header
+    const BRANDS: [&[u8]; 6] = [b"heic", b"heix", b"heim", b"heis", b"mif1", b"msf1"];
+
+    pub struct Heic;
+
+    impl ImageDecoder for Heic {
+        fn name(&self) -> &'static str {
+            "heic"
+        }
+
+        fn sniff(&self, header: &[u8]) -> bool {
+            header.get(4..8) == Some(b"ftyp") && header.get(8..12).is_some_and(|brand| BRANDS.contains(&brand))
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<DynamicImage> {
+            let context = HeifContext::read_from_bytes(bytes)?;
+            let handle = context.primary_image_handle()?;
+            let image = LibHeif::new().decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)?;
+            let plane = image.planes().interleaved.context("HEIC image has no interleaved RGB plane")?;
+
+            // Rows may be padded beyond width * 3 bytes
+            let (width, height) = (plane.width, plane.height);
+            let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+            for row in plane.data.chunks(plane.stride).take(height as usize) {
+                pixels.extend_from_slice(&row[..width as usize * 3]);
+            }
+            let rgb = RgbImage::from_raw(width, height, pixels).context("HEIC plane is smaller than its dimensions")?;
+            Ok(DynamicImage::ImageRgb8(rgb))
+        }
+    }
+}
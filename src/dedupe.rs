@@ -0,0 +1,114 @@
+//! `dedupe` subcommand: remove near-duplicate faces from a finished run
+//!
+//! The after-the-fact counterpart of `--dedup`. Every face in the manifest is
+//! hashed from its saved crop (the face box within it, when the manifest
+//! records one), and faces within `--dedup-distance` of a better one are
+//! dropped: crop files, sidecars and manifest rows of every variant and size.
+//! Faces are ranked by quality score, then detector score, so the sharpest
+//! copy of each repeat survives. `--dry-run` only lists what would go.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::crop::FaceBox;
+use crate::custody::SEAL_FILE;
+use crate::manifest::{self, ManifestRecord, MANIFEST_FILE};
+use crate::phash;
+use crate::{say, say_err};
+
+/// A face is every row cut from the same detection
+type FaceKey = (PathBuf, Option<usize>, usize);
+
+pub fn run(output_dir: &Path, max_distance: u32, dry_run: bool) -> Result<()> {
+    let manifest_path = output_dir.join(MANIFEST_FILE);
+    let records = manifest::read(&manifest_path)?;
+
+    let mut faces: BTreeMap<FaceKey, Vec<&ManifestRecord>> = BTreeMap::new();
+    for record in &records {
+        faces.entry(key(record)).or_default().push(record);
+    }
+
+    // Best face first, so each kept face is the one its repeats are measured against
+    let mut ranked: Vec<(&FaceKey, &Vec<&ManifestRecord>)> = faces.iter().collect();
+    ranked.sort_by(|(_, a), (_, b)| {
+        let rank = |rows: &[&ManifestRecord]| (rows[0].quality.unwrap_or(0.0), rows[0].score);
+        rank(b).partial_cmp(&rank(a)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut kept: Vec<u64> = Vec::new();
+    let mut dropped: HashSet<&FaceKey> = HashSet::new();
+    for (face, rows) in ranked {
+        let hash = match face_hash(output_dir, rows[0]) {
+            Ok(hash) => hash,
+            Err(e) => {
+                say_err!(DedupeUnchecked, rows[0].crop, format!("{:#}", e));
+                continue;
+            }
+        };
+        if kept.iter().any(|&other| phash::distance(hash, other) <= max_distance) {
+            dropped.insert(face);
+        } else {
+            kept.push(hash);
+        }
+    }
+
+    let (gone, remaining): (Vec<&ManifestRecord>, Vec<&ManifestRecord>) =
+        records.iter().partition(|record| dropped.contains(&key(record)));
+    if dry_run {
+        for record in &gone {
+            say!(DedupeCrop, record.crop);
+        }
+        say!(DedupeWouldDrop, dropped.len(), faces.len(), gone.len(), max_distance);
+        return Ok(());
+    }
+    if gone.is_empty() {
+        say!(DedupeNone, faces.len(), max_distance);
+        return Ok(());
+    }
+
+    let remaining: Vec<ManifestRecord> = remaining.into_iter().cloned().collect();
+    manifest::rewrite(&manifest_path, &remaining)?;
+    for record in &gone {
+        let crop = output_dir.join(&record.crop);
+        for path in [crop.with_extension("json"), crop] {
+            if path.exists() {
+                fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+    }
+    say!(DedupeDropped, dropped.len(), faces.len(), gone.len(), max_distance);
+
+    let seal = output_dir.join(SEAL_FILE);
+    if seal.exists() {
+        say!(SealStale, seal.display());
+    }
+    Ok(())
+}
+
+fn key(record: &ManifestRecord) -> FaceKey {
+    (record.source.clone(), record.frame, record.detection_id)
+}
+
+/// Perceptual hash of the face in a saved crop
+fn face_hash(output_dir: &Path, record: &ManifestRecord) -> Result<u64> {
+    let path = output_dir.join(&record.crop);
+    let image = image::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let face = match &record.face_box {
+        Some(face_box) => face_region(&image, face_box),
+        None => image,
+    };
+    Ok(phash::phash(&face))
+}
+
+/// The face box within a crop, matching the tight crop `--dedup` hashes during a run
+fn face_region(image: &DynamicImage, face_box: &FaceBox) -> DynamicImage {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let x = ((face_box.cx - face_box.width / 2.0) * width).clamp(0.0, width - 1.0);
+    let y = ((face_box.cy - face_box.height / 2.0) * height).clamp(0.0, height - 1.0);
+    let w = (face_box.width * width).clamp(1.0, width - x);
+    let h = (face_box.height * height).clamp(1.0, height - y);
+    image.crop_imm(x as u32, y as u32, w as u32, h as u32)
+}
@@ -0,0 +1,100 @@
+//! `detect` subcommand: face boxes for every input image, without cropping
+//!
+//! Decodes and detects exactly as `extract` would with the same flags and
+//! applies the size, aspect and score filters. Each image gets a JSON file
+//! under `detections/` (named like the annotation images, `a_b_photo.json`)
+//! with its size and every kept face's box, score and eye positions, and
+//! `detections.jsonl` collects the same records in one file. No crops are
+//! written, so this is the quick way to annotate originals or to check what a
+//! corpus yields before committing to a full run. `--no-crop` turns a plain
+//! run into this mode.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::detector::{self, BBox};
+use crate::landmarks::{self, Point};
+use crate::{annotations, collect_images, decode_image, detect_image, filter_valid_faces, say, say_err, Args};
+
+pub const DETECTIONS_FILE: &str = "detections.jsonl";
+pub const DETECTIONS_DIR: &str = "detections";
+
+#[derive(Serialize)]
+struct ImageDetections<'a> {
+    source: &'a Path,
+    width: u32,
+    height: u32,
+    faces: Vec<Face>,
+}
+
+#[derive(Serialize)]
+struct Face {
+    bbox: BBox,
+    score: f64,
+    /// Eye centers (viewer's left, viewer's right), from the detector or estimated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eyes: Option<[Point; 2]>,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let paths: Vec<PathBuf> = collect_images(&args.input);
+    if paths.is_empty() {
+        anyhow::bail!("No images found in {}", args.input.display());
+    }
+    let mut detector = detector::load(args)?;
+
+    fs::create_dir_all(args.output.join(DETECTIONS_DIR)).context("Failed to create detections directory")?;
+    let path = args.output.join(DETECTIONS_FILE);
+    let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    let (mut faces, mut errors) = (0, 0);
+    for source in &paths {
+        let detected = match decode_image(args, source).and_then(|decoded| detect_image(args, detector.as_mut(), decoded)) {
+            Ok(detected) => detected,
+            Err(e) => {
+                say_err!(DetectFailed, source.display(), format!("{:#}", e));
+                errors += 1;
+                continue;
+            }
+        };
+        let kept = filter_valid_faces(args, &detected.faces, &detected.image, detected.min_score);
+        faces += kept.len();
+        let gray = detected.image.to_luma8();
+        let record = ImageDetections {
+            source,
+            width: detected.image.width(),
+            height: detected.image.height(),
+            faces: kept
+                .iter()
+                .map(|face| Face {
+                    bbox: face.bbox,
+                    score: face.score,
+                    eyes: face
+                        .landmarks
+                        .or_else(|| landmarks::estimate_landmarks(&gray, &face.bbox))
+                        .map(|p| [p.left_eye, p.right_eye]),
+                })
+                .collect(),
+        };
+
+        let json = args.output.join(DETECTIONS_DIR).join(format!("{}.json", annotations::flat_name(&args.input, source)));
+        fs::write(&json, serde_json::to_string_pretty(&record)?).with_context(|| format!("Failed to write {}", json.display()))?;
+        serde_json::to_writer(&mut out, &record)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush().with_context(|| format!("Failed to write {}", path.display()))?;
+
+    say!(
+        DetectDone,
+        faces,
+        paths.len() - errors,
+        errors,
+        args.output.join(DETECTIONS_DIR).display(),
+        path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! `detect-one` subcommand: explain what the pipeline makes of a single image
+//!
+//! Decodes and detects exactly as a run would with the same flags, then lists
+//! every raw detection with its score and the verdict of each filter, so "why
+//! was this obvious face skipped?" has a direct answer. Nothing is written to
+//! the output directory. `--save` writes an annotated copy (green kept, red
+//! skipped, numbered like the listing) and `--show` opens it in the system
+//! image viewer.
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, GrayImage, Rgba};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::detector::{self, BBox, Detection};
+use crate::{crop, decode_image, detect_image, exposure_filtered, exposure_rejects, face_checks, landmarks};
+use crate::{pose_filtered, pose_rejects, quality, say, watermark, Args, FaceCheck};
+
+const KEPT: Rgba<u8> = Rgba([0, 220, 0, 255]);
+const SKIPPED: Rgba<u8> = Rgba([230, 0, 0, 255]);
+
+pub fn run(args: &Args, path: &Path, show: bool, save: Option<&Path>) -> Result<()> {
+    let mut detector = detector::load(args)?;
+    let detected = detect_image(args, detector.as_mut(), decode_image(args, path)?)?;
+    let image = &detected.image;
+    let gray = image.to_luma8();
+
+    say!(
+        InspectHeader,
+        path.display(),
+        image.width(),
+        image.height(),
+        format!("{:?}", args.backend),
+        detected.faces.len()
+    );
+    if detected.before.is_some() {
+        say!(InspectEnhanced);
+    }
+
+    let mut verdicts = Vec::new();
+    for (i, face) in detected.faces.iter().enumerate() {
+        let bbox = &face.bbox;
+        say!(InspectFace, i + 1, bbox.x, bbox.y, bbox.width, bbox.height, format!("{:.2}", face.score));
+
+        let checks = all_checks(args, face, image, &gray, detected.min_score);
+        for check in &checks {
+            if check.passed {
+                say!(InspectCheckPassed, check.detail);
+            } else {
+                say!(InspectCheckFailed, check.detail);
+            }
+        }
+        let kept = checks.iter().all(|check| check.passed);
+        if kept {
+            say!(InspectKept);
+        } else {
+            say!(InspectSkipped);
+        }
+        verdicts.push(kept);
+    }
+
+    let kept = verdicts.iter().filter(|&&kept| kept).count();
+    say!(InspectSummary, kept, verdicts.len());
+
+    if !show && save.is_none() {
+        return Ok(());
+    }
+    let labels: Vec<(BBox, bool, String)> = detected
+        .faces
+        .iter()
+        .zip(&verdicts)
+        .enumerate()
+        .map(|(i, (face, &kept))| (face.bbox, kept, (i + 1).to_string()))
+        .collect();
+    let annotated = annotate(image, &labels);
+    let target = match save {
+        Some(path) => path.to_path_buf(),
+        None => preview_path(path),
+    };
+    annotated
+        .save(&target)
+        .with_context(|| format!("Failed to save {}", target.display()))?;
+    say!(InspectAnnotated, target.display());
+    if show {
+        open_viewer(&target)?;
+    }
+    Ok(())
+}
+
+/// Every check a detection must pass: validity, then the exposure and pose filters when set
+pub fn all_checks(args: &Args, face: &Detection, image: &DynamicImage, gray: &GrayImage, min_score: f64) -> Vec<FaceCheck> {
+    let bbox = &face.bbox;
+    let mut checks: Vec<FaceCheck> = face_checks(args, face, image, min_score).into();
+    let face_crop = crop::padded_crop(image, bbox, 0);
+    if exposure_filtered(args) {
+        let exposure = quality::exposure(&face_crop);
+        checks.push(FaceCheck {
+            reason: "exposure",
+            passed: !exposure_rejects(args, &face_crop),
+            detail: format!("brightness {:.0}, contrast {:.0}", exposure.brightness, exposure.contrast),
+        });
+    }
+    let points = face.landmarks.or_else(|| landmarks::estimate_landmarks(gray, bbox));
+    let pose = points.map(|p| landmarks::head_pose(&p, bbox, face.landmarks.is_some()));
+    if pose_filtered(args) {
+        checks.push(FaceCheck {
+            reason: "pose",
+            passed: !pose_rejects(args, pose),
+            detail: match pose {
+                Some(pose) => format!("yaw {:.0}°, pitch {:.0}°, roll {:.0}°", pose.yaw, pose.pitch, pose.roll),
+                None => "no landmarks to estimate the head pose from".to_string(),
+            },
+        });
+    }
+    checks
+}
+
+/// Boxes in green (kept) or red (skipped), each with its label in the top-left corner
+pub fn annotate(image: &DynamicImage, labels: &[(BBox, bool, String)]) -> DynamicImage {
+    let mut canvas = image.to_rgba8();
+    let scale = (canvas.width().max(canvas.height()) / 400).max(1);
+    for (bbox, kept, label) in labels {
+        let color = if *kept { KEPT } else { SKIPPED };
+        for inset in 0..2 {
+            let width = bbox.width.saturating_sub(2 * inset as u32).max(1);
+            let height = bbox.height.saturating_sub(2 * inset as u32).max(1);
+            draw_hollow_rect_mut(&mut canvas, Rect::at(bbox.x + inset, bbox.y + inset).of_size(width, height), color);
+        }
+        let (x, y) = (bbox.x.max(0) as u32 + 3, bbox.y.max(0) as u32 + 3);
+        watermark::draw_line(&mut canvas, label, x, y, scale);
+    }
+    // RGB so the copy can be saved as JPEG too
+    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+}
+
+/// Without `--save`, the preview goes to the temp directory as PNG
+fn preview_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    env::temp_dir().join(format!("detect-one_{}.png", stem))
+}
+
+/// Hand `path` to the platform's default image viewer
+fn open_viewer(path: &Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .spawn()
+        .with_context(|| format!("Failed to open a viewer for {}; the image is saved there", path.display()))?;
+    Ok(())
+}
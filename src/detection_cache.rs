@@ -0,0 +1,143 @@
+//! Detection cache (`--detection-cache`)
+//!
+//! Detection is by far the slowest stage, yet tuning a dataset usually means
+//! rerunning the same corpus with other filters, padding or framing. With a
+//! cache file, every detection result is stored under a hash of the image's
+//! pixels and the detector settings, so a rerun that only changes what
+//! happens after detection skips the detector for every image seen before.
+//! Changing the backend, model, thresholds or anything else the detector
+//! sees gives new keys, so stale results are never reused. The cache is a
+//! `redb` database that can be shared by any number of output directories.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use redb::{Database, TableDefinition};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::detector::{Detection, FaceDetectorBackend};
+use crate::Args;
+
+/// Detection results by key, as JSON
+const DETECTIONS: TableDefinition<&[u8], &str> = TableDefinition::new("detections");
+
+/// One database per process, shared by the detectors of all workers
+static DB: Mutex<Option<Arc<Database>>> = Mutex::new(None);
+
+static HITS: AtomicUsize = AtomicUsize::new(0);
+static MISSES: AtomicUsize = AtomicUsize::new(0);
+
+/// What the detector returned for one image
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    min_score: f64,
+    faces: Vec<Detection>,
+}
+
+/// Serves detections of the wrapped backend from the cache, storing the ones it has to compute
+pub struct Cached {
+    inner: Box<dyn FaceDetectorBackend>,
+    db: Arc<Database>,
+    /// Detector settings, hashed into every key
+    settings: String,
+    /// Score floor of the last image, when it came from the cache
+    cached_min_score: Option<f64>,
+}
+
+impl Cached {
+    pub fn open(path: &Path, args: &Args, inner: Box<dyn FaceDetectorBackend>) -> Result<Self> {
+        let mut shared = DB.lock().unwrap_or_else(|e| e.into_inner());
+        let db = match &*shared {
+            Some(db) => db.clone(),
+            None => {
+                let db = Arc::new(
+                    Database::create(path)
+                        .with_context(|| format!("Failed to open detection cache {}", path.display()))?,
+                );
+                *shared = Some(db.clone());
+                db
+            }
+        };
+        Ok(Cached { inner, db, settings: settings(args)?, cached_min_score: None })
+    }
+
+    fn key(&self, image: &DynamicImage) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.settings.as_bytes());
+        hasher.update(image.width().to_le_bytes());
+        hasher.update(image.height().to_le_bytes());
+        hasher.update(format!("{:?}", image.color()).as_bytes());
+        hasher.update(image.as_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    fn lookup(&self, key: &[u8]) -> Result<Option<Entry>> {
+        let txn = self.db.begin_read()?;
+        let table = match txn.open_table(DETECTIONS) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // An entry that no longer parses is recomputed and overwritten
+        Ok(table.get(key)?.and_then(|json| serde_json::from_str(json.value()).ok()))
+    }
+
+    fn store(&self, key: &[u8], entry: &Entry) -> Result<()> {
+        let json = serde_json::to_string(entry)?;
+        let txn = self.db.begin_write()?;
+        txn.open_table(DETECTIONS)?.insert(key, json.as_str())?;
+        txn.commit().context("Failed to write detection cache")
+    }
+}
+
+impl FaceDetectorBackend for Cached {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        let key = self.key(image);
+        if let Some(entry) = self.lookup(&key)? {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            self.cached_min_score = Some(entry.min_score);
+            return Ok(entry.faces);
+        }
+        MISSES.fetch_add(1, Ordering::Relaxed);
+        self.cached_min_score = None;
+        let faces = self.inner.detect(image)?;
+        self.store(&key, &Entry { min_score: self.inner.min_score(), faces: faces.clone() })?;
+        Ok(faces)
+    }
+
+    fn min_score(&self) -> f64 {
+        self.cached_min_score.unwrap_or_else(|| self.inner.min_score())
+    }
+}
+
+/// Every setting that changes what the detector reports
+fn settings(args: &Args) -> Result<String> {
+    Ok(serde_json::to_string(&serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "backend": args.backend,
+        "model": args.model,
+        "yolo_confidence": args.yolo_confidence,
+        "threshold": args.threshold,
+        "min_face_size": args.min_face_size,
+        "pyramid_scale": args.pyramid_scale,
+        "window_step": args.window_step,
+        "small_backend": args.small_backend,
+        "small_model": args.small_model,
+        "small_image_side": args.small_image_side,
+        "prescreen": args.prescreen,
+        "max_detect_dim": args.max_detect_dim,
+        "nms_iou": args.nms_iou,
+        "nms_merge": args.nms_merge,
+        "ensemble": args.ensemble,
+        "ensemble_votes": args.ensemble_votes,
+        "ensemble_iou": args.ensemble_iou,
+    }))?)
+}
+
+/// Cache hits and misses so far in this run
+pub fn counts() -> (usize, usize) {
+    (HITS.load(Ordering::Relaxed), MISSES.load(Ordering::Relaxed))
+}
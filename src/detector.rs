@@ -0,0 +1,388 @@
+//! Face detector backends
+//!
+//! `--backend rustface` (the default) runs the SeetaFace cascade on grayscale
+//! frames. `--backend yolov8` runs the YOLOv8-face ONNX model natively and needs
+//! a build with `--features yolov8`. Every backend implements
+//! [`FaceDetectorBackend`] and reports plain [`Detection`]s, so the pipeline never
+//! sees backend-specific types; a new detector only needs an impl and a
+//! [`Backend`] variant wired up in [`load`].
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use rustface::ImageData;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::detection_cache::Cached;
+use crate::ensemble::Ensemble;
+use crate::landmarks::Point;
+use crate::timeout::Timed;
+use crate::Args;
+
+/// Which detector implementation to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// SeetaFace cascade (`model.bin`), CPU-only and fast
+    #[default]
+    Rustface,
+    /// YOLOv8-face ONNX model, more robust to pose and occlusion
+    Yolov8,
+}
+
+impl Backend {
+    /// Map a raw score in this backend's units to 0-1
+    pub fn confidence(self, score: f64) -> f64 {
+        match self {
+            Backend::Rustface => (score / RUSTFACE_FULL_SCORE).clamp(0.0, 1.0),
+            Backend::Yolov8 => score,
+        }
+    }
+
+    /// Raw score in this backend's units for a 0-1 `confidence`; the inverse of [`Backend::confidence`]
+    pub fn score(self, confidence: f64) -> f64 {
+        match self {
+            Backend::Rustface => confidence * RUSTFACE_FULL_SCORE,
+            Backend::Yolov8 => confidence,
+        }
+    }
+}
+
+/// Face bounding box in source-image pixel coordinates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<&rustface::Rectangle> for BBox {
+    fn from(rect: &rustface::Rectangle) -> Self {
+        BBox {
+            x: rect.x(),
+            y: rect.y(),
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+impl BBox {
+    /// Intersection over union of two boxes
+    pub fn iou(&self, other: &BBox) -> f32 {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = (self.x + self.width as i32).min(other.x + other.width as i32);
+        let y1 = (self.y + self.height as i32).min(other.y + other.height as i32);
+        if x1 <= x0 || y1 <= y0 {
+            return 0.0;
+        }
+        let inter = ((x1 - x0) * (y1 - y0)) as f32;
+        let union = (self.width * self.height + other.width * other.height) as f32 - inter;
+        inter / union
+    }
+}
+
+/// Five-point facial keypoints in source-image coordinates, eyes ordered left to right in the image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Landmarks {
+    pub left_eye: Point,
+    pub right_eye: Point,
+    pub nose: Point,
+    pub mouth_left: Point,
+    pub mouth_right: Point,
+}
+
+/// One detected face
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detection {
+    pub bbox: BBox,
+    /// Raw score in the backend's own units (recorded in the manifest)
+    pub score: f64,
+    /// Score mapped to 0-1 so backends can be compared
+    pub confidence: f64,
+    /// Keypoints, for backends that predict them
+    pub landmarks: Option<Landmarks>,
+}
+
+pub trait FaceDetectorBackend {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>>;
+
+    /// Lowest raw score `filter_valid_faces` accepts for the image last passed to `detect`
+    fn min_score(&self) -> f64;
+}
+
+/// Side of the blank image run through a freshly loaded detector
+const WARM_UP_SIDE: u32 = 64;
+
+/// Load the backend selected in `args` (routed by image size with `--small-backend`, combined
+/// with others by `--ensemble`) and apply its settings
+pub fn load(args: &Args) -> Result<Box<dyn FaceDetectorBackend>> {
+    let backend: Box<dyn FaceDetectorBackend> = match args.per_image_timeout {
+        Some(seconds) => Box::new(Timed::start(args, seconds)?),
+        None => load_uncached(args)?,
+    };
+    Ok(match &args.detection_cache {
+        Some(path) => Box::new(Cached::open(path, args, backend)?),
+        None => backend,
+    })
+}
+
+/// Everything `load` applies except the detection cache and the timeout
+pub fn load_uncached(args: &Args) -> Result<Box<dyn FaceDetectorBackend>> {
+    let backend: Box<dyn FaceDetectorBackend> = match args.small_backend {
+        _ if !args.ensemble.is_empty() => Box::new(Ensemble::load(args)?),
+        Some(small) => Box::new(Router {
+            small: load_backend(&backend_args(args, small, args.small_model.as_deref()))?,
+            large: load_backend(args)?,
+            side: args.small_image_side,
+            small_last: false,
+        }),
+        None => load_backend(args)?,
+    };
+    let backend = match args.prescreen {
+        Some(side) => Box::new(Prescreen { inner: backend, side }),
+        None => backend,
+    };
+    Ok(match args.nms_iou {
+        Some(iou) => Box::new(Suppress { inner: backend, iou, merge: args.nms_merge }),
+        None => backend,
+    })
+}
+
+/// Load and warm up the single backend selected in `args`
+pub fn load_backend(args: &Args) -> Result<Box<dyn FaceDetectorBackend>> {
+    let mut backend: Box<dyn FaceDetectorBackend> = match args.backend {
+        Backend::Rustface => Box::new(RustfaceBackend::load(args)?),
+        #[cfg(feature = "yolov8")]
+        Backend::Yolov8 => Box::new(crate::yolo::YoloV8::load(args)?),
+        #[cfg(not(feature = "yolov8"))]
+        Backend::Yolov8 => {
+            anyhow::bail!("This build has no YOLOv8 support; rebuild with `cargo build --release --features yolov8`")
+        }
+    };
+
+    // One dummy inference pages in the weights and sizes the buffers, so the first real image isn't slow
+    backend
+        .detect(&DynamicImage::new_rgb8(WARM_UP_SIDE, WARM_UP_SIDE))
+        .context("Detector warm-up failed")?;
+    Ok(match args.max_detect_dim {
+        Some(side) => Box::new(Downscale { inner: backend, side }),
+        None => backend,
+    })
+}
+
+/// Copy of `args` selecting `backend`, and `model` if given
+pub fn backend_args(args: &Args, backend: Backend, model: Option<&Path>) -> Args {
+    let mut copy = args.clone();
+    copy.backend = backend;
+    if let Some(model) = model {
+        copy.model = model.to_path_buf();
+    }
+    copy
+}
+
+/// Sends images whose longest side is at most `side` to `small` and all others to `large`
+struct Router {
+    small: Box<dyn FaceDetectorBackend>,
+    large: Box<dyn FaceDetectorBackend>,
+    side: u32,
+    /// Whether the last image went to `small`, which decides the score floor
+    small_last: bool,
+}
+
+impl FaceDetectorBackend for Router {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        self.small_last = image.width().max(image.height()) <= self.side;
+        if self.small_last {
+            self.small.detect(image)
+        } else {
+            self.large.detect(image)
+        }
+    }
+
+    fn min_score(&self) -> f64 {
+        if self.small_last {
+            self.small.min_score()
+        } else {
+            self.large.min_score()
+        }
+    }
+}
+
+/// Runs the wrapped backend on a thumbnail first and only on the full image if that found a face.
+/// Faces too small to survive the downscale are missed: the speed/recall trade of `--prescreen`.
+struct Prescreen {
+    inner: Box<dyn FaceDetectorBackend>,
+    side: u32,
+}
+
+impl FaceDetectorBackend for Prescreen {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        if image.width().max(image.height()) > self.side {
+            let thumbnail = image.resize(self.side, self.side, FilterType::Triangle);
+            if self.inner.detect(&thumbnail)?.is_empty() {
+                return Ok(Vec::new());
+            }
+        }
+        self.inner.detect(image)
+    }
+
+    fn min_score(&self) -> f64 {
+        self.inner.min_score()
+    }
+}
+
+/// Runs the wrapped backend on a copy of large images shrunk to `side` and maps the boxes back
+/// to the original (`--max-detect-dim`). Faces below the detector's minimum size after shrinking are missed.
+struct Downscale {
+    inner: Box<dyn FaceDetectorBackend>,
+    side: u32,
+}
+
+impl FaceDetectorBackend for Downscale {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        if image.width().max(image.height()) <= self.side {
+            return self.inner.detect(image);
+        }
+        let small = image.resize(self.side, self.side, FilterType::Triangle);
+        let sx = image.width() as f32 / small.width() as f32;
+        let sy = image.height() as f32 / small.height() as f32;
+        let mut faces = self.inner.detect(&small)?;
+        for face in &mut faces {
+            let b = face.bbox;
+            face.bbox = BBox {
+                x: (b.x as f32 * sx).round() as i32,
+                y: (b.y as f32 * sy).round() as i32,
+                width: (b.width as f32 * sx).round() as u32,
+                height: (b.height as f32 * sy).round() as u32,
+            };
+            if let Some(points) = &mut face.landmarks {
+                for point in [
+                    &mut points.left_eye,
+                    &mut points.right_eye,
+                    &mut points.nose,
+                    &mut points.mouth_left,
+                    &mut points.mouth_right,
+                ] {
+                    point.x *= sx;
+                    point.y *= sy;
+                }
+            }
+        }
+        Ok(faces)
+    }
+
+    fn min_score(&self) -> f64 {
+        self.inner.min_score()
+    }
+}
+
+/// Non-maximum suppression over the wrapped backend's detections (`--nms-iou`), so one face
+/// reported twice in slightly shifted boxes is only cropped once
+struct Suppress {
+    inner: Box<dyn FaceDetectorBackend>,
+    iou: f32,
+    /// Average each group into one box rather than keeping its best member's box
+    merge: bool,
+}
+
+impl FaceDetectorBackend for Suppress {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        Ok(suppress(self.inner.detect(image)?, self.iou, self.merge))
+    }
+
+    fn min_score(&self) -> f64 {
+        self.inner.min_score()
+    }
+}
+
+/// Keep the best of every group of detections overlapping by at least `iou`, best first
+fn suppress(mut faces: Vec<Detection>, iou: f32, merge: bool) -> Vec<Detection> {
+    faces.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Detection> = Vec::new();
+    let mut groups: Vec<Vec<Detection>> = Vec::new();
+    for face in faces {
+        match kept.iter().position(|best| best.bbox.iou(&face.bbox) >= iou) {
+            Some(i) => groups[i].push(face),
+            None => {
+                kept.push(face.clone());
+                groups.push(vec![face]);
+            }
+        }
+    }
+    if merge {
+        for (best, group) in kept.iter_mut().zip(&groups) {
+            best.bbox = merged_box(group);
+        }
+    }
+    kept
+}
+
+/// Score-weighted average of the boxes in `group`
+fn merged_box(group: &[Detection]) -> BBox {
+    let total: f64 = group.iter().map(|face| face.score.max(f64::EPSILON)).sum();
+    let mean = |value: fn(&BBox) -> f64| {
+        group.iter().map(|face| value(&face.bbox) * face.score.max(f64::EPSILON)).sum::<f64>() / total
+    };
+    BBox {
+        x: mean(|b| b.x as f64).round() as i32,
+        y: mean(|b| b.y as f64).round() as i32,
+        width: mean(|b| b.width as f64).round() as u32,
+        height: mean(|b| b.height as f64).round() as u32,
+    }
+}
+
+/// RustFace scores at or above this map to full confidence
+const RUSTFACE_FULL_SCORE: f64 = 10.0;
+
+/// SeetaFace cascade through `rustface`
+pub struct RustfaceBackend {
+    detector: Box<dyn rustface::Detector>,
+    /// Quality floor applied on top of the detector's own threshold (`--rustface-min-score`)
+    min_score: f64,
+}
+
+impl RustfaceBackend {
+    pub fn load(args: &Args) -> Result<Self> {
+        crate::model::verify_checksum(&args.model, args.skip_checksum)
+            .with_context(|| format!("Refusing to load {}", args.model.display()))?;
+        let model_path = args.model.to_str().context("Model path is not valid UTF-8")?;
+        let mut detector = rustface::create_detector(model_path)
+            .context("Failed to load face detection model")?;
+
+        // Configure detector
+        detector.set_min_face_size(args.min_face_size);
+        detector.set_score_thresh(args.threshold);
+        detector.set_pyramid_scale_factor(args.pyramid_scale);
+        detector.set_slide_window_step(args.window_step, args.window_step);
+
+        Ok(RustfaceBackend { detector, min_score: args.rustface_min_score })
+    }
+}
+
+impl FaceDetectorBackend for RustfaceBackend {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        let gray = image.to_luma8();
+        let (width, height) = gray.dimensions();
+        let mut image_data = ImageData::new(&gray, width, height);
+        Ok(self
+            .detector
+            .detect(&mut image_data)
+            .iter()
+            .map(|face| Detection {
+                bbox: BBox::from(face.bbox()),
+                score: face.score(),
+                confidence: Backend::Rustface.confidence(face.score()),
+                landmarks: None,
+            })
+            .collect())
+    }
+
+    fn min_score(&self) -> f64 {
+        self.min_score
+    }
+}
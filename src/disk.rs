@@ -0,0 +1,192 @@
+//! Disk space guard (`--max-output-bytes`, `--min-free-space`)
+//!
+//! Before each image the run checks that the output directory is under its
+//! quota and that the disk holding it keeps `--min-free-space` free. When
+//! either is about to be crossed, the run stops cleanly: the manifest is
+//! already flushed crop by crop, the checkpoint is written and the summary
+//! says how far it got, so `--resume` can pick up once there is room. With
+//! `--on-disk-full pause` it waits instead, checking again every few seconds,
+//! for space to be freed or crops to be moved off.
+//!
+//! The quota counts what the output directory held when the run started plus
+//! the crops written since; free space is read from the filesystem, which
+//! only works on Unix.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+use crate::checkpoint::CheckpointWriter;
+use crate::{say, shutdown};
+
+/// Checking period while paused
+const PAUSE_POLL: Duration = Duration::from_secs(10);
+
+/// A number of bytes, written plain or with a K, M, G or T suffix (powers of 1024)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().trim_end_matches(['B', 'b']);
+        let (number, unit) = match text.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&text[..i], c.to_ascii_uppercase()),
+            _ => (text, ' '),
+        };
+        let scale: u64 = match unit {
+            ' ' => 1,
+            'K' => 1 << 10,
+            'M' => 1 << 20,
+            'G' => 1 << 30,
+            'T' => 1 << 40,
+            _ => return Err(format!("'{}' is not a size (use bytes or a K, M, G or T suffix, e.g. 500G)", s)),
+        };
+        match number.trim().parse::<f64>() {
+            Ok(n) if n >= 0.0 && n.is_finite() => Ok(ByteSize((n * scale as f64) as u64)),
+            _ => Err(format!("'{}' is not a size (use bytes or a K, M, G or T suffix, e.g. 500G)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const UNITS: [(u64, &str); 4] = [(1 << 40, "T"), (1 << 30, "G"), (1 << 20, "M"), (1 << 10, "K")];
+        match UNITS.iter().find(|(scale, _)| self.0 >= *scale) {
+            Some((scale, unit)) if self.0.is_multiple_of(*scale) => write!(f, "{}{}", self.0 / scale, unit),
+            Some((scale, unit)) => write!(f, "{:.1}{}", self.0 as f64 / *scale as f64, unit),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ByteSize> for String {
+    fn from(size: ByteSize) -> Self {
+        size.to_string()
+    }
+}
+
+/// What to do when the output runs out of room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskFull {
+    /// End the run, as if interrupted
+    #[default]
+    Stop,
+    /// Wait until there is room again
+    Pause,
+}
+
+pub struct DiskGuard {
+    output: PathBuf,
+    max_output: Option<u64>,
+    min_free: u64,
+    on_full: DiskFull,
+    /// Bytes in the output directory, counted once and then kept up to date as crops are written
+    used: u64,
+}
+
+impl DiskGuard {
+    pub fn new(output: &Path, max_output: Option<ByteSize>, min_free: ByteSize, on_full: DiskFull) -> Self {
+        let used = if max_output.is_some() { dir_size(output) } else { 0 };
+        DiskGuard { output: output.to_path_buf(), max_output: max_output.map(|s| s.0), min_free: min_free.0, on_full, used }
+    }
+
+    /// Count a file the run wrote into the output
+    pub fn wrote(&mut self, bytes: u64) {
+        self.used += bytes;
+    }
+
+    /// Whether the run must stop for lack of room; with `--on-disk-full pause`, waits for room first
+    pub fn out_of_space(&mut self, checkpoint: Option<&mut CheckpointWriter>, faces: usize) -> bool {
+        let Some(reason) = self.shortage() else {
+            return false;
+        };
+        if self.on_full == DiskFull::Stop {
+            say!(DiskStopped, reason);
+            return true;
+        }
+
+        say!(DiskPaused, reason);
+        if let Some(checkpoint) = checkpoint {
+            if let Err(e) = checkpoint.flush(faces) {
+                eprintln!("⚠️  {:#}", e);
+            }
+        }
+        let paused = Instant::now();
+        loop {
+            thread::sleep(PAUSE_POLL);
+            if shutdown::requested() {
+                return true;
+            }
+            // Crops may have been moved off while waiting
+            if self.max_output.is_some() {
+                self.used = dir_size(&self.output);
+            }
+            if self.shortage().is_none() {
+                break;
+            }
+        }
+        say!(DiskResumed, format!("{}s", paused.elapsed().as_secs()));
+        false
+    }
+
+    /// What would be overrun by going on, if anything
+    fn shortage(&self) -> Option<String> {
+        if let Some(max) = self.max_output.filter(|&max| self.used >= max) {
+            return Some(format!("output holds {} of --max-output-bytes {}", ByteSize(self.used), ByteSize(max)));
+        }
+        match free_bytes(&self.output) {
+            Some(free) if free < self.min_free => {
+                Some(format!("{} free, under --min-free-space {}", ByteSize(free), ByteSize(self.min_free)))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Space available to unprivileged writers on the filesystem holding `path`
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // Field widths differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_path: &Path) -> Option<u64> {
+    None
+}
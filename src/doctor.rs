@@ -0,0 +1,203 @@
+//! `doctor` subcommand: environment self-test for field deployments
+//!
+//! Runs every stage the extractor depends on against a built-in synthetic image
+//! and prints a pass/fail line per check, so a support request can start from
+//! one pasted report instead of a round of back-and-forth questions.
+
+use anyhow::Result;
+use image::{DynamicImage, ImageFormat, Luma};
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_ellipse_mut, draw_filled_rect_mut};
+use imageproc::rect::Rect;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::detector;
+use crate::i18n::{self, Msg};
+use crate::model;
+use crate::{say, Args};
+
+/// Detection passes timed for the throughput baseline
+const BENCH_ROUNDS: u32 = 5;
+
+/// Slower than this per 640x480 frame is reported as a warning
+const SLOW_SECS_PER_IMAGE: f64 = 1.0;
+
+#[derive(Default)]
+struct Report {
+    failures: usize,
+    warnings: usize,
+}
+
+impl Report {
+    fn pass(&mut self, check: &str, detail: impl AsRef<str>) {
+        say!(DoctorPass, format!("{:<14}", check), detail.as_ref());
+    }
+
+    fn warn(&mut self, check: &str, detail: impl AsRef<str>) {
+        self.warnings += 1;
+        say!(DoctorWarn, format!("{:<14}", check), detail.as_ref());
+    }
+
+    fn fail(&mut self, check: &str, detail: impl AsRef<str>) {
+        self.failures += 1;
+        say!(DoctorFail, format!("{:<14}", check), detail.as_ref());
+    }
+
+    fn info(&mut self, check: &str, detail: impl AsRef<str>) {
+        say!(DoctorInfo, format!("{:<14}", check), detail.as_ref());
+    }
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    say!(DoctorHeader, env!("CARGO_PKG_VERSION"));
+    let mut report = Report::default();
+
+    check_model_file(&mut report, &args.model);
+    check_model_checksum(&mut report, args);
+    check_output_dir(&mut report, &args.output);
+    let test_image = check_codecs(&mut report);
+    check_detection(&mut report, args, test_image);
+    report_hardware(&mut report);
+
+    if report.failures == 0 {
+        say!(DoctorPassed, report.warnings);
+        Ok(())
+    } else {
+        anyhow::bail!("{} check(s) failed", report.failures)
+    }
+}
+
+fn check_model_file(report: &mut Report, model: &Path) {
+    match fs::metadata(model) {
+        Ok(meta) if meta.len() > 0 => report.pass("model file", i18n::text(Msg::DoctorModelFile, &[&model.display(), &meta.len()])),
+        Ok(_) => report.fail("model file", i18n::text(Msg::DoctorModelEmpty, &[&model.display()])),
+        Err(e) => report.fail("model file", format!("{}: {}", model.display(), e)),
+    }
+}
+
+fn check_model_checksum(report: &mut Report, args: &Args) {
+    if args.skip_checksum {
+        report.info("model checksum", i18n::text(Msg::DoctorChecksumSkipped, &[]));
+        return;
+    }
+    match model::verify_checksum(&args.model, false) {
+        Ok(()) => report.pass("model checksum", i18n::text(Msg::DoctorChecksumOk, &[])),
+        Err(e) => report.fail("model checksum", e.to_string()),
+    }
+}
+
+fn check_output_dir(report: &mut Report, output: &Path) {
+    let probe = output.join(".doctor_write_test");
+    let result = fs::create_dir_all(output).and_then(|_| fs::write(&probe, b"ok"));
+    let _ = fs::remove_file(&probe);
+    match result {
+        Ok(()) => report.pass("output dir", i18n::text(Msg::DoctorWritable, &[&output.display()])),
+        Err(e) => report.fail("output dir", format!("{}: {}", output.display(), e)),
+    }
+}
+
+/// Round-trip the built-in test image through every bundled encoder/decoder
+fn check_codecs(report: &mut Report) -> DynamicImage {
+    let image = synthetic_face();
+    for format in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::Bmp] {
+        let mut bytes = Vec::new();
+        let roundtrip = image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .and_then(|_| image::load_from_memory_with_format(&bytes, format));
+        match roundtrip {
+            Ok(decoded) if decoded.width() == image.width() => {
+                report.pass("codec", i18n::text(Msg::DoctorCodecOk, &[&format!("{:?}", format)]))
+            }
+            Ok(_) => report.fail("codec", i18n::text(Msg::DoctorCodecDimensions, &[&format!("{:?}", format)])),
+            Err(e) => report.fail("codec", format!("{:?}: {}", format, e)),
+        }
+    }
+    image
+}
+
+fn check_detection(report: &mut Report, args: &Args, image: DynamicImage) {
+    let mut detector = match detector::load(args) {
+        Ok(detector) => {
+            report.pass("model load", i18n::text(Msg::DoctorModelLoaded, &[]));
+            detector
+        }
+        Err(e) => {
+            report.fail("model load", format!("{:#}", e));
+            return;
+        }
+    };
+
+    match detector.detect(&image) {
+        Ok(faces) => report.pass("detection", i18n::text(Msg::DoctorDetected, &[&faces.len()])),
+        Err(e) => {
+            report.fail("detection", format!("{:#}", e));
+            return;
+        }
+    }
+
+    let start = Instant::now();
+    for _ in 0..BENCH_ROUNDS {
+        let _ = detector.detect(&image);
+    }
+    let per_image = start.elapsed().as_secs_f64() / BENCH_ROUNDS as f64;
+    let detail = i18n::text(
+        Msg::DoctorThroughput,
+        &[&format!("{:.1}", per_image * 1000.0), &format!("{:.0}", 3600.0 / per_image.max(1e-6))],
+    );
+    if per_image > SLOW_SECS_PER_IMAGE {
+        report.warn("throughput", detail);
+    } else {
+        report.pass("throughput", detail);
+    }
+}
+
+fn report_hardware(report: &mut Report) {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    report.info("cpu", i18n::text(Msg::DoctorCpu, &[&std::env::consts::OS, &std::env::consts::ARCH, &cores]));
+    report.info("cpu features", cpu_features());
+
+    // Both backends run on the CPU; GPU presence is reported for completeness
+    let gpu = Path::new("/dev/nvidia0").exists() || Path::new("/dev/dri").exists();
+    report.info(
+        "gpu",
+        i18n::text(if gpu { Msg::DoctorGpu } else { Msg::DoctorNoGpu }, &[]),
+    );
+}
+
+fn cpu_features() -> String {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let features = [
+            ("sse2", std::arch::is_x86_feature_detected!("sse2")),
+            ("sse4.1", std::arch::is_x86_feature_detected!("sse4.1")),
+            ("avx", std::arch::is_x86_feature_detected!("avx")),
+            ("avx2", std::arch::is_x86_feature_detected!("avx2")),
+            ("fma", std::arch::is_x86_feature_detected!("fma")),
+        ];
+        features
+            .iter()
+            .map(|(name, on)| format!("{}{}", if *on { "+" } else { "-" }, name))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        format!("{}neon", if std::arch::is_aarch64_feature_detected!("neon") { "+" } else { "-" })
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        "unknown".to_string()
+    }
+}
+
+/// A 640x480 grayscale frame with a simple face-like pattern
+fn synthetic_face() -> DynamicImage {
+    let mut canvas = image::GrayImage::from_pixel(640, 480, Luma([90]));
+    draw_filled_ellipse_mut(&mut canvas, (320, 240), 90, 120, Luma([200]));
+    draw_filled_circle_mut(&mut canvas, (285, 205), 12, Luma([30]));
+    draw_filled_circle_mut(&mut canvas, (355, 205), 12, Luma([30]));
+    draw_filled_rect_mut(&mut canvas, Rect::at(290, 300).of_size(60, 10), Luma([60]));
+    DynamicImage::ImageLuma8(canvas)
+}
@@ -0,0 +1,152 @@
+//! Face recognition embeddings through `tract` (`--embeddings arcface.onnx`)
+//!
+//! Every accepted face is warped onto the 112x112 ArcFace template, whatever
+//! variants are saved, and run through the recognition model. The
+//! L2-normalized vectors are appended to `embeddings.npy` (float32, one row
+//! per face) and each crop's manifest record points at its row, so variants
+//! of the same detection share one row. The `.npy` header is rewritten after
+//! every row, so the file stays loadable if a run is interrupted.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use tract_onnx::prelude::*;
+
+use crate::crop::ARCFACE_SIZE;
+use crate::model;
+
+pub const EMBEDDINGS_FILE: &str = "embeddings.npy";
+
+/// Magic, version and header length followed by the padded header dict
+const NPY_HEADER_LEN: usize = 128;
+const NPY_PREAMBLE: &[u8] = b"\x93NUMPY\x01\x00";
+
+pub struct Embedder {
+    model: TypedRunnableModel<TypedModel>,
+    file: File,
+    rows: usize,
+    dim: Option<usize>,
+}
+
+impl Embedder {
+    /// Load the recognition model and open `embeddings.npy` in `output`, continuing it when `append` is set
+    pub fn load(path: &Path, output: &Path, skip_checksum: bool, append: bool) -> Result<Self> {
+        model::verify_checksum(path, skip_checksum)?;
+        let side = ARCFACE_SIZE as usize;
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|m| m.with_input_fact(0, f32::fact([1, 3, side, side]).into()))
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| format!("Failed to load embedding model {}", path.display()))?;
+
+        let npy = output.join(EMBEDDINGS_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(!append)
+            .open(&npy)
+            .with_context(|| format!("Failed to open {}", npy.display()))?;
+        let (rows, dim) = if append { read_shape(&mut file)? } else { (0, None) };
+
+        // A valid (possibly empty) array from the start
+        let mut embedder = Embedder { model, file, rows, dim };
+        embedder.write_header()?;
+        embedder
+            .infer(&DynamicImage::new_rgb8(ARCFACE_SIZE, ARCFACE_SIZE))
+            .context("Embedding model warm-up failed")?;
+        Ok(embedder)
+    }
+
+    /// Embed the aligned face `face` and return its row in `embeddings.npy`
+    pub fn embed(&mut self, face: &DynamicImage) -> Result<usize> {
+        let vector = self.infer(face)?;
+        match self.dim {
+            Some(dim) => anyhow::ensure!(vector.len() == dim, "Embedding has {} values, expected {}", vector.len(), dim),
+            None => self.dim = Some(vector.len()),
+        }
+        let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&bytes).context("Failed to write embedding")?;
+        let row = self.rows;
+        self.rows += 1;
+        self.write_header()?;
+        Ok(row)
+    }
+
+    /// L2-normalized embedding of `face`
+    fn infer(&self, face: &DynamicImage) -> Result<Vec<f32>> {
+        let rgb = face.to_rgb8();
+        let side = ARCFACE_SIZE as usize;
+        anyhow::ensure!(
+            rgb.dimensions() == (ARCFACE_SIZE, ARCFACE_SIZE),
+            "Embedding input must be {}x{}",
+            ARCFACE_SIZE,
+            ARCFACE_SIZE
+        );
+        // ArcFace exports expect RGB scaled to -1..1
+        let input: Tensor = tract_ndarray::Array4::from_shape_fn((1, 3, side, side), |(_, c, y, x)| {
+            (rgb.get_pixel(x as u32, y as u32)[c] as f32 - 127.5) / 127.5
+        })
+        .into();
+
+        let outputs = self
+            .model
+            .run(tvec!(input.into()))
+            .map_err(|e| anyhow::anyhow!("Embedding inference failed: {}", e))?;
+        let mut vector: Vec<f32> = outputs[0]
+            .to_array_view::<f32>()
+            .map_err(|e| anyhow::anyhow!("Unexpected embedding output: {}", e))?
+            .iter()
+            .copied()
+            .collect();
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > f32::EPSILON {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        Ok(vector)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let dict = format!(
+            "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.rows,
+            self.dim.unwrap_or(0)
+        );
+        let mut header = NPY_PREAMBLE.to_vec();
+        header.extend_from_slice(&((NPY_HEADER_LEN - NPY_PREAMBLE.len() - 2) as u16).to_le_bytes());
+        header.extend_from_slice(dict.as_bytes());
+        header.resize(NPY_HEADER_LEN - 1, b' ');
+        header.push(b'\n');
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header).context("Failed to write embeddings header")?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Rows and width recorded in an existing `embeddings.npy`
+fn read_shape(file: &mut File) -> Result<(usize, Option<usize>)> {
+    let mut header = vec![0; NPY_HEADER_LEN];
+    let read = file.read(&mut header)?;
+    header.truncate(read);
+    if header.is_empty() {
+        return Ok((0, None));
+    }
+    let text = String::from_utf8_lossy(&header);
+    let shape = text
+        .split("'shape': (")
+        .nth(1)
+        .and_then(|rest| rest.split(')').next())
+        .context("embeddings.npy has no shape in its header")?;
+    let mut dims = shape.split(',').map(|d| d.trim().parse::<usize>());
+    match (dims.next(), dims.next()) {
+        (Some(Ok(rows)), Some(Ok(dim))) => Ok((rows, Some(dim).filter(|&d| d > 0))),
+        _ => anyhow::bail!("Unreadable shape ({}) in embeddings.npy", shape),
+    }
+}
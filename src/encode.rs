@@ -0,0 +1,107 @@
+//! Crop file format and compression (`--format`, `--jpeg-quality`, `--webp-quality`)
+//!
+//! JPEG is the default. PNG is lossless, for pipelines sensitive to
+//! compression artifacts. WebP is written lossless unless `--webp-quality` is
+//! given; lossy WebP goes through libwebp and needs a build with
+//! `--features webp-lossy`. Crops are written to a `.tmp` file and renamed
+//! into place, so a crash never leaves a truncated image under the final name.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use crate::{cloud, metadata, Args};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Jpg,
+    Png,
+    Webp,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Reject settings this build cannot honor before any work is done
+pub fn check(args: &Args) -> Result<()> {
+    if args.webp_quality.is_some() && args.format != OutputFormat::Webp {
+        anyhow::bail!("--webp-quality only applies to --format webp");
+    }
+    if args.preserve_metadata && args.format == OutputFormat::Webp {
+        anyhow::bail!("--preserve-metadata only works with --format jpg or png");
+    }
+    if args.webp_quality.is_some() && !cfg!(feature = "webp-lossy") {
+        anyhow::bail!("This build only writes lossless WebP; rebuild with `cargo build --release --features webp-lossy` to use --webp-quality");
+    }
+    Ok(())
+}
+
+/// Save `image` to `path` in the format and quality selected in `args`, with the source's EXIF block if given
+pub fn save(image: &DynamicImage, path: &Path, args: &Args, exif: Option<&[u8]>) -> Result<()> {
+    let mut bytes = encode(image, args.format, args.jpeg_quality, args.webp_quality)?;
+    if let Some(exif) = exif {
+        bytes = metadata::embed(bytes, args.format, exif);
+    }
+    write_atomic(path, &bytes)
+}
+
+/// Suffix of a crop still being written; `verify-output` removes any left behind
+pub const PARTIAL_SUFFIX: &str = ".tmp";
+
+/// Write `bytes` to `path` through a temporary file next to it, renamed once complete, and queue it for a cloud `--output`
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    let partial = PathBuf::from(partial);
+    fs::write(&partial, bytes).with_context(|| format!("Failed to create {}", path.display()))?;
+    fs::rename(&partial, path).with_context(|| format!("Failed to create {}", path.display()))?;
+    cloud::written(path, bytes);
+    Ok(())
+}
+
+/// `image` as the bytes of a `format` file
+pub fn encode(image: &DynamicImage, format: OutputFormat, jpeg_quality: u8, webp_quality: Option<u8>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match format {
+        OutputFormat::Jpg => image.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, jpeg_quality))?,
+        OutputFormat::Png => image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?,
+        OutputFormat::Webp => {
+            // The WebP encoder only takes 8-bit RGB(A)
+            let image = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            image.write_with_encoder(webp_encoder(&mut bytes, webp_quality))?;
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "webp-lossy")]
+fn webp_encoder<W: std::io::Write>(writer: W, quality: Option<u8>) -> WebPEncoder<W> {
+    use image::codecs::webp::WebPQuality;
+    match quality {
+        Some(quality) => WebPEncoder::new_with_quality(writer, WebPQuality::lossy(quality)),
+        None => WebPEncoder::new_lossless(writer),
+    }
+}
+
+#[cfg(not(feature = "webp-lossy"))]
+fn webp_encoder<W: std::io::Write>(writer: W, _quality: Option<u8>) -> WebPEncoder<W> {
+    WebPEncoder::new_lossless(writer)
+}
@@ -0,0 +1,174 @@
+//! Ensemble detection over several backends (`--ensemble`)
+//!
+//! Every image goes through `--backend` and each extra backend listed in
+//! `--ensemble`. Boxes from different backends that overlap by at least
+//! `--ensemble-iou` count as one face, found by each backend that reported
+//! one of them; the most confident box stands for the group. A face is kept
+//! when at least `--ensemble-votes` backends found it: 1 takes the union for
+//! recall, higher values demand agreement for precision. Scores from the
+//! extra backends are converted to `--backend`'s units through their 0-1
+//! confidence, so a single score floor applies to all of them.
+//!
+//! How much each backend contributed is tallied across all detectors of the
+//! run, worker threads included, and printed with the summary.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::detector::{self, Backend, Detection, FaceDetectorBackend};
+use crate::{say, Args};
+
+/// An extra backend from `--ensemble`: `yolov8`, or `yolov8=path/to/model.onnx` for its own model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Member {
+    pub backend: Backend,
+    pub model: Option<PathBuf>,
+}
+
+impl FromStr for Member {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, model) = match s.split_once('=') {
+            Some((name, model)) => (name, Some(PathBuf::from(model))),
+            None => (s, None),
+        };
+        let backend = Backend::from_str(name.trim(), true).map_err(|_| format!("unknown backend '{}'", name))?;
+        Ok(Member { backend, model })
+    }
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.backend.to_possible_value().map_or(String::new(), |v| v.get_name().to_string());
+        match &self.model {
+            Some(model) => write!(f, "{}={}", name, model.display()),
+            None => write!(f, "{}", name),
+        }
+    }
+}
+
+impl TryFrom<String> for Member {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Member> for String {
+    fn from(member: Member) -> Self {
+        member.to_string()
+    }
+}
+
+/// What one backend added to the run
+#[derive(Debug, Default)]
+struct Contribution {
+    /// Boxes it reported
+    detected: usize,
+    /// Faces kept that it found
+    kept: usize,
+    /// Faces kept that only it found
+    alone: usize,
+}
+
+/// Contributions by backend label, shared by every ensemble detector of the run
+static CONTRIBUTIONS: Mutex<BTreeMap<String, Contribution>> = Mutex::new(BTreeMap::new());
+
+/// `--backend` followed by the `--ensemble` members, run on every image
+pub struct Ensemble {
+    /// Units every score is converted to
+    primary: Backend,
+    members: Vec<(String, Backend, Box<dyn FaceDetectorBackend>)>,
+    iou: f32,
+    votes: usize,
+}
+
+impl Ensemble {
+    pub fn load(args: &Args) -> Result<Self> {
+        let primary = Member { backend: args.backend, model: None };
+        let mut members = vec![(primary.to_string(), args.backend, detector::load_backend(args)?)];
+        for member in &args.ensemble {
+            let member_args = detector::backend_args(args, member.backend, member.model.as_deref());
+            let mut label = member.to_string();
+            // The same backend listed twice still gets a line of its own in the report
+            if members.iter().any(|(taken, _, _)| *taken == label) {
+                label = format!("{} #{}", label, members.len() + 1);
+            }
+            members.push((label, member.backend, detector::load_backend(&member_args)?));
+        }
+        Ok(Ensemble { primary: args.backend, members, iou: args.ensemble_iou, votes: args.ensemble_votes })
+    }
+}
+
+impl FaceDetectorBackend for Ensemble {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        let mut found: Vec<(usize, Detection)> = Vec::new();
+        let mut reported = vec![0; self.members.len()];
+        for (i, (_, backend, detector)) in self.members.iter_mut().enumerate() {
+            let faces = detector.detect(image)?;
+            reported[i] = faces.len();
+            for mut face in faces {
+                if *backend != self.primary {
+                    face.score = self.primary.score(face.confidence);
+                }
+                found.push((i, face));
+            }
+        }
+        found.sort_by(|a, b| b.1.confidence.total_cmp(&a.1.confidence));
+
+        // Each group is led by its most confident box and lists the members that reported it
+        let mut groups: Vec<(Detection, Vec<usize>)> = Vec::new();
+        for (member, face) in found {
+            match groups.iter_mut().find(|(best, _)| best.bbox.iou(&face.bbox) >= self.iou) {
+                Some((_, finders)) => {
+                    if !finders.contains(&member) {
+                        finders.push(member);
+                    }
+                }
+                None => groups.push((face, vec![member])),
+            }
+        }
+
+        let mut contributions = CONTRIBUTIONS.lock().unwrap_or_else(|e| e.into_inner());
+        for (i, (label, _, _)) in self.members.iter().enumerate() {
+            let contribution = contributions.entry(label.clone()).or_default();
+            contribution.detected += reported[i];
+            for (_, finders) in groups.iter().filter(|(_, finders)| finders.len() >= self.votes) {
+                if finders.contains(&i) {
+                    contribution.kept += 1;
+                    if finders.len() == 1 {
+                        contribution.alone += 1;
+                    }
+                }
+            }
+        }
+        Ok(groups
+            .into_iter()
+            .filter(|(_, finders)| finders.len() >= self.votes)
+            .map(|(face, _)| face)
+            .collect())
+    }
+
+    fn min_score(&self) -> f64 {
+        self.members[0].2.min_score()
+    }
+}
+
+/// Print what each backend contributed over the run
+pub fn report() {
+    let contributions = CONTRIBUTIONS.lock().unwrap_or_else(|e| e.into_inner());
+    say!(EnsembleHeader);
+    for (label, contribution) in contributions.iter() {
+        say!(EnsembleBackend, label, contribution.detected, contribution.kept, contribution.alone);
+    }
+}
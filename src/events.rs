@@ -0,0 +1,162 @@
+//! Structured progress events for GUIs and orchestration wrappers
+//!
+//! With `--events-socket PATH` the extractor listens on a Unix domain socket and
+//! streams one JSON object per line to every connected client. Unlike the human
+//! oriented stdout text, the event schema is versioned and kept stable.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bumped whenever an existing event changes shape
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    RunStarted {
+        total_images: usize,
+        target_faces: usize,
+    },
+    ImageDone {
+        index: usize,
+        path: &'a Path,
+        faces: usize,
+        total_faces: usize,
+    },
+    ImageFailed {
+        index: usize,
+        path: &'a Path,
+        error: String,
+    },
+    ChunkDone {
+        chunk: usize,
+        chunks: usize,
+        faces: usize,
+    },
+    TargetReached {
+        total_faces: usize,
+    },
+    RunFinished {
+        processed: usize,
+        errors: usize,
+        total_faces: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    ts_ms: u128,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Broadcasts events to connected clients; a disabled sink drops everything
+pub struct EventSink {
+    #[cfg(unix)]
+    inner: Option<unix::Broadcaster>,
+}
+
+impl EventSink {
+    pub fn disabled() -> Self {
+        EventSink {
+            #[cfg(unix)]
+            inner: None,
+        }
+    }
+
+    /// Listen on a Unix domain socket at `path` (replacing a stale socket file)
+    #[cfg(unix)]
+    pub fn bind(path: &Path) -> Result<Self> {
+        Ok(EventSink {
+            inner: Some(unix::Broadcaster::bind(path)?),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(path: &Path) -> Result<Self> {
+        anyhow::bail!("--events-socket {} is only supported on Unix platforms", path.display())
+    }
+
+    pub fn emit(&self, event: Event) {
+        #[cfg(unix)]
+        if let Some(inner) = &self.inner {
+            let ts_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis());
+            let envelope = Envelope {
+                version: SCHEMA_VERSION,
+                ts_ms,
+                event: &event,
+            };
+            if let Ok(mut line) = serde_json::to_string(&envelope) {
+                line.push('\n');
+                inner.send(line.as_bytes());
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = event;
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use anyhow::{Context, Result};
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A client that stops reading is dropped rather than stalling extraction
+    const WRITE_TIMEOUT: Duration = Duration::from_millis(250);
+
+    pub struct Broadcaster {
+        path: PathBuf,
+        clients: Arc<Mutex<Vec<UnixStream>>>,
+    }
+
+    impl Broadcaster {
+        pub fn bind(path: &Path) -> Result<Self> {
+            if path.exists() {
+                fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+            }
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Failed to listen on {}", path.display()))?;
+
+            let clients = Arc::new(Mutex::new(Vec::new()));
+            let accepted = Arc::clone(&clients);
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                    if let Ok(mut clients) = accepted.lock() {
+                        clients.push(stream);
+                    }
+                }
+            });
+
+            Ok(Broadcaster {
+                path: path.to_path_buf(),
+                clients,
+            })
+        }
+
+        /// Write to every client, dropping the ones that have gone away
+        pub fn send(&self, bytes: &[u8]) {
+            if let Ok(mut clients) = self.clients.lock() {
+                clients.retain_mut(|client| client.write_all(bytes).is_ok());
+            }
+        }
+    }
+
+    impl Drop for Broadcaster {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
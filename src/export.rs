@@ -0,0 +1,139 @@
+//! `export` subcommand: license-aware release archives
+//!
+//! Picks crops from a finished run with `--where` (the predicate language of
+//! `tag`, e.g. `license in ('cc-by', 'internal')`) and packs them into one
+//! gzipped tar per value of `--split-by`, the `license` tag by default. Each
+//! archive carries its own `manifest.jsonl`, so one collected pool can feed
+//! differently licensed releases. Rows without a value go to
+//! `unspecified.tar.gz`.
+//!
+//! `--split-by` takes several fields or tag axes (`source,cond`), giving one
+//! group per combination of values, and `--layout folders` writes the groups
+//! as nested directories (`cctv/night/`) instead of archives.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::Path;
+
+use crate::manifest::{self, ManifestRecord, MANIFEST_FILE};
+use crate::say;
+use crate::tag::{self, Predicate};
+
+const UNSPECIFIED: &str = "unspecified";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportLayout {
+    /// One `<group>.tar.gz` per group
+    Archives,
+    /// One directory per group, nested by `--split-by` axis
+    Folders,
+}
+
+pub fn run(from: &Path, to: &Path, filter: Option<&str>, split_by: &[String], layout: ExportLayout) -> Result<()> {
+    let records = manifest::read(&from.join(MANIFEST_FILE))?;
+    let predicate = filter.map(Predicate::parse).transpose()?.unwrap_or_default();
+
+    let mut groups: BTreeMap<Vec<String>, Vec<&ManifestRecord>> = BTreeMap::new();
+    for record in &records {
+        let row = serde_json::to_value(record).context("Failed to serialize manifest record")?;
+        if !predicate.matches(&row) {
+            continue;
+        }
+        let group = split_by
+            .iter()
+            .map(|axis| match tag::lookup(&row, axis) {
+                None | Some(Value::Null) => UNSPECIFIED.to_string(),
+                Some(Value::String(s)) => file_name(s),
+                Some(other) => file_name(&other.to_string()),
+            })
+            .collect();
+        groups.entry(group).or_default().push(record);
+    }
+    if groups.is_empty() {
+        anyhow::bail!("No rows of {} match the filter", from.join(MANIFEST_FILE).display());
+    }
+
+    fs::create_dir_all(to).context("Failed to create export directory")?;
+    let mut exported = 0;
+    for (group, rows) in &groups {
+        let values: Vec<String> = split_by.iter().zip(group).map(|(axis, value)| format!("{}={}", axis, value)).collect();
+        let path = match layout {
+            ExportLayout::Archives => {
+                let path = to.join(format!("{}.tar.gz", group.join("_")));
+                write_archive(&path, from, rows).with_context(|| format!("Failed to write {}", path.display()))?;
+                path
+            }
+            ExportLayout::Folders => {
+                let path = group.iter().fold(to.to_path_buf(), |path, value| path.join(value));
+                write_folder(&path, from, rows).with_context(|| format!("Failed to write {}", path.display()))?;
+                path
+            }
+        };
+        say!(ExportGroup, path.display(), rows.len(), values.join(", "));
+        exported += rows.len();
+    }
+
+    match layout {
+        ExportLayout::Archives => say!(ExportedArchives, exported, records.len(), groups.len(), to.display()),
+        ExportLayout::Folders => say!(ExportedFolders, exported, records.len(), groups.len(), to.display()),
+    }
+    Ok(())
+}
+
+/// Gzipped tar of the crops in `rows` plus a manifest listing only them
+fn write_archive(path: &Path, from: &Path, rows: &[&ManifestRecord]) -> Result<()> {
+    let file = File::create(path)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let mut lines = String::new();
+    for record in rows {
+        archive
+            .append_path_with_name(from.join(&record.crop), &record.crop)
+            .with_context(|| format!("Failed to add crop {}", record.crop))?;
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(lines.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, MANIFEST_FILE, lines.as_bytes())?;
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Copies of the crops in `rows`, at their paths within the run, plus a manifest listing only them
+fn write_folder(dir: &Path, from: &Path, rows: &[&ManifestRecord]) -> Result<()> {
+    let mut lines = String::new();
+    for record in rows {
+        let target = dir.join(&record.crop);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from.join(&record.crop), &target).with_context(|| format!("Failed to copy crop {}", record.crop))?;
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+    fs::write(dir.join(MANIFEST_FILE), lines)?;
+    Ok(())
+}
+
+/// Tag values become file and directory names, so anything but letters, digits, `-`, `_` and `.` is replaced
+fn file_name(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    // Keep `.` and `..` from climbing out of the export directory
+    if name.chars().all(|c| c == '.') {
+        name.replace('.', "_")
+    } else {
+        name
+    }
+}
@@ -0,0 +1,284 @@
+//! gRPC detection service (`serve`, build with `--features grpc`)
+//!
+//! `DetectFaces` (see `proto/face_detection.proto`) is one bidirectional stream:
+//! clients send encoded images and get back the boxes that pass the run's
+//! filters, in request order. Images are decoded and detected by the same
+//! backend and filters as `extract` with the same flags, on `--jobs` worker
+//! threads that each own a detector, so a long-lived connection avoids paying
+//! for model loading and process start-up per image. Up to `IN_FLIGHT`
+//! requests of a stream are worked on at once; later ones wait, which pushes
+//! back on clients sending faster than the workers keep up.
+//!
+//! The standard `grpc.health.v1.Health` service answers readiness probes. It
+//! reports NOT_SERVING, for the server as a whole and for `FaceDetection`,
+//! until every worker has loaded and warmed up its detector, so a load
+//! balancer holds traffic back while models are still loading. With
+//! `--ready-listen`, plain HTTP `GET /readyz` answers 503 until then and 200
+//! after, for orchestrators that probe over HTTP. A worker
+//! whose detector fails to load drops out; if none is left, waiting requests
+//! are answered with an error and the server stops.
+
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self as std_mpsc, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+use tonic_health::ServingStatus;
+
+use crate::detector::{self, FaceDetectorBackend};
+use crate::pipeline::Jobs;
+use crate::{decode, decoders, detect_image, filter_valid_faces, say, say_err, Args};
+
+pub mod proto {
+    tonic::include_proto!("facedetect.v1");
+}
+
+use proto::face_detection_server::{FaceDetection, FaceDetectionServer};
+use proto::{BoundingBox, DetectRequest, DetectResponse, Face};
+
+/// Requests of one stream being worked on at once
+const IN_FLIGHT: usize = 32;
+
+/// Largest image accepted in one request
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// One image for the workers, and where its detections go
+struct Job {
+    image: Vec<u8>,
+    reply: oneshot::Sender<Result<DetectResponse, String>>,
+}
+
+struct Service {
+    jobs: Sender<Job>,
+}
+
+/// The job queue the workers share, and how many of them can still take jobs
+struct Pool {
+    queue: Mutex<Receiver<Job>>,
+    live: AtomicUsize,
+    /// Ends the server: Ctrl-C, or the last worker gone
+    stop: Arc<Notify>,
+}
+
+impl Pool {
+    /// Count out a worker that can't take jobs; the last one stops the server and answers what is left with an error
+    fn leave(&self) {
+        if self.live.fetch_sub(1, Ordering::SeqCst) > 1 {
+            return;
+        }
+        say_err!(NoWorkers);
+        self.stop.notify_one();
+        // Jobs keep coming until the server has stopped and dropped its senders
+        let queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        while let Ok(job) = queue.recv() {
+            let _ = job.reply.send(Err("no detector worker is running".to_string()));
+        }
+    }
+}
+
+type Detections = Pin<Box<dyn Stream<Item = Result<DetectResponse, Status>> + Send + 'static>>;
+
+#[tonic::async_trait]
+impl FaceDetection for Service {
+    type DetectFacesStream = Detections;
+
+    async fn detect_faces(&self, request: Request<Streaming<DetectRequest>>) -> Result<Response<Detections>, Status> {
+        let mut incoming = request.into_inner();
+        let jobs = self.jobs.clone();
+        let (pending_tx, mut pending_rx) = mpsc::channel::<(String, oneshot::Receiver<_>)>(IN_FLIGHT);
+        let (out_tx, out_rx) = mpsc::channel(IN_FLIGHT);
+
+        // Hand each image to the workers as it arrives...
+        let errors = out_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let request = match incoming.message().await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => break,
+                    Err(status) => {
+                        let _ = errors.send(Err(status)).await;
+                        break;
+                    }
+                };
+                let (reply, response) = oneshot::channel();
+                if jobs.send(Job { image: request.image, reply }).is_err() || pending_tx.send((request.id, response)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // ...and answer in the order they came in
+        tokio::spawn(async move {
+            while let Some((id, response)) = pending_rx.recv().await {
+                let message = match response.await {
+                    Ok(Ok(detections)) => DetectResponse { id, ..detections },
+                    Ok(Err(error)) => DetectResponse { id, error, ..Default::default() },
+                    Err(_) => DetectResponse { id, error: "detector worker stopped".to_string(), ..Default::default() },
+                };
+                if out_tx.send(Ok(message)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(out_rx))))
+    }
+}
+
+/// Serve `DetectFaces` on `listen`, and `/readyz` on `ready_listen`, until Ctrl-C
+pub fn run(args: &Args, listen: &str, ready_listen: Option<&str>) -> Result<()> {
+    let address: SocketAddr = listen.parse().with_context(|| format!("Invalid --listen address {}", listen))?;
+    let ready_address: Option<SocketAddr> = ready_listen
+        .map(|ready| ready.parse().with_context(|| format!("Invalid --ready-listen address {}", ready)))
+        .transpose()?;
+    // A bad model or backend should fail here, not on the first request
+    drop(detector::load(args)?);
+    let workers = match args.jobs {
+        Jobs::Fixed(n) => n,
+        Jobs::Auto => thread::available_parallelism().map_or(4, |n| n.get()),
+    };
+
+    let (jobs, queue) = std_mpsc::channel();
+    let pool = Pool { queue: Mutex::new(queue), live: AtomicUsize::new(workers), stop: Arc::new(Notify::new()) };
+    let (ready_tx, mut ready_rx) = mpsc::unbounded_channel::<bool>();
+    thread::scope(|scope| {
+        let runtime = tokio::runtime::Runtime::new().context("Failed to start the async runtime")?;
+        let (mut health, health_service) = tonic_health::server::health_reporter();
+        runtime.block_on(async {
+            health.set_service_status("", ServingStatus::NotServing).await;
+            health.set_not_serving::<FaceDetectionServer<Service>>().await;
+        });
+        let serving = Arc::new(AtomicBool::new(false));
+        if let Some(ready_address) = ready_address {
+            let listener = runtime
+                .block_on(TcpListener::bind(ready_address))
+                .with_context(|| format!("Failed to listen on {}", ready_address))?;
+            runtime.spawn(serve_readyz(listener, serving.clone()));
+            say!(ReadyzListening, ready_address);
+        }
+
+        // Workers start once the readiness port is claimed, so a taken port fails before any model loads
+        for id in 0..workers {
+            let pool = &pool;
+            let ready = ready_tx.clone();
+            scope.spawn(move || worker(id, args, pool, ready));
+        }
+        // Ready once every worker has reported in, as long as at least one has a detector
+        runtime.spawn(async move {
+            let mut loaded = 0;
+            for _ in 0..workers {
+                match ready_rx.recv().await {
+                    Some(ok) => loaded += usize::from(ok),
+                    None => break,
+                }
+            }
+            if loaded > 0 {
+                health.set_service_status("", ServingStatus::Serving).await;
+                health.set_serving::<FaceDetectionServer<Service>>().await;
+                serving.store(true, Ordering::SeqCst);
+                say!(ServerReady, loaded, workers);
+            }
+        });
+
+        let stop = pool.stop.clone();
+        runtime.spawn(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            stop.notify_one();
+        });
+
+        say!(ServerListening, address, workers);
+        let service = FaceDetectionServer::new(Service { jobs }).max_decoding_message_size(MAX_MESSAGE_BYTES);
+        let server = Server::builder().add_service(health_service).add_service(service);
+        let served = runtime.block_on(server.serve_with_shutdown(address, pool.stop.notified()));
+        // Dropping the runtime ends the stream tasks and their job senders, which stops the workers
+        drop(runtime);
+        served.context("gRPC server failed")?;
+        if pool.live.load(Ordering::SeqCst) == 0 {
+            anyhow::bail!("No detector worker could run");
+        }
+        say!(ServerStopped);
+        Ok(())
+    })
+}
+
+/// Answer HTTP `GET /readyz` with 503 until `ready` is set, then 200; any other path is 404
+async fn serve_readyz(listener: TcpListener, ready: Arc<AtomicBool>) {
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else { continue };
+        let ready = ready.clone();
+        tokio::spawn(async move {
+            // Only the request line matters; probes send no body
+            let mut request = [0; 1024];
+            let Ok(n) = stream.read(&mut request).await else { return };
+            let target = std::str::from_utf8(&request[..n]).ok().and_then(|r| r.split_whitespace().nth(1)).unwrap_or("");
+            let (status, body) = match target.split('?').next() {
+                Some("/readyz") if ready.load(Ordering::SeqCst) => ("200 OK", "ready\n"),
+                Some("/readyz") => ("503 Service Unavailable", "not ready\n"),
+                _ => ("404 Not Found", "not found\n"),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// Load a detector, report on `ready` whether that worked, then answer jobs until the queue closes
+fn worker(id: usize, args: &Args, pool: &Pool, ready: mpsc::UnboundedSender<bool>) {
+    // Loading includes the warm-up inference, so a ready worker answers its first job at full speed
+    let loaded = detector::load(args);
+    let _ = ready.send(loaded.is_ok());
+    let mut detector = match loaded {
+        Ok(detector) => detector,
+        Err(e) => {
+            say_err!(ServeWorkerFailed, id + 1, format!("{:#}", e));
+            pool.leave();
+            return;
+        }
+    };
+    loop {
+        let Ok(Ok(job)) = pool.queue.lock().map(|queue| queue.recv()) else {
+            break;
+        };
+        let _ = job.reply.send(detect(args, detector.as_mut(), &job.image).map_err(|e| format!("{:#}", e)));
+    }
+}
+
+/// Boxes of the faces in `bytes` that pass the filters
+fn detect(args: &Args, detector: &mut dyn FaceDetectorBackend, bytes: &[u8]) -> Result<DetectResponse> {
+    let image = decoders::registry().read().unwrap_or_else(|e| e.into_inner()).decode_bytes(bytes)?;
+    let enhanced = if args.enhance_scans { decode::enhance_scan(&image) } else { None };
+    let detected = detect_image(args, detector, (image, enhanced))?;
+    let faces = filter_valid_faces(args, &detected.faces, &detected.image, detected.min_score)
+        .into_iter()
+        .map(|face| Face {
+            bbox: Some(BoundingBox {
+                x: face.bbox.x,
+                y: face.bbox.y,
+                width: face.bbox.width,
+                height: face.bbox.height,
+            }),
+            score: face.score,
+            confidence: face.confidence,
+        })
+        .collect();
+    Ok(DetectResponse {
+        width: detected.image.width(),
+        height: detected.image.height(),
+        faces,
+        ..Default::default()
+    })
+}
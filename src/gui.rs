@@ -0,0 +1,361 @@
+//! Minimal desktop front-end (`--features gui`)
+//!
+//! The window only collects settings and shows progress: extraction itself runs
+//! as a child process of this same binary, reporting back through the
+//! `--events-socket` stream, so the GUI and the CLI can never drift apart.
+//! The preview pane runs the detector in-process on a single sample image,
+//! keeping it loaded until the model or a detector setting changes. Stop asks
+//! the child to wind down like Ctrl-C would, so its manifest, checkpoint and
+//! summary are written, and only kills it if it hasn't exited after a while.
+
+use anyhow::Result;
+use eframe::egui;
+use image::Rgba;
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+
+use crate::detector::{self, FaceDetectorBackend};
+use crate::{collect_images, decode, filter_valid_faces, Args};
+
+/// How long a stopped run may take to finish its current images before it is killed
+const STOP_GRACE: Duration = Duration::from_secs(30);
+
+/// Form values the preview detector was loaded with: model, minimum face size and threshold
+type DetectorKey = (String, u32, u64);
+
+pub fn run() -> Result<()> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([960.0, 720.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Face Dataset Generator",
+        options,
+        Box::new(|_cc| Ok(Box::new(App::default()))),
+    )
+    .map_err(|e| anyhow::anyhow!("GUI failed: {}", e))
+}
+
+/// Live counters fed by the child's event stream
+#[derive(Default)]
+struct Progress {
+    total_images: usize,
+    images_done: usize,
+    errors: usize,
+    faces: usize,
+    last_path: String,
+    finished: bool,
+}
+
+struct App {
+    input: String,
+    output: String,
+    model: String,
+    min_face_size: u32,
+    threshold: f64,
+    target_faces: usize,
+
+    samples: Vec<PathBuf>,
+    sample_index: usize,
+    preview: Option<egui::TextureHandle>,
+    preview_note: String,
+    detector: Option<(DetectorKey, Box<dyn FaceDetectorBackend>)>,
+
+    child: Option<Child>,
+    /// When Stop was pressed for the running child
+    stopping: Option<Instant>,
+    progress: Arc<Mutex<Progress>>,
+    status: String,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App {
+            input: "./images".into(),
+            output: "./faces".into(),
+            model: "./model.bin".into(),
+            min_face_size: 40,
+            threshold: 2.0,
+            target_faces: 5000,
+            samples: Vec::new(),
+            sample_index: 0,
+            preview: None,
+            preview_note: "Pick an input folder and press \"Next sample\"".into(),
+            detector: None,
+            child: None,
+            stopping: None,
+            progress: Arc::new(Mutex::new(Progress::default())),
+            status: String::new(),
+        }
+    }
+}
+
+impl App {
+    /// Build CLI arguments from the current form values
+    fn cli_args(&self) -> Vec<String> {
+        vec![
+            "--input".into(),
+            self.input.clone(),
+            "--output".into(),
+            self.output.clone(),
+            "--model".into(),
+            self.model.clone(),
+            "--min-face-size".into(),
+            self.min_face_size.to_string(),
+            "--threshold".into(),
+            self.threshold.to_string(),
+            "--target-faces".into(),
+            self.target_faces.to_string(),
+        ]
+    }
+
+    fn refresh_preview(&mut self, ctx: &egui::Context) {
+        if self.samples.is_empty() {
+            self.samples = collect_images(Path::new(&self.input));
+            self.sample_index = 0;
+        }
+        let Some(path) = self.samples.get(self.sample_index % self.samples.len().max(1)).cloned() else {
+            self.preview_note = format!("No images found in {}", self.input);
+            return;
+        };
+
+        match self.render_preview(&path) {
+            Ok((image, accepted, total)) => {
+                let size = [image.width() as usize, image.height() as usize];
+                let color = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+                self.preview = Some(ctx.load_texture("preview", color, egui::TextureOptions::LINEAR));
+                self.preview_note = format!("{}: {} of {} detections accepted", path.display(), accepted, total);
+            }
+            Err(e) => {
+                self.preview = None;
+                self.preview_note = format!("{}: {:#}", path.display(), e);
+            }
+        }
+    }
+
+    /// Detect on one image and draw accepted (green) and filtered-out (red) boxes
+    fn render_preview(&mut self, path: &Path) -> Result<(image::RgbaImage, usize, usize)> {
+        let args = Args::try_parse_from(std::iter::once("face_extractor".to_string()).chain(self.cli_args()))?;
+        let key = (self.model.clone(), self.min_face_size, self.threshold.to_bits());
+        let detector = match &mut self.detector {
+            Some((loaded, detector)) if *loaded == key => detector,
+            slot => &mut slot.insert((key, detector::load(&args)?)).1,
+        };
+
+        let image = decode::open(path, &args)?;
+        let faces = detector.detect(&image)?;
+        let accepted = filter_valid_faces(&args, &faces, &image, detector.min_score());
+
+        let mut canvas = image.to_rgba8();
+        for face in &faces {
+            let bbox = &face.bbox;
+            let ok = accepted.iter().any(|a| std::ptr::eq(*a, face));
+            let color = if ok { Rgba([0, 220, 0, 255]) } else { Rgba([230, 0, 0, 255]) };
+            let rect = Rect::at(bbox.x, bbox.y).of_size(bbox.width.max(1), bbox.height.max(1));
+            draw_hollow_rect_mut(&mut canvas, rect, color);
+        }
+        Ok((canvas, accepted.len(), faces.len()))
+    }
+
+    fn start_run(&mut self, ctx: &egui::Context) {
+        let socket = std::env::temp_dir().join(format!("face_dataset_generator_{}.sock", std::process::id()));
+        let exe = match std::env::current_exe() {
+            Ok(exe) => exe,
+            Err(e) => {
+                self.status = format!("Cannot locate executable: {}", e);
+                return;
+            }
+        };
+
+        let mut command = Command::new(exe);
+        command.args(self.cli_args());
+        if cfg!(unix) {
+            command.arg("--events-socket").arg(&socket);
+        }
+
+        match command.spawn() {
+            Ok(child) => {
+                self.child = Some(child);
+                self.stopping = None;
+                self.progress = Arc::new(Mutex::new(Progress::default()));
+                self.status = "Running…".into();
+                #[cfg(unix)]
+                follow_events(socket, Arc::clone(&self.progress), ctx.clone());
+                #[cfg(not(unix))]
+                let _ = (socket, ctx);
+            }
+            Err(e) => self.status = format!("Failed to start extraction: {}", e),
+        }
+    }
+
+    /// Ask the child to stop gracefully; `poll_child` kills it if it takes too long
+    fn stop_run(&mut self) {
+        let Some(child) = &mut self.child else { return };
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+        }
+        #[cfg(not(unix))]
+        let _ = child.kill();
+        self.stopping = Some(Instant::now());
+        self.status = "Stopping after the current images…".into();
+    }
+
+    fn poll_child(&mut self) {
+        if let Some(child) = &mut self.child {
+            if self.stopping.is_some_and(|since| since.elapsed() > STOP_GRACE) {
+                let _ = child.kill();
+                self.stopping = None;
+            }
+            if let Ok(Some(status)) = child.try_wait() {
+                self.status = if status.success() {
+                    "Finished".into()
+                } else {
+                    format!("Extraction exited with {}", status)
+                };
+                self.child = None;
+            }
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_child();
+
+        egui::SidePanel::left("settings").min_width(300.0).show(ctx, |ui| {
+            ui.heading("Settings");
+            ui.add_space(8.0);
+
+            folder_row(ui, "Input folder", &mut self.input);
+            folder_row(ui, "Output folder", &mut self.output);
+            ui.horizontal(|ui| {
+                ui.label("Model");
+                ui.text_edit_singleline(&mut self.model);
+                if ui.button("Browse…").clicked() {
+                    if let Some(file) = rfd::FileDialog::new().pick_file() {
+                        self.model = file.display().to_string();
+                    }
+                }
+            });
+
+            ui.add_space(8.0);
+            ui.add(egui::Slider::new(&mut self.min_face_size, 20..=300).text("Min face size (px)"));
+            ui.add(egui::Slider::new(&mut self.threshold, 0.5..=5.0).text("Confidence threshold"));
+            ui.add(egui::Slider::new(&mut self.target_faces, 1..=100_000).logarithmic(true).text("Target faces"));
+
+            ui.add_space(12.0);
+            ui.horizontal(|ui| {
+                if ui.button("Next sample").clicked() {
+                    self.sample_index += 1;
+                    self.refresh_preview(ctx);
+                }
+                if ui.button("Refresh preview").clicked() {
+                    self.samples.clear();
+                    self.refresh_preview(ctx);
+                }
+            });
+
+            ui.add_space(12.0);
+            ui.separator();
+            let running = self.child.is_some();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!running, egui::Button::new("▶ Start extraction")).clicked() {
+                    self.start_run(ctx);
+                }
+                if ui.add_enabled(running && self.stopping.is_none(), egui::Button::new("■ Stop")).clicked() {
+                    self.stop_run();
+                }
+            });
+
+            let progress = self.progress.lock().map(|p| {
+                (p.faces, p.images_done, p.total_images, p.errors, p.last_path.clone(), p.finished)
+            });
+            if let Ok((faces, done, total, errors, last, finished)) = progress {
+                let fraction = faces as f32 / self.target_faces.max(1) as f32;
+                ui.add(egui::ProgressBar::new(fraction.min(1.0)).text(format!("{} / {} faces", faces, self.target_faces)));
+                ui.label(format!("Images: {} / {}   Errors: {}", done, total, errors));
+                if !last.is_empty() && !finished {
+                    ui.small(last);
+                }
+            }
+            ui.label(&self.status);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.label(&self.preview_note);
+            if let Some(texture) = &self.preview {
+                ui.add(egui::Image::from_texture(texture).shrink_to_fit());
+            }
+        });
+
+        if self.child.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+    }
+}
+
+fn folder_row(ui: &mut egui::Ui, label: &str, value: &mut String) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.text_edit_singleline(value);
+        if ui.button("Browse…").clicked() {
+            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                *value = dir.display().to_string();
+            }
+        }
+    });
+}
+
+/// Connect to the child's event socket and mirror events into `progress`
+#[cfg(unix)]
+fn follow_events(socket: PathBuf, progress: Arc<Mutex<Progress>>, ctx: egui::Context) {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+
+    std::thread::spawn(move || {
+        // The child needs a moment to load the model and bind the socket
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let stream = loop {
+            match UnixStream::connect(&socket) {
+                Ok(stream) => break stream,
+                Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(100)),
+                Err(_) => return,
+            }
+        };
+
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+            let count = |key: &str| event.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            if let Ok(mut p) = progress.lock() {
+                match event.get("event").and_then(|v| v.as_str()) {
+                    Some("run_started") => p.total_images = count("total_images"),
+                    Some("image_done") => {
+                        p.images_done += 1;
+                        p.faces = count("total_faces");
+                        p.last_path = event.get("path").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    }
+                    Some("image_failed") => {
+                        p.images_done += 1;
+                        p.errors += 1;
+                    }
+                    Some("run_finished") => {
+                        p.faces = count("total_faces");
+                        p.finished = true;
+                    }
+                    _ => {}
+                }
+            }
+            ctx.request_repaint();
+        }
+    });
+}
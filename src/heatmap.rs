@@ -0,0 +1,96 @@
+//! Where faces sit within their source frames (`--heatmap`)
+//!
+//! Face centers are binned on a grid in frame-relative coordinates, so frames
+//! of different sizes add up. At the end of the run the grid is written as a
+//! color-mapped `heatmap.png` and the report shows the share of faces in each
+//! third of the frame. On fixed-camera corpora this shows where to put an ROI;
+//! elsewhere it exposes spatial bias in what was collected.
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageBuffer, Rgb};
+use std::path::Path;
+
+use crate::detector::BBox;
+use crate::say;
+
+pub const HEATMAP_FILE: &str = "heatmap.png";
+
+/// Cells per side of the accumulation grid
+const GRID: usize = 32;
+
+/// Side of the written image
+const IMAGE_SIDE: u32 = 256;
+
+/// Share of all faces in one ninth of the frame above which the report warns
+const BIAS_SHARE: f64 = 1.0 / 3.0;
+
+pub struct Heatmap {
+    cells: Vec<u32>,
+    faces: usize,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Heatmap {
+            cells: vec![0; GRID * GRID],
+            faces: 0,
+        }
+    }
+}
+
+impl Heatmap {
+    /// Count the center of `bbox` in a `width` x `height` frame
+    pub fn add(&mut self, bbox: &BBox, width: u32, height: u32) {
+        let cx = (bbox.x as f64 + bbox.width as f64 / 2.0) / width.max(1) as f64;
+        let cy = (bbox.y as f64 + bbox.height as f64 / 2.0) / height.max(1) as f64;
+        let cell = |v: f64| ((v * GRID as f64) as usize).min(GRID - 1);
+        self.cells[cell(cy.clamp(0.0, 1.0)) * GRID + cell(cx.clamp(0.0, 1.0))] += 1;
+        self.faces += 1;
+    }
+
+    /// Write `heatmap.png` into `output` and print the per-third breakdown
+    pub fn finish(&self, output: &Path) -> Result<()> {
+        let path = output.join(HEATMAP_FILE);
+        self.render()
+            .save(&path)
+            .with_context(|| format!("Failed to save {}", path.display()))?;
+
+        say!(HeatmapHeader, self.faces, path.display());
+        if self.faces == 0 {
+            return Ok(());
+        }
+        let thirds = self.thirds();
+        for row in &thirds {
+            let shares: Vec<String> = row.iter().map(|&n| format!("{:>5.1}%", 100.0 * n as f64 / self.faces as f64)).collect();
+            say!(HeatmapRow, shares.join(" "));
+        }
+        let busiest = thirds.iter().flatten().max().copied().unwrap_or(0);
+        let share = busiest as f64 / self.faces as f64;
+        if share > BIAS_SHARE {
+            say!(HeatmapBias, format!("{:.0}", share * 100.0));
+        }
+        Ok(())
+    }
+
+    /// Face counts per third of the frame, top row first
+    fn thirds(&self) -> [[usize; 3]; 3] {
+        let mut thirds = [[0; 3]; 3];
+        for (i, &count) in self.cells.iter().enumerate() {
+            let (row, col) = (i / GRID, i % GRID);
+            thirds[row * 3 / GRID][col * 3 / GRID] += count as usize;
+        }
+        thirds
+    }
+
+    /// Counts on a black-red-yellow-white scale, smoothed up to `IMAGE_SIDE`
+    fn render(&self) -> DynamicImage {
+        let max = self.cells.iter().copied().max().unwrap_or(0).max(1) as f64;
+        let grid = ImageBuffer::from_fn(GRID as u32, GRID as u32, |x, y| {
+            let t = self.cells[y as usize * GRID + x as usize] as f64 / max;
+            let channel = |from: f64| ((t * 3.0 - from).clamp(0.0, 1.0) * 255.0) as u8;
+            Rgb([channel(0.0), channel(1.0), channel(2.0)])
+        });
+        DynamicImage::ImageRgb8(grid).resize_exact(IMAGE_SIDE, IMAGE_SIDE, FilterType::Triangle)
+    }
+}
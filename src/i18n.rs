@@ -0,0 +1,953 @@
+//! Message catalog for run output (`--lang`, `--message-codes`)
+//!
+//! Every progress and summary line the pipeline prints is a [`Msg`] with a
+//! stable code (`FDG-110`), a fixed icon/indent prefix and one template per
+//! language. Templates use positional `{0}`, `{1}`, ... placeholders so a
+//! translation can reorder arguments. With `--message-codes` each line carries
+//! its code, which runbooks can reference whatever the language.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+/// Language of the run output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Español
+    Es,
+    /// Français
+    Fr,
+}
+
+struct Settings {
+    lang: Lang,
+    codes: bool,
+    quiet: bool,
+}
+
+static SETTINGS: RwLock<Settings> = RwLock::new(Settings { lang: Lang::En, codes: false, quiet: false });
+
+/// Select the output language; called again once a config file or profile may have changed it
+pub fn init(lang: Lang, codes: bool, quiet: bool) {
+    *SETTINGS.write().unwrap_or_else(PoisonError::into_inner) = Settings { lang, codes, quiet };
+}
+
+fn settings() -> RwLockReadGuard<'static, Settings> {
+    SETTINGS.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether `--quiet` silenced everything but errors
+pub fn quiet() -> bool {
+    settings().quiet
+}
+
+macro_rules! catalog {
+    ($($name:ident = $code:literal, $prefix:literal, en: $en:literal, es: $es:literal, fr: $fr:literal;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        // Server and model download messages are only printed by builds with those features
+        #[cfg_attr(any(not(feature = "grpc"), not(feature = "yolov8")), allow(dead_code))]
+        pub enum Msg {
+            $($name,)*
+        }
+
+        impl Msg {
+            pub fn code(self) -> u16 {
+                match self {
+                    $(Msg::$name => $code,)*
+                }
+            }
+
+            fn prefix(self) -> &'static str {
+                match self {
+                    $(Msg::$name => $prefix,)*
+                }
+            }
+
+            fn template(self, lang: Lang) -> &'static str {
+                match self {
+                    $(Msg::$name => match lang {
+                        Lang::En => $en,
+                        Lang::Es => $es,
+                        Lang::Fr => $fr,
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+catalog! {
+    // Run lifecycle (1xx)
+    Banner = 100, "🚀 ",
+        en: "Face Dataset Generator",
+        es: "Generador de conjuntos de datos de rostros",
+        fr: "Générateur de jeux de données de visages";
+    Target = 101, "",
+        en: "Target: {0} faces",
+        es: "Objetivo: {0} rostros",
+        fr: "Objectif : {0} visages";
+    ModelLoaded = 102, "✅ ",
+        en: "Model loaded and configured",
+        es: "Modelo cargado y configurado",
+        fr: "Modèle chargé et configuré";
+    ImagesFound = 103, "📁 ",
+        en: "Found {0} images to process",
+        es: "Se encontraron {0} imágenes para procesar",
+        fr: "{0} images à traiter";
+    NoImages = 104, "❌ ",
+        en: "No images found in {0}",
+        es: "No se encontraron imágenes en {0}",
+        fr: "Aucune image trouvée dans {0}";
+    BurstsSkipped = 105, "📸 ",
+        en: "{0} photo bursts: skipping {1} near-duplicate frames (--keep-all-burst to keep them)",
+        es: "{0} ráfagas de fotos: se omiten {1} fotogramas casi duplicados (--keep-all-burst para conservarlos)",
+        fr: "{0} rafales photo : {1} images quasi identiques ignorées (--keep-all-burst pour les garder)";
+    Complete = 106, "\n🎉 ",
+        en: "Processing complete!",
+        es: "¡Procesamiento completo!",
+        fr: "Traitement terminé !";
+    Results = 107, "📊 ",
+        en: "Results:",
+        es: "Resultados:",
+        fr: "Résultats :";
+    ImagesProcessed = 108, "  - ",
+        en: "Images processed: {0}",
+        es: "Imágenes procesadas: {0}",
+        fr: "Images traitées : {0}";
+    Errors = 109, "  - ",
+        en: "Errors: {0}",
+        es: "Errores: {0}",
+        fr: "Erreurs : {0}";
+    FacesExtracted = 110, "  - ",
+        en: "Faces extracted: {0}",
+        es: "Rostros extraídos: {0}",
+        fr: "Visages extraits : {0}";
+    ScansEnhanced = 111, "  - ",
+        en: "Scans enhanced: {0} (accepted faces {1} before, {2} after enhancement)",
+        es: "Escaneos mejorados: {0} (rostros aceptados: {1} antes, {2} después de la mejora)",
+        fr: "Numérisations améliorées : {0} (visages acceptés : {1} avant, {2} après amélioration)";
+    OutputDir = 112, "  - ",
+        en: "Output directory: {0}",
+        es: "Directorio de salida: {0}",
+        fr: "Répertoire de sortie : {0}";
+    EventsStreaming = 113, "📡 ",
+        en: "Streaming progress events on {0}",
+        es: "Transmitiendo eventos de progreso en {0}",
+        fr: "Diffusion des événements de progression sur {0}";
+    TargetReached = 114, "🎯 ",
+        en: "Target reached! Extracted {0} faces",
+        es: "¡Objetivo alcanzado! {0} rostros extraídos",
+        fr: "Objectif atteint ! {0} visages extraits";
+    Processing = 115, "",
+        en: "[{0}/{1}] Processing: {2}",
+        es: "[{0}/{1}] Procesando: {2}",
+        fr: "[{0}/{1}] Traitement : {2}";
+    Extracted = 116, "  ✅ ",
+        en: "Extracted {0} faces",
+        es: "{0} rostros extraídos",
+        fr: "{0} visages extraits";
+    ImageError = 117, "  ❌ ",
+        en: "Error: {0}",
+        es: "Error: {0}",
+        fr: "Erreur : {0}";
+    ScanEnhanced = 118, "  🔆 ",
+        en: "Enhanced scan: {0} faces before, {1} after",
+        es: "Escaneo mejorado: {0} rostros antes, {1} después",
+        fr: "Numérisation améliorée : {0} visages avant, {1} après";
+    WorkersAdjusted = 119, "⚙️  ",
+        en: "Workers: {0} decode, {1} detect (CPU {2}%)",
+        es: "Trabajadores: {0} de decodificación, {1} de detección (CPU {2}%)",
+        fr: "Workers : {0} décodage, {1} détection (CPU {2} %)";
+    WorkerFailed = 120, "  ❌ ",
+        en: "Detect worker {0} stopped: {1}",
+        es: "El trabajador de detección {0} se detuvo: {1}",
+        fr: "Le worker de détection {0} s'est arrêté : {1}";
+    Prioritized = 121, "⏫ ",
+        en: "Prioritized {0} queued images from {1} folders listed in {2}",
+        es: "Se priorizaron {0} imágenes en cola de {1} carpetas listadas en {2}",
+        fr: "{0} images en attente priorisées depuis {1} dossiers listés dans {2}";
+    Resuming = 122, "⏯️  ",
+        en: "Resuming: {0} images already processed, {1} faces so far, {2} images left",
+        es: "Reanudando: {0} imágenes ya procesadas, {1} rostros hasta ahora, quedan {2} imágenes",
+        fr: "Reprise : {0} images déjà traitées, {1} visages jusqu'ici, {2} images restantes";
+    NoCheckpoint = 123, "⏯️  ",
+        en: "No checkpoint in {0}; starting from the beginning",
+        es: "No hay punto de control en {0}; empezando desde el principio",
+        fr: "Aucun point de reprise dans {0} ; démarrage depuis le début";
+    Relaxing = 124, "🪜 ",
+        en: "Relaxation level {0}: retrying {1} rejected candidates for {2} missing faces at {3}% of the thresholds",
+        es: "Nivel de relajación {0}: reintentando {1} candidatos rechazados para {2} rostros faltantes al {3}% de los umbrales",
+        fr: "Niveau d'assouplissement {0} : nouvel essai de {1} candidats rejetés pour {2} visages manquants à {3} % des seuils";
+    SecondPass = 150, "🪜 ",
+        en: "Yield of {0} faces per image is below {1}: retrying {2} images without a face at {3}% of the thresholds",
+        es: "El rendimiento de {0} rostros por imagen está por debajo de {1}: reintentando {2} imágenes sin rostro al {3}% de los umbrales",
+        fr: "Le rendement de {0} visages par image est inférieur à {1} : nouvel essai de {2} images sans visage à {3} % des seuils";
+    SecondPassFound = 151, "  🔎 ",
+        en: "Second pass found {0} low-confidence faces",
+        es: "La segunda pasada encontró {0} rostros de baja confianza",
+        fr: "Le second passage a trouvé {0} visages de faible confiance";
+    EnsembleHeader = 152, "  - ",
+        en: "Ensemble contributions:",
+        es: "Aportaciones del conjunto:",
+        fr: "Contributions de l'ensemble :";
+    EnsembleBackend = 153, "      ",
+        en: "{0}: {1} detected, {2} kept, {3} found by it alone",
+        es: "{0}: {1} detectados, {2} conservados, {3} hallados solo por él",
+        fr: "{0} : {1} détectés, {2} conservés, {3} trouvés par lui seul";
+    DetectionCache = 154, "  - ",
+        en: "Detection cache: {0} hits, {1} misses",
+        es: "Caché de detecciones: {0} aciertos, {1} fallos",
+        fr: "Cache de détections : {0} succès, {1} échecs";
+    ContactSheets = 155, "  - ",
+        en: "Contact sheets: {0} written with {1} faces in {2}",
+        es: "Hojas de contactos: {0} escritas con {1} rostros en {2}",
+        fr: "Planches contact : {0} écrites avec {1} visages dans {2}";
+    StatsWritten = 156, "  - ",
+        en: "Statistics: {0}",
+        es: "Estadísticas: {0}",
+        fr: "Statistiques : {0}";
+    ExitImageErrors = 157, "⚠️  ",
+        en: "Exiting with status {0}: {1} images failed",
+        es: "Saliendo con estado {0}: fallaron {1} imágenes",
+        fr: "Sortie avec le statut {0} : {1} images en échec";
+    ExitShortOfTarget = 158, "⚠️  ",
+        en: "Exiting with status {0}: {1} of {2} target faces extracted",
+        es: "Saliendo con estado {0}: extraídos {1} de {2} rostros objetivo",
+        fr: "Sortie avec le statut {0} : {1} visages extraits sur {2} visés";
+    DiskStopped = 159, "💾 ",
+        en: "Stopping for lack of room: {0}",
+        es: "Deteniendo por falta de espacio: {0}",
+        fr: "Arrêt faute de place : {0}";
+    DiskPaused = 160, "💾 ",
+        en: "Pausing until there is room again: {0}",
+        es: "En pausa hasta que vuelva a haber espacio: {0}",
+        fr: "En pause jusqu'à ce qu'il y ait de nouveau de la place : {0}";
+    DiskResumed = 161, "▶️  ",
+        en: "Room again, resuming after {0}",
+        es: "Vuelve a haber espacio, reanudando tras {0}",
+        fr: "De nouveau de la place, reprise après {0}";
+    OutOfSpace = 162, "\n💾 ",
+        en: "Run stopped for lack of disk space; the results below are partial, continue with --resume once there is room",
+        es: "Ejecución detenida por falta de espacio en disco; los resultados son parciales, continúe con --resume cuando haya espacio",
+        fr: "Exécution arrêtée faute d'espace disque ; les résultats ci-dessous sont partiels, reprenez avec --resume quand il y aura de la place";
+    Sampled = 163, "🎲 ",
+        en: "Sampled {0} of {1} images (seed {2}; pass --seed {2} to draw the same ones)",
+        es: "Muestreadas {0} de {1} imágenes (semilla {2}; use --seed {2} para obtener las mismas)",
+        fr: "{0} images tirées sur {1} (graine {2} ; passez --seed {2} pour tirer les mêmes)";
+    Shuffled = 164, "🎲 ",
+        en: "Processing images in random order (seed {0})",
+        es: "Procesando imágenes en orden aleatorio (semilla {0})",
+        fr: "Traitement des images dans un ordre aléatoire (graine {0})";
+    ShardSelected = 165, "🧩 ",
+        en: "Shard {0} of {1}: processing {2} of {3} images",
+        es: "Fragmento {0} de {1}: procesando {2} de {3} imágenes",
+        fr: "Fragment {0} sur {1} : traitement de {2} images sur {3}";
+    DuplicatesDropped = 125, "  - ",
+        en: "Near-duplicate faces dropped: {0} (within {1} bits)",
+        es: "Rostros casi duplicados descartados: {0} (a {1} bits o menos)",
+        fr: "Visages quasi dupliqués écartés : {0} (à {1} bits ou moins)";
+    ExposureRejected = 126, "  - ",
+        en: "Faces rejected for brightness or contrast: {0}",
+        es: "Rostros rechazados por brillo o contraste: {0}",
+        fr: "Visages rejetés pour luminosité ou contraste : {0}";
+    PoseRejected = 127, "  - ",
+        en: "Faces rejected for head pose: {0}",
+        es: "Rostros rechazados por la pose de la cabeza: {0}",
+        fr: "Visages rejetés pour la pose de la tête : {0}";
+    StreamOpened = 128, "📡 ",
+        en: "Reading {0} at {1} frames per second",
+        es: "Leyendo {0} a {1} fotogramas por segundo",
+        fr: "Lecture de {0} à {1} images par seconde";
+    StreamEnded = 129, "📡 ",
+        en: "Stream closed after {0} frames",
+        es: "Flujo cerrado tras {0} fotogramas",
+        fr: "Flux fermé après {0} images";
+    Watching = 130, "👀 ",
+        en: "Watching {0} for new images (Ctrl-C to stop)",
+        es: "Vigilando {0} en busca de imágenes nuevas (Ctrl-C para detener)",
+        fr: "Surveillance de {0} pour de nouvelles images (Ctrl-C pour arrêter)";
+    NewImage = 131, "",
+        en: "[{0}] New image: {1}",
+        es: "[{0}] Imagen nueva: {1}",
+        fr: "[{0}] Nouvelle image : {1}";
+    NicePaused = 132, "⏸️  ",
+        en: "Other processes are using {0}% of the CPU, pausing",
+        es: "Otros procesos usan el {0}% de la CPU, en pausa",
+        fr: "D'autres processus utilisent {0} % du CPU, pause";
+    NiceResumed = 133, "▶️  ",
+        en: "Resuming after {0} paused",
+        es: "Reanudando tras {0} en pausa",
+        fr: "Reprise après {0} de pause";
+    NiceUnsupported = 134, "⚠️  ",
+        en: "--nice-mode needs /proc to measure CPU load; running without pauses",
+        es: "--nice-mode necesita /proc para medir la carga de CPU; se ejecuta sin pausas",
+        fr: "--nice-mode a besoin de /proc pour mesurer la charge CPU ; exécution sans pause";
+    TeeWritten = 135, "  - ",
+        en: "Also written: {0}",
+        es: "También escrito: {0}",
+        fr: "Également écrit : {0}";
+    RejectedSaved = 136, "  - ",
+        en: "Rejected faces saved for review: {0} (in {1})",
+        es: "Rostros rechazados guardados para revisión: {0} (en {1})",
+        fr: "Visages rejetés enregistrés pour revue : {0} (dans {1})";
+    Downloading = 137, "⬇️  ",
+        en: "Downloading {0} images from the input list ({1} at a time)",
+        es: "Descargando {0} imágenes de la lista de entrada ({1} a la vez)",
+        fr: "Téléchargement de {0} images de la liste d'entrée ({1} à la fois)";
+    DownloadFailed = 138, "⚠️  ",
+        en: "Download failed, skipping {0}: {1}",
+        es: "La descarga falló, se omite {0}: {1}",
+        fr: "Échec du téléchargement, {0} ignorée : {1}";
+    Downloaded = 139, "  - ",
+        en: "Downloaded: {0} ({1} failed)",
+        es: "Descargadas: {0} ({1} fallidas)",
+        fr: "Téléchargées : {0} ({1} en échec)";
+    CloudStaging = 140, "☁️  ",
+        en: "Staging output in {0}, uploaded to {1} when the run ends",
+        es: "Salida preparada en {0}, se sube a {1} al terminar la ejecución",
+        fr: "Sortie préparée dans {0}, envoyée vers {1} à la fin de l'exécution";
+    CloudUploaded = 141, "☁️  ",
+        en: "Uploaded {0} files to {1}",
+        es: "{0} archivos subidos a {1}",
+        fr: "{0} fichiers envoyés vers {1}";
+    Stopping = 142, "⏹️  ",
+        en: "Stopping after the images in progress (press Ctrl-C again to quit at once)",
+        es: "Deteniendo tras las imágenes en curso (pulsa Ctrl-C otra vez para salir de inmediato)",
+        fr: "Arrêt après les images en cours (Ctrl-C de nouveau pour quitter immédiatement)";
+    Interrupted = 143, "⏹️  ",
+        en: "Run interrupted; the results below are partial, continue with --resume",
+        es: "Ejecución interrumpida; los resultados son parciales, continúa con --resume",
+        fr: "Exécution interrompue ; résultats partiels, reprenez avec --resume";
+    SkippingExisting = 144, "⏭️  ",
+        en: "Skipping {0} images that already have crops ({1} faces), {2} images left",
+        es: "Se omiten {0} imágenes que ya tienen recortes ({1} rostros), quedan {2} imágenes",
+        fr: "{0} images ayant déjà des recadrages ignorées ({1} visages), {2} images restantes";
+    NameCollisions = 145, "  - ",
+        en: "Crop names already taken: {0} ({1})",
+        es: "Nombres de recorte ya existentes: {0} ({1})",
+        fr: "Noms de recadrage déjà pris : {0} ({1})";
+    ExactDuplicates = 146, "  - ",
+        en: "Exact duplicate crops skipped: {0}",
+        es: "Recortes idénticos omitidos: {0}",
+        fr: "Recadrages identiques ignorés : {0}";
+    LabelsWritten = 147, "  - ",
+        en: "Identities: {0} (labels in {1})",
+        es: "Identidades: {0} (etiquetas en {1})",
+        fr: "Identités : {0} (étiquettes dans {1})";
+    SplitFaces = 148, "  - ",
+        en: "Split: {0} train, {1} val, {2} test faces",
+        es: "División: {0} rostros de entrenamiento, {1} de validación, {2} de prueba",
+        fr: "Répartition : {0} visages d'entraînement, {1} de validation, {2} de test";
+    FaceLimitDropped = 149, "  - ",
+        en: "Faces over the per-image limit of {0} (by {1}): {2}",
+        es: "Rostros por encima del límite de {0} por imagen (por {1}): {2}",
+        fr: "Visages au-delà de la limite de {0} par image (par {1}) : {2}";
+
+    // Chunked plans (2xx)
+    PlanHeader = 200, "🗺️  ",
+        en: "Execution plan: {0} chunks",
+        es: "Plan de ejecución: {0} bloques",
+        fr: "Plan d'exécution : {0} lots";
+    PlanCalibrated = 201, "  ⏱️  ",
+        en: "Calibrated at {0}s/image over {1} images",
+        es: "Calibrado en {0}s/imagen con {1} imágenes",
+        fr: "Calibré à {0} s/image sur {1} images";
+    PlanCalibrationFailed = 202, "  ⚠️  ",
+        en: "Calibration failed (no decodable sample images); estimates unavailable",
+        es: "La calibración falló (ninguna imagen de muestra decodificable); no hay estimaciones",
+        fr: "Échec de la calibration (aucune image d'échantillon lisible) ; estimations indisponibles";
+    PlanChunk = 203, "  ",
+        en: "Chunk {0}/{1}: images {2}-{3} ({4} images) ~ {5}",
+        es: "Bloque {0}/{1}: imágenes {2}-{3} ({4} imágenes) ~ {5}",
+        fr: "Lot {0}/{1} : images {2}-{3} ({4} images) ~ {5}";
+    PlanTotal = 204, "  ",
+        en: "Total: {0} images, estimated runtime {1}",
+        es: "Total: {0} imágenes, duración estimada {1}",
+        fr: "Total : {0} images, durée estimée {1}";
+    PlanResuming = 205, "♻️  ",
+        en: "Resuming plan: {0}/{1} chunks already complete, {2} faces extracted",
+        es: "Reanudando el plan: {0}/{1} bloques ya completos, {2} rostros extraídos",
+        fr: "Reprise du plan : {0}/{1} lots déjà terminés, {2} visages extraits";
+    ChunkStarting = 206, "\n📦 ",
+        en: "Chunk {0}/{1} starting",
+        es: "Iniciando bloque {0}/{1}",
+        fr: "Début du lot {0}/{1}";
+    ChunkDone = 207, "📦 ",
+        en: "Chunk {0}/{1} done: {2} images, {3} errors, {4} faces in {5} (estimated {6})",
+        es: "Bloque {0}/{1} terminado: {2} imágenes, {3} errores, {4} rostros en {5} (estimado {6})",
+        fr: "Lot {0}/{1} terminé : {2} images, {3} erreurs, {4} visages en {5} (estimé {6})";
+    CheckpointMismatch = 208, "⚠️  ",
+        en: "Ignoring {0}: it was written for a different corpus or chunk size",
+        es: "Se ignora {0}: se escribió para otro corpus u otro tamaño de bloque",
+        fr: "{0} ignoré : il a été écrit pour un autre corpus ou une autre taille de lot";
+    CheckpointUnreadable = 209, "⚠️  ",
+        en: "Ignoring unreadable {0}: {1}",
+        es: "Se ignora {0} ilegible: {1}",
+        fr: "{0} illisible ignoré : {1}";
+
+    // Video tracking (3xx)
+    SequencesFound = 300, "🎞️  ",
+        en: "Found {0} clips ({1} frames) to track",
+        es: "Se encontraron {0} clips ({1} fotogramas) para seguir",
+        fr: "{0} clips ({1} images) à suivre";
+    ClipsVideoOnly = 301, "⚠️  ",
+        en: "Clips are only cut from video files; frame folders are tracked but not clipped",
+        es: "Los clips solo se cortan de archivos de vídeo; las carpetas de fotogramas se siguen pero no se recortan",
+        fr: "Les clips ne sont découpés que dans les fichiers vidéo ; les dossiers d'images sont suivis mais pas découpés";
+    SequenceFps = 302, "🎞️  ",
+        en: "{0}: {1} frames at {2} fps",
+        es: "{0}: {1} fotogramas a {2} fps",
+        fr: "{0} : {1} images à {2} i/s";
+    Sequence = 303, "🎞️  ",
+        en: "{0}: {1} frames",
+        es: "{0}: {1} fotogramas",
+        fr: "{0} : {1} images";
+    TrackDone = 304, "  ✅ ",
+        en: "Track {0}: frames {1}-{2} ({3} crops, {4} clips)",
+        es: "Pista {0}: fotogramas {1}-{2} ({3} recortes, {4} clips)",
+        fr: "Piste {0} : images {1}-{2} ({3} recadrages, {4} clips)";
+    FrameError = 305, "  ❌ ",
+        en: "Error on frame {0}: {1}",
+        es: "Error en el fotograma {0}: {1}",
+        fr: "Erreur sur l'image {0} : {1}";
+
+    // Composition (4xx)
+    CompositionHeader = 400, "📋 ",
+        en: "Composition:",
+        es: "Composición:",
+        fr: "Composition :";
+    QuotaProgress = 401, "  - ",
+        en: "{0}: {1}/{2}",
+        es: "{0}: {1}/{2}",
+        fr: "{0} : {1}/{2}";
+    CappedByIdentity = 402, "  - ",
+        en: "Skipped by max_per_identity: {0}",
+        es: "Omitidos por max_per_identity: {0}",
+        fr: "Ignorés par max_per_identity : {0}";
+    UnmetQuotas = 403, "⚠️  ",
+        en: "Unmet quotas: {0}",
+        es: "Cuotas incumplidas: {0}",
+        fr: "Quotas non atteints : {0}";
+    QuotaShort = 404, "",
+        en: "{0} (short {1})",
+        es: "{0} (faltan {1})",
+        fr: "{0} (manque {1})";
+
+    // Spatial distribution (5xx)
+    HeatmapHeader = 500, "🗺️  ",
+        en: "Face positions across {0} faces (heatmap: {1}), by thirds of the frame:",
+        es: "Posiciones de {0} rostros (mapa de calor: {1}), por tercios del encuadre:",
+        fr: "Positions de {0} visages (carte de chaleur : {1}), par tiers du cadre :";
+    HeatmapRow = 501, "    ",
+        en: "{0}",
+        es: "{0}",
+        fr: "{0}";
+    HeatmapBias = 502, "⚠️  ",
+        en: "{0}% of faces fall in one ninth of the frame; the collection is spatially biased",
+        es: "El {0}% de los rostros cae en una novena parte del encuadre; la colección tiene un sesgo espacial",
+        fr: "{0} % des visages tombent dans un neuvième du cadre ; la collecte présente un biais spatial";
+
+    // Detection server (6xx)
+    ServerListening = 600, "🛰️  ",
+        en: "DetectFaces listening on {0} with {1} detector workers (Ctrl-C to stop)",
+        es: "DetectFaces escuchando en {0} con {1} trabajadores de detección (Ctrl-C para detener)",
+        fr: "DetectFaces à l'écoute sur {0} avec {1} travailleurs de détection (Ctrl-C pour arrêter)";
+    ServerReady = 601, "✅ ",
+        en: "{0} of {1} detector workers ready; health status is SERVING",
+        es: "{0} de {1} trabajadores de detección listos; estado de salud SERVING",
+        fr: "{0} travailleurs de détection sur {1} prêts ; état de santé SERVING";
+    ServeWorkerFailed = 602, "❌ ",
+        en: "Detector worker {0} failed to start: {1}",
+        es: "El trabajador de detección {0} no pudo iniciarse: {1}",
+        fr: "Le travailleur de détection {0} n'a pas pu démarrer : {1}";
+    NoWorkers = 603, "❌ ",
+        en: "No detector worker is left; answering waiting requests with an error and stopping the server",
+        es: "No queda ningún trabajador de detección; se responde con un error a las solicitudes pendientes y se detiene el servidor",
+        fr: "Plus aucun travailleur de détection ; les requêtes en attente reçoivent une erreur et le serveur s'arrête";
+    ServerStopped = 604, "👋 ",
+        en: "Server stopped",
+        es: "Servidor detenido",
+        fr: "Serveur arrêté";
+    ReadyzListening = 605, "🩺 ",
+        en: "Readiness on http://{0}/readyz",
+        es: "Disponibilidad en http://{0}/readyz",
+        fr: "Disponibilité sur http://{0}/readyz";
+
+    // Configuration and models (7xx)
+    ConfigLoaded = 700, "⚙️  ",
+        en: "Loaded config from {0}",
+        es: "Configuración cargada desde {0}",
+        fr: "Configuration chargée depuis {0}";
+    ProfileApplied = 701, "⚙️  ",
+        en: "Profile {0}: {1}",
+        es: "Perfil {0}: {1}",
+        fr: "Profil {0} : {1}";
+    ConfigKeyUnknown = 702, "⚠️  ",
+        en: "Unknown config key '{0}' in {1} (ignored)",
+        es: "Clave de configuración desconocida '{0}' en {1} (se ignora)",
+        fr: "Clé de configuration inconnue « {0} » dans {1} (ignorée)";
+    ModelUnverified = 703, "⚠️  ",
+        en: "{0} is not in the model registry; checksum {1} not verified",
+        es: "{0} no está en el registro de modelos; suma de comprobación {1} sin verificar",
+        fr: "{0} n'est pas dans le registre des modèles ; somme de contrôle {1} non vérifiée";
+    ModelPresent = 704, "🧠 ",
+        en: "YOLO face detection model already exists at: {0}",
+        es: "El modelo YOLO de detección de rostros ya existe en: {0}",
+        fr: "Le modèle YOLO de détection de visages existe déjà : {0}";
+    ModelDownloading = 705, "⬇️  ",
+        en: "Downloading YOLOv8 face detection model from {0}...",
+        es: "Descargando el modelo YOLOv8 de detección de rostros desde {0}...",
+        fr: "Téléchargement du modèle YOLOv8 de détection de visages depuis {0}...";
+    ModelDownloadProgress = 706, "  ⬇️  ",
+        en: "{0}% ({1} / {2} MB)",
+        es: "{0}% ({1} / {2} MB)",
+        fr: "{0} % ({1} / {2} Mo)";
+    ModelDownloadRetry = 707, "⚠️  ",
+        en: "Download attempt {0}/{1} failed: {2} (retrying in {3}s)",
+        es: "El intento de descarga {0}/{1} falló: {2} (se reintenta en {3} s)",
+        fr: "La tentative de téléchargement {0}/{1} a échoué : {2} (nouvel essai dans {3} s)";
+    ModelDownloaded = 708, "✅ ",
+        en: "Model downloaded successfully",
+        es: "Modelo descargado correctamente",
+        fr: "Modèle téléchargé avec succès";
+
+    // Inspection tools (8xx)
+    StatsEmpty = 800, "📭 ",
+        en: "{0} lists no crops",
+        es: "{0} no contiene recortes",
+        fr: "{0} ne contient aucun recadrage";
+    StatsHeader = 801, "📊 ",
+        en: "{0}",
+        es: "{0}",
+        fr: "{0}";
+    StatsCrops = 802, "  - ",
+        en: "Crops: {0} ({1} missing on disk, {2} MB)",
+        es: "Recortes: {0} ({1} ausentes en disco, {2} MB)",
+        fr: "Recadrages : {0} ({1} absents du disque, {2} Mo)";
+    StatsFaces = 803, "  - ",
+        en: "Faces: {0} from {1} sources",
+        es: "Rostros: {0} de {1} fuentes",
+        fr: "Visages : {0} issus de {1} sources";
+    StatsRange = 804, "  - ",
+        en: "{0}: mean {1}, min {2}, max {3}",
+        es: "{0}: media {1}, mín. {2}, máx. {3}",
+        fr: "{0} : moyenne {1}, min {2}, max {3}";
+    StatsCounts = 805, "  - ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    StatsScore = 806, "",
+        en: "Detector score",
+        es: "Puntuación del detector",
+        fr: "Score du détecteur";
+    StatsQuality = 807, "",
+        en: "Quality",
+        es: "Calidad",
+        fr: "Qualité";
+    StatsFaceWidth = 808, "",
+        en: "Face width (px)",
+        es: "Ancho del rostro (px)",
+        fr: "Largeur du visage (px)";
+    StatsVariants = 809, "",
+        en: "Variants",
+        es: "Variantes",
+        fr: "Variantes";
+    StatsTiers = 810, "",
+        en: "Tiers",
+        es: "Niveles",
+        fr: "Niveaux";
+    StatsFacing = 811, "",
+        en: "Facing",
+        es: "Orientación",
+        fr: "Orientation";
+    StatsRelaxation = 812, "",
+        en: "Relaxation level",
+        es: "Nivel de relajación",
+        fr: "Niveau d'assouplissement";
+    StatsTag = 813, "",
+        en: "Tag {0}",
+        es: "Etiqueta {0}",
+        fr: "Étiquette {0}";
+    StatsUnset = 814, "",
+        en: "unset",
+        es: "sin valor",
+        fr: "non défini";
+    StatusHeader = 820, "📋 ",
+        en: "Run in {0}",
+        es: "Ejecución en {0}",
+        fr: "Exécution dans {0}";
+    StatusMeta = 821, "  - ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    StatusFailed = 822, "  - ",
+        en: "Images failed: {0}",
+        es: "Imágenes fallidas: {0}",
+        fr: "Images en échec : {0}";
+    StatusChunks = 823, "  - ",
+        en: "Chunks completed: {0}/{1}",
+        es: "Bloques completados: {0}/{1}",
+        fr: "Lots terminés : {0}/{1}";
+    StatusFailure = 824, "  ❌ ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    ReportWritten = 830, "🖼️  ",
+        en: "Report of {0} sources: {1}",
+        es: "Informe de {0} fuentes: {1}",
+        fr: "Rapport de {0} sources : {1}";
+    VisualizeFailed = 835, "❌ ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    VisualizeDone = 836, "🖼️  ",
+        en: "{0} images drawn into {1}: {2} faces accepted, {3} rejected",
+        es: "{0} imágenes dibujadas en {1}: {2} rostros aceptados, {3} rechazados",
+        fr: "{0} images annotées dans {1} : {2} visages acceptés, {3} rejetés";
+    VisualizeReason = 837, "  - ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    RegressComparing = 840, "🔬 ",
+        en: "Comparing detections against {0}",
+        es: "Comparando detecciones con {0}",
+        fr: "Comparaison des détections avec {0}";
+    RegressFailed = 841, "  ❌ ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    RegressChanged = 842, "  ⚠️  ",
+        en: "{0}: {1} lost, {2} added",
+        es: "{0}: {1} perdidas, {2} nuevas",
+        fr: "{0} : {1} perdues, {2} ajoutées";
+    RegressHeader = 843, "\n📊 ",
+        en: "Regression against {0} sources:",
+        es: "Regresión sobre {0} fuentes:",
+        fr: "Régression sur {0} sources :";
+    RegressKept = 844, "  - ",
+        en: "Detections kept: {0}",
+        es: "Detecciones conservadas: {0}",
+        fr: "Détections conservées : {0}";
+    RegressLost = 845, "  - ",
+        en: "Detections lost: {0}",
+        es: "Detecciones perdidas: {0}",
+        fr: "Détections perdues : {0}";
+    RegressAdded = 846, "  - ",
+        en: "Detections added: {0}",
+        es: "Detecciones nuevas: {0}",
+        fr: "Détections ajoutées : {0}";
+    RegressSourcesChanged = 847, "  - ",
+        en: "Sources changed: {0}",
+        es: "Fuentes con cambios: {0}",
+        fr: "Sources modifiées : {0}";
+    RegressUnreadable = 848, "  - ",
+        en: "Sources unreadable: {0}",
+        es: "Fuentes ilegibles: {0}",
+        fr: "Sources illisibles : {0}";
+    RegressGallery = 849, "🖼️  ",
+        en: "Diff gallery: {0}",
+        es: "Galería de diferencias: {0}",
+        fr: "Galerie des différences : {0}";
+    InspectHeader = 850, "🔎 ",
+        en: "{0} ({1}x{2}), {3} backend, {4} raw detections",
+        es: "{0} ({1}x{2}), motor {3}, {4} detecciones en bruto",
+        fr: "{0} ({1}x{2}), moteur {3}, {4} détections brutes";
+    InspectEnhanced = 851, "  🧽 ",
+        en: "Scan enhancement applied before detection",
+        es: "Mejora de escaneo aplicada antes de la detección",
+        fr: "Amélioration de numérisation appliquée avant la détection";
+    InspectFace = 852, "\n  ",
+        en: "#{0} box {1},{2} {3}x{4}, score {5}",
+        es: "#{0} recuadro {1},{2} {3}x{4}, puntuación {5}",
+        fr: "#{0} cadre {1},{2} {3}x{4}, score {5}";
+    InspectCheckPassed = 853, "     ✅ ",
+        en: "{0}",
+        es: "{0}",
+        fr: "{0}";
+    InspectCheckFailed = 854, "     ❌ ",
+        en: "{0}",
+        es: "{0}",
+        fr: "{0}";
+    InspectKept = 855, "     ➡️  ",
+        en: "kept",
+        es: "conservado",
+        fr: "conservé";
+    InspectSkipped = 856, "     ➡️  ",
+        en: "skipped",
+        es: "descartado",
+        fr: "écarté";
+    InspectSummary = 857, "\n📊 ",
+        en: "{0} of {1} detections would be cropped (before --target-faces, --dedup and quotas)",
+        es: "Se recortarían {0} de {1} detecciones (antes de --target-faces, --dedup y las cuotas)",
+        fr: "{0} détections sur {1} seraient recadrées (avant --target-faces, --dedup et les quotas)";
+    InspectAnnotated = 858, "🖼️  ",
+        en: "Annotated image: {0}",
+        es: "Imagen anotada: {0}",
+        fr: "Image annotée : {0}";
+    DetectFailed = 860, "❌ ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    DetectDone = 861, "🔎 ",
+        en: "{0} faces in {1} images ({2} errors), written to {3} and {4}",
+        es: "{0} rostros en {1} imágenes ({2} errores), escritos en {3} y {4}",
+        fr: "{0} visages dans {1} images ({2} erreurs), écrits dans {3} et {4}";
+    DoctorHeader = 870, "🩺 ",
+        en: "Face Dataset Generator doctor (v{0})",
+        es: "Diagnóstico de Face Dataset Generator (v{0})",
+        fr: "Diagnostic de Face Dataset Generator (v{0})";
+    DoctorPass = 871, "  ✅ ",
+        en: "{0} {1}",
+        es: "{0} {1}",
+        fr: "{0} {1}";
+    DoctorWarn = 872, "  ⚠️  ",
+        en: "{0} {1}",
+        es: "{0} {1}",
+        fr: "{0} {1}";
+    DoctorFail = 873, "  ❌ ",
+        en: "{0} {1}",
+        es: "{0} {1}",
+        fr: "{0} {1}";
+    DoctorInfo = 874, "  ℹ️  ",
+        en: "{0} {1}",
+        es: "{0} {1}",
+        fr: "{0} {1}";
+    DoctorPassed = 875, "\n✅ ",
+        en: "All checks passed ({0} warnings)",
+        es: "Todas las comprobaciones superadas ({0} advertencias)",
+        fr: "Toutes les vérifications ont réussi ({0} avertissements)";
+    DoctorModelFile = 876, "",
+        en: "{0} ({1} bytes)",
+        es: "{0} ({1} bytes)",
+        fr: "{0} ({1} octets)";
+    DoctorModelEmpty = 877, "",
+        en: "{0} is empty",
+        es: "{0} está vacío",
+        fr: "{0} est vide";
+    DoctorChecksumSkipped = 878, "",
+        en: "skipped (--skip-checksum)",
+        es: "omitida (--skip-checksum)",
+        fr: "ignorée (--skip-checksum)";
+    DoctorChecksumOk = 879, "",
+        en: "matches the model registry (or model is unregistered)",
+        es: "coincide con el registro de modelos (o el modelo no está registrado)",
+        fr: "correspond au registre des modèles (ou modèle non enregistré)";
+    DoctorWritable = 880, "",
+        en: "{0} is writable",
+        es: "{0} admite escritura",
+        fr: "{0} est accessible en écriture";
+    DoctorCodecOk = 881, "",
+        en: "{0} encode/decode ok",
+        es: "{0} codificación/decodificación correctas",
+        fr: "{0} encodage/décodage ok";
+    DoctorCodecDimensions = 882, "",
+        en: "{0} decoded with wrong dimensions",
+        es: "{0} decodificado con dimensiones incorrectas",
+        fr: "{0} décodé avec de mauvaises dimensions";
+    DoctorModelLoaded = 883, "",
+        en: "detector created and configured",
+        es: "detector creado y configurado",
+        fr: "détecteur créé et configuré";
+    DoctorDetected = 884, "",
+        en: "ran on test image ({0} candidate faces)",
+        es: "ejecutado sobre la imagen de prueba ({0} rostros candidatos)",
+        fr: "exécuté sur l'image de test ({0} visages candidats)";
+    DoctorThroughput = 885, "",
+        en: "{0} ms per 640x480 image (~{1} images/hour single-threaded)",
+        es: "{0} ms por imagen de 640x480 (~{1} imágenes/hora en un solo hilo)",
+        fr: "{0} ms par image 640x480 (~{1} images/heure sur un seul thread)";
+    DoctorCpu = 886, "",
+        en: "{0} {1} with {2} logical cores",
+        es: "{0} {1} con {2} núcleos lógicos",
+        fr: "{0} {1} avec {2} cœurs logiques";
+    DoctorGpu = 887, "",
+        en: "GPU device present (not used by the detector backends)",
+        es: "dispositivo GPU presente (los motores de detección no lo usan)",
+        fr: "périphérique GPU présent (non utilisé par les moteurs de détection)";
+    DoctorNoGpu = 888, "",
+        en: "no GPU device found",
+        es: "no se encontró ningún dispositivo GPU",
+        fr: "aucun périphérique GPU trouvé";
+
+    // Manifest tools (9xx)
+    VerifyStarting = 900, "🔏 ",
+        en: "Verifying {0}",
+        es: "Verificando {0}",
+        fr: "Vérification de {0}";
+    CertificateWritten = 901, "📜 ",
+        en: "Certificate written to {0}",
+        es: "Certificado escrito en {0}",
+        fr: "Certificat écrit dans {0}";
+    VerifyPassed = 902, "✅ ",
+        en: "{0}/{1} crops verified, {2} failures, {3} warnings",
+        es: "{0}/{1} recortes verificados, {2} fallos, {3} advertencias",
+        fr: "{0}/{1} recadrages vérifiés, {2} échecs, {3} avertissements";
+    VerifyFailed = 903, "❌ ",
+        en: "{0}/{1} crops verified, {2} failures, {3} warnings",
+        es: "{0}/{1} recortes verificados, {2} fallos, {3} advertencias",
+        fr: "{0}/{1} recadrages vérifiés, {2} échecs, {3} avertissements";
+    SealStale = 904, "⚠️  ",
+        en: "{0} describes the manifest before this edit; `verify` will report a digest mismatch",
+        es: "{0} describe el manifiesto anterior a esta edición; `verify` informará de un resumen distinto",
+        fr: "{0} décrit le manifeste d'avant cette modification ; `verify` signalera une empreinte différente";
+    DedupeUnchecked = 910, "⚠️  ",
+        en: "Keeping {0} unchecked: {1}",
+        es: "Se conserva {0} sin comprobar: {1}",
+        fr: "{0} conservé sans vérification : {1}";
+    DedupeCrop = 911, "  🗑️  ",
+        en: "{0}",
+        es: "{0}",
+        fr: "{0}";
+    DedupeWouldDrop = 912, "🔍 ",
+        en: "Would drop {0} of {1} faces ({2} crops) at distance {3}",
+        es: "Se descartarían {0} de {1} rostros ({2} recortes) a distancia {3}",
+        fr: "{0} visages sur {1} ({2} recadrages) seraient supprimés à la distance {3}";
+    DedupeNone = 913, "✅ ",
+        en: "No near-duplicates among {0} faces at distance {1}",
+        es: "Ningún casi duplicado entre {0} rostros a distancia {1}",
+        fr: "Aucun quasi-doublon parmi {0} visages à la distance {1}";
+    DedupeDropped = 914, "🧹 ",
+        en: "Dropped {0} of {1} faces ({2} crops) at distance {3}",
+        es: "Descartados {0} de {1} rostros ({2} recortes) a distancia {3}",
+        fr: "{0} visages sur {1} supprimés ({2} recadrages) à la distance {3}";
+    Tagged = 920, "🏷️  ",
+        en: "Tagged {0} of {1} rows in {2}",
+        es: "Etiquetadas {0} de {1} filas en {2}",
+        fr: "{0} lignes sur {1} étiquetées dans {2}";
+    Untagged = 921, "🏷️  ",
+        en: "Untagged {0} of {1} rows in {2}",
+        es: "Etiqueta quitada de {0} de {1} filas en {2}",
+        fr: "Étiquette retirée de {0} lignes sur {1} dans {2}";
+    ExportGroup = 925, "  📦 ",
+        en: "{0}: {1} crops ({2})",
+        es: "{0}: {1} recortes ({2})",
+        fr: "{0} : {1} recadrages ({2})";
+    ExportedArchives = 926, "✅ ",
+        en: "Exported {0} of {1} crops into {2} archives in {3}",
+        es: "Exportados {0} de {1} recortes en {2} archivos comprimidos en {3}",
+        fr: "{0} recadrages sur {1} exportés dans {2} archives dans {3}";
+    ExportedFolders = 927, "✅ ",
+        en: "Exported {0} of {1} crops into {2} folders in {3}",
+        es: "Exportados {0} de {1} recortes en {2} carpetas en {3}",
+        fr: "{0} recadrages sur {1} exportés dans {2} dossiers dans {3}";
+    OutputUncheckable = 930, "⚠️  ",
+        en: "Could not check {0}: {1}",
+        es: "No se pudo comprobar {0}: {1}",
+        fr: "Impossible de vérifier {0} : {1}";
+    OutputBroken = 931, "  🗑️  ",
+        en: "{0} ({1})",
+        es: "{0} ({1})",
+        fr: "{0} ({1})";
+    OutputUnfinished = 932, "",
+        en: "unfinished write",
+        es: "escritura sin terminar",
+        fr: "écriture inachevée";
+    OutputTruncated = 933, "",
+        en: "truncated",
+        es: "truncado",
+        fr: "tronqué";
+    OutputWouldRemove = 934, "🔍 ",
+        en: "Would remove {0} broken files ({1} images checked)",
+        es: "Se eliminarían {0} archivos dañados ({1} imágenes comprobadas)",
+        fr: "{0} fichiers endommagés seraient supprimés ({1} images vérifiées)";
+    OutputComplete = 935, "✅ ",
+        en: "All {0} images in {1} are complete",
+        es: "Las {0} imágenes de {1} están completas",
+        fr: "Les {0} images de {1} sont complètes";
+    OutputRemoved = 936, "🧹 ",
+        en: "Removed {0} broken files ({1} images checked)",
+        es: "Eliminados {0} archivos dañados ({1} imágenes comprobadas)",
+        fr: "{0} fichiers endommagés supprimés ({1} images vérifiées)";
+    OutputRowsOrphaned = 937, "⚠️  ",
+        en: "{0} manifest rows point at removed crops; `verify` will report them missing",
+        es: "{0} filas del manifiesto apuntan a recortes eliminados; `verify` los dará por ausentes",
+        fr: "{0} lignes du manifeste pointent vers des recadrages supprimés ; `verify` les signalera manquants";
+    BackfillStarting = 940, "🔁 ",
+        en: "Backfilling {0} rows from {1} source images",
+        es: "Completando {0} filas a partir de {1} imágenes de origen",
+        fr: "Complétion de {0} lignes à partir de {1} images sources";
+    BackfillFailed = 941, "  ❌ ",
+        en: "{0}: {1}",
+        es: "{0}: {1}",
+        fr: "{0} : {1}";
+    Backfilled = 942, "✅ ",
+        en: "Backfilled {0} of {1} rows in {2}",
+        es: "Completadas {0} de {1} filas en {2}",
+        fr: "{0} lignes sur {1} complétées dans {2}";
+    BackfillVideoSkipped = 943, "⚠️  ",
+        en: "Skipped {0} rows cut from video frames",
+        es: "Omitidas {0} filas recortadas de fotogramas de vídeo",
+        fr: "{0} lignes issues d'images vidéo ignorées";
+    BackfillUnreadable = 944, "⚠️  ",
+        en: "{0} rows left as they were because their source could not be read",
+        es: "{0} filas sin cambios porque no se pudo leer su origen",
+        fr: "{0} lignes laissées telles quelles car leur source est illisible";
+    QaSampled = 950, "✅ ",
+        en: "Sampled {0} of {1} crops into {2}",
+        es: "Muestreados {0} de {1} recortes en {2}",
+        fr: "{0} recadrages sur {1} échantillonnés dans {2}";
+    QaStratum = 951, "   ",
+        en: "{0}: {1} of {2}",
+        es: "{0}: {1} de {2}",
+        fr: "{0} : {1} sur {2}";
+    QaSeed = 952, "   ",
+        en: "Seed: {0} (pass --seed {0} to draw the same sample)",
+        es: "Semilla: {0} (use --seed {0} para obtener la misma muestra)",
+        fr: "Graine : {0} (passez --seed {0} pour tirer le même échantillon)";
+    QaSignoff = 953, "📝 ",
+        en: "Fill in {0} and keep it with the release",
+        es: "Rellene {0} y consérvelo con la versión",
+        fr: "Remplissez {0} et conservez-le avec la version";
+    SampleSkipped = 960, "  ❌ ",
+        en: "Skipping {0}: {1}",
+        es: "Se omite {0}: {1}",
+        fr: "{0} ignoré : {1}";
+    SamplesExported = 961, "✅ ",
+        en: "Exported {0} watermarked samples to {1}",
+        es: "Exportadas {0} muestras con marca de agua a {1}",
+        fr: "{0} échantillons filigranés exportés vers {1}";
+    SampleWatermark = 962, "   ",
+        en: "Watermark: \"{0}\"",
+        es: "Marca de agua: \"{0}\"",
+        fr: "Filigrane : « {0} »";
+}
+
+/// `msg` in the selected language with `args` substituted, without prefix or code
+pub fn text(msg: Msg, args: &[&dyn Display]) -> String {
+    let lang = settings().lang;
+    let mut out = msg.template(lang).to_string();
+    for (i, arg) in args.iter().enumerate() {
+        out = out.replace(&format!("{{{}}}", i), &arg.to_string());
+    }
+    out
+}
+
+/// A full output line: prefix, optional `[FDG-nnn]` code, then the text
+pub fn line(msg: Msg, args: &[&dyn Display]) -> String {
+    let codes = settings().codes;
+    let code = if codes { format!("[FDG-{}] ", msg.code()) } else { String::new() };
+    format!("{}{}{}", msg.prefix(), code, text(msg, args))
+}
+
+/// Print a catalog message to stdout, unless `--quiet`: `say!(Extracted, count)`
+#[macro_export]
+macro_rules! say {
+    ($msg:ident $(, $arg:expr)* $(,)?) => {
+        if !$crate::i18n::quiet() {
+            $crate::progress::println(&$crate::i18n::line($crate::i18n::Msg::$msg, &[$(&$arg as &dyn std::fmt::Display),*]), false)
+        }
+    };
+}
+
+/// Print a catalog message to stderr
+#[macro_export]
+macro_rules! say_err {
+    ($msg:ident $(, $arg:expr)* $(,)?) => {
+        $crate::progress::println(&$crate::i18n::line($crate::i18n::Msg::$msg, &[$(&$arg as &dyn std::fmt::Display),*]), true)
+    };
+}
@@ -0,0 +1,55 @@
+//! Identity labels from the input layout (`--labels-from-dirs`, `--labels-csv`)
+//!
+//! For corpora stored as one folder per person (`input/alice/001.jpg`), each
+//! crop's `label` in the manifest is the folder directly below `--input` its
+//! source sits in, however deep the file is nested under it. Sources outside
+//! `--input`, such as `--input-list` entries, take the name of their parent
+//! folder; images lying directly in `--input` get no label. `labels.csv` lists
+//! every labelled crop in the manifest with its label and a numeric class id,
+//! assigned in label order, ready for recognition training.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+use crate::manifest::{self, csv_escape, MANIFEST_FILE};
+
+pub const LABELS_FILE: &str = "labels.csv";
+
+/// Identity of `source`: its top folder below `input`, or its parent folder's name
+pub fn label(input: &Path, source: &Path) -> Option<String> {
+    let folder = match source.strip_prefix(input) {
+        Ok(relative) => {
+            let mut components = relative.components();
+            let top = components.next()?;
+            // A file directly in --input has nothing above it to name it
+            components.next()?;
+            top
+        }
+        Err(_) => source.parent()?.components().next_back()?,
+    };
+    match folder {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+/// Write `labels.csv` for the manifest in `output_dir`; returns the number of identities
+pub fn write_csv(output_dir: &Path) -> Result<usize> {
+    let records = manifest::read(&output_dir.join(MANIFEST_FILE))?;
+    let mut ids: BTreeMap<&str, usize> = records.iter().filter_map(|r| r.label.as_deref()).map(|l| (l, 0)).collect();
+    for (id, value) in ids.values_mut().enumerate() {
+        *value = id;
+    }
+
+    let mut csv = String::from("crop,label,label_id\n");
+    for record in &records {
+        if let Some(label) = record.label.as_deref() {
+            csv.push_str(&format!("{},{},{}\n", csv_escape(&record.crop), csv_escape(label), ids[label]));
+        }
+    }
+    let path = output_dir.join(LABELS_FILE);
+    fs::write(&path, csv).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(ids.len())
+}
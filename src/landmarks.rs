@@ -0,0 +1,177 @@
+//! Lightweight eye and mouth localization inside a detected face box
+//!
+//! RustFace only returns boxes, so eye centers are estimated directly from the
+//! pixels: in a frontal-ish face the irises and brows are the darkest compact
+//! blobs in the upper half of the box, one on each side. The mouth is the
+//! darkest wide band below them; the nose is placed between eyes and mouth.
+
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+
+use crate::detector::{BBox, Landmarks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Head rotation in degrees: yaw is positive turned towards the viewer's right, pitch positive tilted down,
+/// roll positive with the viewer's right eye lower
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HeadPose {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+/// How far the nose tip sits in front of the eyes, in inter-eye distances
+const NOSE_DEPTH: f32 = 0.6;
+
+/// Frontal proportions from the ArcFace template: nose and mouth distance below the eye line,
+/// as a fraction of the eye-to-mouth distance and in inter-eye distances
+const FRONTAL_NOSE_RATIO: f32 = 0.49;
+const FRONTAL_MOUTH_DISTANCE: f32 = 1.155;
+
+/// Coarse head pose from five points. A nose offset from the eye axis gives yaw and pitch directly
+/// when `nose_measured` (detector keypoints). The pixel heuristic always puts the nose on that axis,
+/// so then yaw comes from how far the eyes sit off the box center and pitch from how much the
+/// eye-to-mouth distance is foreshortened, which has no sign and is reported as tilted down.
+pub fn head_pose(points: &Landmarks, bbox: &BBox, nose_measured: bool) -> HeadPose {
+    let (left, right) = (points.left_eye, points.right_eye);
+    let (dx, dy) = (right.x - left.x, right.y - left.y);
+    let d = (dx * dx + dy * dy).sqrt().max(1.0);
+    let (ux, uy) = (dx / d, dy / d);
+    let (nx, ny) = (-uy, ux);
+    let mid = Point {
+        x: (left.x + right.x) / 2.0,
+        y: (left.y + right.y) / 2.0,
+    };
+    // Offsets from the eye midpoint along the eye line and towards the chin
+    let across = |p: Point| (p.x - mid.x) * ux + (p.y - mid.y) * uy;
+    let down = |p: Point| (p.x - mid.x) * nx + (p.y - mid.y) * ny;
+
+    let mouth = Point {
+        x: (points.mouth_left.x + points.mouth_right.x) / 2.0,
+        y: (points.mouth_left.y + points.mouth_right.y) / 2.0,
+    };
+    let mouth_distance = down(mouth);
+    let depth = NOSE_DEPTH * d;
+
+    let (yaw, pitch) = if nose_measured {
+        let nose = points.nose;
+        (across(nose).atan2(depth), (down(nose) - FRONTAL_NOSE_RATIO * mouth_distance).atan2(depth))
+    } else {
+        let center = Point {
+            x: bbox.x as f32 + bbox.width as f32 / 2.0,
+            y: bbox.y as f32 + bbox.height as f32 / 2.0,
+        };
+        let foreshortened = (mouth_distance / (FRONTAL_MOUTH_DISTANCE * d)).clamp(0.0, 1.0);
+        ((-across(center)).atan2(depth), foreshortened.acos())
+    };
+
+    HeadPose {
+        yaw: yaw.to_degrees(),
+        pitch: pitch.to_degrees(),
+        roll: dy.atan2(dx).to_degrees(),
+    }
+}
+
+/// Estimated eye centers as (viewer's left, viewer's right), or `None` if no plausible pair was found
+pub fn estimate_eyes(gray: &GrayImage, bbox: &BBox) -> Option<(Point, Point)> {
+    let (bx, by) = (bbox.x as f32, bbox.y as f32);
+    let (bw, bh) = (bbox.width as f32, bbox.height as f32);
+    if bw < 16.0 || bh < 16.0 {
+        return None;
+    }
+
+    let window = (bw / 10.0).max(3.0) as u32;
+    let top = by + 0.2 * bh;
+    let bottom = by + 0.55 * bh;
+
+    let left = darkest_window(gray, bx + 0.1 * bw, bx + 0.5 * bw, top, bottom, (window, window))?;
+    let right = darkest_window(gray, bx + 0.5 * bw, bx + 0.9 * bw, top, bottom, (window, window))?;
+
+    // Reject pairs that cannot be two eyes of the same face
+    let distance = right.x - left.x;
+    let plausible = distance > 0.25 * bw && distance < 0.75 * bw && (right.y - left.y).abs() < 0.25 * bw;
+    plausible.then_some((left, right))
+}
+
+/// Five facial points: estimated eyes, a mouth line found below them, and a nose placed in between.
+/// Mouth distances follow the proportions of the ArcFace template.
+pub fn estimate_landmarks(gray: &GrayImage, bbox: &BBox) -> Option<Landmarks> {
+    let (left_eye, right_eye) = estimate_eyes(gray, bbox)?;
+
+    let (dx, dy) = (right_eye.x - left_eye.x, right_eye.y - left_eye.y);
+    let d = (dx * dx + dy * dy).sqrt();
+    let (ux, uy) = (dx / d, dy / d);
+    // Unit vector from the eye line towards the chin
+    let (nx, ny) = (-uy, ux);
+    let mid = Point {
+        x: (left_eye.x + right_eye.x) / 2.0,
+        y: (left_eye.y + right_eye.y) / 2.0,
+    };
+
+    let band = ((0.6 * d) as u32, ((0.08 * d) as u32).max(2));
+    let mouth_distance = darkest_window(
+        gray,
+        mid.x - 0.35 * d,
+        mid.x + 0.35 * d,
+        mid.y + 0.85 * d,
+        mid.y + 1.45 * d,
+        band,
+    )
+    .map_or(1.155 * d, |found| ((found.x - mid.x) * nx + (found.y - mid.y) * ny).clamp(0.95 * d, 1.35 * d));
+
+    let along = |t: f32, s: f32| Point {
+        x: mid.x + t * nx + s * ux,
+        y: mid.y + t * ny + s * uy,
+    };
+    Some(Landmarks {
+        left_eye,
+        right_eye,
+        nose: along(0.49 * mouth_distance, 0.0),
+        mouth_left: along(mouth_distance, -0.414 * d),
+        mouth_right: along(mouth_distance, 0.414 * d),
+    })
+}
+
+/// Center of the `kw`x`kh` window with the lowest mean intensity inside the given region
+fn darkest_window(gray: &GrayImage, x0: f32, x1: f32, y0: f32, y1: f32, (kw, kh): (u32, u32)) -> Option<Point> {
+    let clamp_x = |v: f32| (v.max(0.0) as u32).min(gray.width());
+    let clamp_y = |v: f32| (v.max(0.0) as u32).min(gray.height());
+    let (x0, x1, y0, y1) = (clamp_x(x0), clamp_x(x1), clamp_y(y0), clamp_y(y1));
+    if kw == 0 || x1 < x0 + kw || y1 < y0 + kh {
+        return None;
+    }
+
+    // Integral image of the search region
+    let (w, h) = ((x1 - x0) as usize, (y1 - y0) as usize);
+    let mut integral = vec![0u64; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0u64;
+        for x in 0..w {
+            row += gray.get_pixel(x0 + x as u32, y0 + y as u32)[0] as u64;
+            integral[(y + 1) * (w + 1) + x + 1] = integral[y * (w + 1) + x + 1] + row;
+        }
+    }
+
+    let (kw, kh) = (kw as usize, kh as usize);
+    let mut best: Option<(u64, usize, usize)> = None;
+    for y in 0..=h - kh {
+        for x in 0..=w - kw {
+            let sum = integral[(y + kh) * (w + 1) + x + kw] + integral[y * (w + 1) + x]
+                - integral[y * (w + 1) + x + kw]
+                - integral[(y + kh) * (w + 1) + x];
+            if best.is_none_or(|(b, _, _)| sum < b) {
+                best = Some((sum, x, y));
+            }
+        }
+    }
+
+    best.map(|(_, x, y)| Point {
+        x: x0 as f32 + x as f32 + kw as f32 / 2.0,
+        y: y0 as f32 + y as f32 + kh as f32 / 2.0,
+    })
+}
@@ -1,234 +1,2261 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use image::{DynamicImage, GenericImageView, GrayImage, RgbImage};
-use rustface::{Detector, FaceInfo, ImageData};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
 use walkdir::WalkDir;
 
-#[derive(Parser)]
+mod annotations;
+mod archive;
+mod backfill;
+mod burst;
+mod checkpoint;
+mod clips;
+mod cloud;
+mod composition;
+mod contact_sheet;
+mod config;
+mod control;
+mod crop;
+mod custody;
+mod decode;
+mod decoders;
+mod dedupe;
+mod detect;
+mod detection_cache;
+mod detect_one;
+mod detector;
+mod disk;
+mod doctor;
+#[cfg(feature = "embeddings")]
+mod embeddings;
+mod encode;
+mod ensemble;
+mod events;
+mod export;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "gui")]
+mod gui;
+mod heatmap;
+mod i18n;
+mod labels;
+mod landmarks;
+mod manifest;
+mod metadata;
+mod model;
+mod naming;
+mod nice;
+mod orientation;
+mod phash;
+mod pipeline;
+mod planner;
+mod profile;
+mod progress;
+mod qa;
+mod quality;
+mod redact;
+mod rejected;
+mod results_db;
+mod regress;
+mod relax;
+mod remote;
+mod report;
+mod rotate;
+mod sampling;
+mod shard;
+mod run_stats;
+mod sample_export;
+mod show;
+mod split;
+mod shutdown;
+mod stats;
+mod stdio;
+mod store;
+mod stream;
+mod tag;
+mod tee;
+mod timeout;
+mod tracking;
+mod verify_output;
+mod visualize;
+mod watch;
+mod watermark;
+mod wizard;
+#[cfg(feature = "yolov8")]
+mod yolo;
+
+use annotations::AnnotationFormat;
+use checkpoint::CheckpointWriter;
+use clips::ClipLength;
+use composition::{Composition, FaceTraits};
+use contact_sheet::{ContactSheets, Grid};
+use control::PriorityControl;
+use crop::{Framing, OutputSize, PadFill, PadMode, ResizeFilter, Variant};
+use decode::Color;
+use detector::{Backend, Detection, FaceDetectorBackend};
+use disk::{ByteSize, DiskFull, DiskGuard};
+use encode::OutputFormat;
+use events::{Event, EventSink};
+use export::ExportLayout;
+use heatmap::Heatmap;
+use run_stats::RunStats;
+use i18n::Lang;
+use landmarks::HeadPose;
+use manifest::{ManifestRecord, ManifestWriter, Provenance};
+use naming::{NameFields, OnCollision, Placement};
+use nice::NiceMode;
+use orientation::Facing;
+use profile::Profile;
+use phash::SeenFaces;
+use pipeline::Jobs;
+use quality::{FaceRank, Tier};
+use rejected::RejectedFaces;
+use relax::Shortfall;
+use results_db::ResultsDb;
+use split::{Split, SplitGroup, SplitRatios};
+use stdio::StdinFormat;
+use tee::{Tee, TeeSpec};
+
+#[derive(Parser, Clone, Serialize, Deserialize)]
 #[command(name = "face_extractor")]
 #[command(about = "Extract faces from images using RustFace detector")]
 struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Commands>,
+
+    /// Load settings from a TOML file; flags given on the command line take precedence
+    #[arg(long, global = true)]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
+    /// Print the effective settings as TOML, after config file and profile, and exit
+    #[arg(long, global = true)]
+    #[serde(skip)]
+    dump_config: bool,
+
     /// Input directory containing images
-    #[arg(short, long, default_value = "./images")]
+    #[arg(short, long, global = true, default_value = "./images")]
     input: PathBuf,
 
+    /// Read frames from a live stream instead of --input: an RTSP/HTTP URL or a V4L2 device like /dev/video0 (needs ffmpeg)
+    #[arg(long, global = true, conflicts_with_all = ["export_sequences", "export_clips", "resume", "skip_existing", "chunk_size", "relax_on_shortfall", "min_yield"])]
+    input_stream: Option<String>,
+
+    /// Read inputs from stdin instead of --input, as paths one per line or as length-prefixed image bytes, and print a JSON result per input to stdout
+    #[arg(long, global = true, value_enum, num_args = 0..=1, default_missing_value = "paths", conflicts_with_all = ["input_stream", "input_list", "watch", "export_sequences", "export_clips", "resume", "skip_existing", "chunk_size", "relax_on_shortfall", "min_yield"])]
+    stdin: Option<StdinFormat>,
+
+    /// Read the images to process from a file instead of --input: one local path or http(s) URL per line (URLs need the remote build feature), or a .csv with path and optional label and weight columns
+    #[arg(long, global = true, conflicts_with_all = ["input_stream", "watch", "export_sequences", "export_clips"])]
+    input_list: Option<PathBuf>,
+
+    /// Parallel downloads for URLs in --input-list
+    #[arg(long, global = true, default_value_t = 8)]
+    download_jobs: usize,
+
+    /// Keep watching --input after the existing images and process new ones as they appear
+    #[arg(long, global = true, conflicts_with_all = ["input_stream", "export_sequences", "export_clips", "chunk_size", "relax_on_shortfall", "min_yield"])]
+    watch: bool,
+
+    /// Frames per second sampled from --input-stream
+    #[arg(long, global = true, default_value = "2")]
+    stream_fps: f64,
+
     /// Output directory for extracted faces
-    #[arg(short, long, default_value = "./faces")]
+    #[arg(short, long, global = true, default_value = "./faces")]
     output: PathBuf,
 
     /// Path to the face detection model
-    #[arg(short, long, default_value = "./model.bin")]
+    #[arg(short, long, global = true, default_value = "./model.bin")]
     model: PathBuf,
 
+    /// Face detector implementation (yolov8 requires a build with --features yolov8)
+    #[arg(long, global = true, value_enum, default_value = "rustface")]
+    backend: Backend,
+
+    /// Also run these backends on every image and combine their faces (BACKEND or BACKEND=MODEL, comma-separated)
+    #[arg(long, global = true, value_delimiter = ',', value_name = "BACKEND[=MODEL]", conflicts_with = "small_backend")]
+    ensemble: Vec<ensemble::Member>,
+
+    /// Backends that must find a face for --ensemble to keep it (1 keeps the union)
+    #[arg(long, global = true, default_value = "1")]
+    ensemble_votes: usize,
+
+    /// Overlap (IoU) at which boxes from different --ensemble backends count as the same face
+    #[arg(long, global = true, default_value = "0.4")]
+    ensemble_iou: f32,
+
+    /// Minimum YOLOv8 confidence (0.0-1.0); --threshold applies to rustface only
+    #[arg(long, global = true, default_value = "0.5")]
+    yolo_confidence: f64,
+
     /// Minimum face size (pixels)
-    #[arg(long, default_value = "40")]
+    #[arg(long, global = true, default_value = "40")]
     min_face_size: u32,
 
     /// Confidence threshold (0.0-5.0)
-    #[arg(long, default_value = "2.0")]
+    #[arg(long, global = true, default_value = "2.0")]
     threshold: f64,
 
+    /// RustFace score a kept face must exceed, on top of --threshold
+    #[arg(long, global = true, default_value = "2.0")]
+    rustface_min_score: f64,
+
+    /// Preset of speed/recall settings; explicit flags and config values take precedence
+    #[arg(long, global = true, value_enum)]
+    profile: Option<Profile>,
+
+    /// Scale step between image pyramid levels (closer to 1 finds more faces, slower)
+    #[arg(long, global = true, default_value = "0.8")]
+    pyramid_scale: f32,
+
+    /// Sliding window step in pixels (smaller finds more faces, slower)
+    #[arg(long, global = true, default_value = "4")]
+    window_step: u32,
+
+    /// Smallest share of the image a face may cover (0-1)
+    #[arg(long, global = true, default_value = "0.02")]
+    min_face_ratio: f64,
+
+    /// Largest share of the image a face may cover (0-1)
+    #[arg(long, global = true, default_value = "0.4")]
+    max_face_ratio: f64,
+
+    /// Keep at most this many faces from each image, the best ones by --rank-faces-by
+    #[arg(long, global = true, value_name = "K", conflicts_with = "relax_on_shortfall")]
+    max_faces_per_image: Option<usize>,
+
+    /// What --max-faces-per-image ranks the faces of an image by
+    #[arg(long, global = true, value_enum, default_value = "score", requires = "max_faces_per_image")]
+    rank_faces_by: FaceRank,
+
+    /// Narrowest face box accepted, as width over height
+    #[arg(long, global = true, default_value = "0.5")]
+    min_aspect: f64,
+
+    /// Widest face box accepted, as width over height
+    #[arg(long, global = true, default_value = "2.0")]
+    max_aspect: f64,
+
+    /// Narrowest and shortest face box kept, in pixels
+    #[arg(long, global = true, default_value = "40")]
+    min_crop_size: u32,
+
+    /// Margin around standard crops, as a fraction of the face box width plus height (loose crops get twice this)
+    #[arg(long, global = true, default_value = "0.125")]
+    padding: f64,
+
+    /// --padding as a percentage, e.g. 20 for 0.2
+    #[arg(long, global = true, conflicts_with = "padding")]
+    padding_pct: Option<f64>,
+
+    /// Grow the shorter side of padded crops so they come out square
+    #[arg(long, global = true)]
+    square_crop: bool,
+
+    /// Crops reaching past the image border: clip them there, or keep the full framing and reflect or fill the rest
+    #[arg(long, global = true, value_enum, alias = "edge-policy", default_value = "clip")]
+    pad_mode: PadMode,
+
+    /// Fill for the part of crops outside the image under --pad-mode constant
+    #[arg(long, global = true, value_enum, default_value = "black")]
+    pad_fill: PadFill,
+
+    /// Detector for small images (longest side up to --small-image-side); --backend handles the larger ones
+    #[arg(long, global = true, value_enum)]
+    small_backend: Option<Backend>,
+
+    /// Model for --small-backend [default: --model]
+    #[arg(long, global = true, requires = "small_backend")]
+    small_model: Option<PathBuf>,
+
+    /// Longest side, in pixels, up to which an image counts as small for --small-backend
+    #[arg(long, global = true, default_value = "640")]
+    small_image_side: u32,
+
+    /// Detect on a thumbnail with this longest side first and skip images where it finds no face
+    #[arg(long, global = true)]
+    prescreen: Option<u32>,
+
+    /// Detect on a copy of larger images scaled down to this longest side, then crop from the original
+    #[arg(long, global = true, value_name = "PIXELS")]
+    max_detect_dim: Option<u32>,
+
+    /// When an image yields no face, detect again with it turned 90, 180 and 270 degrees
+    #[arg(long, global = true)]
+    rotate_retry: bool,
+
+    /// Record every image, detection, rejection reason and crop in this SQLite database (requires a build with --features sqlite)
+    #[arg(long, global = true, value_name = "PATH")]
+    db: Option<PathBuf>,
+
+    /// Reuse detections stored in this cache file, keyed by image content and detector settings, and store new ones
+    #[arg(long, global = true, value_name = "PATH")]
+    detection_cache: Option<PathBuf>,
+
+    /// Give up on an image whose detection takes longer than this many seconds, count it as failed and go on
+    #[arg(long, global = true, value_name = "SECS")]
+    per_image_timeout: Option<f64>,
+
+    /// Stop before the output directory grows past this size, in bytes or with a K, M, G or T suffix
+    #[arg(long, global = true, value_name = "SIZE")]
+    max_output_bytes: Option<ByteSize>,
+
+    /// Stop before the disk holding the output has less than this free; 0 turns the check off
+    #[arg(long, global = true, value_name = "SIZE", default_value = "256M")]
+    min_free_space: ByteSize,
+
+    /// When the output runs out of room: `stop` the run, or `pause` until there is room again
+    #[arg(long, global = true, value_enum, default_value = "stop")]
+    on_disk_full: DiskFull,
+
+    /// Drop detections overlapping a higher-scoring one by at least this IoU (0-1)
+    #[arg(long, global = true, value_name = "IOU")]
+    nms_iou: Option<f32>,
+
+    /// Merge the boxes --nms-iou groups into one score-weighted box instead of keeping only the best
+    #[arg(long, global = true, requires = "nms_iou")]
+    nms_merge: bool,
+
+    /// Skip the per-crop quality score (no tiers, no quality in the manifest)
+    #[arg(long, global = true)]
+    skip_quality: bool,
+
     /// Target number of faces to extract
-    #[arg(long, default_value = "5000")]
+    #[arg(long, global = true, default_value = "5000")]
     target_faces: usize,
+
+    /// Split the corpus into chunks of this many images, print an estimated plan and checkpoint after each chunk
+    #[arg(long, global = true)]
+    chunk_size: Option<usize>,
+
+    /// Number of images sampled to calibrate per-image runtime for the chunk plan
+    #[arg(long, global = true, default_value = "20")]
+    calibration_sample: usize,
+
+    /// Print the chunk plan and exit without extracting (requires --chunk-size)
+    #[arg(long, global = true)]
+    plan_only: bool,
+
+    /// Skip images recorded in the output's checkpoint and keep counting faces from where that run stopped
+    #[arg(long, global = true, conflicts_with_all = ["chunk_size", "export_sequences", "export_clips"])]
+    resume: bool,
+
+    /// Skip images the output's run store lists as having crops and add to its manifest, so reruns only process new files
+    #[arg(long, global = true, conflicts_with_all = ["chunk_size", "export_sequences", "export_clips"])]
+    skip_existing: bool,
+
+    /// Images processed between checkpoint writes
+    #[arg(long, global = true, default_value = "100")]
+    checkpoint_every: usize,
+
+    /// If the corpus runs out before --target-faces, retry rejected candidates at progressively relaxed thresholds
+    #[arg(long, global = true, conflicts_with_all = ["export_sequences", "export_clips"])]
+    relax_on_shortfall: bool,
+
+    /// Faces per image the strict pass should average; below it, images without a face are retried once at lowered thresholds
+    #[arg(long, global = true, value_name = "RATE", conflicts_with_all = ["relax_on_shortfall", "export_sequences", "export_clips"])]
+    min_yield: Option<f64>,
+
+    /// Share of the detector threshold and score floor the --min-yield pass runs at
+    #[arg(long, global = true, default_value = "0.7", requires = "min_yield")]
+    second_pass_factor: f64,
+
+    /// Mirror crops so every face looks the same way (left or right); the flip is recorded in the manifest
+    #[arg(long, global = true, value_enum)]
+    normalize_facing: Option<Facing>,
+
+    /// Framing variants saved for every accepted face, linked by a shared detection ID in the manifest
+    #[arg(long, global = true, value_enum, value_delimiter = ',', default_value = "standard")]
+    variants: Vec<Variant>,
+
+    /// Warp every crop onto the 112x112 ArcFace landmark template, ready for recognition training (same as --variants arcface)
+    #[arg(long, global = true, conflicts_with = "variants")]
+    align: bool,
+
+    /// Square sizes (pixels) to export every crop at, each into its own subdirectory (e.g. 112,224,512)
+    #[arg(long, global = true, value_delimiter = ',')]
+    output_sizes: Vec<u32>,
+
+    /// Resize every saved crop to exactly this resolution, WIDTHxHEIGHT (cropped to fit, never stretched)
+    #[arg(long, global = true, conflicts_with = "output_sizes")]
+    output_size: Option<OutputSize>,
+
+    /// Resampling filter for --output-size and --output-sizes
+    #[arg(long, global = true, value_enum, default_value = "lanczos")]
+    resize_filter: ResizeFilter,
+
+    /// Crop file name before the variant and extension; placeholders: {stem} {index} {score} {score100} {x} {y} {w} {h} {hash} {timestamp}, with optional :05 or :.2 specs
+    #[arg(long, global = true, default_value = naming::DEFAULT_TEMPLATE)]
+    name_template: String,
+
+    /// Name crops by a hash of their pixels instead, so exact duplicates are saved once and names stay stable across reruns
+    #[arg(long, global = true, conflicts_with_all = ["name_template", "on_collision"])]
+    name_by_hash: bool,
+
+    /// When a crop's file name already exists in --output: overwrite it, skip the new crop, or rename it with a _N suffix
+    #[arg(long, global = true, value_enum, default_value = "overwrite")]
+    on_collision: OnCollision,
+
+    /// File format of saved crops (png is lossless)
+    #[arg(long, global = true, value_enum, default_value = "jpg")]
+    format: OutputFormat,
+
+    /// JPEG quality (1-100) for --format jpg
+    #[arg(long, global = true, default_value = "75", value_parser = clap::value_parser!(u8).range(1..=100))]
+    jpeg_quality: u8,
+
+    /// Lossy WebP quality (0-100) for --format webp; lossless when omitted
+    #[arg(long, global = true, value_parser = clap::value_parser!(u8).range(0..=100))]
+    webp_quality: Option<u8>,
+
+    /// Reject faces whose mean luma (0-255) is below this, such as backlit silhouettes
+    #[arg(long, global = true)]
+    min_brightness: Option<f64>,
+
+    /// Reject faces whose mean luma (0-255) is above this, such as blown-out faces
+    #[arg(long, global = true)]
+    max_brightness: Option<f64>,
+
+    /// Reject faces whose luma spread (5th to 95th percentile, 0-255) is below this
+    #[arg(long, global = true)]
+    min_contrast: Option<f64>,
+
+    /// Reject faces turned further left or right than this many degrees (estimated from landmarks)
+    #[arg(long, global = true)]
+    max_yaw: Option<f32>,
+
+    /// Reject faces tilted further up or down than this many degrees
+    #[arg(long, global = true)]
+    max_pitch: Option<f32>,
+
+    /// Reject faces whose eye line is rotated more than this many degrees
+    #[arg(long, global = true)]
+    max_roll: Option<f32>,
+
+    /// Write heatmap.png of where faces sit in their source frames and break the positions down in the report
+    #[arg(long, global = true, conflicts_with_all = ["export_sequences", "export_clips"])]
+    heatmap: bool,
+
+    /// Lay out thumbnails of saved faces on PNG contact sheets of COLUMNSxROWS cells, for scanning a run for junk
+    #[arg(long, global = true, value_name = "GRID", num_args = 0..=1, default_missing_value = "10x10", conflicts_with_all = ["export_sequences", "export_clips"])]
+    contact_sheet: Option<Grid>,
+
+    /// Drop faces whose perceptual hash is close to one already saved in this run
+    #[arg(long, global = true)]
+    dedup: bool,
+
+    /// Largest Hamming distance (of 64 bits) at which --dedup treats two faces as duplicates
+    #[arg(long, global = true, default_value = "6")]
+    dedup_distance: u32,
+
+    /// ArcFace-style ONNX recognition model; writes an embedding per face to embeddings.npy (requires a build with --features embeddings)
+    #[arg(long, global = true, conflicts_with_all = ["export_sequences", "export_clips"])]
+    embeddings: Option<PathBuf>,
+
+    /// Stream JSON progress events to clients of a Unix socket created at this path
+    #[arg(long, global = true)]
+    events_socket: Option<PathBuf>,
+
+    /// Background color that transparent pixels are composited over before detection and cropping
+    #[arg(long, global = true, default_value = "white")]
+    alpha_background: Color,
+
+    /// Normalize grayscale and low-bit-depth scans (contrast stretch, dither smoothing) before detection
+    #[arg(long, global = true)]
+    enhance_scans: bool,
+
+    /// Process every frame of photo bursts instead of only the sharpest one
+    #[arg(long, global = true)]
+    keep_all_burst: bool,
+
+    /// Process only this many images, drawn at random from those found, for a pilot run
+    #[arg(long, global = true, value_name = "N", conflicts_with_all = ["sample_fraction", "stdin", "input_stream"])]
+    sample: Option<usize>,
+
+    /// Process only this share of the images found (0-1), drawn at random
+    #[arg(long, global = true, value_name = "SHARE", conflicts_with_all = ["stdin", "input_stream"])]
+    sample_fraction: Option<f64>,
+
+    /// Process the images in random order
+    #[arg(long, global = true, conflicts_with_all = ["stdin", "input_stream"])]
+    shuffle: bool,
+
+    /// Seed for random draws (--sample, --sample-fraction, --shuffle, and the qa-sample and show --random picks), to draw the same again
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// Process only this shard of the corpus, from 0 to --shard-count minus 1
+    #[arg(long, global = true, value_name = "I", requires = "shard_count")]
+    shard_index: Option<usize>,
+
+    /// Split the corpus into this many shards, one per machine, by a hash of each image's path
+    #[arg(long, global = true, value_name = "N", requires = "shard_index", conflicts_with_all = ["stdin", "input_stream", "watch", "export_sequences", "export_clips"])]
+    shard_count: Option<usize>,
+
+    /// Treat each video file and frame folder as a clip: track faces across frames and write stabilized crops, one folder per track
+    #[arg(long, global = true)]
+    export_sequences: bool,
+
+    /// Cut face-centered video clips of this length (e.g. 2s) from every track, re-encoded with ffmpeg
+    #[arg(long, global = true)]
+    export_clips: Option<ClipLength>,
+
+    /// YAML spec of category quotas (frontal, profile, low_light, occluded) and a per-identity cap to fill during collection
+    #[arg(long, global = true)]
+    composition: Option<PathBuf>,
+
+    /// Sort crops into gold/, silver/ and bronze/ by combined quality score
+    #[arg(long, global = true)]
+    tiered_output: bool,
+
+    /// Sort crops into train/, val/ and test/ by these shares, e.g. 80/10/10; all faces of a group share a split
+    #[arg(long, global = true, value_name = "TRAIN/VAL/TEST")]
+    split: Option<SplitRatios>,
+
+    /// What stays together in one split: faces of the same source image, or of the same identity label
+    #[arg(long, global = true, value_enum, default_value = "source", requires = "split")]
+    split_group: SplitGroup,
+
+    /// Minimum quality (0-1) for the gold and silver tiers, as GOLD,SILVER
+    #[arg(long, global = true, value_delimiter = ',', default_value = "0.7,0.45")]
+    tier_cutoffs: Vec<f64>,
+
+    /// Record the folder below --input each source sits in (one folder per person) as the identity label of its crops
+    #[arg(long, global = true)]
+    labels_from_dirs: bool,
+
+    /// Also write labels.csv with each labelled crop, its label and a numeric class id
+    #[arg(long, global = true, requires = "labels_from_dirs")]
+    labels_csv: bool,
+
+    /// Tag every crop of this run, as AXIS:VALUE (repeatable, e.g. --tag source:cctv --tag cond:night)
+    #[arg(long = "tag", global = true, value_name = "AXIS:VALUE")]
+    tags: Vec<String>,
+
+    /// Write a JSON sidecar with the crop's full metadata next to every crop
+    #[arg(long, global = true)]
+    sidecars: bool,
+
+    /// Also write the manifest as manifest.csv (one row per crop)
+    #[arg(long, global = true)]
+    manifest_csv: bool,
+
+    /// Also save source images with detector training annotations in these formats (yolo, voc)
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    annotations: Vec<AnnotationFormat>,
+
+    /// Also save a copy of each source image with the other faces blurred and the extracted ones outlined
+    #[arg(long, global = true)]
+    redacted_originals: bool,
+
+    /// Save crops of faces the filters reject here, named and tagged with the filters they failed
+    #[arg(long, global = true, value_name = "DIR", conflicts_with_all = ["export_sequences", "export_clips"])]
+    save_rejected: Option<PathBuf>,
+
+    /// Save crops without any metadata from the source image (the default)
+    #[arg(long, global = true)]
+    strip_metadata: bool,
+
+    /// Copy the source's EXIF (camera, capture time, GPS) into JPEG and PNG crops, minus the thumbnail
+    #[arg(long, global = true, conflicts_with = "strip_metadata")]
+    preserve_metadata: bool,
+
+    /// Only detect: write per-image JSON of face boxes and scores instead of crops (same as the detect subcommand)
+    #[arg(long, global = true)]
+    no_crop: bool,
+
+    /// Extra output fed from the same pass: crops:DIR, tar:FILE.tar.gz or coco:FILE.json, with optional ,format=,size=,quality= (repeatable)
+    #[arg(long, global = true, value_name = "KIND:PATH", conflicts_with_all = ["export_sequences", "export_clips", "resume", "skip_existing", "chunk_size"])]
+    tee: Vec<TeeSpec>,
+
+    /// Language of progress and summary output
+    #[arg(long, global = true, value_enum, default_value = "en")]
+    lang: Lang,
+
+    /// Prefix output lines with stable message codes (e.g. [FDG-110]) for runbooks and log search
+    #[arg(long, global = true)]
+    message_codes: bool,
+
+    /// Print nothing but errors: no progress bar, per-image lines or summary
+    #[arg(long, global = true)]
+    quiet: bool,
+
+    /// Exit with status 2 when any image failed and 3 when the target was not reached, not only on total failure; with verify, fail on anything unproven
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// End the run by printing its results and settings as one JSON object on the last line of stdout
+    #[arg(long, global = true, conflicts_with_all = ["stdin", "export_sequences", "export_clips"])]
+    output_json: bool,
+
+    /// Pause between images while other processes keep the CPU busy, for background runs on shared machines
+    #[arg(long, global = true, conflicts_with = "input_stream")]
+    nice_mode: bool,
+
+    /// CPU share used by other processes above which `--nice-mode` pauses (0-1)
+    #[arg(long, global = true, default_value = "0.5")]
+    nice_max_load: f64,
+
+    /// Load models even if their SHA-256 does not match the model registry
+    #[arg(long, global = true)]
+    skip_checksum: bool,
+
+    /// Worker threads per stage (decode, detect), or `auto` to tune them from queue depths and CPU load
+    #[arg(long, global = true, default_value = "1")]
+    jobs: Jobs,
+}
+
+#[derive(Subcommand, Clone)]
+enum Commands {
+    /// Detect, filter and crop faces from --input into --output (what runs without a subcommand)
+    Extract,
+
+    /// Detect and filter faces like extract, but only write their boxes to detections.jsonl
+    Detect,
+
+    /// Summarize the crops of the run in --output from its manifest
+    Stats,
+
+    /// Remove near-duplicate faces (within --dedup-distance) from the run in --output
+    Dedupe {
+        /// List the crops that would be removed without touching anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Interactively describe your corpus and goal, then write a tuned config file
+    Init {
+        /// Where to write the generated config
+        #[arg(default_value = "config.toml")]
+        path: PathBuf,
+
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check the model, image codecs, detector and hardware, and print a pass/fail report
+    Doctor,
+
+    /// Pack the crops of a finished run into one release archive per license (or other field)
+    Export {
+        /// Output directory of a previous run (must contain manifest.jsonl)
+        #[arg(long, default_value = "./faces")]
+        from: PathBuf,
+
+        /// Directory to write the archives into
+        #[arg(long, default_value = "./release")]
+        to: PathBuf,
+
+        /// Only rows matching this condition, e.g. "license in ('cc-by','internal') && score>3"
+        #[arg(long = "where")]
+        filter: Option<String>,
+
+        /// Fields or tag axes whose values each get their own archive or folder (e.g. source,cond)
+        #[arg(long, value_delimiter = ',', default_value = "license")]
+        split_by: Vec<String>,
+
+        /// Write gzipped tars or plain folders
+        #[arg(long, value_enum, default_value = "archives")]
+        layout: ExportLayout,
+    },
+
+    /// Export a watermarked subset of a finished run for sharing with partners
+    ExportSamples {
+        /// Output directory of a previous run (must contain manifest.jsonl)
+        #[arg(long, default_value = "./faces")]
+        from: PathBuf,
+
+        /// Directory to write the watermarked samples into
+        #[arg(long)]
+        to: PathBuf,
+
+        /// Number of crops to export, spread evenly over the run
+        #[arg(long, default_value = "25")]
+        count: usize,
+
+        /// Text stamped across every sample
+        #[arg(long, default_value = "SAMPLE - DO NOT REDISTRIBUTE")]
+        watermark_text: String,
+    },
+
+    /// Draw a stratified random sample of crops with montage pages and a QA sign-off sheet
+    QaSample {
+        /// Output directory of a previous run (must contain manifest.jsonl)
+        #[arg(long, default_value = "./faces")]
+        from: PathBuf,
+
+        /// Directory to write the sample, montages and signoff.csv into
+        #[arg(long, default_value = "./qa")]
+        to: PathBuf,
+
+        /// Number of crops to sample
+        #[arg(long, default_value = "200")]
+        n: usize,
+
+        /// Spread the sample evenly over these strata
+        #[arg(long, value_enum, default_value = "score")]
+        stratify: qa::Stratify,
+    },
+
+    /// Write report.html into a finished run: its crops grouped by source image, with scores and rejections
+    Report {
+        /// Output directory of a previous run (must contain manifest.jsonl)
+        #[arg(long, default_value = "./faces")]
+        from: PathBuf,
+
+        /// --save-rejected directory of the same run, to list what each filter turned down per source
+        #[arg(long)]
+        rejected: Option<PathBuf>,
+
+        /// Most sources to show
+        #[arg(long, default_value = "1000")]
+        limit: usize,
+    },
+
+    /// Draw crops from a finished run right in the terminal (kitty graphics or sixel)
+    Show {
+        /// Output directory of a previous run (must contain manifest.jsonl)
+        #[arg(long, default_value = "./faces")]
+        from: PathBuf,
+
+        /// Show this many crops picked at random instead of the first ones
+        #[arg(long)]
+        random: Option<usize>,
+
+        /// Number of crops to show without --random
+        #[arg(long, default_value = "20")]
+        limit: usize,
+
+        /// Side of each thumbnail in pixels
+        #[arg(long, default_value = "128", value_parser = clap::value_parser!(u32).range(16..))]
+        size: u32,
+
+        /// Terminal graphics protocol
+        #[arg(long, value_enum, default_value = "auto")]
+        protocol: show::Protocol,
+    },
+
+    /// Check a finished run against its manifest and seal, and issue a verification certificate
+    Verify {
+        /// Manifest of the run to check; crops are resolved relative to its folder
+        #[arg(long, default_value = "./faces/manifest.jsonl")]
+        manifest: PathBuf,
+
+        /// Write the certificate JSON here instead of printing it
+        #[arg(long)]
+        certificate: Option<PathBuf>,
+    },
+
+    /// Find and remove truncated crops and unfinished `.tmp` files in --output left by a crash
+    VerifyOutput {
+        /// List the broken files without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Draw accepted (green) and rejected (red, with the failed filters) faces onto copies of the input images
+    Visualize {
+        /// Directory to write the annotated copies into
+        #[arg(long, default_value = "./visualize")]
+        to: PathBuf,
+    },
+
+    /// Detect faces in one image and show every detection with each filter's verdict
+    DetectOne {
+        /// Image to inspect
+        image: PathBuf,
+
+        /// Open the annotated image in the system viewer
+        #[arg(long)]
+        show: bool,
+
+        /// Save the annotated image here
+        #[arg(long)]
+        save: Option<PathBuf>,
+    },
+
+    /// Re-detect the sources of an older manifest with the current settings and report lost and added faces
+    Regress {
+        /// Manifest of the run to compare against
+        #[arg(long)]
+        baseline: PathBuf,
+
+        /// Directory for annotated images of changed sources and an index.html
+        #[arg(long, default_value = "./regress")]
+        gallery: PathBuf,
+
+        /// Minimum overlap (IoU) for a current box to count as the same face as a baseline box
+        #[arg(long, default_value = "0.5")]
+        iou: f32,
+    },
+
+    /// Fill in fields added by newer analysis stages on an existing output
+    Backfill {
+        /// Manifest to enrich
+        #[arg(long, default_value = "./faces/manifest.jsonl")]
+        manifest: PathBuf,
+
+        /// Stages to run, comma-separated
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "quality,landmarks")]
+        stages: Vec<backfill::Stage>,
+    },
+
+    /// Add or remove curator tags on manifest rows in bulk
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// Summarize the run in --output from its run store, also while it is still going
+    Status {
+        /// List every failed image with its error
+        #[arg(long)]
+        failed: bool,
+    },
+
+    /// Open the graphical front-end
+    #[cfg(feature = "gui")]
+    Gui,
+
+    /// Serve face detection over gRPC: a DetectFaces stream of images in, filtered boxes out
+    #[cfg(feature = "grpc")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        listen: String,
+
+        /// Also answer HTTP GET /readyz on this address: 503 until every worker's detector has loaded, then 200
+        #[arg(long, value_name = "ADDR")]
+        ready_listen: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum TagAction {
+    /// Set KEY=VALUE on every matching row
+    Add {
+        /// Tag to set, as AXIS:VALUE or KEY=VALUE (e.g. cond:night, occlusion=partial)
+        tag: String,
+
+        /// Only rows matching this condition, e.g. "score<2.5 && variant==standard"
+        #[arg(long = "where")]
+        filter: Option<String>,
+
+        /// Manifest to edit
+        #[arg(long, default_value = "./faces/manifest.jsonl")]
+        manifest: PathBuf,
+    },
+
+    /// Remove KEY from every matching row
+    Remove {
+        /// Tag key to remove
+        key: String,
+
+        /// Only rows matching this condition
+        #[arg(long = "where")]
+        filter: Option<String>,
+
+        /// Manifest to edit
+        #[arg(long, default_value = "./faces/manifest.jsonl")]
+        manifest: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
-    
-    println!("🚀 Face Dataset Generator");
-    println!("Target: {} faces", args.target_faces);
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)?;
+    i18n::init(args.lang, args.message_codes, args.quiet);
+
+    match &args.command {
+        Some(Commands::Init { path, force }) => return wizard::run(path, *force),
+        Some(Commands::ExportSamples { from, to, count, watermark_text }) => {
+            return sample_export::run(from, to, *count, watermark_text);
+        }
+        Some(Commands::Export { from, to, filter, split_by, layout }) => {
+            return export::run(from, to, filter.as_deref(), split_by, *layout);
+        }
+        Some(Commands::QaSample { from, to, n, stratify }) => {
+            return qa::run(from, to, *n, *stratify, args.seed);
+        }
+        Some(Commands::Report { from, rejected, limit }) => return report::run(from, rejected.as_deref(), *limit),
+        Some(Commands::Show { from, random, limit, size, protocol }) => {
+            return show::run(from, *random, *limit, *size, *protocol, args.seed);
+        }
+        Some(Commands::Verify { manifest, certificate }) => {
+            // --strict fails on anything unproven: unhashed records, unlisted crops, a missing or unsigned seal
+            return custody::verify(manifest, args.strict, certificate.as_deref());
+        }
+        Some(Commands::Backfill { manifest, stages }) => {
+            return backfill::run(manifest, stages, &args);
+        }
+        Some(Commands::Status { failed }) => return store::status(&args.output, *failed),
+        Some(Commands::Stats) => return stats::run(&args.output),
+        Some(Commands::VerifyOutput { dry_run }) => return verify_output::run(&args.output, *dry_run),
+        Some(Commands::Tag { action: TagAction::Add { tag, filter, manifest } }) => {
+            let (key, value) = tag::parse(tag)?;
+            return tag::run(manifest, &key, Some(&value), filter.as_deref());
+        }
+        Some(Commands::Tag { action: TagAction::Remove { key, filter, manifest } }) => {
+            return tag::run(manifest, key, None, filter.as_deref());
+        }
+        _ => {}
+    }
+
+    #[cfg(feature = "gui")]
+    if let Some(Commands::Gui) = &args.command {
+        return gui::run();
+    }
+
+    let mut from_file = config::FileSettings::default();
+    if let Some(config_path) = args.config.clone() {
+        from_file = config::apply_file(&mut args, &matches, &config_path)?;
+    }
+    let mut from_profile = Vec::new();
+    if let Some(profile) = args.profile {
+        from_profile = config::apply_profile(&mut args, &matches, profile, &from_file.keys)?;
+    }
+    if args.dump_config {
+        print!("{}", config::dump(&args)?);
+        return Ok(());
+    }
+
+    // With --stdin, stdout carries only JSON results, so every message from here on goes to stderr
+    if args.stdin.is_some() {
+        progress::reserve_stdout();
+    }
+
+    // Settings from the file and profile may change the language, so they're reported after it's set again
+    i18n::init(args.lang, args.message_codes, args.quiet);
+    if let Some(config_path) = &args.config {
+        for key in &from_file.unknown {
+            say_err!(ConfigKeyUnknown, key, config_path.display());
+        }
+        say!(ConfigLoaded, config_path.display());
+    }
+    if let Some(profile) = args.profile {
+        say!(ProfileApplied, profile.name(), from_profile.join(", "));
+    }
+
+    // A cloud --output is staged locally; crops go up as they are written, the rest once everything succeeded
+    let staged = cloud::stage_output(&mut args)?;
+    run(args)?;
+    if let Some(staged) = staged {
+        staged.upload()?;
+    }
+    match EXIT_STATUS.load(Ordering::Relaxed) {
+        0 => Ok(()),
+        status => std::process::exit(status),
+    }
+}
+
+/// Detection subcommands and the extraction run, once options are final
+fn run(mut args: Args) -> Result<()> {
+    if args.align {
+        args.variants = vec![Variant::Arcface];
+    }
+
+    #[cfg(feature = "grpc")]
+    if let Some(Commands::Serve { listen, ready_listen }) = &args.command {
+        return grpc::run(&args, listen, ready_listen.as_deref());
+    }
+
+    match &args.command {
+        Some(Commands::Doctor) => return doctor::run(&args),
+        Some(Commands::Detect) => return detect::run(&args),
+        Some(Commands::Dedupe { dry_run }) => return dedupe::run(&args.output, args.dedup_distance, *dry_run),
+        Some(Commands::Regress { baseline, gallery, iou }) => return regress::run(&args, baseline, gallery, *iou),
+        Some(Commands::DetectOne { image, show, save }) => return detect_one::run(&args, image, *show, save.as_deref()),
+        Some(Commands::Visualize { to }) => return visualize::run(&args, to),
+        _ => {}
+    }
+
+    if args.no_crop {
+        return detect::run(&args);
+    }
+    if args.plan_only && args.chunk_size.is_none() {
+        anyhow::bail!("--plan-only requires --chunk-size");
+    }
+    if args.stream_fps <= 0.0 {
+        anyhow::bail!("--stream-fps must be greater than 0");
+    }
+    if !(0.0..1.0).contains(&args.min_face_ratio) || args.max_face_ratio <= args.min_face_ratio {
+        anyhow::bail!("--min-face-ratio and --max-face-ratio must satisfy 0 <= min < max");
+    }
+    if args.min_aspect <= 0.0 || args.max_aspect <= args.min_aspect {
+        anyhow::bail!("--min-aspect and --max-aspect must satisfy 0 < min < max");
+    }
+    if args.max_detect_dim == Some(0) {
+        anyhow::bail!("--max-detect-dim must be at least 1");
+    }
+    if args.nms_iou.is_some_and(|iou| !(iou > 0.0 && iou <= 1.0)) {
+        anyhow::bail!("--nms-iou must be greater than 0 and at most 1");
+    }
+    if args.min_yield.is_some_and(|rate| rate <= 0.0) {
+        anyhow::bail!("--min-yield must be greater than 0");
+    }
+    if !(args.second_pass_factor > 0.0 && args.second_pass_factor < 1.0) {
+        anyhow::bail!("--second-pass-factor must be between 0 and 1");
+    }
+    if !args.ensemble.is_empty() && !(1..=args.ensemble.len() + 1).contains(&args.ensemble_votes) {
+        anyhow::bail!("--ensemble-votes must be between 1 and the number of backends ({})", args.ensemble.len() + 1);
+    }
+    if !(args.ensemble_iou > 0.0 && args.ensemble_iou <= 1.0) {
+        anyhow::bail!("--ensemble-iou must be greater than 0 and at most 1");
+    }
+    if args.sample == Some(0) {
+        anyhow::bail!("--sample must be at least 1");
+    }
+    if args.sample_fraction.is_some_and(|share| !(share > 0.0 && share <= 1.0)) {
+        anyhow::bail!("--sample-fraction must be greater than 0 and at most 1");
+    }
+    if args.shard_count == Some(0) {
+        anyhow::bail!("--shard-count must be at least 1");
+    }
+    if let (Some(index), Some(count)) = (args.shard_index, args.shard_count) {
+        if index >= count {
+            anyhow::bail!("--shard-index must be less than --shard-count ({})", count);
+        }
+    }
+    if args.per_image_timeout.is_some_and(|secs| !(secs > 0.0 && secs.is_finite())) {
+        anyhow::bail!("--per-image-timeout must be a positive number of seconds");
+    }
+    if args.max_faces_per_image == Some(0) {
+        anyhow::bail!("--max-faces-per-image must be at least 1");
+    }
+    if let Some(pct) = args.padding_pct {
+        args.padding = pct / 100.0;
+    }
+    if args.padding < 0.0 {
+        anyhow::bail!("--padding must not be negative");
+    }
+    if !(0.0..=1.0).contains(&args.nice_max_load) {
+        anyhow::bail!("--nice-max-load must be between 0 and 1");
+    }
+    if args.tiered_output && args.skip_quality {
+        anyhow::bail!("--tiered-output needs quality scores; drop --skip-quality (also set by --profile fast)");
+    }
+    if args.download_jobs == 0 {
+        anyhow::bail!("--download-jobs must be at least 1");
+    }
+    if args.watch && (archive::is_archive(&args.input) || cloud::is_uri(&args.input)) {
+        anyhow::bail!("--watch needs a folder as --input, not an archive or bucket");
+    }
+    encode::check(&args)?;
+    naming::check_template(&args.name_template)?;
+    for tag in &args.tags {
+        tag::parse(tag)?;
+    }
+    if cfg!(not(feature = "sqlite")) && args.db.is_some() {
+        anyhow::bail!("This build has no SQLite support; rebuild with `cargo build --release --features sqlite`");
+    }
+    if cfg!(not(feature = "embeddings")) && args.embeddings.is_some() {
+        anyhow::bail!("This build has no embedding support; rebuild with `cargo build --release --features embeddings`");
+    }
+    if args.split_group == SplitGroup::Label
+        && !args.labels_from_dirs
+        && !args.input_list.as_deref().is_some_and(remote::is_csv)
+    {
+        anyhow::bail!("--split-group label needs --labels-from-dirs or a CSV --input-list with a label column");
+    }
+    if !matches!(args.tier_cutoffs[..], [gold, silver] if gold >= silver) {
+        anyhow::bail!("--tier-cutoffs takes two values, GOLD,SILVER, with GOLD >= SILVER");
+    }
+
+    say!(Banner);
+    say!(Target, args.target_faces);
 
     // Create output directory
     fs::create_dir_all(&args.output)
         .context("Failed to create output directory")?;
+    for dir in output_subdirs(&args) {
+        fs::create_dir_all(args.output.join(dir))
+            .context("Failed to create output subdirectory")?;
+    }
+    for dir in annotations::subdirs(&args.annotations) {
+        fs::create_dir_all(args.output.join(dir))
+            .context("Failed to create annotation export directory")?;
+    }
+    if args.redacted_originals {
+        fs::create_dir_all(args.output.join(redact::REDACTED_DIR))
+            .context("Failed to create redacted originals directory")?;
+    }
+    annotations::write_dataset_files(&args.output, &args.annotations)?;
 
-    // Load face detection model
-    let mut detector = rustface::create_detector(args.model.to_str().unwrap())
-        .context("Failed to load face detection model")?;
+    // With --jobs the detect workers load their own detectors; the main thread only needs one to detect on itself
+    let pipelined = args.jobs != Jobs::Fixed(1)
+        && args.input_stream.is_none()
+        && args.stdin.is_none()
+        && !args.export_sequences
+        && args.export_clips.is_none();
+    let detector = if pipelined { None } else { Some(detector::load(&args)?) };
+    if detector.is_some() {
+        say!(ModelLoaded);
+    }
+    shutdown::install();
 
-    // Configure detector
-    detector.set_min_face_size(args.min_face_size);
-    detector.set_score_thresh(args.threshold);
-    detector.set_pyramid_scale_factor(0.8);
-    detector.set_slide_window_step(4, 4);
+    if let Some(source) = &args.input_stream {
+        let manifest = ManifestWriter::open(&args.output, false, args.manifest_csv)?;
+        let mut extractor = Extractor::new(&args, detector, manifest)?;
+        #[cfg(feature = "embeddings")]
+        if let Some(model) = &args.embeddings {
+            extractor.embedder = Some(embeddings::Embedder::load(model, &args.output, args.skip_checksum, false)?);
+        }
+        let stats = stream::run(&mut extractor, source)?;
+        return finish_run(&mut extractor, &stats);
+    }
 
-    println!("✅ Model loaded and configured");
+    if let Some(format) = args.stdin {
+        let manifest = ManifestWriter::open(&args.output, false, args.manifest_csv)?;
+        let mut extractor = Extractor::new(&args, detector, manifest)?;
+        #[cfg(feature = "embeddings")]
+        if let Some(model) = &args.embeddings {
+            extractor.embedder = Some(embeddings::Embedder::load(model, &args.output, args.skip_checksum, false)?);
+        }
+        let stats = stdio::run(&mut extractor, format)?;
+        return finish_run(&mut extractor, &stats);
+    }
+
+    if args.export_sequences || args.export_clips.is_some() {
+        let manifest = ManifestWriter::open(&args.output, false, args.manifest_csv)?;
+        let mut extractor = Extractor::new(&args, detector, manifest)?;
+        let stats = tracking::run(&mut extractor)?;
+        return finish_run(&mut extractor, &stats);
+    }
 
     // Find all image files
-    let image_paths: Vec<PathBuf> = WalkDir::new(&args.input)
+    let mut urls = HashMap::new();
+    let mut list_labels = HashMap::new();
+    let mut weights = HashMap::new();
+    let mut image_paths = match &args.input_list {
+        Some(list) => {
+            let input = remote::resolve(list, &args.output, args.download_jobs)?;
+            if args.split_group == SplitGroup::Label && !args.labels_from_dirs && !input.has_label_column {
+                anyhow::bail!("--split-group label needs a label column in {}", list.display());
+            }
+            urls = input.urls;
+            list_labels = input.labels;
+            weights = input.weights;
+            input.paths
+        }
+        None => collect_images(&args.input),
+    };
+
+    say!(ImagesFound, image_paths.len());
+
+    if image_paths.is_empty() && !args.watch {
+        say!(NoImages, args.input_list.as_ref().unwrap_or(&args.input).display());
+        return Ok(());
+    }
+
+    if !args.keep_all_burst {
+        let (selected, bursts) = burst::select(image_paths, &args);
+        image_paths = selected;
+        if bursts.groups > 0 {
+            say!(BurstsSkipped, bursts.groups, bursts.dropped);
+        }
+    }
+
+    image_paths = shard::select(image_paths, &urls, &args);
+    image_paths = sampling::apply(image_paths, &weights, &args);
+
+    let previous = if args.resume {
+        let previous = checkpoint::load(&args.output)?;
+        if previous.is_none() {
+            say!(NoCheckpoint, args.output.display());
+        }
+        previous
+    } else {
+        None
+    };
+    if let Some(progress) = &previous {
+        image_paths.retain(|path| !progress.processed.contains(path));
+        say!(Resuming, progress.processed.len(), progress.faces_extracted, image_paths.len());
+    }
+    let mut existing_faces = 0;
+    if args.skip_existing {
+        // Outputs of older versions have no run store, only their manifest
+        let (sources, faces) = if store::exists(&args.output) {
+            store::with_crops(&args.output)?
+        } else {
+            manifest::extracted(&args.output)?
+        };
+        let before = image_paths.len();
+        image_paths.retain(|path| !sources.contains(path));
+        say!(SkippingExisting, before - image_paths.len(), faces, image_paths.len());
+        existing_faces = faces;
+    }
+
+    // A resumed run, rerun or chunk plan keeps adding to the manifest already there
+    let resuming = previous.is_some()
+        || args.skip_existing
+        || (args.chunk_size.is_some() && planner::checkpoint_exists(&args.output)?);
+    let manifest = ManifestWriter::open(&args.output, resuming, args.manifest_csv)?;
+
+    let mut extractor = Extractor::new(&args, detector, manifest)?;
+    #[cfg(feature = "embeddings")]
+    if let Some(model) = &args.embeddings {
+        extractor.embedder = Some(embeddings::Embedder::load(model, &args.output, args.skip_checksum, resuming)?);
+    }
+    extractor.checkpoint = Some(CheckpointWriter::open(&args.output, resuming, args.checkpoint_every)?);
+    extractor.urls = urls;
+    extractor.list_labels = list_labels;
+    if args.name_by_hash && resuming {
+        // Crops kept by the earlier run count as first copies too
+        let records = manifest::read(&args.output.join(manifest::MANIFEST_FILE))?;
+        extractor.content_names = records
+            .iter()
+            .filter_map(|record| Some(Path::new(&record.crop).file_name()?.to_string_lossy().into_owned()))
+            .collect();
+    }
+    match &previous {
+        Some(progress) => extractor.face_counter.store(progress.faces_extracted, Ordering::Relaxed),
+        None => extractor.face_counter.store(existing_faces, Ordering::Relaxed),
+    }
+    extractor.events.emit(Event::RunStarted {
+        total_images: image_paths.len(),
+        target_faces: args.target_faces,
+    });
+
+    progress::start(image_paths.len(), args.target_faces, extractor.face_counter.load(Ordering::Relaxed));
+    let mut stats = if let Some(chunk_size) = args.chunk_size {
+        match planner::run_chunked(&mut extractor, &image_paths, chunk_size)? {
+            Some(stats) => stats,
+            None => return Ok(()),
+        }
+    } else {
+        run_batch(&mut extractor, &image_paths, 0, image_paths.len())?
+    };
+    if args.watch && !stats.interrupted && !stats.out_of_space {
+        watch::run(&mut extractor, &mut stats, &image_paths)?;
+    }
+    let stopped = stats.interrupted || stats.out_of_space;
+    if args.relax_on_shortfall && extractor.face_counter.load(Ordering::Relaxed) < args.target_faces && !stopped {
+        relax::run(&mut extractor, &mut stats)?;
+    }
+    if !stats.interrupted && !stats.out_of_space {
+        relax::second_pass(&mut extractor, &mut stats)?;
+    }
+
+    if let Some(checkpoint) = &mut extractor.checkpoint {
+        checkpoint.flush(extractor.face_counter.load(Ordering::Relaxed))?;
+    }
+    finish_run(&mut extractor, &stats)
+}
+
+/// Seal the manifest, emit the final event and print the results summary
+fn finish_run(ex: &mut Extractor, stats: &BatchStats) -> Result<()> {
+    let args = ex.args;
+    progress::finish();
+    let final_count = ex.face_counter.load(Ordering::Relaxed);
+    custody::write_seal(args)?;
+    ex.tee.finish()?;
+    let identities = if args.labels_csv { Some(labels::write_csv(&args.output)?) } else { None };
+    ex.events.emit(Event::RunFinished {
+        processed: stats.processed,
+        errors: stats.errors,
+        total_faces: final_count,
+    });
+
+    if stats.interrupted {
+        say!(Interrupted);
+    } else if stats.out_of_space {
+        say!(OutOfSpace);
+    } else {
+        say!(Complete);
+    }
+    say!(Results);
+    say!(ImagesProcessed, stats.processed);
+    say!(Errors, stats.errors);
+    say!(FacesExtracted, final_count);
+    if args.enhance_scans {
+        let scans = &ex.scans;
+        say!(ScansEnhanced, scans.enhanced, scans.faces_before, scans.faces_after);
+    }
+    if let Some(seen) = &ex.seen {
+        say!(DuplicatesDropped, seen.dropped, args.dedup_distance);
+        seen.write(&args.output, args.dedup_distance)?;
+    }
+    if exposure_filtered(args) {
+        say!(ExposureRejected, ex.exposure_rejected);
+    }
+    if pose_filtered(args) {
+        say!(PoseRejected, ex.pose_rejected);
+    }
+    if !args.ensemble.is_empty() {
+        ensemble::report();
+    }
+    if args.detection_cache.is_some() {
+        let (hits, misses) = detection_cache::counts();
+        say!(DetectionCache, hits, misses);
+    }
+    if let Some(limit) = args.max_faces_per_image {
+        say!(FaceLimitDropped, limit, value_name(args.rank_faces_by), ex.over_face_limit);
+    }
+    if let (Some(rejected), Some(dir)) = (&ex.rejected, &args.save_rejected) {
+        say!(RejectedSaved, rejected.saved, dir.display());
+    }
+    if args.split.is_some() {
+        let count = |split| ex.split_faces.get(&split).copied().unwrap_or(0);
+        say!(SplitFaces, count(Split::Train), count(Split::Val), count(Split::Test));
+    }
+    if args.name_by_hash {
+        say!(ExactDuplicates, ex.exact_duplicates);
+    }
+    if ex.collisions > 0 {
+        say!(NameCollisions, ex.collisions, value_name(args.on_collision));
+    }
+    if let Some(identities) = identities {
+        say!(LabelsWritten, identities, labels::LABELS_FILE);
+    }
+    say!(OutputDir, args.output.display());
+    for spec in &args.tee {
+        say!(TeeWritten, spec);
+    }
+    if let Some(composition) = &ex.composition {
+        composition.report();
+    }
+    if let Some(heatmap) = &ex.heatmap {
+        heatmap.finish(&args.output)?;
+    }
+    if let Some(sheets) = &mut ex.contact_sheets {
+        sheets.finish()?;
+    }
+    ex.run_stats.write(&args.output, stats.processed, stats.errors)?;
+
+    let exit_status = if stats.errors > 0 && (args.strict || stats.processed == 0) {
+        say_err!(ExitImageErrors, EXIT_IMAGE_ERRORS, stats.errors);
+        EXIT_IMAGE_ERRORS
+    } else if args.strict && final_count < args.target_faces {
+        say_err!(ExitShortOfTarget, EXIT_SHORT_OF_TARGET, final_count, args.target_faces);
+        EXIT_SHORT_OF_TARGET
+    } else {
+        0
+    };
+    EXIT_STATUS.store(exit_status, Ordering::Relaxed);
+
+    if args.output_json {
+        let summary = RunSummary {
+            processed: stats.processed,
+            errors: stats.errors,
+            extracted: ex.run_stats.faces(),
+            total_faces: final_count,
+            target_reached: final_count >= args.target_faces,
+            interrupted: stats.interrupted,
+            out_of_space: stats.out_of_space,
+            exit_status,
+            seconds: ex.run_stats.seconds(),
+            output: &args.output,
+            config: serde_json::to_value(args).context("Failed to serialize settings")?,
+        };
+        println!("{}", serde_json::to_string(&summary)?);
+    }
+    Ok(())
+}
+
+/// Final line printed by `--output-json`
+#[derive(Serialize)]
+struct RunSummary<'a> {
+    processed: usize,
+    errors: usize,
+    /// Faces saved by this run
+    extracted: usize,
+    /// Faces in the output, counting earlier runs that were resumed
+    total_faces: usize,
+    target_reached: bool,
+    interrupted: bool,
+    out_of_space: bool,
+    exit_status: i32,
+    seconds: f64,
+    output: &'a Path,
+    config: serde_json::Value,
+}
+
+/// Directories below the output directory that crops are written into
+fn output_subdirs(args: &Args) -> Vec<PathBuf> {
+    let splits: Vec<PathBuf> = if args.split.is_some() {
+        Split::ALL.iter().map(|s| PathBuf::from(s.name())).collect()
+    } else {
+        vec![PathBuf::new()]
+    };
+    let tiers: Vec<PathBuf> = splits
+        .iter()
+        .flat_map(|split| {
+            if args.tiered_output {
+                Tier::ALL.iter().map(|t| split.join(t.name())).collect()
+            } else {
+                vec![split.clone()]
+            }
+        })
+        .collect();
+    tiers
+        .iter()
+        .flat_map(|tier| {
+            let sizes: Vec<PathBuf> = args.output_sizes.iter().map(|s| tier.join(s.to_string())).collect();
+            if sizes.is_empty() { vec![tier.clone()] } else { sizes }
+        })
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect()
+}
+
+/// Recursively list files under `input` that a registered decoder recognizes by content
+fn collect_images(input: &Path) -> Vec<PathBuf> {
+    let decoders = decoders::registry().read().unwrap_or_else(|e| e.into_inner());
+    if cloud::is_uri(input) {
+        return cloud::list(input, &decoders).unwrap_or_else(|e| {
+            eprintln!("❌ {:#}", e);
+            Vec::new()
+        });
+    }
+    if archive::is_archive(input) {
+        return archive::list(input, &decoders).unwrap_or_else(|e| {
+            eprintln!("❌ {:#}", e);
+            Vec::new()
+        });
+    }
+    WalkDir::new(input)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
-        .filter_map(|e| {
-            let path = e.path();
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_str()?.to_lowercase();
-                if matches!(ext_str.as_str(), "jpg" | "jpeg" | "png" | "bmp") {
-                    Some(path.to_path_buf())
-                } else {
-                    None
-                }
-            } else {
-                None
+        .map(|e| e.into_path())
+        .filter(|path| decoders.recognizes(path))
+        .collect()
+}
+
+/// Per-run state threaded through the image loop
+struct Extractor<'a> {
+    args: &'a Args,
+    /// Loaded on first use when the pipeline's workers do the detecting
+    detector: Option<Box<dyn FaceDetectorBackend>>,
+    face_counter: AtomicUsize,
+    manifest: ManifestWriter,
+    events: EventSink,
+    scans: ScanStats,
+    composition: Option<Composition>,
+    control: PriorityControl,
+    /// Progress log for `--resume`; absent for sequence runs
+    checkpoint: Option<CheckpointWriter>,
+    /// Candidates for `--relax-on-shortfall`
+    shortfall: Option<Shortfall>,
+    /// Relaxation level of the pass in progress
+    relaxation: Option<u8>,
+    /// Whether the pass in progress is the `--min-yield` retry
+    low_confidence: bool,
+    /// Index of the `--input-stream` frame being processed
+    frame: Option<usize>,
+    /// Hashes of saved faces for `--dedup`
+    seen: Option<SeenFaces>,
+    /// Faces rejected by the brightness and contrast filters
+    exposure_rejected: usize,
+    /// Faces rejected by the head-pose limits
+    pose_rejected: usize,
+    /// Face positions for `--heatmap`
+    heatmap: Option<Heatmap>,
+    /// Sheets being filled for `--contact-sheet`
+    contact_sheets: Option<ContactSheets>,
+    /// Tags from `--tag`, set on every record
+    tags: BTreeMap<String, String>,
+    /// Load monitor for `--nice-mode`
+    nice: Option<NiceMode>,
+    /// Extra outputs from `--tee`
+    tee: Tee,
+    /// Crops of filtered-out faces for `--save-rejected`
+    rejected: Option<RejectedFaces>,
+    /// Source URL of each image downloaded for `--input-list`
+    urls: HashMap<PathBuf, String>,
+    /// Label of each image from a CSV `--input-list`
+    list_labels: HashMap<PathBuf, String>,
+    /// Crops saved for the image in progress, collected for `--stdin` results
+    saved_crops: Option<Vec<String>>,
+    /// Crop names that were already taken, handled per `--on-collision`
+    collisions: usize,
+    /// Names given out under `--name-by-hash` in this run
+    content_names: HashSet<String>,
+    /// Crops left out by `--name-by-hash` because an identical one was saved
+    exact_duplicates: usize,
+    /// Faces saved into each `--split`
+    split_faces: HashMap<Split, usize>,
+    /// Accepted faces left out by `--max-faces-per-image`
+    over_face_limit: usize,
+    /// Database from `--db`
+    results: Option<ResultsDb>,
+    /// Histograms and counts for `stats.json`
+    run_stats: RunStats,
+    /// Output quota and free-space check
+    disk: DiskGuard,
+    #[cfg(feature = "embeddings")]
+    embedder: Option<embeddings::Embedder>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(args: &'a Args, detector: Option<Box<dyn FaceDetectorBackend>>, manifest: ManifestWriter) -> Result<Self> {
+        let events = match &args.events_socket {
+            Some(path) => {
+                let sink = EventSink::bind(path)?;
+                say!(EventsStreaming, path.display());
+                sink
             }
+            None => EventSink::disabled(),
+        };
+        let composition = args
+            .composition
+            .as_deref()
+            .map(|path| Composition::load(path, args.target_faces))
+            .transpose()?;
+
+        Ok(Extractor {
+            args,
+            detector,
+            face_counter: AtomicUsize::new(0),
+            manifest,
+            events,
+            scans: ScanStats::default(),
+            composition,
+            control: PriorityControl::new(&args.output, &args.input),
+            checkpoint: None,
+            shortfall: (args.relax_on_shortfall || args.min_yield.is_some()).then(Shortfall::default),
+            relaxation: None,
+            low_confidence: false,
+            frame: None,
+            seen: args.dedup.then(SeenFaces::default),
+            exposure_rejected: 0,
+            pose_rejected: 0,
+            heatmap: args.heatmap.then(Heatmap::default),
+            contact_sheets: args.contact_sheet.map(|grid| ContactSheets::open(&args.output, grid)).transpose()?,
+            tags: args.tags.iter().map(|t| tag::parse(t)).collect::<Result<_>>()?,
+            nice: args.nice_mode.then(|| NiceMode::new(args.nice_max_load)),
+            tee: Tee::open(args)?,
+            rejected: args
+                .save_rejected
+                .as_deref()
+                .map(|dir| RejectedFaces::open(dir, args.resume || args.skip_existing))
+                .transpose()?,
+            urls: HashMap::new(),
+            list_labels: HashMap::new(),
+            saved_crops: None,
+            collisions: 0,
+            content_names: HashSet::new(),
+            exact_duplicates: 0,
+            split_faces: HashMap::new(),
+            over_face_limit: 0,
+            results: args.db.as_deref().map(|path| ResultsDb::open(path, args)).transpose()?,
+            run_stats: RunStats::default(),
+            disk: DiskGuard::new(&args.output, args.max_output_bytes, args.min_free_space, args.on_disk_full),
+            #[cfg(feature = "embeddings")]
+            embedder: None,
         })
-        .collect();
+    }
+
+    /// The main thread's detector, for calibration, relaxation passes and `--watch` after a pipelined batch
+    fn detector(&mut self) -> Result<&mut dyn FaceDetectorBackend> {
+        if self.detector.is_none() {
+            self.detector = Some(detector::load(self.args)?);
+        }
+        Ok(self.detector.as_deref_mut().expect("detector loaded above"))
+    }
+}
 
-    println!("📁 Found {} images to process", image_paths.len());
+/// Whether any of the brightness and contrast filters is set
+fn exposure_filtered(args: &Args) -> bool {
+    args.min_brightness.is_some() || args.max_brightness.is_some() || args.min_contrast.is_some()
+}
 
-    if image_paths.is_empty() {
-        println!("❌ No images found in {}", args.input.display());
-        return Ok(());
+/// Whether the tight face region `face` fails the brightness and contrast filters
+fn exposure_rejects(args: &Args, face: &DynamicImage) -> bool {
+    if !exposure_filtered(args) {
+        return false;
+    }
+    let exposure = quality::exposure(face);
+    args.min_brightness.is_some_and(|min| exposure.brightness < min)
+        || args.max_brightness.is_some_and(|max| exposure.brightness > max)
+        || args.min_contrast.is_some_and(|min| exposure.contrast < min)
+}
+
+/// Whether any of the head-pose limits is set
+fn pose_filtered(args: &Args) -> bool {
+    args.max_yaw.is_some() || args.max_pitch.is_some() || args.max_roll.is_some()
+}
+
+/// Whether `pose` exceeds a head-pose limit; without landmarks the face can't be shown to be within them
+fn pose_rejects(args: &Args, pose: Option<HeadPose>) -> bool {
+    if !pose_filtered(args) {
+        return false;
     }
+    let Some(pose) = pose else { return true };
+    args.max_yaw.is_some_and(|max| pose.yaw.abs() > max)
+        || args.max_pitch.is_some_and(|max| pose.pitch.abs() > max)
+        || args.max_roll.is_some_and(|max| pose.roll.abs() > max)
+}
+
+/// Effect of `--enhance-scans` on the images it actually changed
+#[derive(Debug, Default)]
+struct ScanStats {
+    enhanced: usize,
+    faces_before: usize,
+    faces_after: usize,
+}
+
+/// Exit status of a run that completed with image errors (always when every image failed, otherwise with `--strict`)
+const EXIT_IMAGE_ERRORS: i32 = 2;
+
+/// Exit status of a `--strict` run that fell short of `--target-faces`
+const EXIT_SHORT_OF_TARGET: i32 = 3;
 
-    let face_counter = AtomicUsize::new(0);
-    let mut processed = 0;
-    let mut errors = 0;
+/// Exit status `main` ends with once the run has finished without a fatal error
+static EXIT_STATUS: AtomicI32 = AtomicI32::new(0);
 
-    // Process images sequentially
-    for (i, path) in image_paths.iter().enumerate() {
-        let current_count = face_counter.load(Ordering::Relaxed);
-        if current_count >= args.target_faces {
-            println!("🎯 Target reached! Extracted {} faces", current_count);
+/// Per-batch counters shared by the plain run and the chunked planner
+#[derive(Debug, Default, Clone, Copy)]
+struct BatchStats {
+    processed: usize,
+    errors: usize,
+    extracted: usize,
+    target_reached: bool,
+    /// Stopped early by Ctrl-C or SIGTERM
+    interrupted: bool,
+    /// Stopped early by the output quota or free-space check
+    out_of_space: bool,
+}
+
+/// Process a contiguous slice of the corpus; `offset` and `total` only affect progress output
+fn run_batch(ex: &mut Extractor, paths: &[PathBuf], offset: usize, total: usize) -> Result<BatchStats> {
+    let mut stats = BatchStats::default();
+
+    if ex.args.jobs != Jobs::Fixed(1) {
+        return pipeline::run_batch(ex, paths, offset, total);
+    }
+
+    // Process images sequentially; the queue is owned so operators can reprioritize what is left
+    let mut queue = paths.to_vec();
+    for i in 0..queue.len() {
+        if should_stop(ex, &mut stats) {
             break;
         }
 
-        println!("[{}/{}] Processing: {}", i + 1, image_paths.len(), path.display());
-        
-        match process_image(path, &args.output, &mut *detector, &face_counter, args.target_faces) {
-            Ok(extracted) => {
-                processed += 1;
-                if extracted > 0 {
-                    println!("  ✅ Extracted {} faces", extracted);
+        yield_to_load(ex);
+        ex.control.poll(&mut queue[i..]);
+        let path = &queue[i];
+        if !progress::active() {
+            say!(Processing, offset + i + 1, total, path.display());
+        }
+        let result = process_image(ex, path);
+        record_outcome(ex, &mut stats, offset + i, path, result);
+    }
+
+    Ok(stats)
+}
+
+/// Whether to take no further images, because the target is met or a stop was asked for
+fn should_stop(ex: &mut Extractor, stats: &mut BatchStats) -> bool {
+    if shutdown::requested() {
+        stats.interrupted = true;
+    } else if target_reached(ex) {
+        stats.target_reached = true;
+    } else if out_of_space(ex) {
+        // A pause on a full disk also ends when the run is interrupted
+        stats.interrupted = shutdown::requested();
+        stats.out_of_space = !stats.interrupted;
+    }
+    stats.interrupted || stats.target_reached || stats.out_of_space
+}
+
+/// Check the output quota and free space before taking another image
+fn out_of_space(ex: &mut Extractor) -> bool {
+    let faces = ex.face_counter.load(Ordering::Relaxed);
+    ex.disk.out_of_space(ex.checkpoint.as_mut(), faces)
+}
+
+/// Announce the end of the run once `--target-faces` crops exist
+fn target_reached(ex: &Extractor) -> bool {
+    let current_count = ex.face_counter.load(Ordering::Relaxed);
+    if current_count < ex.args.target_faces {
+        return false;
+    }
+    say!(TargetReached, current_count);
+    ex.events.emit(Event::TargetReached { total_faces: current_count });
+    true
+}
+
+/// Wait out busy periods on the machine when `--nice-mode` is on
+fn yield_to_load(ex: &mut Extractor) {
+    if let Some(nice) = &mut ex.nice {
+        nice.wait(ex.checkpoint.as_mut(), ex.face_counter.load(Ordering::Relaxed));
+    }
+}
+
+/// Count, print and emit the result of processing the image at `index`
+fn record_outcome(ex: &mut Extractor, stats: &mut BatchStats, index: usize, path: &Path, result: Result<usize>) {
+    match result {
+        Ok(extracted) => {
+            stats.processed += 1;
+            stats.extracted += extracted;
+            if extracted > 0 && !progress::active() {
+                say!(Extracted, extracted);
+            }
+            progress::advance(ex.face_counter.load(Ordering::Relaxed));
+            ex.events.emit(Event::ImageDone {
+                index,
+                path,
+                faces: extracted,
+                total_faces: ex.face_counter.load(Ordering::Relaxed),
+            });
+            if let Some(checkpoint) = &mut ex.checkpoint {
+                if let Err(e) = checkpoint.record(path, extracted, ex.face_counter.load(Ordering::Relaxed)) {
+                    eprintln!("⚠️  {:#}", e);
                 }
             }
-            Err(e) => {
-                errors += 1;
-                eprintln!("  ❌ Error: {}", e);
+        }
+        Err(e) => {
+            stats.errors += 1;
+            say_err!(ImageError, e);
+            progress::advance(ex.face_counter.load(Ordering::Relaxed));
+            if let Some(db) = &ex.results {
+                if let Err(e) = db.failure(path, &format!("{:#}", e)) {
+                    eprintln!("⚠️  {:#}", e);
+                }
+            }
+            ex.events.emit(Event::ImageFailed {
+                index,
+                path,
+                error: format!("{:#}", e),
+            });
+            // Failed images stay out of the finished set so a resumed run retries them
+            if let Some(checkpoint) = &mut ex.checkpoint {
+                if let Err(e) = checkpoint.record_failure(path, format!("{:#}", e), ex.face_counter.load(Ordering::Relaxed)) {
+                    eprintln!("⚠️  {:#}", e);
+                }
             }
         }
     }
+}
 
-    let final_count = face_counter.load(Ordering::Relaxed);
-    println!("\n🎉 Processing complete!");
-    println!("📊 Results:");
-    println!("  - Images processed: {}", processed);
-    println!("  - Errors: {}", errors);
-    println!("  - Faces extracted: {}", final_count);
-    println!("  - Output directory: {}", args.output.display());
+/// An image that went through the detector, waiting to be cropped
+struct Detected {
+    image: DynamicImage,
+    faces: Vec<Detection>,
+    min_score: f64,
+    /// Faces accepted on the unenhanced scan, when `--enhance-scans` changed the image
+    before: Option<usize>,
+    /// Clockwise turn the faces were found at, when `--rotate-retry` needed one
+    rotation: Option<u16>,
+}
 
-    Ok(())
+/// Decode stage: the image as read, plus its enhanced version if `--enhance-scans` changed it
+fn decode_image(args: &Args, image_path: &Path) -> Result<(DynamicImage, Option<DynamicImage>)> {
+    let raw = decode::open_raw(image_path, args)?;
+    let enhanced = if args.enhance_scans { decode::enhance_scan(&raw) } else { None };
+    Ok((raw, enhanced))
 }
 
-fn process_image(
-    image_path: &Path,
-    output_dir: &Path,
-    detector: &mut dyn Detector,
-    face_counter: &AtomicUsize,
-    target: usize,
-) -> Result<usize> {
-    // Check if we've already reached our target
-    let current_count = face_counter.load(Ordering::Relaxed);
-    if current_count >= target {
-        return Ok(0);
+/// Detection stage
+fn detect_image(
+    args: &Args,
+    detector: &mut dyn FaceDetectorBackend,
+    (raw, enhanced): (DynamicImage, Option<DynamicImage>),
+) -> Result<Detected> {
+    // Count what the untouched scan would have yielded so the enhancement can be judged
+    let before = match &enhanced {
+        Some(_) => {
+            let raw_faces = detector.detect(&raw)?;
+            Some(filter_valid_faces(args, &raw_faces, &raw, detector.min_score()).len())
+        }
+        None => None,
+    };
+    let image = enhanced.unwrap_or(raw);
+    let mut faces = detector.detect(&image)?;
+    let mut rotation = None;
+    if faces.is_empty() && args.rotate_retry {
+        if let Some((turn, turned)) = rotate::retry(detector, &image)? {
+            rotation = Some(turn);
+            faces = turned;
+        }
     }
+    // Read after detecting: with --small-backend the floor depends on the image size
+    let min_score = detector.min_score();
 
-    // Load image
-    let image = image::open(image_path)
-        .context("Failed to open image")?;
+    Ok(Detected { image, faces, min_score, before, rotation })
+}
 
-    // Detect faces
-    let faces = detect_faces(detector, &image.to_luma8())?;
-    
-    if faces.is_empty() {
+fn process_image(ex: &mut Extractor, image_path: &Path) -> Result<usize> {
+    // Check if we've already reached our target
+    if ex.face_counter.load(Ordering::Relaxed) >= ex.args.target_faces {
         return Ok(0);
     }
 
+    let decoded = decode_image(ex.args, image_path)?;
+    let detected = detect_image(ex.args, ex.detector()?, decoded)?;
+    extract_faces(ex, image_path, detected)
+}
+
+/// Extraction stage: filter, crop, save and record the detected faces
+fn extract_faces(ex: &mut Extractor, image_path: &Path, detected: Detected) -> Result<usize> {
+    let args = ex.args;
+    let target = args.target_faces;
+    let Detected { image, faces, min_score, before, rotation } = detected;
+    let gray = image.to_luma8();
+    // Rejected crops and annotations come from the strict pass only; retries would repeat them
+    let strict = ex.relaxation.is_none() && !ex.low_confidence;
+
     // Filter valid faces (good size, confidence)
-    let valid_faces = filter_valid_faces(&faces, &image);
-    
+    let valid_faces = filter_valid_faces(args, &faces, &image, min_score);
+    if strict {
+        for face in &faces {
+            for check in face_checks(args, face, &image, min_score).iter().filter(|check| !check.passed) {
+                ex.run_stats.rejected(check.reason);
+            }
+        }
+    }
+
+    if let Some(rejected) = ex.rejected.as_mut().filter(|_| strict) {
+        for face in &faces {
+            let failed: Vec<&str> = face_checks(args, face, &image, min_score)
+                .iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.reason)
+                .collect();
+            if !failed.is_empty() {
+                rejected.save(args, image_path, ex.frame, &image, face, &failed)?;
+            }
+        }
+    }
+
+    // Row ids of `faces` in the --db database, in the same order
+    let rows: Vec<i64> = match &ex.results {
+        Some(db) => {
+            let image_row = db.image(image_path, ex.frame, image.dimensions(), faces.len(), !strict)?;
+            faces
+                .iter()
+                .map(|face| {
+                    let failed: Vec<&str> = face_checks(args, face, &image, min_score)
+                        .iter()
+                        .filter(|check| !check.passed)
+                        .map(|check| check.reason)
+                        .collect();
+                    db.detection(image_row, face, &failed)
+                })
+                .collect::<Result<_>>()?
+        }
+        None => Vec::new(),
+    };
+    let row_of = |face: &Detection| faces.iter().position(|f| std::ptr::eq(f, face)).and_then(|i| rows.get(i).copied());
+    // Count why an accepted face was dropped after all, and record it in the --db database
+    let drop_row = |ex: &mut Extractor, face: &Detection, reason: &str| {
+        ex.run_stats.rejected(reason);
+        match (&ex.results, row_of(face)) {
+            (Some(db), Some(row)) => db.reject(row, reason),
+            _ => Ok(()),
+        }
+    };
+
+    if let Some(before) = before {
+        say!(ScanEnhanced, before, valid_faces.len());
+        ex.scans.enhanced += 1;
+        ex.scans.faces_before += before;
+        ex.scans.faces_after += valid_faces.len();
+    }
+
+    if let Some(shortfall) = &mut ex.shortfall {
+        shortfall.consider(image_path, faces.len(), valid_faces.len());
+    }
+
+    // Annotations cover every detected face, even those the target or quotas keep from being cropped
+    if !args.annotations.is_empty() && strict {
+        annotations::write(&args.annotations, &args.output, &args.input, image_path, &image, &faces, &valid_faces)?;
+    }
+
+    // Crowd shots would otherwise flood the dataset with small background faces
+    let valid_faces = match args.max_faces_per_image {
+        Some(limit) if valid_faces.len() > limit => {
+            ex.over_face_limit += valid_faces.len() - limit;
+            let best = best_faces(args, &image, valid_faces.clone(), limit);
+            for face in valid_faces.iter().filter(|face| !best.iter().any(|kept| std::ptr::eq(*kept, **face))) {
+                drop_row(ex, face, "limit")?;
+            }
+            best
+        }
+        _ => valid_faces,
+    };
+
     if valid_faces.is_empty() {
         return Ok(0);
     }
 
     // Extract and save faces
     let mut extracted = 0;
-    let filename_stem = image_path.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown");
+    let mut extracted_boxes = Vec::new();
+    // Relaxed passes see only the faces left over, so they leave the strict pass's copy alone
+    let redacted = (args.redacted_originals && strict)
+        .then(|| redact::file_name(&args.input, image_path, ex.frame));
+    let exif = if args.preserve_metadata { metadata::read_exif(image_path) } else { None };
+    let label = match ex.list_labels.get(image_path) {
+        Some(label) => Some(label.clone()),
+        None if args.labels_from_dirs => labels::label(&args.input, image_path),
+        None => None,
+    };
+    let split = args.split.map(|ratios| match (args.split_group, &label) {
+        (SplitGroup::Label, Some(label)) => ratios.assign(label),
+        _ => ratios.assign(&image_path.to_string_lossy()),
+    });
 
-    for (i, face) in valid_faces.iter().enumerate() {
-        let current = face_counter.load(Ordering::Relaxed);
+    for face in valid_faces {
+        let current = ex.face_counter.load(Ordering::Relaxed);
         if current >= target {
             break;
         }
 
-        let bbox = face.bbox();
-        
-        // Crop face from original image with padding
-        let padding = ((bbox.width() + bbox.height()) / 8) as i32; // 12.5% padding
-        let x = (bbox.x() - padding).max(0) as u32;
-        let y = (bbox.y() - padding).max(0) as u32;
-        let width = ((bbox.width() as i32 + 2 * padding) as u32).min(image.width() - x);
-        let height = ((bbox.height() as i32 + 2 * padding) as u32).min(image.height() - y);
-        
-        let face_img = image.crop_imm(x, y, width, height);
-
-        // Generate unique filename
-        let face_filename = format!("{}_{:04}_{:.0}.jpg", 
-            filename_stem, 
-            current + 1,
-            face.score() * 100.0
-        );
-        let face_path = output_dir.join(face_filename);
-
-        // Save face
-        face_img.save(&face_path)
-            .context("Failed to save face image")?;
-
-        face_counter.fetch_add(1, Ordering::Relaxed);
+        let bbox = &face.bbox;
+        let detection_id = shard::detection_id(current, args);
+
+        // Mirror so the dominant orientation always faces the same way
+        let facing = orientation::estimate_facing(&gray, bbox);
+        let mirrored = args
+            .normalize_facing
+            .is_some_and(|wanted| facing.is_opposite(wanted));
+
+        let face_crop = crop::padded_crop(&image, bbox, 0);
+        if exposure_rejects(args, &face_crop) {
+            ex.exposure_rejected += 1;
+            drop_row(ex, face, "exposure")?;
+            if let Some(rejected) = ex.rejected.as_mut().filter(|_| strict) {
+                rejected.save(args, image_path, ex.frame, &image, face, &["exposure"])?;
+            }
+            continue;
+        }
+
+        // Prefer keypoints from the detector over the pixel heuristics
+        let points = face.landmarks.or_else(|| landmarks::estimate_landmarks(&gray, bbox));
+        let pose = points.map(|p| landmarks::head_pose(&p, bbox, face.landmarks.is_some()));
+        if pose_rejects(args, pose) {
+            ex.pose_rejected += 1;
+            drop_row(ex, face, "pose")?;
+            if let Some(rejected) = ex.rejected.as_mut().filter(|_| strict) {
+                rejected.save(args, image_path, ex.frame, &image, face, &["pose"])?;
+            }
+            continue;
+        }
+
+        // Hash the bare face so framing variants and padding don't hide a repeat
+        let hash = ex.seen.as_ref().map(|_| phash::phash(&face_crop));
+        if let (Some(seen), Some(hash)) = (&mut ex.seen, hash) {
+            if let Some((cluster, distance)) = seen.nearest(hash, args.dedup_distance) {
+                let member = phash::Member {
+                    source: image_path.to_path_buf(),
+                    detection_id,
+                    bbox: *bbox,
+                    crops: Vec::new(),
+                    distance,
+                    kept: false,
+                };
+                seen.drop_into(cluster, member);
+                drop_row(ex, face, "duplicate")?;
+                continue;
+            }
+        }
+
+        // With a composition spec, only faces that fill an open quota are kept; the slot is claimed once a crop is saved
+        let open_quota = match &mut ex.composition {
+            Some(composition) => match composition.admit(&FaceTraits::measure(&gray, bbox, facing), image_path) {
+                Some(quota) => Some(quota),
+                None => {
+                    drop_row(ex, face, "quota")?;
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let eyes = points.map(|p| [p.left_eye, p.right_eye]);
+        let quality = (!args.skip_quality).then(|| quality::score(&face_crop, face.confidence));
+        let tier = quality
+            .filter(|_| args.tiered_output)
+            .map(|quality| Tier::for_quality(quality, &args.tier_cutoffs));
+        let placement = Placement { split, tier };
+
+        // Cut and name every variant first, so a face with no crop left to write is passed over whole;
+        // filenames only carry the variant when something other than the classic crop is requested
+        let tag_variant = args.variants != [Variant::Standard];
+        let mut planned = Vec::new();
+        for &variant in &args.variants {
+            let (mut face_img, frame) = crop::crop_variant(&image, bbox, points.as_ref(), variant, Framing::of(args));
+            if mirrored {
+                face_img = face_img.fliph();
+            }
+            let suffix = if tag_variant { format!("_{}", variant.name()) } else { String::new() };
+            let name = if args.name_by_hash {
+                naming::content_name(&face_img)
+            } else {
+                naming::render(&args.name_template, &NameFields { source: image_path, index: detection_id, score: face.score, bbox })?
+            };
+            let file = format!("{}{}.{}", name, suffix, args.format.extension());
+            let file = if args.name_by_hash {
+                // The same name means the same pixels, so only the first copy in a run is kept
+                let first = ex.content_names.insert(file.clone());
+                if !first {
+                    ex.exact_duplicates += 1;
+                }
+                first.then_some(file)
+            } else {
+                naming::resolve(args, placement, file, &mut ex.collisions)
+            };
+            if let Some(file) = file {
+                planned.push((variant, file, face_img, frame));
+            }
+        }
+        if planned.is_empty() {
+            continue;
+        }
+        let quota = match (&mut ex.composition, open_quota) {
+            (Some(composition), Some(open)) => Some(composition.claim(open, image_path)),
+            _ => None,
+        };
+
+        // One embedding per detection, taken from the template-aligned face whatever variants are saved
+        #[cfg(feature = "embeddings")]
+        let embedding = match &mut ex.embedder {
+            Some(embedder) => Some(embedder.embed(&crop::crop_variant(&image, bbox, points.as_ref(), Variant::Arcface, Framing::of(args)).0)?),
+            None => None,
+        };
+        #[cfg(not(feature = "embeddings"))]
+        let embedding = None;
+
+        let mut crops = Vec::new();
+        // The sheet shows the face as first saved, before any resizing
+        let thumbnail = ex.contact_sheets.as_ref().map(|_| ContactSheets::thumbnail(&planned[0].2));
+
+        for (variant, face_filename, face_img, frame) in planned {
+            let edge_fill = frame
+                .filter(|_| args.pad_mode != PadMode::Clip)
+                .and_then(|frame| crop::edge_fill(&image, &frame.region));
+
+            // Tee outputs get the full-resolution crop and size it themselves
+            let full_res = (!ex.tee.is_empty()).then(|| face_img.clone());
+
+            // Every requested size is scaled from the full-resolution crop, never from a smaller copy
+            let filter = args.resize_filter.filter_type();
+            let outputs: Vec<(Option<u32>, DynamicImage)> = if args.output_sizes.is_empty() {
+                let face_img = match args.output_size {
+                    Some(size) => face_img.resize_to_fill(size.width, size.height, filter),
+                    None => face_img,
+                };
+                vec![(None, face_img)]
+            } else {
+                args.output_sizes
+                    .iter()
+                    .map(|&size| (Some(size), face_img.resize_to_fill(size, size, filter)))
+                    .collect()
+            };
+
+            let mut tee_record = None;
+            for (size, crop_img) in outputs {
+                let fill = size.map(|s| (s, s)).or(args.output_size.map(|o| (o.width, o.height)));
+                let face_box = frame.map(|frame| crop::face_box(&image, bbox, &frame.region, args.pad_mode, fill, mirrored));
+                let crop_name = naming::crop_path(placement, size, &face_filename);
+
+                // Save face
+                encode::save(&crop_img, &args.output.join(&crop_name), args, exif.as_deref())
+                    .context("Failed to save face image")?;
+                let sha256 = custody::file_sha256(&args.output.join(&crop_name))?;
+                ex.disk.wrote(fs::metadata(args.output.join(&crop_name)).map_or(0, |meta| meta.len()));
+                if let (Some(db), Some(row)) = (&ex.results, row_of(face)) {
+                    db.crop(row, &crop_name, variant.name())?;
+                }
+
+                let record = ManifestRecord {
+                    crop: crop_name,
+                    source: image_path.to_path_buf(),
+                    url: ex.urls.get(image_path).cloned(),
+                    label: label.clone(),
+                    detection_id,
+                    variant,
+                    size,
+                    bbox: *bbox,
+                    score: face.score,
+                    padding: frame.map(|frame| frame.padding),
+                    image_width: Some(image.width()),
+                    image_height: Some(image.height()),
+                    face_box,
+                    edge_fill,
+                    facing: Some(facing),
+                    mirrored,
+                    rotation,
+                    quota: quota.clone(),
+                    quality,
+                    tier,
+                    split,
+                    eyes,
+                    pose,
+                    sha256: Some(sha256),
+                    relaxation: ex.relaxation,
+                    low_confidence: ex.low_confidence,
+                    frame: ex.frame,
+                    embedding,
+                    redacted: redacted.clone(),
+                    tags: ex.tags.clone(),
+                    ..Default::default()
+                };
+                if args.sidecars {
+                    let mut provenance = Provenance::new(args);
+                    provenance.landmarks = points;
+                    if before.is_some() {
+                        provenance.preprocessing.push("scan enhancement".to_string());
+                    }
+                    provenance.preprocessing.push(format!("{} crop", variant.name()));
+                    if record.edge_fill.is_some() {
+                        provenance.preprocessing.push(format!("{} padding", value_name(args.pad_mode)));
+                    }
+                    if mirrored {
+                        provenance.preprocessing.push("mirrored".to_string());
+                    }
+                    if let Some((width, height)) = fill {
+                        provenance.preprocessing.push(format!("resized to {}x{} ({})", width, height, value_name(args.resize_filter)));
+                    }
+                    manifest::write_sidecar(&args.output, &record, &provenance)?;
+                }
+                ex.manifest.append(&record)?;
+                if let Some(saved) = &mut ex.saved_crops {
+                    saved.push(record.crop.clone());
+                }
+                if tee_record.is_none() {
+                    tee_record = Some(ManifestRecord { crop: tee_name(&record.crop, size), size: None, ..record.clone() });
+                }
+                crops.push(record.crop);
+            }
+            if let (Some(full_res), Some(record)) = (full_res, tee_record) {
+                let face_box = |fill| frame.map(|frame| crop::face_box(&image, bbox, &frame.region, args.pad_mode, fill, mirrored));
+                ex.tee.add(&record, &full_res, &face_box)?;
+            }
+        }
+
+        if let Some(shortfall) = &mut ex.shortfall {
+            shortfall.cropped(image_path, *bbox);
+        }
+        if let (Some(sheets), Some(thumbnail), Some(crop)) = (&mut ex.contact_sheets, thumbnail, crops.first()) {
+            sheets.add(thumbnail, crop)?;
+        }
+        if let (Some(seen), Some(hash)) = (&mut ex.seen, hash) {
+            let member = phash::Member {
+                source: image_path.to_path_buf(),
+                detection_id,
+                bbox: *bbox,
+                crops,
+                distance: 0,
+                kept: true,
+            };
+            seen.insert(hash, member);
+        }
+        if let Some(heatmap) = &mut ex.heatmap {
+            heatmap.add(bbox, image.width(), image.height());
+        }
+        ex.run_stats.saved(image_path, bbox, face.score);
+        extracted_boxes.push(*bbox);
+        if let Some(split) = split {
+            *ex.split_faces.entry(split).or_default() += 1;
+        }
+        ex.face_counter.fetch_add(1, Ordering::Relaxed);
         extracted += 1;
     }
 
+    if let Some(name) = redacted.filter(|_| extracted > 0) {
+        redact::write(&args.output, &name, &image, &faces, &extracted_boxes)?;
+    }
+
     Ok(extracted)
 }
 
-fn detect_faces(detector: &mut dyn Detector, gray: &GrayImage) -> Result<Vec<FaceInfo>> {
-    let (width, height) = gray.dimensions();
-    let mut image_data = ImageData::new(gray, width, height);
-    let faces = detector.detect(&mut image_data);
-    Ok(faces)
+/// Command-line spelling of a flag value
+fn value_name(value: impl ValueEnum) -> String {
+    value.to_possible_value().map_or_else(String::new, |v| v.get_name().to_string())
 }
 
-fn filter_valid_faces<'a>(faces: &'a [FaceInfo], image: &DynamicImage) -> Vec<&'a FaceInfo> {
-    let (img_width, img_height) = image.dimensions();
-    let img_area = (img_width * img_height) as f64;
-    
+/// Name of a crop in the tee outputs: the primary name without its `--output-sizes` folder
+fn tee_name(crop_name: &str, size: Option<u32>) -> String {
+    match size {
+        Some(size) => crop_name.replacen(&format!("{}/", size), "", 1),
+        None => crop_name.to_string(),
+    }
+}
+
+fn filter_valid_faces<'a>(args: &Args, faces: &'a [Detection], image: &DynamicImage, min_score: f64) -> Vec<&'a Detection> {
     faces
         .iter()
-        .filter(|face| {
-            let bbox = face.bbox();
-            let face_area = (bbox.width() * bbox.height()) as f64;
-            let face_ratio = face_area / img_area;
-            
-            // Face should be 2-40% of image area (removes tiny and huge faces)
-            let size_ok = face_ratio > 0.02 && face_ratio < 0.4;
-            
-            // Good confidence score (RustFace uses different scale)
-            let confidence_ok = face.score() > 2.0;
-            
-            // Face should be reasonably rectangular (not too thin/wide)
-            let aspect_ratio = bbox.width() as f64 / bbox.height() as f64;
-            let ratio_ok = aspect_ratio > 0.5 && aspect_ratio < 2.0;
-            
-            // Minimum size check
-            let min_size_ok = bbox.width() >= 40 && bbox.height() >= 40;
-            
-            size_ok && confidence_ok && ratio_ok && min_size_ok
-        })
+        .filter(|face| face_checks(args, face, image, min_score).iter().all(|check| check.passed))
         .collect()
 }
+
+/// The `limit` best of `faces` by `--rank-faces-by`, kept in detection order
+fn best_faces<'a>(args: &Args, image: &DynamicImage, faces: Vec<&'a Detection>, limit: usize) -> Vec<&'a Detection> {
+    let mut ranked: Vec<(usize, f64)> = faces
+        .iter()
+        .enumerate()
+        .map(|(i, face)| {
+            let key = match args.rank_faces_by {
+                FaceRank::Score => face.score,
+                FaceRank::Sharpness => quality::sharpness(&crop::padded_crop(image, &face.bbox, 0)),
+            };
+            (i, key)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut kept: Vec<usize> = ranked.into_iter().take(limit).map(|(i, _)| i).collect();
+    kept.sort_unstable();
+    kept.into_iter().map(|i| faces[i]).collect()
+}
+
+/// Verdict of one validity check on a detection, with the measured value for `detect-one`
+struct FaceCheck {
+    /// Short name of the filter, used as the rejection reason
+    reason: &'static str,
+    passed: bool,
+    detail: String,
+}
+
+/// The checks a detection must all pass to be kept
+fn face_checks(args: &Args, face: &Detection, image: &DynamicImage, min_score: f64) -> [FaceCheck; 4] {
+    let (img_width, img_height) = image.dimensions();
+    let img_area = (img_width * img_height) as f64;
+    let bbox = &face.bbox;
+    let face_area = (bbox.width * bbox.height) as f64;
+    let face_ratio = face_area / img_area;
+    let aspect_ratio = bbox.width as f64 / bbox.height as f64;
+
+    [
+        // Good confidence score (in the backend's own units)
+        FaceCheck {
+            reason: "score",
+            passed: face.score > min_score,
+            detail: format!("score {:.2} (needs more than {:.2})", face.score, min_score),
+        },
+        // Face should cover a sensible share of the image (removes tiny and huge faces)
+        FaceCheck {
+            reason: "coverage",
+            passed: face_ratio > args.min_face_ratio && face_ratio < args.max_face_ratio,
+            detail: format!(
+                "covers {:.1}% of the image (needs {}-{}%)",
+                face_ratio * 100.0,
+                args.min_face_ratio * 100.0,
+                args.max_face_ratio * 100.0
+            ),
+        },
+        // Face should be reasonably rectangular (not too thin/wide)
+        FaceCheck {
+            reason: "aspect",
+            passed: aspect_ratio > args.min_aspect && aspect_ratio < args.max_aspect,
+            detail: format!("aspect ratio {:.2} (needs {}-{})", aspect_ratio, args.min_aspect, args.max_aspect),
+        },
+        // Minimum size check
+        FaceCheck {
+            reason: "size",
+            passed: bbox.width >= args.min_crop_size && bbox.height >= args.min_crop_size,
+            detail: format!(
+                "{}x{} px (needs at least {}x{})",
+                bbox.width, bbox.height, args.min_crop_size, args.min_crop_size
+            ),
+        },
+    ]
+}
@@ -0,0 +1,320 @@
+//! Per-crop manifest (`manifest.jsonl`) written alongside the extracted faces
+//!
+//! One JSON object per line, flushed as each crop is saved so a crash never loses
+//! the provenance of faces that are already on disk. `--manifest-csv` mirrors the
+//! main columns into `manifest.csv` for spreadsheet and pandas users.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::crop::{EdgeFill, FaceBox, Variant};
+use crate::detector::{BBox, Backend, Landmarks};
+use crate::landmarks::{HeadPose, Point};
+use crate::orientation::Facing;
+use crate::quality::Tier;
+use crate::split::Split;
+use crate::Args;
+
+pub const MANIFEST_FILE: &str = "manifest.jsonl";
+pub const CSV_FILE: &str = "manifest.csv";
+
+const CSV_HEADER: &str = "crop,source,detection_id,variant,size,bbox_x,bbox_y,bbox_width,bbox_height,score,padding,image_width,image_height,face_cx,face_cy,face_width,face_height,quality,tier,sha256,tags";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManifestRecord {
+    /// Crop filename, relative to the output directory
+    pub crop: String,
+    pub source: PathBuf,
+    /// Where `--input-list` downloaded the source from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Identity from the source's folder (`--labels-from-dirs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Shared by every variant cut from the same detection
+    pub detection_id: usize,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Edge length of the square export when `--output-sizes` is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u32>,
+    pub bbox: BBox,
+    pub score: f64,
+    /// Context added on every side of the box, in source pixels; absent for rotated aligned crops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding: Option<u32>,
+    /// Dimensions of the source image (or video frame)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_height: Option<u32>,
+    /// Face center and size as fractions of the crop, for augmentation around the face; absent for rotated aligned crops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub face_box: Option<FaceBox>,
+    /// Source pixels per side filled beyond the image border under `--pad-mode reflect` or `constant`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edge_fill: Option<EdgeFill>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facing: Option<Facing>,
+    /// Whether the crop was mirrored by `--normalize-facing`
+    #[serde(default)]
+    pub mirrored: bool,
+    /// Clockwise turn, in degrees, the source needed for the face to be found (`--rotate-retry`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<u16>,
+    /// Video track the crop belongs to (`--export-sequences`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track: Option<usize>,
+    /// Zero-based frame index within the source video or frame folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<usize>,
+    /// Composition quota the face was counted towards (`--composition`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<String>,
+    /// Combined quality score in 0-1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<Tier>,
+    /// Dataset split the crop was sorted into (`--split`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<Split>,
+    /// Estimated eye centers (viewer's left, viewer's right) in source-image pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eyes: Option<[Point; 2]>,
+    /// Estimated head pose in degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<HeadPose>,
+    /// Hex SHA-256 of the crop file, checked by `verify`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// `--relax-on-shortfall` level the face was accepted at; absent for the strict pass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relaxation: Option<u8>,
+    /// Found by the `--min-yield` second pass at lowered thresholds
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_confidence: bool,
+    /// Row of this face's vector in `embeddings.npy` (`--embeddings`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<usize>,
+    /// Copy of the source with bystanders blurred (`--redacted-originals`), relative to the output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redacted: Option<String>,
+    /// Filters the face failed, comma-separated; only in the manifest of `--save-rejected`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+    /// Curator annotations added with the `tag` subcommand
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl ManifestRecord {
+    fn csv_row(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        [
+            self.crop.clone(),
+            self.source.display().to_string(),
+            self.detection_id.to_string(),
+            self.variant.name().to_string(),
+            optional(self.size.map(|s| s.to_string())),
+            self.bbox.x.to_string(),
+            self.bbox.y.to_string(),
+            self.bbox.width.to_string(),
+            self.bbox.height.to_string(),
+            self.score.to_string(),
+            optional(self.padding.map(|p| p.to_string())),
+            optional(self.image_width.map(|w| w.to_string())),
+            optional(self.image_height.map(|h| h.to_string())),
+            optional(self.face_box.map(|f| format!("{:.4}", f.cx))),
+            optional(self.face_box.map(|f| format!("{:.4}", f.cy))),
+            optional(self.face_box.map(|f| format!("{:.4}", f.width))),
+            optional(self.face_box.map(|f| format!("{:.4}", f.height))),
+            optional(self.quality.map(|q| format!("{:.4}", q))),
+            optional(self.tier.map(|t| t.name().to_string())),
+            optional(self.sha256.clone()),
+            self.tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(";"),
+        ]
+        .iter()
+        .map(|field| csv_escape(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+pub fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Fields of one CSV line, undoing `csv_escape`
+pub fn csv_split(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().expect("at least one field").push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().expect("at least one field").push(c),
+        }
+    }
+    fields
+}
+
+pub struct ManifestWriter {
+    writer: BufWriter<File>,
+    csv: Option<BufWriter<File>>,
+}
+
+impl ManifestWriter {
+    /// Open `manifest.jsonl` (and `manifest.csv` if `csv` is set) in `output_dir`,
+    /// truncating them unless `append` is set
+    pub fn open(output_dir: &Path, append: bool, csv: bool) -> Result<Self> {
+        let writer = BufWriter::new(open_file(&output_dir.join(MANIFEST_FILE), append)?);
+
+        let csv = if csv {
+            let path = output_dir.join(CSV_FILE);
+            let file = open_file(&path, append)?;
+            let fresh = file.metadata().map_or(true, |m| m.len() == 0);
+            let mut csv = BufWriter::new(file);
+            if fresh {
+                writeln!(csv, "{}", CSV_HEADER)?;
+            }
+            Some(csv)
+        } else {
+            None
+        };
+
+        Ok(ManifestWriter { writer, csv })
+    }
+
+    pub fn append(&mut self, record: &ManifestRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush().context("Failed to write manifest record")?;
+
+        if let Some(csv) = &mut self.csv {
+            writeln!(csv, "{}", record.csv_row())?;
+            csv.flush().context("Failed to write CSV manifest row")?;
+        }
+        Ok(())
+    }
+}
+
+fn open_file(path: &Path, append: bool) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("Failed to open manifest {}", path.display()))
+}
+
+/// What a sidecar adds to the manifest row: where the crop came from and what was done to it
+#[derive(Debug, Serialize)]
+pub struct Provenance {
+    /// All five keypoints, from the detector or estimated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub landmarks: Option<Landmarks>,
+    /// Steps applied between decoding and saving, in order
+    pub preprocessing: Vec<String>,
+    pub pipeline: Pipeline,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Pipeline {
+    pub version: &'static str,
+    pub backend: Backend,
+    pub model: PathBuf,
+}
+
+impl Provenance {
+    pub fn new(args: &Args) -> Self {
+        Provenance {
+            landmarks: None,
+            preprocessing: Vec::new(),
+            pipeline: Pipeline {
+                version: env!("CARGO_PKG_VERSION"),
+                backend: args.backend,
+                model: args.model.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    #[serde(flatten)]
+    record: &'a ManifestRecord,
+    #[serde(flatten)]
+    provenance: &'a Provenance,
+}
+
+/// Write `record` and its provenance as pretty JSON next to its crop (`face.jpg` -> `face.json`)
+pub fn write_sidecar(output_dir: &Path, record: &ManifestRecord, provenance: &Provenance) -> Result<()> {
+    let path = output_dir.join(&record.crop).with_extension("json");
+    let json = serde_json::to_string_pretty(&Sidecar { record, provenance })?;
+    fs::write(&path, json).with_context(|| format!("Failed to write sidecar {}", path.display()))
+}
+
+/// Replace the manifest at `path` with `records` (and its CSV twin, if there is one)
+pub fn rewrite(path: &Path, records: &[ManifestRecord]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let staging = dir.join(".manifest.rewrite");
+    fs::create_dir_all(&staging).context("Failed to create staging directory")?;
+
+    let csv = dir.join(CSV_FILE);
+    let has_csv = path.file_name() == Some(MANIFEST_FILE.as_ref()) && csv.exists();
+    let mut writer = ManifestWriter::open(&staging, false, has_csv)?;
+    for record in records {
+        writer.append(record)?;
+    }
+    drop(writer);
+
+    // Swap in complete files only, so an interrupted edit leaves the old manifest intact
+    fs::rename(staging.join(MANIFEST_FILE), path).context("Failed to replace manifest")?;
+    if has_csv {
+        fs::rename(staging.join(CSV_FILE), &csv).context("Failed to replace CSV manifest")?;
+    }
+    fs::remove_dir_all(&staging).context("Failed to remove staging directory")
+}
+
+/// Sources with crops in the manifest in `output_dir`, and how many faces they hold; empty if there is none
+pub fn extracted(output_dir: &Path) -> Result<(HashSet<PathBuf>, usize)> {
+    let path = output_dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(Default::default());
+    }
+    let records = read(&path)?;
+    let faces: HashSet<(&Path, Option<usize>, usize)> =
+        records.iter().map(|r| (r.source.as_path(), r.frame, r.detection_id)).collect();
+    Ok((records.iter().map(|r| r.source.clone()).collect(), faces.len()))
+}
+
+/// Read every record from a manifest file, skipping blank lines
+pub fn read(path: &Path) -> Result<Vec<ManifestRecord>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid manifest record at {}:{}", path.display(), i + 1))
+        })
+        .collect()
+}
@@ -0,0 +1,222 @@
+//! Source metadata in saved crops (`--strip-metadata`, `--preserve-metadata`)
+//!
+//! Crops are encoded from decoded pixels, so by default they carry no EXIF:
+//! no GPS position, camera serial or capture time leaves with a distributed
+//! dataset. `--preserve-metadata` copies the EXIF block of JPEG and PNG sources
+//! into JPEG and PNG crops unchanged, except for the embedded thumbnail, which
+//! would show the whole scene the face was cut from. The orientation tag is
+//! kept too; crops are cut from the stored pixels, so viewers rotate them the
+//! same way as the source.
+//!
+//! Burst selection also reads the capture time from the same block.
+
+use flate2::Crc;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::decoders;
+use crate::encode::OutputFormat;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Bytes read from the start of a file to find its EXIF block; a JPEG's can't be larger than 64 KiB
+const EXIF_SEARCH_LEN: u64 = 256 * 1024;
+
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_SUB_SEC_TIME_ORIGINAL: u16 = 0x9291;
+
+/// The TIFF-structured EXIF block of a JPEG or PNG file, if it has one
+pub fn read_exif(path: &Path) -> Option<Vec<u8>> {
+    let bytes = decoders::read_input(path).ok()?;
+    let mut exif = if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg_exif(&bytes)?
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        png_exif(&bytes)?
+    } else {
+        return None;
+    }
+    .to_vec();
+    drop_thumbnail(&mut exif);
+    Some(exif)
+}
+
+/// When the photo was taken, from EXIF `DateTimeOriginal` (or `DateTime`), in seconds on the camera's clock
+pub fn capture_time(path: &Path) -> Option<f64> {
+    // Only the start of a local file is read; archive members and cloud objects are fetched whole
+    let bytes = match File::open(path) {
+        Ok(file) => {
+            let mut head = Vec::new();
+            file.take(EXIF_SEARCH_LEN).read_to_end(&mut head).ok()?;
+            head
+        }
+        Err(_) => decoders::read_input(path).ok()?,
+    };
+    let tiff = if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg_exif(&bytes)?
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        png_exif(&bytes)?
+    } else {
+        return None;
+    };
+    exif_time(tiff)
+}
+
+/// Add `exif` to an encoded crop; blocks too large for a JPEG segment are left out
+pub fn embed(mut bytes: Vec<u8>, format: OutputFormat, exif: &[u8]) -> Vec<u8> {
+    match format {
+        OutputFormat::Jpg => {
+            let length = 2 + EXIF_HEADER.len() + exif.len();
+            let Ok(length) = u16::try_from(length) else {
+                return bytes;
+            };
+            // After the JFIF header if there is one, as some readers expect it first
+            let at = if bytes.get(2..4) == Some(&[0xFF, 0xE0]) {
+                4 + u16::from_be_bytes([bytes[4], bytes[5]]) as usize
+            } else {
+                2
+            };
+            let mut segment = vec![0xFF, 0xE1];
+            segment.extend_from_slice(&length.to_be_bytes());
+            segment.extend_from_slice(EXIF_HEADER);
+            segment.extend_from_slice(exif);
+            bytes.splice(at..at, segment);
+        }
+        OutputFormat::Png => {
+            // Right after IHDR, which is always the first chunk
+            let at = PNG_SIGNATURE.len() + 8 + 13 + 4;
+            let mut crc = Crc::new();
+            crc.update(b"eXIf");
+            crc.update(exif);
+            let mut chunk = (exif.len() as u32).to_be_bytes().to_vec();
+            chunk.extend_from_slice(b"eXIf");
+            chunk.extend_from_slice(exif);
+            chunk.extend_from_slice(&crc.sum().to_be_bytes());
+            bytes.splice(at..at, chunk);
+        }
+        OutputFormat::Webp => {}
+    }
+    bytes
+}
+
+/// Payload of the APP1 Exif segment, searched up to the start of the image data
+fn jpeg_exif(bytes: &[u8]) -> Option<&[u8]> {
+    let mut at = 2;
+    while at + 4 <= bytes.len() && bytes[at] == 0xFF {
+        let marker = bytes[at + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            return None;
+        }
+        let length = u16::from_be_bytes([bytes[at + 2], bytes[at + 3]]) as usize;
+        let payload = bytes.get(at + 4..at + 2 + length)?;
+        if marker == 0xE1 && payload.starts_with(EXIF_HEADER) {
+            return Some(&payload[EXIF_HEADER.len()..]);
+        }
+        at += 2 + length;
+    }
+    None
+}
+
+fn png_exif(bytes: &[u8]) -> Option<&[u8]> {
+    let mut at = PNG_SIGNATURE.len();
+    while at + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[at..at + 4].try_into().ok()?) as usize;
+        let kind = &bytes[at + 4..at + 8];
+        let data = bytes.get(at + 8..at + 8 + length)?;
+        if kind == b"eXIf" {
+            return Some(data);
+        }
+        if kind == b"IEND" {
+            return None;
+        }
+        at += 12 + length;
+    }
+    None
+}
+
+fn exif_time(tiff: &[u8]) -> Option<f64> {
+    let big_endian = match tiff.get(0..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| -> Option<usize> {
+        let b = tiff.get(at..at + 2)?;
+        Some(if big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) } as usize)
+    };
+    let u32_at = |at: usize| -> Option<usize> {
+        let b: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) } as usize)
+    };
+    // Offset of the value field of `tag` in the IFD at `ifd`
+    let entry = |ifd: usize, tag: u16| -> Option<usize> {
+        let count = u16_at(ifd)?;
+        (0..count).map(|i| ifd + 2 + i * 12).find(|&at| u16_at(at) == Some(tag as usize)).map(|at| at + 8)
+    };
+    // ASCII values of more than four bytes are stored elsewhere, shorter ones in the entry itself
+    let ascii = |ifd: usize, tag: u16| -> Option<&str> {
+        let field = entry(ifd, tag)?;
+        let count = u32_at(field - 4)?;
+        let at = if count > 4 { u32_at(field)? } else { field };
+        let text = std::str::from_utf8(tiff.get(at..at + count)?).ok()?;
+        Some(text.trim_end_matches('\0').trim())
+    };
+
+    let ifd0 = u32_at(4)?;
+    let exif_ifd = entry(ifd0, TAG_EXIF_IFD).and_then(u32_at);
+    let original = exif_ifd.and_then(|ifd| ascii(ifd, TAG_DATE_TIME_ORIGINAL));
+    let seconds = parse_exif_date(original.or_else(|| ascii(ifd0, TAG_DATE_TIME))?)?;
+    let fraction = exif_ifd
+        .filter(|_| original.is_some())
+        .and_then(|ifd| ascii(ifd, TAG_SUB_SEC_TIME_ORIGINAL))
+        .and_then(|digits| format!("0.{}", digits).parse::<f64>().ok())
+        .unwrap_or(0.0);
+    Some(seconds + fraction)
+}
+
+/// `YYYY:MM:DD HH:MM:SS` as seconds since 1970-01-01 00:00:00 on the same clock
+fn parse_exif_date(text: &str) -> Option<f64> {
+    let (date, time) = text.split_once(' ')?;
+    let date: Vec<i64> = date.split(':').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    let time: Vec<i64> = time.split(':').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    let ([year, month, day], [hour, minute, second]) = (date.as_slice(), time.as_slice()) else {
+        return None;
+    };
+    if !(1..=12).contains(month) || !(1..=31).contains(day) {
+        return None;
+    }
+    // Days since the epoch from a civil date (Howard Hinnant's algorithm)
+    let year = if *month <= 2 { year - 1 } else { *year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if *month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some((days * 86_400 + hour * 3600 + minute * 60 + second) as f64)
+}
+
+/// Unlink the second IFD, which holds the thumbnail, by zeroing the first IFD's next-IFD offset
+fn drop_thumbnail(tiff: &mut [u8]) {
+    let big_endian = match tiff.get(0..2) {
+        Some(b"MM") => true,
+        Some(b"II") => false,
+        _ => return,
+    };
+    let read = |bytes: &[u8]| -> usize {
+        match bytes.len() {
+            2 if big_endian => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+            _ if big_endian => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+        }
+    };
+    let Some(ifd) = tiff.get(4..8).map(read) else { return };
+    let Some(entries) = tiff.get(ifd..ifd + 2).map(read) else { return };
+    let next = ifd + 2 + entries * 12;
+    if let Some(offset) = tiff.get_mut(next..next + 4) {
+        offset.fill(0);
+    }
+}
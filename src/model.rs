@@ -1,88 +1,197 @@
-use anyhow::Result;
-use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::io::Write;
+#[cfg(feature = "yolov8")]
+use std::fs::File;
+#[cfg(feature = "yolov8")]
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+#[cfg(feature = "yolov8")]
+use std::path::PathBuf;
+#[cfg(feature = "yolov8")]
+use std::thread;
+#[cfg(feature = "yolov8")]
+use std::time::Duration;
 use thiserror::Error;
-use std::process::Command;
+
+#[cfg(feature = "yolov8")]
+use crate::i18n;
+use crate::say_err;
+
+/// A model file whose contents are known to be good
+struct KnownModel {
+    file: &'static str,
+    sha256: &'static str,
+}
+
+/// Checksums of the models this tool ships with or downloads. A file named like an
+/// entry must match it; a renamed copy is recognized by its hash.
+const REGISTRY: &[KnownModel] = &[
+    // SeetaFace frontal detector (seeta_fd_frontal_v1.0.bin)
+    KnownModel {
+        file: "model.bin",
+        sha256: "c4619d066ed35e84d9a8e842860b0dff567aba0cbb139881075538761db3ff5d",
+    },
+];
+
+/// YOLOv8n face detection model (smaller and faster than YOLOv11 but efficient for face detection)
+#[cfg(feature = "yolov8")]
+const MODEL_URL: &str = "https://github.com/akanametov/yolov8-face/releases/download/v0.0.0/yolov8n-face.onnx";
+#[cfg(feature = "yolov8")]
+const MODEL_FILE: &str = "yolov8n-face.onnx";
+
+/// Download attempts before giving up; waits double after each failure
+#[cfg(feature = "yolov8")]
+const ATTEMPTS: u32 = 3;
+#[cfg(feature = "yolov8")]
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
 
 #[derive(Error, Debug)]
 pub enum ModelError {
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
+    #[cfg(feature = "yolov8")]
     #[error("Download error: {0}")]
     DownloadError(String),
+    #[cfg(feature = "yolov8")]
     #[error("Invalid model path: {0}")]
     InvalidPath(String),
+    #[error("Checksum mismatch for {file}: expected {expected}, got {actual} (use --skip-checksum to override)")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Check `path` against the registry unless `skip` is set; unknown models only get a warning
+pub fn verify_checksum(path: &Path, skip: bool) -> Result<(), ModelError> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    verify_as(path, name, skip)
+}
+
+/// Like `verify_checksum`, but look the file up in the registry under `name`
+fn verify_as(path: &Path, name: &str, skip: bool) -> Result<(), ModelError> {
+    if skip {
+        return Ok(());
+    }
+    let actual = hex::encode(Sha256::digest(fs::read(path)?));
+    if REGISTRY.iter().any(|known| known.sha256 == actual) {
+        return Ok(());
+    }
+
+    match REGISTRY.iter().find(|known| known.file == name) {
+        Some(known) => Err(ModelError::ChecksumMismatch {
+            file: path.display().to_string(),
+            expected: known.sha256.to_string(),
+            actual,
+        }),
+        None => {
+            say_err!(ModelUnverified, path.display(), actual);
+            Ok(())
+        }
+    }
 }
 
-pub fn ensure_yolo_model(model_dir: &Path) -> Result<PathBuf, ModelError> {
+#[cfg(feature = "yolov8")]
+pub fn ensure_yolo_model(model_dir: &Path, skip_checksum: bool) -> Result<PathBuf, ModelError> {
     fs::create_dir_all(model_dir)?;
-    
-    let model_path = model_dir.join("yolov8n-face.onnx");
-    
+
+    let model_path = model_dir.join(MODEL_FILE);
+
     if model_path.exists() {
-        println!("YOLO face detection model already exists at: {}", model_path.display());
+        if !i18n::quiet() {
+            say_err!(ModelPresent, model_path.display());
+        }
+        verify_checksum(&model_path, skip_checksum)?;
         return Ok(model_path);
     }
-    
-    println!("Downloading YOLOv8 face detection model...");
-    
-    // Create a simple Python script to download the model
-    let script_path = model_dir.join("download_model.py");
-    let mut script_file = fs::File::create(&script_path)?;
-    
-    write!(script_file, r#"
-import requests
-import os
-import sys
-
-def download_file(url, local_path):
-    with requests.get(url, stream=True) as r:
-        r.raise_for_status()
-        with open(local_path, 'wb') as f:
-            for chunk in r.iter_content(chunk_size=8192):
-                f.write(chunk)
-    return local_path
-
-# YOLOv8n face detection model (smaller and faster than YOLOv11 but efficient for face detection)
-MODEL_URL = "https://github.com/akanametov/yolov8-face/releases/download/v0.0.0/yolov8n-face.onnx"
-LOCAL_PATH = os.path.join('{}', 'yolov8n-face.onnx')
-
-try:
-    print(f"Downloading model from {{MODEL_URL}}...")
-    download_file(MODEL_URL, LOCAL_PATH)
-    print(f"Model downloaded successfully to {{LOCAL_PATH}}")
-    sys.exit(0)
-except Exception as e:
-    print(f"Error downloading model: {{e}}")
-    sys.exit(1)
-"#, model_dir.display())?;
-
-    // Run the Python script to download the model
-    let output = Command::new("python")
-        .arg(&script_path)
-        .output()
-        .map_err(|e| ModelError::DownloadError(format!("Failed to run Python: {}", e)))?;
-        
-    if !output.status.success() {
-        let error_message = String::from_utf8_lossy(&output.stderr);
-        return Err(ModelError::DownloadError(format!(
-            "Model download script failed: {}",
-            error_message
-        )));
+
+    if !i18n::quiet() {
+        say_err!(ModelDownloading, MODEL_URL);
+    }
+
+    // Download next to the target and rename, so an interrupted download never looks like a model
+    let partial_path = model_path.with_extension("onnx.part");
+    let mut delay = FIRST_RETRY_DELAY;
+    for attempt in 1..=ATTEMPTS {
+        match download(MODEL_URL, &partial_path) {
+            Ok(()) => break,
+            Err(e) if attempt < ATTEMPTS => {
+                say_err!(ModelDownloadRetry, attempt, ATTEMPTS, e, delay.as_secs());
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&partial_path);
+                return Err(e);
+            }
+        }
+    }
+
+    // Never keep a corrupted download around to be picked up by the next run
+    if let Err(e) = verify_as(&partial_path, MODEL_FILE, skip_checksum) {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+    fs::rename(&partial_path, &model_path)?;
+
+    if !i18n::quiet() {
+        say_err!(ModelDownloaded);
     }
 
-    println!("Model downloaded successfully");
-    
-    // Clean up the script
-    let _ = fs::remove_file(script_path);
-    
     if !model_path.exists() {
         return Err(ModelError::InvalidPath(format!(
             "Expected model file not found after download: {}",
             model_path.display()
         )));
     }
-    
+
     Ok(model_path)
 }
+
+/// Stream `url` into `dest`, printing progress every 10%
+#[cfg(feature = "yolov8")]
+fn download(url: &str, dest: &Path) -> Result<(), ModelError> {
+    let mut response = reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .build()
+        .and_then(|client| client.get(url).send())
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ModelError::DownloadError(e.to_string()))?;
+
+    let total = response.content_length();
+    let mut writer = BufWriter::new(File::create(dest)?);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut received: u64 = 0;
+    let mut reported = 0;
+    loop {
+        let n = response
+            .read(&mut buffer)
+            .map_err(|e| ModelError::DownloadError(e.to_string()))?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..n])?;
+        received += n as u64;
+
+        if let Some(total) = total.filter(|&t| t > 0) {
+            let percent = received * 100 / total;
+            if percent >= reported + 10 {
+                reported = percent - percent % 10;
+                if !i18n::quiet() {
+                    say_err!(ModelDownloadProgress, reported, format!("{:.1}", received as f64 / 1e6), format!("{:.1}", total as f64 / 1e6));
+                }
+            }
+        }
+    }
+    writer.flush()?;
+
+    if total.is_some_and(|t| t != received) {
+        return Err(ModelError::DownloadError(format!(
+            "connection closed after {} of {} bytes",
+            received,
+            total.unwrap_or(0)
+        )));
+    }
+    Ok(())
+}
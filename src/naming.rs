@@ -0,0 +1,182 @@
+//! Crop file names (`--name-template`) and what happens when one is already
+//! taken (`--on-collision`)
+//!
+//! A name is the template filled in for one face, then `_<variant>` when
+//! several framings are saved, then the format's extension. Placeholders are
+//! `{name}` or `{name:spec}`, where spec is a zero-padded width and/or a
+//! precision, as in Rust's `format!`: `{index:05}`, `{score:.2}`. The default
+//! template gives `<source stem>_<face number>_<score x 100>`. With
+//! `--name-by-hash` the name is a hash of the crop's pixels instead, which
+//! stays the same whatever order or run a face is found in.
+//!
+//! Face numbers start over with every fresh run, so a second run into the same
+//! output directory, or two sources sharing a stem, can produce a name that is
+//! already on disk. `overwrite` replaces the old file, as earlier versions
+//! always did; `skip` keeps it and leaves the new crop out; `rename` appends
+//! `_1`, `_2`, ... until the name is free in every size and tier folder.
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::detector::BBox;
+use crate::quality::Tier;
+use crate::split::Split;
+use crate::Args;
+
+/// Name of every crop unless `--name-template` says otherwise
+pub const DEFAULT_TEMPLATE: &str = "{stem}_{index:04}_{score100}";
+
+/// What a name template can refer to for one face
+pub struct NameFields<'a> {
+    pub source: &'a Path,
+    pub index: usize,
+    pub score: f64,
+    pub bbox: &'a BBox,
+}
+
+enum Value {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl NameFields<'_> {
+    fn get(&self, placeholder: &str) -> Option<Value> {
+        Some(match placeholder {
+            "stem" => Value::Text(self.source.file_stem().map_or("unknown".into(), |s| s.to_string_lossy().into_owned())),
+            "index" => Value::Int(self.index as i64),
+            "score" => Value::Float(self.score),
+            // Rounded the way `{:.0}` does, which older names used
+            "score100" => Value::Int(format!("{:.0}", self.score * 100.0).parse().unwrap_or(0)),
+            "x" => Value::Int(self.bbox.x as i64),
+            "y" => Value::Int(self.bbox.y as i64),
+            "w" => Value::Int(self.bbox.width as i64),
+            "h" => Value::Int(self.bbox.height as i64),
+            // Tells apart sources that share a stem in different folders
+            "hash" => Value::Text(hex::encode(&Sha256::digest(self.source.to_string_lossy().as_bytes())[..4])),
+            "timestamp" => Value::Int(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)),
+            _ => return None,
+        })
+    }
+}
+
+/// Fill in `template` for one face
+pub fn render(template: &str, fields: &NameFields) -> Result<String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..].find('}').map(|i| start + i).context("Unclosed { in --name-template")?;
+        let (placeholder, spec) = rest[start + 1..end].split_once(':').unwrap_or((&rest[start + 1..end], ""));
+        let value = fields
+            .get(placeholder)
+            .with_context(|| format!("Unknown placeholder {{{}}} in --name-template", placeholder))?;
+        name.push_str(&format_value(value, spec)?);
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// `value` per a `[0][width][.precision]` spec; widths pad numbers with zeros
+fn format_value(value: Value, spec: &str) -> Result<String> {
+    let (width, precision) = spec.split_once('.').map_or((spec, None), |(w, p)| (w, Some(p)));
+    let width: usize = if width.is_empty() { 0 } else { width.parse().with_context(|| format!("Bad format spec :{}", spec))? };
+    let precision: Option<usize> = precision.map(str::parse).transpose().with_context(|| format!("Bad format spec :{}", spec))?;
+    Ok(match (value, precision) {
+        (Value::Int(n), _) => format!("{:0width$}", n, width = width),
+        (Value::Float(x), Some(precision)) => format!("{:0width$.precision$}", x, width = width, precision = precision),
+        (Value::Float(x), None) => format!("{:0width$}", x, width = width),
+        (Value::Text(text), _) => text,
+    })
+}
+
+/// Name for `--name-by-hash`: the start of the SHA-256 of the crop's size, layout and pixels
+pub fn content_name(image: &DynamicImage) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(image.width().to_le_bytes());
+    hasher.update(image.height().to_le_bytes());
+    hasher.update(format!("{:?}", image.color()).as_bytes());
+    hasher.update(image.as_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// Reject a template that can't name crops before any work is done
+pub fn check_template(template: &str) -> Result<()> {
+    if template.contains(['/', '\\']) {
+        bail!("--name-template must not contain path separators");
+    }
+    let sample = NameFields { source: Path::new("sample.jpg"), index: 1, score: 1.0, bbox: &BBox::default() };
+    if render(template, &sample)?.is_empty() {
+        bail!("--name-template gives an empty name");
+    }
+    Ok(())
+}
+
+/// What to do when a crop's file name already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnCollision {
+    /// Replace the existing file
+    #[default]
+    Overwrite,
+    /// Keep the existing file and don't save the new crop
+    Skip,
+    /// Save the new crop under the first free `_N` name
+    Rename,
+}
+
+/// Folders above the size folder that a face's crops go into
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Placement {
+    pub split: Option<Split>,
+    pub tier: Option<Tier>,
+}
+
+/// Path of a crop below --output: split folder, tier folder, size folder, then the file
+pub fn crop_path(placement: Placement, size: Option<u32>, file: &str) -> String {
+    let mut path = file.to_string();
+    if let Some(size) = size {
+        path = format!("{}/{}", size, path);
+    }
+    if let Some(tier) = placement.tier {
+        path = format!("{}/{}", tier.name(), path);
+    }
+    if let Some(split) = placement.split {
+        path = format!("{}/{}", split.name(), path);
+    }
+    path
+}
+
+/// Whether `file` already exists in any of the folders a crop is saved to
+fn taken(args: &Args, placement: Placement, file: &str) -> bool {
+    let sizes: Vec<Option<u32>> = if args.output_sizes.is_empty() {
+        vec![None]
+    } else {
+        args.output_sizes.iter().map(|&size| Some(size)).collect()
+    };
+    sizes.into_iter().any(|size| args.output.join(crop_path(placement, size, file)).exists())
+}
+
+/// The name to save `file` under, or None to leave it out; `collisions` counts names found taken
+pub fn resolve(args: &Args, placement: Placement, file: String, collisions: &mut usize) -> Option<String> {
+    if !taken(args, placement, &file) {
+        return Some(file);
+    }
+    *collisions += 1;
+    match args.on_collision {
+        OnCollision::Overwrite => Some(file),
+        OnCollision::Skip => None,
+        OnCollision::Rename => {
+            let (stem, extension) = file.rsplit_once('.').unwrap_or((&file, ""));
+            (1..)
+                .map(|n| format!("{}_{}.{}", stem, n, extension))
+                .find(|candidate| !taken(args, placement, candidate))
+        }
+    }
+}
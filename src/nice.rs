@@ -0,0 +1,117 @@
+//! Background processing on shared workstations (`--nice-mode`)
+//!
+//! Between images the run measures how much CPU the rest of the machine is
+//! using, leaving out its own time. Above `--nice-max-load` the run pauses
+//! and samples again every few seconds, resuming once other work has dropped
+//! well below the limit. The checkpoint is flushed when a pause begins, so a
+//! run stopped while paused loses nothing and continues with `--resume`.
+//! With `--jobs`, the decode and detect workers hold off during a pause too.
+//! Load is read from `/proc`, so nice mode only throttles on Linux.
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::checkpoint::CheckpointWriter;
+use crate::pipeline::CpuSample;
+use crate::say;
+
+/// Shortest window the load is measured over while running
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Sampling period while paused
+const PAUSE_POLL: Duration = Duration::from_secs(5);
+
+/// Share of the limit other work must fall under before resuming, so the run doesn't flap
+const RESUME_FACTOR: f64 = 0.75;
+
+struct Sample {
+    cpu: CpuSample,
+    own: u64,
+    at: Instant,
+}
+
+pub struct NiceMode {
+    max_load: f64,
+    last: Option<Sample>,
+    paused: Arc<AtomicBool>,
+}
+
+impl NiceMode {
+    pub fn new(max_load: f64) -> Self {
+        let last = Sample::read();
+        if last.is_none() {
+            say!(NiceUnsupported);
+        }
+        NiceMode { max_load, last, paused: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// Set for as long as a pause lasts, for worker threads to check
+    pub fn paused(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
+
+    /// Pause while other processes keep the CPU busier than the limit
+    pub fn wait(&mut self, checkpoint: Option<&mut CheckpointWriter>, faces: usize) {
+        let Some(load) = self.load_since_last(CHECK_INTERVAL) else {
+            return;
+        };
+        if load <= self.max_load {
+            return;
+        }
+
+        say!(NicePaused, format!("{:.0}", load * 100.0));
+        if let Some(checkpoint) = checkpoint {
+            if let Err(e) = checkpoint.flush(faces) {
+                eprintln!("⚠️  {:#}", e);
+            }
+        }
+        self.paused.store(true, Ordering::Relaxed);
+        let paused = Instant::now();
+        loop {
+            thread::sleep(PAUSE_POLL);
+            match self.load_since_last(Duration::ZERO) {
+                Some(load) if load > self.max_load * RESUME_FACTOR => continue,
+                _ => break,
+            }
+        }
+        self.paused.store(false, Ordering::Relaxed);
+        say!(NiceResumed, format!("{}s", paused.elapsed().as_secs()));
+    }
+
+    /// CPU share used by other processes since the last sample, once at least `min_window` has passed
+    fn load_since_last(&mut self, min_window: Duration) -> Option<f64> {
+        let last = self.last.as_ref()?;
+        if last.at.elapsed() < min_window {
+            return None;
+        }
+        let now = Sample::read()?;
+        let total = now.cpu.total.checked_sub(last.cpu.total).filter(|&t| t > 0)?;
+        let busy = now.cpu.busy.saturating_sub(last.cpu.busy);
+        let own = now.own.saturating_sub(last.own);
+        self.last = Some(now);
+        Some(busy.saturating_sub(own) as f64 / total as f64)
+    }
+}
+
+impl Sample {
+    fn read() -> Option<Self> {
+        Some(Sample {
+            cpu: CpuSample::read()?,
+            own: own_ticks()?,
+            at: Instant::now(),
+        })
+    }
+}
+
+/// User plus system time of this process, in the same clock ticks as `/proc/stat`
+fn own_ticks() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name may contain spaces; the numeric fields start after its closing parenthesis
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
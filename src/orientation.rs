@@ -0,0 +1,74 @@
+//! Coarse left/right facing estimate for detected faces
+//!
+//! RustFace reports no pose, so the direction a face is turned is estimated from
+//! where its texture sits: eyes, nose and mouth carry most of the gradient energy
+//! and crowd towards the side the face is turned to, while the far side is mostly
+//! smooth cheek and hair.
+
+use clap::ValueEnum;
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+
+use crate::detector::BBox;
+
+/// Left/right energy imbalance below which a face counts as frontal
+const FRONTAL_TOLERANCE: f64 = 0.08;
+
+/// Direction a face is turned, from the viewer's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Facing {
+    Left,
+    Right,
+    #[value(skip)]
+    Frontal,
+}
+
+impl Facing {
+    /// True if mirroring a face facing `self` would make it face `wanted`
+    pub fn is_opposite(self, wanted: Facing) -> bool {
+        matches!(
+            (self, wanted),
+            (Facing::Left, Facing::Right) | (Facing::Right, Facing::Left)
+        )
+    }
+}
+
+/// Estimate the facing of the face inside `bbox` from its horizontal gradient balance
+pub fn estimate_facing(gray: &GrayImage, bbox: &BBox) -> Facing {
+    let x0 = bbox.x.max(0) as u32;
+    let y0 = bbox.y.max(0) as u32;
+    let x1 = (bbox.x + bbox.width as i32).clamp(0, gray.width() as i32) as u32;
+    let y1 = (bbox.y + bbox.height as i32).clamp(0, gray.height() as i32) as u32;
+    if x1 <= x0 + 2 || y1 <= y0 + 2 {
+        return Facing::Frontal;
+    }
+
+    let mid = (x0 + x1) / 2;
+    let (mut left, mut right) = (0.0, 0.0);
+    for y in y0..y1 - 1 {
+        for x in x0..x1 - 1 {
+            let p = gray.get_pixel(x, y)[0] as f64;
+            let dx = (gray.get_pixel(x + 1, y)[0] as f64 - p).abs();
+            let dy = (gray.get_pixel(x, y + 1)[0] as f64 - p).abs();
+            if x < mid {
+                left += dx + dy;
+            } else {
+                right += dx + dy;
+            }
+        }
+    }
+
+    let total = left + right;
+    if total == 0.0 {
+        return Facing::Frontal;
+    }
+    let imbalance = (left - right) / total;
+    if imbalance > FRONTAL_TOLERANCE {
+        Facing::Left
+    } else if imbalance < -FRONTAL_TOLERANCE {
+        Facing::Right
+    } else {
+        Facing::Frontal
+    }
+}
@@ -0,0 +1,131 @@
+//! 64-bit perceptual hashes for spotting near-identical images
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Serialize, Serializer};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::detector::BBox;
+
+pub const DUPLICATES_FILE: &str = "duplicates.json";
+
+/// Side of the downscaled image the DCT runs on
+const SIZE: usize = 32;
+
+/// Low-frequency block kept from the DCT
+const KEEP: usize = 8;
+
+/// Classic DCT pHash: low-frequency coefficients compared against their median
+pub fn phash(image: &DynamicImage) -> u64 {
+    let small = image
+        .resize_exact(SIZE as u32, SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = small.pixels().map(|p| p[0] as f64).collect();
+
+    // Separable 2D DCT-II, only the KEEP x KEEP corner is needed
+    let cos: Vec<f64> = (0..KEEP * SIZE)
+        .map(|i| {
+            let (u, x) = (i / SIZE, i % SIZE);
+            ((2 * x + 1) as f64 * u as f64 * PI / (2 * SIZE) as f64).cos()
+        })
+        .collect();
+    let mut rows = vec![0.0; SIZE * KEEP];
+    for y in 0..SIZE {
+        for u in 0..KEEP {
+            rows[y * KEEP + u] = (0..SIZE).map(|x| pixels[y * SIZE + x] * cos[u * SIZE + x]).sum();
+        }
+    }
+    let mut coeffs = [0.0; KEEP * KEEP];
+    for v in 0..KEEP {
+        for u in 0..KEEP {
+            coeffs[v * KEEP + u] = (0..SIZE).map(|y| rows[y * KEEP + u] * cos[v * SIZE + y]).sum();
+        }
+    }
+
+    // The DC term only encodes overall brightness
+    let mut sorted = coeffs[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    coeffs
+        .iter()
+        .enumerate()
+        .filter(|&(_, &c)| c > median)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i))
+}
+
+/// Number of differing bits between two hashes
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Hashes of the faces saved so far in a run (`--dedup`), each with the faces dropped as its repeats
+#[derive(Debug, Default)]
+pub struct SeenFaces {
+    clusters: Vec<Cluster>,
+    /// Faces dropped as near-duplicates
+    pub dropped: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Cluster {
+    #[serde(serialize_with = "hex")]
+    hash: u64,
+    members: Vec<Member>,
+}
+
+/// One face in a duplicate cluster; the first member is the one that was kept
+#[derive(Debug, Serialize)]
+pub struct Member {
+    pub source: PathBuf,
+    pub detection_id: usize,
+    pub bbox: BBox,
+    /// Crops saved for the face, empty for dropped faces
+    pub crops: Vec<String>,
+    /// Hash distance to the kept face
+    pub distance: u32,
+    pub kept: bool,
+}
+
+impl SeenFaces {
+    /// Closest saved face within `max_distance` bits of `hash`, with its distance
+    pub fn nearest(&self, hash: u64, max_distance: u32) -> Option<(usize, u32)> {
+        self.clusters
+            .iter()
+            .enumerate()
+            .map(|(i, cluster)| (i, distance(cluster.hash, hash)))
+            .filter(|&(_, d)| d <= max_distance)
+            .min_by_key(|&(_, d)| d)
+    }
+
+    /// Record a face dropped as a repeat of the saved face `cluster`
+    pub fn drop_into(&mut self, cluster: usize, member: Member) {
+        self.clusters[cluster].members.push(member);
+        self.dropped += 1;
+    }
+
+    /// Start a cluster for a saved face
+    pub fn insert(&mut self, hash: u64, member: Member) {
+        self.clusters.push(Cluster { hash, members: vec![member] });
+    }
+
+    /// Write the clusters that caught at least one repeat to `duplicates.json` in `output`
+    pub fn write(&self, output: &Path, max_distance: u32) -> Result<()> {
+        let clusters: Vec<&Cluster> = self.clusters.iter().filter(|c| c.members.len() > 1).collect();
+        let report = serde_json::json!({
+            "max_distance": max_distance,
+            "dropped": self.dropped,
+            "clusters": clusters,
+        });
+        let path = output.join(DUPLICATES_FILE);
+        fs::write(&path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn hex<S: Serializer>(hash: &u64, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:016x}", hash))
+}
@@ -0,0 +1,422 @@
+//! Parallel decode/detect pipeline (`--jobs N` or `--jobs auto`)
+//!
+//! Images flow through three stages: decode workers read and enhance files,
+//! detect workers (each with its own detector) find faces, and the main thread
+//! crops, saves and records them in completion order. Bounded queues between
+//! the stages keep memory flat.
+//!
+//! With `auto` both stages start with one worker and a controller rebalances
+//! them every few seconds: a full detect queue means detection is the
+//! bottleneck, an empty one means decoding is. Workers are added while the CPU
+//! has headroom, and moved between stages once it is saturated.
+//!
+//! Only the detect workers load detectors; if none of them manages to, the
+//! batch ends with the load error. While `--nice-mode` has the run paused,
+//! the workers idle as well.
+
+use anyhow::{Error, Result};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    decode_image, detect_image, detector, extract_faces, out_of_space, progress, record_outcome, say, say_err, shutdown, target_reached, yield_to_load, BatchStats,
+    Detected, Extractor,
+};
+
+/// How often `auto` reconsiders the worker split
+const REBALANCE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// CPU utilization above which workers are moved rather than added
+const CPU_SATURATED: f64 = 0.9;
+
+/// How long an idle or parked worker waits before checking again
+const IDLE_POLL: Duration = Duration::from_millis(50);
+
+/// Worker threads per stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Jobs {
+    Fixed(usize),
+    Auto,
+}
+
+impl FromStr for Jobs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(Jobs::Auto),
+            n => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Jobs::Fixed(n)),
+                _ => Err(format!("'{}' is not a job count (use a number >= 1 or auto)", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Jobs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Jobs::Fixed(n) => write!(f, "{}", n),
+            Jobs::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl TryFrom<String> for Jobs {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Jobs> for String {
+    fn from(jobs: Jobs) -> Self {
+        jobs.to_string()
+    }
+}
+
+/// Worker bookkeeping for one stage: how many may run, and how many items wait in front of it
+#[derive(Default)]
+struct Stage {
+    active: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+type Decoded = (usize, PathBuf, Result<(DynamicImage, Option<DynamicImage>)>);
+type DetectedItem = (usize, PathBuf, Result<Detected>);
+
+/// Pipelined counterpart of `run_batch`
+pub fn run_batch(ex: &mut Extractor, paths: &[PathBuf], offset: usize, total: usize) -> Result<BatchStats> {
+    let args = ex.args;
+    let max_workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let (initial, spawned) = match args.jobs {
+        Jobs::Fixed(n) => (n, n),
+        Jobs::Auto => (1, max_workers),
+    };
+    let capacity = 2 * spawned;
+
+    let decode = Stage::default();
+    let detect = Stage::default();
+    decode.active.store(initial, Ordering::Relaxed);
+    detect.active.store(initial, Ordering::Relaxed);
+    // Decoders take from `queue[next]`; the main thread may reorder everything after it
+    let mut queue = paths.to_vec();
+    ex.control.poll(&mut queue);
+    let queue = Mutex::new(queue);
+    let next = AtomicUsize::new(0);
+    let decoders_left = AtomicUsize::new(spawned);
+    let stop = AtomicBool::new(false);
+    let paused = ex.nice.as_ref().map_or_else(|| Arc::new(AtomicBool::new(false)), |nice| nice.paused());
+    let loads = Mutex::new(Loads::default());
+
+    let (decoded_tx, decoded_rx) = mpsc::sync_channel::<Decoded>(capacity);
+    let (detected_tx, detected_rx) = mpsc::sync_channel::<DetectedItem>(capacity);
+    let decoded_rx = Mutex::new(decoded_rx);
+
+    let mut stats = BatchStats::default();
+    thread::scope(|scope| {
+        for id in 0..spawned {
+            let tx = decoded_tx.clone();
+            let (queue, decode, detect, next, decoders_left, stop, paused) =
+                (&queue, &decode, &detect, &next, &decoders_left, &stop, &*paused);
+            scope.spawn(move || {
+                decode_worker(id, queue, args, decode, detect, next, stop, paused, tx);
+                decoders_left.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+        for id in 0..spawned {
+            let tx = detected_tx.clone();
+            let (detect, decoded_rx, decoders_left, stop, paused, loads) =
+                (&detect, &decoded_rx, &decoders_left, &stop, &*paused, &loads);
+            scope.spawn(move || detect_worker(id, args, detect, decoded_rx, decoders_left, stop, paused, loads, tx));
+        }
+        drop(decoded_tx);
+        drop(detected_tx);
+
+        let mut controller = (args.jobs == Jobs::Auto).then(|| Controller::new(max_workers, capacity));
+        loop {
+            if shutdown::requested() {
+                stats.interrupted = true;
+                break;
+            }
+            if let Some(controller) = &mut controller {
+                controller.tick(&decode, &detect);
+            }
+            if let Ok(mut queue) = queue.lock() {
+                let start = next.load(Ordering::Relaxed).min(queue.len());
+                ex.control.poll(&mut queue[start..]);
+            }
+            let (index, path, detected) = match detected_rx.recv_timeout(IDLE_POLL) {
+                Ok(item) => item,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            if target_reached(ex) {
+                stats.target_reached = true;
+                break;
+            }
+            if out_of_space(ex) {
+                stats.interrupted = shutdown::requested();
+                stats.out_of_space = !stats.interrupted;
+                break;
+            }
+
+            yield_to_load(ex);
+            if !progress::active() {
+                say!(Processing, offset + index + 1, total, path.display());
+            }
+            let result = detected.and_then(|detected| extract_faces(ex, &path, detected));
+            record_outcome(ex, &mut stats, offset + index, &path, result);
+        }
+
+        // Unblock workers waiting on a full queue so the scope can join them
+        stop.store(true, Ordering::Relaxed);
+        drop(detected_rx);
+    });
+
+    match loads.into_inner().unwrap_or_else(|e| e.into_inner()).error {
+        Some(e) => Err(e.context("No detect worker could load a detector")),
+        None => Ok(stats),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn decode_worker(
+    id: usize,
+    queue: &Mutex<Vec<PathBuf>>,
+    args: &crate::Args,
+    decode: &Stage,
+    detect: &Stage,
+    next: &AtomicUsize,
+    stop: &AtomicBool,
+    paused: &AtomicBool,
+    tx: SyncSender<Decoded>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        if paused.load(Ordering::Relaxed) {
+            thread::sleep(IDLE_POLL);
+            continue;
+        }
+        if id >= decode.active.load(Ordering::Relaxed) {
+            if queue.lock().map_or(true, |queue| next.load(Ordering::Relaxed) >= queue.len()) {
+                break;
+            }
+            thread::sleep(IDLE_POLL);
+            continue;
+        }
+        // Claim the next position under the lock so a concurrent reorder never skips or repeats an image
+        let claimed = queue.lock().ok().and_then(|queue| {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            queue.get(index).map(|path| (index, path.clone()))
+        });
+        let Some((index, path)) = claimed else {
+            break;
+        };
+        let decoded = decode_image(args, &path);
+        detect.queued.fetch_add(1, Ordering::Relaxed);
+        if !send_until_stopped(&tx, (index, path, decoded), stop) {
+            break;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn detect_worker(
+    id: usize,
+    args: &crate::Args,
+    detect: &Stage,
+    rx: &Mutex<Receiver<Decoded>>,
+    decoders_left: &AtomicUsize,
+    stop: &AtomicBool,
+    paused: &AtomicBool,
+    loads: &Mutex<Loads>,
+    tx: SyncSender<DetectedItem>,
+) {
+    // Each worker owns a detector, loaded the first time the controller activates it
+    let mut detector = None;
+    while !stop.load(Ordering::Relaxed) {
+        if paused.load(Ordering::Relaxed) {
+            thread::sleep(IDLE_POLL);
+            continue;
+        }
+        if id >= detect.active.load(Ordering::Relaxed) {
+            if decoders_left.load(Ordering::Relaxed) == 0 {
+                break;
+            }
+            thread::sleep(IDLE_POLL);
+            continue;
+        }
+
+        let received = rx
+            .lock()
+            .map_err(|_| RecvTimeoutError::Disconnected)
+            .and_then(|rx| rx.recv_timeout(IDLE_POLL));
+        let (index, path, decoded) = match received {
+            Ok(item) => item,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        detect.queued.fetch_sub(1, Ordering::Relaxed);
+
+        if detector.is_none() {
+            lock(loads).loading += 1;
+            let loaded = detector::load(args);
+            let mut loads = lock(loads);
+            loads.loading -= 1;
+            match loaded {
+                Ok(loaded) => {
+                    loads.loaded += 1;
+                    detector = Some(loaded);
+                }
+                Err(e) => {
+                    say_err!(WorkerFailed, id + 1, e);
+                    // No worker has a detector and none is still loading one: the batch can't go on
+                    if loads.loaded == 0 && loads.loading == 0 {
+                        loads.error = Some(e);
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    break;
+                }
+            }
+        }
+        let detector = detector.as_deref_mut().expect("detector loaded above");
+
+        let detected = decoded.and_then(|decoded| detect_image(args, detector, decoded));
+        if !send_until_stopped(&tx, (index, path, detected), stop) {
+            break;
+        }
+    }
+}
+
+/// Detectors the detect workers have loaded so far, and the error if none could be
+#[derive(Default)]
+struct Loads {
+    loading: usize,
+    loaded: usize,
+    error: Option<Error>,
+}
+
+fn lock(loads: &Mutex<Loads>) -> std::sync::MutexGuard<'_, Loads> {
+    loads.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Send `item`, waiting while the queue is full; false once the run stops or the receiver is gone
+fn send_until_stopped<T>(tx: &SyncSender<T>, mut item: T, stop: &AtomicBool) -> bool {
+    loop {
+        match tx.try_send(item) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(back)) if !stop.load(Ordering::Relaxed) => {
+                item = back;
+                thread::sleep(IDLE_POLL);
+            }
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Rebalances `--jobs auto` from queue depth and CPU load
+struct Controller {
+    max_workers: usize,
+    capacity: usize,
+    last: Instant,
+    cpu: Option<CpuSample>,
+}
+
+impl Controller {
+    fn new(max_workers: usize, capacity: usize) -> Self {
+        Controller {
+            max_workers,
+            capacity,
+            last: Instant::now(),
+            cpu: CpuSample::read(),
+        }
+    }
+
+    fn tick(&mut self, decode: &Stage, detect: &Stage) {
+        if self.last.elapsed() < REBALANCE_INTERVAL {
+            return;
+        }
+        self.last = Instant::now();
+
+        let sample = CpuSample::read();
+        let utilization = match (&self.cpu, &sample) {
+            (Some(before), Some(now)) => now.utilization_since(before),
+            _ => None,
+        };
+        self.cpu = sample;
+        let saturated = utilization.is_some_and(|u| u >= CPU_SATURATED);
+
+        let decoders = decode.active.load(Ordering::Relaxed);
+        let detectors = detect.active.load(Ordering::Relaxed);
+        let waiting = detect.queued.load(Ordering::Relaxed);
+
+        let (new_decoders, new_detectors) = if waiting >= self.capacity / 2 {
+            // Decoded images pile up: detection is the bottleneck
+            if !saturated && detectors < self.max_workers {
+                (decoders, detectors + 1)
+            } else if decoders > 1 {
+                (decoders - 1, (detectors + 1).min(self.max_workers))
+            } else {
+                (decoders, detectors)
+            }
+        } else if waiting == 0 {
+            // Detectors are starved: decoding is the bottleneck
+            if !saturated && decoders < self.max_workers {
+                (decoders + 1, detectors)
+            } else if detectors > 1 {
+                ((decoders + 1).min(self.max_workers), detectors - 1)
+            } else {
+                (decoders, detectors)
+            }
+        } else {
+            (decoders, detectors)
+        };
+
+        if (new_decoders, new_detectors) != (decoders, detectors) {
+            decode.active.store(new_decoders, Ordering::Relaxed);
+            detect.active.store(new_detectors, Ordering::Relaxed);
+            let cpu = utilization.map_or("?".to_string(), |u| format!("{:.0}", u * 100.0));
+            say!(WorkersAdjusted, new_decoders, new_detectors, cpu);
+        }
+    }
+}
+
+/// Aggregate CPU time counters from `/proc/stat` (Linux only; elsewhere CPU load is unknown)
+pub struct CpuSample {
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuSample {
+    pub fn read() -> Option<Self> {
+        let stat = fs::read_to_string(Path::new("/proc/stat")).ok()?;
+        let fields: Vec<u64> = stat
+            .lines()
+            .next()?
+            .split_whitespace()
+            .skip(1)
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        // user nice system idle iowait irq softirq steal ...
+        let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+        let total: u64 = fields.iter().take(8).sum();
+        Some(CpuSample { busy: total - idle, total })
+    }
+
+    fn utilization_since(&self, before: &CpuSample) -> Option<f64> {
+        let total = self.total.checked_sub(before.total).filter(|&t| t > 0)?;
+        Some(self.busy.saturating_sub(before.busy) as f64 / total as f64)
+    }
+}
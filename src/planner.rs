@@ -0,0 +1,250 @@
+//! Chunked multi-pass execution planner
+//!
+//! Huge corpora are split into fixed-size chunks whose runtimes are estimated
+//! from a small calibration sample. The plan is printed up front so operators can
+//! schedule multi-day jobs, then executed chunk by chunk. Every completed chunk
+//! is committed to the run store (`run.redb`, see `store`) so an interrupted
+//! run picks up at the next unfinished one. Output directories from older
+//! versions keep the plan in `plan_checkpoint.json`; resuming such a run reads
+//! it and moves it into the store.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::detector::FaceDetectorBackend;
+use crate::events::Event;
+use crate::store::{self, PlanProgress};
+use crate::{decode, run_batch, say, say_err, Args, BatchStats, Extractor};
+
+/// Plan progress file written by older versions
+const LEGACY_CHECKPOINT_FILE: &str = "plan_checkpoint.json";
+
+/// A contiguous range of the (ordered) input list
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub index: usize,
+    pub start: usize,
+    pub end: usize,
+    pub estimated: Duration,
+}
+
+impl Chunk {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+#[derive(Debug)]
+pub struct Plan {
+    pub chunks: Vec<Chunk>,
+    pub secs_per_image: f64,
+    pub calibration_images: usize,
+}
+
+/// Plan progress as older versions wrote it
+#[derive(Debug, Serialize, Deserialize)]
+struct LegacyCheckpoint {
+    total_images: usize,
+    chunk_size: usize,
+    completed_chunks: Vec<usize>,
+    faces_extracted: usize,
+}
+
+/// Time detection (decode + detect, nothing saved) on up to `--calibration-sample` images spread evenly across the corpus
+pub fn calibrate(paths: &[PathBuf], args: &Args, detector: &mut dyn FaceDetectorBackend) -> (f64, usize) {
+    let sample = args.calibration_sample.clamp(1, paths.len().max(1));
+    let stride = (paths.len() / sample).max(1);
+
+    let mut timed = 0;
+    let mut elapsed = Duration::ZERO;
+    for path in paths.iter().step_by(stride).take(sample) {
+        let start = Instant::now();
+        let Ok(image) = decode::open(path, args) else {
+            continue;
+        };
+        if detector.detect(&image).is_ok() {
+            elapsed += start.elapsed();
+            timed += 1;
+        }
+    }
+
+    if timed == 0 {
+        return (0.0, 0);
+    }
+    (elapsed.as_secs_f64() / timed as f64, timed)
+}
+
+impl Plan {
+    pub fn build(total: usize, chunk_size: usize, secs_per_image: f64, calibration_images: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let chunks = (0..total)
+            .step_by(chunk_size)
+            .enumerate()
+            .map(|(index, start)| {
+                let end = (start + chunk_size).min(total);
+                Chunk {
+                    index,
+                    start,
+                    end,
+                    estimated: Duration::from_secs_f64(secs_per_image * (end - start) as f64),
+                }
+            })
+            .collect();
+
+        Plan {
+            chunks,
+            secs_per_image,
+            calibration_images,
+        }
+    }
+
+    pub fn total_estimate(&self) -> Duration {
+        self.chunks.iter().map(|c| c.estimated).sum()
+    }
+
+    pub fn print(&self) {
+        say!(PlanHeader, self.chunks.len());
+        if self.calibration_images > 0 {
+            say!(PlanCalibrated, format!("{:.3}", self.secs_per_image), self.calibration_images);
+        } else {
+            say!(PlanCalibrationFailed);
+        }
+        let total = self.chunks.last().map_or(0, |c| c.end);
+        for chunk in &self.chunks {
+            say!(
+                PlanChunk,
+                format!("{:>3}", chunk.index + 1),
+                self.chunks.len(),
+                chunk.start + 1,
+                chunk.end,
+                chunk.len(),
+                format_duration(chunk.estimated)
+            );
+        }
+        say!(PlanTotal, total, format_duration(self.total_estimate()));
+    }
+}
+
+/// Calibrate, print the plan and (unless `--plan-only`) execute it chunk by chunk
+pub fn run_chunked(ex: &mut Extractor, paths: &[PathBuf], chunk_size: usize) -> Result<Option<BatchStats>> {
+    let args = ex.args;
+    let (secs_per_image, calibrated) = calibrate(paths, args, ex.detector()?);
+    let plan = Plan::build(paths.len(), chunk_size, secs_per_image, calibrated);
+    plan.print();
+
+    if args.plan_only {
+        return Ok(None);
+    }
+
+    let checkpoint = load_checkpoint(&args.output, paths.len(), chunk_size)?;
+    if !checkpoint.completed_chunks.is_empty() {
+        say!(
+            PlanResuming,
+            checkpoint.completed_chunks.len(),
+            plan.chunks.len(),
+            checkpoint.faces_extracted
+        );
+        ex.face_counter.store(checkpoint.faces_extracted, Ordering::Relaxed);
+    }
+
+    let mut totals = BatchStats::default();
+    for chunk in &plan.chunks {
+        if checkpoint.completed_chunks.contains(&chunk.index) {
+            continue;
+        }
+
+        say!(ChunkStarting, chunk.index + 1, plan.chunks.len());
+        let start = Instant::now();
+        let stats = run_batch(ex, &paths[chunk.start..chunk.end], chunk.start, paths.len())?;
+
+        totals.processed += stats.processed;
+        totals.errors += stats.errors;
+        totals.extracted += stats.extracted;
+
+        say!(
+            ChunkDone,
+            chunk.index + 1,
+            plan.chunks.len(),
+            stats.processed,
+            stats.errors,
+            stats.extracted,
+            format_duration(start.elapsed()),
+            format_duration(chunk.estimated)
+        );
+        ex.events.emit(Event::ChunkDone {
+            chunk: chunk.index,
+            chunks: plan.chunks.len(),
+            faces: stats.extracted,
+        });
+
+        if stats.target_reached || stats.interrupted || stats.out_of_space {
+            totals.target_reached = stats.target_reached;
+            totals.interrupted = stats.interrupted;
+            totals.out_of_space = stats.out_of_space;
+            break;
+        }
+
+        store::complete_chunk(&args.output, chunk.index, ex.face_counter.load(Ordering::Relaxed))?;
+    }
+
+    Ok(Some(totals))
+}
+
+/// Whether a previous chunked run left a plan in `output_dir`
+pub fn checkpoint_exists(output_dir: &Path) -> Result<bool> {
+    if output_dir.join(LEGACY_CHECKPOINT_FILE).exists() {
+        return Ok(true);
+    }
+    Ok(store::exists(output_dir) && store::plan(output_dir)?.is_some())
+}
+
+/// The plan progress to resume from, starting a new plan if none matches this corpus and chunk size
+fn load_checkpoint(output_dir: &Path, total_images: usize, chunk_size: usize) -> Result<PlanProgress> {
+    migrate_legacy(output_dir)?;
+    match store::plan(output_dir)? {
+        Some(saved) if saved.total_images == total_images && saved.chunk_size == chunk_size => return Ok(saved),
+        Some(_) => say!(CheckpointMismatch, output_dir.join(store::STORE_FILE).display()),
+        None => {}
+    }
+    store::start_plan(output_dir, total_images, chunk_size)?;
+    Ok(PlanProgress {
+        total_images,
+        chunk_size,
+        ..Default::default()
+    })
+}
+
+/// Move an older version's `plan_checkpoint.json` into the run store
+fn migrate_legacy(output_dir: &Path) -> Result<()> {
+    let path = output_dir.join(LEGACY_CHECKPOINT_FILE);
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    match serde_json::from_str::<LegacyCheckpoint>(&text) {
+        Ok(saved) => {
+            store::start_plan(output_dir, saved.total_images, saved.chunk_size)?;
+            for index in saved.completed_chunks {
+                store::complete_chunk(output_dir, index, saved.faces_extracted)?;
+            }
+        }
+        Err(e) => say_err!(CheckpointUnreadable, path.display(), e),
+    }
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Render a duration as e.g. `2h 05m`, `3m 12s` or `41s`
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}h {:02}m", h, m)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
@@ -0,0 +1,50 @@
+//! Built-in setting presets (`--profile`)
+//!
+//! A profile is a small config table merged below the config file and the
+//! command line: it only fills in settings neither of them set. `fast` trades
+//! recall for throughput on a first pass over an enormous corpus; `thorough`
+//! is meant for the final harvest over the folders that pass turned up.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Larger faces only, coarse pyramid, thumbnail pre-screen, no quality scoring
+    Fast,
+    /// Small faces, fine pyramid and dense window steps
+    Thorough,
+}
+
+const FAST: &str = r#"
+min_face_size = 80
+pyramid_scale = 0.6
+window_step = 8
+prescreen = 1024
+skip_quality = true
+"#;
+
+const THOROUGH: &str = r#"
+min_face_size = 20
+pyramid_scale = 0.9
+window_step = 2
+"#;
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Fast => "fast",
+            Profile::Thorough => "thorough",
+        }
+    }
+
+    /// The settings this profile applies, keyed like a config file
+    pub fn settings(self) -> toml::Table {
+        let text = match self {
+            Profile::Fast => FAST,
+            Profile::Thorough => THOROUGH,
+        };
+        text.parse().expect("built-in profiles are valid TOML")
+    }
+}
@@ -0,0 +1,113 @@
+//! Live progress bar for runs on a terminal
+//!
+//! When stdout is a terminal, a run shows one `indicatif` bar instead of a
+//! line per image: images processed, faces extracted against `--target-faces`,
+//! the current rate in faces per hour and the time left to reach the target
+//! at that rate. Catalog messages printed meanwhile appear above the bar.
+//! Redirected output keeps the per-image lines, which read better in logs,
+//! and `--quiet` drops everything but errors.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Progress {
+    bar: ProgressBar,
+    started: Instant,
+    target: usize,
+    /// Faces already in the output when the bar started, e.g. from a resumed run
+    baseline: usize,
+}
+
+static PROGRESS: Mutex<Option<Progress>> = Mutex::new(None);
+
+/// Set when stdout carries `--stdin` results, so messages move to stderr
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Print every message to stderr from now on, leaving stdout to machine-readable output
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Show the bar for `images` images, if stdout is a terminal and output isn't quiet
+pub fn start(images: usize, target: usize, faces: usize) {
+    if crate::i18n::quiet() || !io::stdout().is_terminal() {
+        return;
+    }
+    let bar = ProgressBar::new(images as u64);
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} [{elapsed_precise}] {bar:30.cyan/blue} {pos}/{len} images  {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+    bar.enable_steady_tick(Duration::from_millis(200));
+    let progress = Progress { bar, started: Instant::now(), target, baseline: faces };
+    progress.bar.set_message(progress.message(faces));
+    if let Ok(mut slot) = PROGRESS.lock() {
+        *slot = Some(progress);
+    }
+}
+
+/// Whether the bar is standing in for the per-image lines
+pub fn active() -> bool {
+    PROGRESS.lock().is_ok_and(|slot| slot.is_some())
+}
+
+/// Count one more image done, with `faces` extracted so far
+pub fn advance(faces: usize) {
+    if let Ok(Some(progress)) = PROGRESS.lock().as_deref() {
+        progress.bar.inc(1);
+        progress.bar.set_message(progress.message(faces));
+    }
+}
+
+/// Print `line` above the bar, or plainly when there is none
+pub fn println(line: &str, stderr: bool) {
+    let slot = PROGRESS.lock();
+    let print = || {
+        if stderr || STDOUT_RESERVED.load(Ordering::Relaxed) {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    };
+    match slot.as_deref() {
+        Ok(Some(progress)) => progress.bar.suspend(print),
+        _ => print(),
+    }
+}
+
+/// Remove the bar before the summary is printed
+pub fn finish() {
+    if let Ok(mut slot) = PROGRESS.lock() {
+        if let Some(progress) = slot.take() {
+            progress.bar.finish_and_clear();
+        }
+    }
+}
+
+impl Progress {
+    fn message(&self, faces: usize) -> String {
+        let hours = self.started.elapsed().as_secs_f64() / 3600.0;
+        let new_faces = faces.saturating_sub(self.baseline);
+        let mut message = format!("{}/{} faces", faces, self.target);
+        if new_faces > 0 && hours > 0.0 {
+            let rate = new_faces as f64 / hours;
+            message.push_str(&format!(", {:.0} faces/h", rate));
+            let left = self.target.saturating_sub(faces) as f64 / rate;
+            message.push_str(&format!(", ETA {}", format_hours(left)));
+        }
+        message
+    }
+}
+
+/// `1h05m`, `12m30s` or `45s`
+fn format_hours(hours: f64) -> String {
+    let secs = (hours * 3600.0).round() as u64;
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, _) => format!("{}h{:02}m", h, m),
+    }
+}
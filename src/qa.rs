@@ -0,0 +1,193 @@
+//! `qa-sample` subcommand: stratified spot-check sample for human QA sign-off
+//!
+//! Draws a random sample of crops from a finished run, spread evenly over
+//! strata (score or quality quartiles, tiers or variants) so reviewers also see
+//! the borderline faces and not just the bulk. The sample folder gets numbered
+//! chips, montage pages of 100 chips labelled with those numbers, and
+//! `signoff.csv` with empty pass/fail/notes columns for the reviewer. The seed
+//! is printed so the same sample can be drawn again for an audit.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImage, Rgba, RgbaImage};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::manifest::{self, csv_escape, ManifestRecord, MANIFEST_FILE};
+use crate::{say, watermark};
+
+const CHIP_SIZE: u32 = 128;
+const MONTAGE_COLUMNS: u32 = 10;
+const CHIPS_PER_PAGE: usize = 100;
+
+/// What the sample is spread across
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Stratify {
+    /// Plain random sample
+    None,
+    /// Quartiles of detector score
+    Score,
+    /// Quartiles of combined quality
+    Quality,
+    /// Gold, silver and bronze tiers
+    Tier,
+    /// Crop framing variants
+    Variant,
+}
+
+/// Small deterministic generator (SplitMix64), enough for shuffling a sample
+pub struct Rng(u64);
+
+impl Rng {
+    /// Generator for `seed`, or for the current time; also returns the seed used
+    pub fn seeded(seed: Option<u64>) -> (Self, u64) {
+        let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64));
+        (Rng(seed), seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in [0, 1)
+    pub fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+pub fn run(from: &Path, to: &Path, n: usize, stratify: Stratify, seed: Option<u64>) -> Result<()> {
+    let records = manifest::read(&from.join(MANIFEST_FILE))?;
+    if records.is_empty() {
+        anyhow::bail!("No crops recorded in {}", from.join(MANIFEST_FILE).display());
+    }
+    let (mut rng, seed) = Rng::seeded(seed);
+
+    let mut strata = stratify_records(&records, stratify);
+    for members in strata.values_mut() {
+        rng.shuffle(members);
+    }
+
+    // Round-robin over strata gives each an equal share; small strata hand their leftover share to the rest
+    let mut sample: Vec<(&str, &ManifestRecord)> = Vec::new();
+    let mut depth = 0;
+    while sample.len() < n && strata.values().any(|members| members.len() > depth) {
+        for (name, members) in &strata {
+            if let Some(record) = members.get(depth).filter(|_| sample.len() < n) {
+                sample.push((name, record));
+            }
+        }
+        depth += 1;
+    }
+
+    let chips = to.join("chips");
+    fs::create_dir_all(&chips).context("Failed to create QA sample directory")?;
+
+    let mut sheet = String::from("sample,chip,crop,source,detection_id,stratum,score,quality,pass,fail,notes\n");
+    let mut thumbnails = Vec::new();
+    for (i, (stratum, record)) in sample.iter().enumerate() {
+        let number = i + 1;
+        let chip = format!("chips/{:04}.jpg", number);
+        let image = image::open(from.join(&record.crop))
+            .with_context(|| format!("Failed to open crop {}", record.crop))?;
+        image.save(to.join(&chip)).with_context(|| format!("Failed to save {}", chip))?;
+        thumbnails.push(image.resize_to_fill(CHIP_SIZE, CHIP_SIZE, FilterType::Triangle));
+
+        let fields = [
+            number.to_string(),
+            chip,
+            record.crop.clone(),
+            record.source.display().to_string(),
+            record.detection_id.to_string(),
+            stratum.to_string(),
+            format!("{:.2}", record.score),
+            record.quality.map(|q| format!("{:.3}", q)).unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+        sheet.push_str(&row.join(","));
+        sheet.push_str(",,,\n");
+    }
+    fs::write(to.join("signoff.csv"), sheet).context("Failed to write sign-off sheet")?;
+
+    for (page, chunk) in thumbnails.chunks(CHIPS_PER_PAGE).enumerate() {
+        let montage = montage(chunk, page * CHIPS_PER_PAGE)?;
+        let name = format!("montage_{:02}.jpg", page + 1);
+        montage.save(to.join(&name)).with_context(|| format!("Failed to save {}", name))?;
+    }
+
+    say!(QaSampled, sample.len(), records.len(), to.display());
+    for (name, members) in &strata {
+        let taken = sample.iter().filter(|(s, _)| s == name).count();
+        say!(QaStratum, name, taken, members.len());
+    }
+    say!(QaSeed, seed);
+    say!(QaSignoff, to.join("signoff.csv").display());
+    Ok(())
+}
+
+/// Group records into named strata; numeric keys are split into quartiles
+fn stratify_records(records: &[ManifestRecord], stratify: Stratify) -> BTreeMap<String, Vec<&ManifestRecord>> {
+    let mut strata: BTreeMap<String, Vec<&ManifestRecord>> = BTreeMap::new();
+    let numeric: fn(&ManifestRecord) -> Option<f64> = match stratify {
+        Stratify::None => {
+            strata.insert("all".to_string(), records.iter().collect());
+            return strata;
+        }
+        Stratify::Tier => {
+            for record in records {
+                let name = record.tier.map_or("untiered", |t| t.name());
+                strata.entry(name.to_string()).or_default().push(record);
+            }
+            return strata;
+        }
+        Stratify::Variant => {
+            for record in records {
+                strata.entry(record.variant.name().to_string()).or_default().push(record);
+            }
+            return strata;
+        }
+        Stratify::Score => |r| Some(r.score),
+        Stratify::Quality => |r| r.quality,
+    };
+
+    let mut scored: Vec<(f64, &ManifestRecord)> = Vec::new();
+    for record in records {
+        match numeric(record) {
+            Some(value) => scored.push((value, record)),
+            None => strata.entry("unscored".to_string()).or_default().push(record),
+        }
+    }
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let per_quartile = scored.len().div_ceil(4).max(1);
+    for (q, chunk) in scored.chunks(per_quartile).enumerate() {
+        let name = format!("Q{} ({:.2}-{:.2})", q + 1, chunk[0].0, chunk[chunk.len() - 1].0);
+        strata.insert(name, chunk.iter().map(|(_, r)| *r).collect());
+    }
+    strata
+}
+
+/// Grid of chips, each stamped with its sample number
+fn montage(chips: &[DynamicImage], first: usize) -> Result<DynamicImage> {
+    let rows = (chips.len() as u32).div_ceil(MONTAGE_COLUMNS);
+    let mut canvas = RgbaImage::from_pixel(MONTAGE_COLUMNS * CHIP_SIZE, rows * CHIP_SIZE, Rgba([32, 32, 32, 255]));
+    for (i, chip) in chips.iter().enumerate() {
+        let x = (i as u32 % MONTAGE_COLUMNS) * CHIP_SIZE;
+        let y = (i as u32 / MONTAGE_COLUMNS) * CHIP_SIZE;
+        canvas.copy_from(&chip.to_rgba8(), x, y)?;
+        watermark::draw_line(&mut canvas, &(first + i + 1).to_string(), x + 4, y + 4, 2);
+    }
+    Ok(DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8()))
+}
@@ -0,0 +1,123 @@
+//! Combined per-face quality score and the gold/silver/bronze tiers built on it
+//!
+//! The score blends detector confidence, sharpness, resolution and exposure into
+//! a single 0-1 value so crops can be ranked without a human looking at them.
+
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use imageproc::filter::filter3x3;
+use serde::{Deserialize, Serialize};
+
+/// Sharpness is measured on a copy no larger than this on its long side
+const SHARPNESS_SIDE: u32 = 512;
+
+/// Laplacian variance at which the sharpness term reaches one half
+const SHARPNESS_HALF: f64 = 100.0;
+
+/// Face side (pixels) at which the resolution term saturates
+const FULL_RESOLUTION: f64 = 160.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    Gold,
+    Silver,
+    Bronze,
+}
+
+impl Tier {
+    pub const ALL: [Tier; 3] = [Tier::Gold, Tier::Silver, Tier::Bronze];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tier::Gold => "gold",
+            Tier::Silver => "silver",
+            Tier::Bronze => "bronze",
+        }
+    }
+
+    /// Place `quality` using `cutoffs` = [gold minimum, silver minimum]
+    pub fn for_quality(quality: f64, cutoffs: &[f64]) -> Tier {
+        match cutoffs {
+            [gold, _] if quality >= *gold => Tier::Gold,
+            [_, silver] if quality >= *silver => Tier::Silver,
+            _ => Tier::Bronze,
+        }
+    }
+}
+
+/// What `--max-faces-per-image` keeps the best faces of an image by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FaceRank {
+    /// Detector confidence
+    #[default]
+    Score,
+    /// Laplacian variance of the face box
+    Sharpness,
+}
+
+/// Quality of the tight face region `face` given the detector's normalized `confidence`, in 0-1
+pub fn score(face: &DynamicImage, confidence: f64) -> f64 {
+    let sharp = sharpness(face);
+    let sharpness = sharp / (sharp + SHARPNESS_HALF);
+
+    let side = face.width().min(face.height()) as f64;
+    let resolution = (side / FULL_RESOLUTION).min(1.0);
+
+    let gray = face.to_luma8();
+    let mean = gray.pixels().map(|p| p[0] as f64).sum::<f64>() / gray.len().max(1) as f64;
+    let exposure = 1.0 - (mean - 128.0).abs() / 128.0;
+
+    0.35 * confidence + 0.3 * sharpness + 0.2 * resolution + 0.15 * exposure
+}
+
+/// Luma statistics of a face region, for the exposure filters
+#[derive(Debug, Clone, Copy)]
+pub struct Exposure {
+    /// Mean luma, 0-255
+    pub brightness: f64,
+    /// Spread between the 5th and 95th luma percentiles, 0-255
+    pub contrast: f64,
+}
+
+/// Brightness and contrast of `face`; percentiles keep a few specular or shadow pixels from counting as contrast
+pub fn exposure(face: &DynamicImage) -> Exposure {
+    let gray = face.to_luma8();
+    let mut histogram = [0usize; 256];
+    gray.pixels().for_each(|p| histogram[p[0] as usize] += 1);
+    let n = gray.len().max(1);
+
+    let brightness = histogram.iter().enumerate().map(|(v, &c)| v * c).sum::<usize>() as f64 / n as f64;
+    let percentile = |q: f64| {
+        let wanted = (q * n as f64).ceil() as usize;
+        let mut seen = 0;
+        histogram
+            .iter()
+            .position(|&c| {
+                seen += c;
+                seen >= wanted.max(1)
+            })
+            .unwrap_or(255) as f64
+    };
+
+    Exposure {
+        brightness,
+        contrast: percentile(0.95) - percentile(0.05),
+    }
+}
+
+/// Variance of the Laplacian: higher means more fine detail in focus
+pub fn sharpness(image: &DynamicImage) -> f64 {
+    let small = if image.width().max(image.height()) > SHARPNESS_SIDE {
+        image.resize(SHARPNESS_SIDE, SHARPNESS_SIDE, FilterType::Triangle)
+    } else {
+        image.clone()
+    };
+    let laplacian = filter3x3::<_, i32, i16>(&small.to_luma8(), &[0, 1, 0, 1, -4, 1, 0, 1, 0]);
+    let values: Vec<f64> = laplacian.pixels().map(|p| p[0] as f64).collect();
+    let n = values.len().max(1) as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n
+}
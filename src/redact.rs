@@ -0,0 +1,71 @@
+//! Redacted copies of source images (`--redacted-originals`)
+//!
+//! For every source image that yields crops, a copy is saved under
+//! `redacted/` with each detected face that was not extracted blurred beyond
+//! recognition and the extracted faces outlined. This gives context-aware
+//! models the scene around each crop without exposing bystanders. The copy is
+//! the decoded (orientation-corrected) image, so manifest boxes line up with
+//! it, and every manifest row names its copy in `redacted`.
+
+use anyhow::{Context, Result};
+use image::{imageops, DynamicImage, GenericImageView, Rgba};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use std::path::Path;
+
+use crate::annotations;
+use crate::detector::{BBox, Detection};
+
+pub const REDACTED_DIR: &str = "redacted";
+
+const OUTLINE: Rgba<u8> = Rgba([0, 220, 0, 255]);
+
+/// Path of the redacted copy of `source` (or of one of its stream frames), relative to the output directory
+pub fn file_name(input: &Path, source: &Path, frame: Option<usize>) -> String {
+    let name = annotations::flat_name(input, source);
+    match frame {
+        Some(frame) => format!("{}/{}_f{:06}.jpg", REDACTED_DIR, name, frame),
+        None => format!("{}/{}.jpg", REDACTED_DIR, name),
+    }
+}
+
+/// Save `image` as `name` with every face in `faces` blurred except those in `extracted`, which are outlined
+pub fn write(output: &Path, name: &str, image: &DynamicImage, faces: &[Detection], extracted: &[BBox]) -> Result<()> {
+    let mut canvas = image.to_rgba8();
+    for face in faces.iter().filter(|face| !extracted.contains(&face.bbox)) {
+        blur(&mut canvas, &face.bbox);
+    }
+    for bbox in extracted {
+        for inset in 0..2 {
+            let width = bbox.width.saturating_sub(2 * inset as u32).max(1);
+            let height = bbox.height.saturating_sub(2 * inset as u32).max(1);
+            draw_hollow_rect_mut(&mut canvas, Rect::at(bbox.x + inset, bbox.y + inset).of_size(width, height), OUTLINE);
+        }
+    }
+
+    let path = output.join(name);
+    DynamicImage::ImageRgba8(canvas)
+        .to_rgb8()
+        .save(&path)
+        .with_context(|| format!("Failed to save {}", path.display()))
+}
+
+/// Blur `bbox`, grown by a margin for hair and jaw, so heavily that the face can't be recognized
+fn blur(canvas: &mut image::RgbaImage, bbox: &BBox) {
+    let margin = (bbox.width + bbox.height) as i32 / 8;
+    let x = (bbox.x - margin).max(0) as u32;
+    let y = (bbox.y - margin).max(0) as u32;
+    if x >= canvas.width() || y >= canvas.height() {
+        return;
+    }
+    let width = ((bbox.width as i32 + 2 * margin) as u32).min(canvas.width() - x);
+    let height = ((bbox.height as i32 + 2 * margin) as u32).min(canvas.height() - y);
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let region = canvas.view(x, y, width, height).to_image();
+    let sigma = (width.max(height) as f32 / 6.0).max(4.0);
+    let blurred = imageops::blur(&region, sigma);
+    imageops::replace(canvas, &blurred, x as i64, y as i64);
+}
@@ -0,0 +1,174 @@
+//! `regress` subcommand: compare current detections against an older manifest
+//!
+//! Re-runs detection with the current settings on every source listed in a
+//! baseline manifest and pairs boxes by IoU. Sources whose detections changed
+//! get an annotated copy in the gallery (green kept, red lost, blue added) and
+//! a row in its `index.html`, so a detector or model upgrade can be reviewed
+//! before a production dataset is regenerated.
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, Rgb};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::detector::{self, BBox};
+use crate::manifest;
+use crate::{decode_image, detect_image, filter_valid_faces, say, say_err, Args};
+
+const KEPT: Rgb<u8> = Rgb([0, 220, 0]);
+const LOST: Rgb<u8> = Rgb([230, 0, 0]);
+const ADDED: Rgb<u8> = Rgb([30, 120, 255]);
+
+/// Baseline and current boxes of one source, split by whether they found a partner
+#[derive(Debug, Default)]
+struct Diff {
+    kept: Vec<BBox>,
+    lost: Vec<BBox>,
+    added: Vec<BBox>,
+}
+
+impl Diff {
+    /// Pair boxes greedily, best overlap first, keeping pairs with IoU >= `min_iou`
+    fn compute(before: &[BBox], after: &[BBox], min_iou: f32) -> Self {
+        let mut pairs: Vec<(f32, usize, usize)> = before
+            .iter()
+            .enumerate()
+            .flat_map(|(i, b)| after.iter().enumerate().map(move |(j, a)| (b.iou(a), i, j)))
+            .filter(|&(iou, _, _)| iou >= min_iou)
+            .collect();
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let (mut used_before, mut used_after) = (HashSet::new(), HashSet::new());
+        let mut diff = Diff::default();
+        for (_, i, j) in pairs {
+            if !used_before.contains(&i) && !used_after.contains(&j) {
+                used_before.insert(i);
+                used_after.insert(j);
+                diff.kept.push(after[j]);
+            }
+        }
+        diff.lost = (0..before.len()).filter(|i| !used_before.contains(i)).map(|i| before[i]).collect();
+        diff.added = (0..after.len()).filter(|j| !used_after.contains(j)).map(|j| after[j]).collect();
+        diff
+    }
+
+    fn changed(&self) -> bool {
+        !self.lost.is_empty() || !self.added.is_empty()
+    }
+}
+
+pub fn run(args: &Args, baseline: &Path, gallery: &Path, min_iou: f32) -> Result<()> {
+    say!(RegressComparing, baseline.display());
+    let records = manifest::read(baseline)?;
+
+    // One box per detection: variants and export sizes repeat it. Video tracks are not re-detectable per row.
+    let mut sources: BTreeMap<PathBuf, Vec<BBox>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for record in records.iter().filter(|r| r.track.is_none()) {
+        if seen.insert((record.source.clone(), record.detection_id)) {
+            sources.entry(record.source.clone()).or_default().push(record.bbox);
+        }
+    }
+    if sources.is_empty() {
+        anyhow::bail!("No image detections recorded in {}", baseline.display());
+    }
+
+    let mut detector = detector::load(args)?;
+    fs::create_dir_all(gallery).context("Failed to create gallery directory")?;
+
+    let (mut kept, mut lost, mut added, mut failed) = (0, 0, 0, 0);
+    let mut rows = Vec::new();
+    for (i, (source, before)) in sources.iter().enumerate() {
+        let detected = match decode_image(args, source).and_then(|images| detect_image(args, detector.as_mut(), images)) {
+            Ok(detected) => detected,
+            Err(e) => {
+                say_err!(RegressFailed, source.display(), format!("{:#}", e));
+                failed += 1;
+                continue;
+            }
+        };
+        let after: Vec<BBox> = filter_valid_faces(args, &detected.faces, &detected.image, detected.min_score)
+            .iter()
+            .map(|face| face.bbox)
+            .collect();
+
+        let diff = Diff::compute(before, &after, min_iou);
+        kept += diff.kept.len();
+        lost += diff.lost.len();
+        added += diff.added.len();
+        if !diff.changed() {
+            continue;
+        }
+
+        say!(RegressChanged, source.display(), diff.lost.len(), diff.added.len());
+        let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+        let name = format!("{:05}_{}.jpg", i, stem);
+        annotate(&detected.image, &diff)
+            .save(gallery.join(&name))
+            .with_context(|| format!("Failed to save diff image {}", name))?;
+        rows.push((source.clone(), name, diff));
+    }
+
+    let index = gallery.join("index.html");
+    fs::write(&index, render_index(baseline, &rows)).context("Failed to write gallery index")?;
+
+    say!(RegressHeader, sources.len());
+    say!(RegressKept, kept);
+    say!(RegressLost, lost);
+    say!(RegressAdded, added);
+    say!(RegressSourcesChanged, rows.len());
+    if failed > 0 {
+        say!(RegressUnreadable, failed);
+    }
+    say!(RegressGallery, index.display());
+    Ok(())
+}
+
+/// Draw every box of `diff` onto a copy of `image`
+fn annotate(image: &DynamicImage, diff: &Diff) -> DynamicImage {
+    let mut canvas = image.to_rgb8();
+    for (boxes, color) in [(&diff.kept, KEPT), (&diff.lost, LOST), (&diff.added, ADDED)] {
+        for bbox in boxes {
+            // Two nested outlines stay visible on large photos
+            for inset in 0..2 {
+                let width = bbox.width.saturating_sub(2 * inset as u32).max(1);
+                let height = bbox.height.saturating_sub(2 * inset as u32).max(1);
+                let rect = Rect::at(bbox.x + inset, bbox.y + inset).of_size(width, height);
+                draw_hollow_rect_mut(&mut canvas, rect, color);
+            }
+        }
+    }
+    DynamicImage::ImageRgb8(canvas)
+}
+
+fn render_index(baseline: &Path, rows: &[(PathBuf, String, Diff)]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Detection regression</title></head><body>\n\
+         <h1>Detection regression against {}</h1>\n\
+         <p><span style=\"color:#00dc00\">kept</span> · <span style=\"color:#e60000\">lost</span> · \
+         <span style=\"color:#1e78ff\">added</span></p>\n",
+        escape(&baseline.display().to_string())
+    );
+    if rows.is_empty() {
+        html.push_str("<p>No changes.</p>\n");
+    }
+    for (source, name, diff) in rows {
+        html.push_str(&format!(
+            "<figure><img src=\"{}\" style=\"max-width:640px\"><figcaption>{} — {} kept, {} lost, {} added</figcaption></figure>\n",
+            escape(name),
+            escape(&source.display().to_string()),
+            diff.kept.len(),
+            diff.lost.len(),
+            diff.added.len()
+        ));
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
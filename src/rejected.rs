@@ -0,0 +1,72 @@
+//! Crops of filtered-out faces for review (`--save-rejected DIR`)
+//!
+//! Every detection the filters turn down is cropped like a kept face and saved
+//! to its own directory, with the filters it failed in the filename
+//! (`photo_0003_180_score-size.jpg`) and in a `rejected` field of that
+//! directory's `manifest.jsonl`. Paging through it shows quickly whether a
+//! filter throws away faces worth keeping. Faces skipped by `--dedup`, quotas
+//! or `--target-faces` are not rejections and are not saved. Only the strict
+//! pass saves rejections; `--relax-on-shortfall` reconsiders the same faces.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::crop::{self, Framing, Variant};
+use crate::detector::Detection;
+use crate::manifest::{ManifestRecord, ManifestWriter};
+use crate::{custody, encode, Args};
+
+pub struct RejectedFaces {
+    dir: PathBuf,
+    manifest: ManifestWriter,
+    pub saved: usize,
+}
+
+impl RejectedFaces {
+    pub fn open(dir: &Path, append: bool) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create rejected faces directory {}", dir.display()))?;
+        Ok(RejectedFaces { dir: dir.to_path_buf(), manifest: ManifestWriter::open(dir, append, false)?, saved: 0 })
+    }
+
+    /// Crop `face` from `image` and save it with the names of the filters it failed
+    pub fn save(
+        &mut self,
+        args: &Args,
+        source: &Path,
+        frame: Option<usize>,
+        image: &DynamicImage,
+        face: &Detection,
+        reasons: &[&str],
+    ) -> Result<()> {
+        self.saved += 1;
+        let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+        let name = format!(
+            "{}_{:04}_{:.0}_{}.{}",
+            stem,
+            self.saved,
+            face.score * 100.0,
+            reasons.join("-"),
+            args.format.extension()
+        );
+        let (crop_img, frame_box) = crop::crop_variant(image, &face.bbox, None, Variant::Standard, Framing::of(args));
+        let path = self.dir.join(&name);
+        encode::save(&crop_img, &path, args, None).context("Failed to save rejected face")?;
+
+        self.manifest.append(&ManifestRecord {
+            crop: name,
+            source: source.to_path_buf(),
+            detection_id: self.saved,
+            bbox: face.bbox,
+            score: face.score,
+            padding: frame_box.map(|frame| frame.padding),
+            image_width: Some(image.width()),
+            image_height: Some(image.height()),
+            frame,
+            sha256: Some(custody::file_sha256(&path)?),
+            rejected: Some(reasons.join(",")),
+            ..Default::default()
+        })
+    }
+}
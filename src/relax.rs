@@ -0,0 +1,174 @@
+//! Relaxed second look at rejected candidates (`--relax-on-shortfall`)
+//!
+//! While the corpus is processed, images whose detections were rejected (or
+//! that had none) are remembered along with the boxes already cropped from
+//! them. If the corpus runs out before `--target-faces`, those images are
+//! detected again at each relaxation level in turn, with the detector threshold
+//! and score floor scaled down, until the target is met or the levels run out.
+//! Crops from a relaxed pass carry their level in the manifest (`relaxation`).
+//! Faces overlapping an earlier crop are skipped, and annotation files keep
+//! the labels of the strict pass.
+//!
+//! `--min-yield` is the same idea keyed on the yield rather than the target:
+//! if the strict pass averaged fewer faces per image than asked, the images
+//! that gave no face at all get one more look at `--second-pass-factor` of the
+//! thresholds, and what it finds is flagged `low_confidence` in the manifest.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use crate::detector::{self, BBox};
+use crate::{decode_image, detect_image, extract_faces, say, say_err, should_stop, target_reached, Args, BatchStats, Extractor};
+
+/// Factor applied to the threshold and score floor at levels 1, 2 and 3
+pub const LEVELS: [f64; 3] = [0.85, 0.7, 0.55];
+
+/// Overlap above which a relaxed detection is taken to be a face already cropped
+const SAME_FACE_IOU: f32 = 0.5;
+
+/// Images worth a relaxed retry, with the boxes already cropped from each
+#[derive(Debug, Default)]
+pub struct Shortfall {
+    candidates: BTreeMap<PathBuf, Vec<BBox>>,
+}
+
+impl Shortfall {
+    /// Remember `path` if the filters rejected any of its detections, or there were none
+    pub fn consider(&mut self, path: &Path, detected: usize, accepted: usize) {
+        if detected == 0 || accepted < detected {
+            self.candidates.entry(path.to_path_buf()).or_default();
+        }
+    }
+
+    /// Candidates nothing was cropped from
+    fn zero_hit(&self) -> Vec<PathBuf> {
+        self.candidates
+            .iter()
+            .filter(|(_, boxes)| boxes.is_empty())
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Note a box cropped from a candidate so later passes leave it alone
+    pub fn cropped(&mut self, path: &Path, bbox: BBox) {
+        if let Some(boxes) = self.candidates.get_mut(path) {
+            boxes.push(bbox);
+        }
+    }
+}
+
+/// Retry the candidates level by level until the target is met
+pub fn run(ex: &mut Extractor, stats: &mut BatchStats) -> Result<()> {
+    let candidates: Vec<PathBuf> = match &ex.shortfall {
+        Some(shortfall) => shortfall.candidates.keys().cloned().collect(),
+        None => return Ok(()),
+    };
+    if candidates.is_empty() {
+        return Ok(());
+    }
+    let floor = ex.detector()?.min_score();
+
+    for (i, &factor) in LEVELS.iter().enumerate() {
+        let level = i as u8 + 1;
+        let relaxed = relaxed_args(ex.args, factor)?;
+        ex.detector = Some(detector::load(&relaxed)?);
+        ex.relaxation = Some(level);
+        say!(
+            Relaxing,
+            level,
+            candidates.len(),
+            ex.args.target_faces - ex.face_counter.load(Ordering::Relaxed),
+            format!("{:.0}", factor * 100.0)
+        );
+
+        for (j, path) in candidates.iter().enumerate() {
+            if should_stop(ex, stats) {
+                return Ok(());
+            }
+            say!(Processing, j + 1, candidates.len(), path.display());
+            match retry(ex, path, floor * factor) {
+                Ok(extracted) => {
+                    stats.extracted += extracted;
+                    if extracted > 0 {
+                        say!(Extracted, extracted);
+                    }
+                }
+                Err(e) => say_err!(ImageError, e),
+            }
+        }
+    }
+
+    stats.target_reached = target_reached(ex);
+    Ok(())
+}
+
+/// Retry the images without a face once at `--second-pass-factor` if the strict pass fell short of `--min-yield`
+pub fn second_pass(ex: &mut Extractor, stats: &mut BatchStats) -> Result<()> {
+    let (Some(min_yield), Some(shortfall)) = (ex.args.min_yield, &ex.shortfall) else {
+        return Ok(());
+    };
+    let faces = ex.face_counter.load(Ordering::Relaxed);
+    let rate = faces as f64 / stats.processed.max(1) as f64;
+    let candidates = shortfall.zero_hit();
+    if rate >= min_yield || candidates.is_empty() || target_reached(ex) {
+        return Ok(());
+    }
+    let factor = ex.args.second_pass_factor;
+    let floor = ex.detector()?.min_score();
+    ex.detector = Some(detector::load(&relaxed_args(ex.args, factor)?)?);
+    ex.low_confidence = true;
+    say!(
+        SecondPass,
+        format!("{:.2}", rate),
+        min_yield,
+        candidates.len(),
+        format!("{:.0}", factor * 100.0)
+    );
+
+    let mut found = 0;
+    for (j, path) in candidates.iter().enumerate() {
+        if should_stop(ex, stats) {
+            break;
+        }
+        say!(Processing, j + 1, candidates.len(), path.display());
+        match retry(ex, path, floor * factor) {
+            Ok(extracted) => {
+                stats.extracted += extracted;
+                found += extracted;
+                if extracted > 0 {
+                    say!(Extracted, extracted);
+                }
+            }
+            Err(e) => say_err!(ImageError, e),
+        }
+    }
+    say!(SecondPassFound, found);
+
+    stats.target_reached = target_reached(ex);
+    Ok(())
+}
+
+/// Detect `path` again with the relaxed detector and crop the faces not taken before
+fn retry(ex: &mut Extractor, path: &Path, min_score: f64) -> Result<usize> {
+    let decoded = decode_image(ex.args, path)?;
+    let mut detected = detect_image(ex.args, ex.detector()?, decoded)?;
+    detected.min_score = min_score;
+    detected.before = None;
+
+    if let Some(cropped) = ex.shortfall.as_ref().and_then(|s| s.candidates.get(path)) {
+        detected
+            .faces
+            .retain(|face| cropped.iter().all(|bbox| face.bbox.iou(bbox) < SAME_FACE_IOU));
+    }
+    extract_faces(ex, path, detected)
+}
+
+/// Copy of `args` with the detector thresholds scaled by `factor`
+fn relaxed_args(args: &Args, factor: f64) -> Result<Args> {
+    let mut settings = toml::Table::try_from(args).context("Failed to serialize settings")?;
+    settings.insert("threshold".to_string(), toml::Value::Float(args.threshold * factor));
+    settings.insert("yolo_confidence".to_string(), toml::Value::Float(args.yolo_confidence * factor));
+    settings.try_into().context("Failed to derive relaxed settings")
+}
@@ -0,0 +1,234 @@
+//! Input lists with remote images (`--input-list urls.txt`)
+//!
+//! Each line of the list is a local image path or an http(s) URL; blank lines
+//! and `#` comments are skipped. URLs are downloaded before the run by
+//! `--download-jobs` threads into `downloads/` in the output directory, named
+//! after a hash of the URL, so a repeated or resumed run reuses finished files
+//! and only fetches what is missing. Failed downloads are retried with growing
+//! waits, unless the server answered with a 4xx error, then reported and left
+//! out. Crops of a downloaded image record its URL in the manifest.
+//! Downloading needs the `remote` build feature.
+//!
+//! A list ending in `.csv` is read as a table with a header row, the way
+//! curated corpora are usually distributed. Its `path` column holds the
+//! entries, relative paths being taken from the CSV's own folder. An optional
+//! `label` column sets each crop's manifest label, ahead of
+//! `--labels-from-dirs`, and an optional `weight` column makes `--sample`
+//! draws favor heavier rows. Other columns are ignored.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "remote")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "remote")]
+use std::sync::Mutex;
+#[cfg(feature = "remote")]
+use std::thread;
+#[cfg(feature = "remote")]
+use std::time::Duration;
+
+use crate::manifest::csv_split;
+use crate::{decoders, say, say_err};
+
+/// Directory in the output that holds downloaded images
+pub const DOWNLOADS_DIR: &str = "downloads";
+
+/// Download attempts per URL before giving up; waits double after each failure
+#[cfg(feature = "remote")]
+const ATTEMPTS: u32 = 3;
+#[cfg(feature = "remote")]
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Images named by an input list, and the URL each downloaded one came from
+#[derive(Default)]
+pub struct InputList {
+    pub paths: Vec<PathBuf>,
+    pub urls: HashMap<PathBuf, String>,
+    /// Labels from a CSV list's `label` column
+    pub labels: HashMap<PathBuf, String>,
+    /// Whether the list is a CSV with a `label` column
+    pub has_label_column: bool,
+    /// Sampling weights from a CSV list's `weight` column
+    pub weights: HashMap<PathBuf, f64>,
+}
+
+/// One row of an input list
+#[derive(Default)]
+struct Entry {
+    source: String,
+    label: Option<String>,
+    weight: Option<f64>,
+}
+
+/// Whether `list` is read as a CSV table rather than one entry per line
+pub fn is_csv(list: &Path) -> bool {
+    list.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn is_url(entry: &str) -> bool {
+    entry.starts_with("http://") || entry.starts_with("https://")
+}
+
+/// Read `list`, downloading its URLs into `output`, and keep the entries some decoder reads
+pub fn resolve(list: &Path, output: &Path, jobs: usize) -> Result<InputList> {
+    let text = fs::read_to_string(list).with_context(|| format!("Failed to read input list {}", list.display()))?;
+    let lines = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let is_csv = is_csv(list);
+    let mut input = InputList::default();
+    let entries = if is_csv {
+        let (entries, has_label_column) = csv_entries(list, lines)?;
+        input.has_label_column = has_label_column;
+        entries
+    } else {
+        lines.map(|line| Entry { source: line.to_string(), ..Default::default() }).collect()
+    };
+
+    let mut downloads = Vec::new();
+    for entry in entries {
+        let path = if is_url(&entry.source) {
+            let path = output.join(DOWNLOADS_DIR).join(download_name(&entry.source));
+            input.urls.insert(path.clone(), entry.source.clone());
+            downloads.push((entry.source, path.clone()));
+            path
+        } else if is_csv {
+            list.parent().unwrap_or(Path::new("")).join(&entry.source)
+        } else {
+            PathBuf::from(entry.source)
+        };
+        if let Some(label) = entry.label {
+            input.labels.insert(path.clone(), label);
+        }
+        if let Some(weight) = entry.weight {
+            input.weights.insert(path.clone(), weight);
+        }
+        input.paths.push(path);
+    }
+
+    let missing: Vec<&(String, PathBuf)> = downloads.iter().filter(|(_, path)| !path.exists()).collect();
+    if !missing.is_empty() {
+        fs::create_dir_all(output.join(DOWNLOADS_DIR)).context("Failed to create downloads directory")?;
+        let jobs = jobs.min(missing.len());
+        say!(Downloading, missing.len(), jobs);
+        let failures = fetch_all(&missing, jobs)?;
+        for (url, e) in &failures {
+            say_err!(DownloadFailed, url, format!("{:#}", e));
+        }
+        say!(Downloaded, missing.len() - failures.len(), failures.len());
+    }
+
+    let decoders = decoders::registry().read().unwrap_or_else(|e| e.into_inner());
+    input.paths.retain(|path| decoders.recognizes(path));
+    Ok(input)
+}
+
+/// Rows of a CSV input list, found by the `path`, `label` and `weight` columns of its header,
+/// and whether it has a `label` column
+fn csv_entries<'a>(list: &Path, mut lines: impl Iterator<Item = &'a str>) -> Result<(Vec<Entry>, bool)> {
+    let header = csv_split(lines.next().unwrap_or_default());
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let path = column("path").with_context(|| format!("{} has no `path` column in its header", list.display()))?;
+    let (label, weight) = (column("label"), column("weight"));
+
+    let mut entries = Vec::new();
+    for (row, line) in lines.enumerate() {
+        let fields = csv_split(line);
+        let field = |i: Option<usize>| i.and_then(|i| fields.get(i)).map(|f| f.trim()).filter(|f| !f.is_empty());
+        let Some(source) = field(Some(path)) else {
+            continue;
+        };
+        let weight = match field(weight) {
+            Some(text) => match text.parse::<f64>() {
+                Ok(w) if w >= 0.0 && w.is_finite() => Some(w),
+                _ => anyhow::bail!("{}: weight '{}' in row {} is not a number of at least 0", list.display(), text, row + 1),
+            },
+            None => None,
+        };
+        entries.push(Entry { source: source.to_string(), label: field(label).map(str::to_string), weight });
+    }
+    Ok((entries, label.is_some()))
+}
+
+/// File name for a downloaded URL: a hash of it, plus the extension its path ends in
+fn download_name(url: &str) -> String {
+    let hash = hex::encode(&Sha256::digest(url.as_bytes())[..8]);
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_lowercase())
+        .filter(|ext| (1..=5).contains(&ext.len()) && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    match extension {
+        Some(ext) => format!("{}.{}", hash, ext),
+        None => hash,
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn fetch_all(_downloads: &[&(String, PathBuf)], _jobs: usize) -> Result<Vec<(String, anyhow::Error)>> {
+    anyhow::bail!("This build can't download URLs from --input-list; rebuild with `cargo build --release --features remote`")
+}
+
+/// Download every file with `jobs` threads; returns the URLs that failed and why
+#[cfg(feature = "remote")]
+fn fetch_all(downloads: &[&(String, PathBuf)], jobs: usize) -> Result<Vec<(String, anyhow::Error)>> {
+    let client = reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .timeout(Duration::from_secs(120))
+        .build()
+        .context("Failed to set up the HTTP client")?;
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                while let Some((url, path)) = downloads.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Err(e) = fetch(&client, url, path) {
+                        failures.lock().unwrap_or_else(|e| e.into_inner()).push((url.clone(), e));
+                    }
+                }
+            });
+        }
+    });
+    Ok(failures.into_inner().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Download `url` to `path` with retries, through a partial file so an interrupted one is never used
+#[cfg(feature = "remote")]
+fn fetch(client: &reqwest::blocking::Client, url: &str, path: &Path) -> Result<()> {
+    let partial = path.with_extension("part");
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let result = client
+            .get(url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| fs::write(&partial, &bytes).map_err(anyhow::Error::from));
+        match result {
+            Ok(()) => return fs::rename(&partial, path).context("Failed to store download"),
+            Err(e) if attempt < ATTEMPTS && !gone(&e) => {
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(e.context(format!("gave up after {} attempts", attempt)));
+            }
+        }
+    }
+}
+
+/// Whether the server answered that the URL won't work, so retrying is pointless (4xx other than timeouts and rate limits)
+#[cfg(feature = "remote")]
+fn gone(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|status| status.is_client_error() && !matches!(status.as_u16(), 408 | 429))
+}
@@ -0,0 +1,120 @@
+//! `report` subcommand: static HTML gallery of a finished run
+//!
+//! Writes `report.html` into the output directory, next to the crops it links,
+//! so the folder can be zipped or served as it is. Crops are grouped by source
+//! image, one thumbnail per face, captioned with its detector score and, when
+//! recorded, quality and tier. With `--rejected DIR` (a `--save-rejected`
+//! directory) each source also lists how many faces each filter turned down.
+//! Sources are ordered by path; `--limit` caps how many are shown, since a
+//! page with a million images helps nobody.
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::manifest::{self, ManifestRecord, MANIFEST_FILE};
+use crate::say;
+
+pub const REPORT_FILE: &str = "report.html";
+
+const THUMBNAIL_SIDE: u32 = 112;
+
+pub fn run(from: &Path, rejected: Option<&Path>, limit: usize) -> Result<()> {
+    let records = manifest::read(&from.join(MANIFEST_FILE))?;
+    let rejections = match rejected {
+        Some(dir) => manifest::read(&dir.join(MANIFEST_FILE))?,
+        None => Vec::new(),
+    };
+
+    // One crop per face is enough to judge it: the first variant, in its largest size
+    let mut faces: BTreeMap<&Path, BTreeMap<(Option<usize>, usize), &ManifestRecord>> = BTreeMap::new();
+    for record in &records {
+        let shown = faces
+            .entry(record.source.as_path())
+            .or_default()
+            .entry((record.frame, record.detection_id))
+            .or_insert(record);
+        if record.variant == shown.variant && record.size > shown.size {
+            *shown = record;
+        }
+    }
+    let mut reasons: BTreeMap<&Path, BTreeMap<&str, usize>> = BTreeMap::new();
+    for record in &rejections {
+        let counts = reasons.entry(record.source.as_path()).or_default();
+        for reason in record.rejected.as_deref().unwrap_or("unknown").split(',') {
+            *counts.entry(reason).or_default() += 1;
+        }
+    }
+    let sources: BTreeSet<&Path> = faces.keys().chain(reasons.keys()).copied().collect();
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Face dataset report</title>\n<style>\n\
+         body{{font-family:sans-serif;background:#1e1e1e;color:#ddd;margin:1.5em}}\n\
+         section{{border-top:1px solid #444;padding:.5em 0}}\n\
+         h2{{font-size:1em;font-weight:normal;word-break:break-all}}\n\
+         figure{{display:inline-block;margin:0 .5em .5em 0;text-align:center;font-size:.8em}}\n\
+         img{{width:{side}px;height:{side}px;object-fit:cover;display:block}}\n\
+         .rejected{{color:#e57373}}\n</style></head><body>\n\
+         <h1>{}</h1>\n<p>{} faces from {} sources",
+        escape(&from.display().to_string()),
+        faces.values().map(BTreeMap::len).sum::<usize>(),
+        faces.len(),
+        side = THUMBNAIL_SIDE,
+    );
+    if rejected.is_some() {
+        html.push_str(&format!(", {} rejected faces", rejections.len()));
+    }
+    html.push_str("</p>\n");
+    if sources.len() > limit {
+        html.push_str(&format!("<p>Showing the first {} of {} sources.</p>\n", limit, sources.len()));
+    }
+
+    for source in sources.iter().take(limit) {
+        html.push_str(&format!("<section><h2>{}</h2>\n", escape(&source.display().to_string())));
+        if let Some(counts) = reasons.get(source) {
+            let summary: Vec<String> = counts.iter().map(|(reason, n)| format!("{} {}", n, escape(reason))).collect();
+            html.push_str(&format!("<p class=\"rejected\">Rejected: {}</p>\n", summary.join(", ")));
+        }
+        for record in faces.get(source).into_iter().flat_map(BTreeMap::values) {
+            html.push_str(&figure(record));
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body></html>\n");
+
+    let path = from.join(REPORT_FILE);
+    fs::write(&path, html).with_context(|| format!("Failed to write {}", path.display()))?;
+    say!(ReportWritten, sources.len().min(limit), path.display());
+    Ok(())
+}
+
+/// Thumbnail and caption of one face
+fn figure(record: &ManifestRecord) -> String {
+    let mut caption = format!("score {:.2}", record.score);
+    if let Some(quality) = record.quality {
+        caption.push_str(&format!("<br>quality {:.2}", quality));
+    }
+    if let Some(tier) = record.tier {
+        caption.push_str(&format!(" · {}", tier.name()));
+    }
+    let crop = escape(&url_path(&record.crop));
+    format!(
+        "<figure><a href=\"{crop}\"><img src=\"{crop}\" loading=\"lazy\" alt=\"\"></a><figcaption>{}</figcaption></figure>\n",
+        caption,
+        crop = crop
+    )
+}
+
+/// `path` as a relative URL, with the characters that would end or break it encoded
+fn url_path(path: &str) -> String {
+    PathBuf::from(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().replace('%', "%25").replace('#', "%23").replace('?', "%3F").replace(' ', "%20"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
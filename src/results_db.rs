@@ -0,0 +1,233 @@
+//! SQLite results database (`--db results.sqlite`)
+//!
+//! Records every processed image, every detection with the reasons it was
+//! accepted or turned down, and every crop written, so questions about a run
+//! are SQL queries instead of log parsing:
+//!
+//! ```sql
+//! SELECT count(*) FROM images WHERE detected = 1;
+//! SELECT reasons, count(*) FROM detections WHERE NOT accepted GROUP BY reasons;
+//! ```
+//!
+//! Each run appends a row to `runs`, and its images point at it, so one
+//! database can collect several runs. A detection counts as accepted when it
+//! passed every filter; `reasons` lists the filters it failed, or why it was
+//! dropped later (`exposure`, `pose`, `duplicate`, `quota`, `limit`). Accepted
+//! detections without crops were cut off by `--target-faces` or a taken name.
+//! Images looked at again by `--relax-on-shortfall` or `--min-yield` get a
+//! second row with `retry` set, holding only the faces that pass found.
+//! Needs a build with `--features sqlite`.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::detector::Detection;
+use crate::store::now_ms;
+use crate::Args;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at_ms INTEGER NOT NULL,
+    version TEXT NOT NULL,
+    input TEXT NOT NULL,
+    output TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS images (
+    id INTEGER PRIMARY KEY,
+    run INTEGER NOT NULL REFERENCES runs(id),
+    source TEXT NOT NULL,
+    frame INTEGER,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    detected INTEGER NOT NULL,
+    retry INTEGER NOT NULL DEFAULT 0,
+    error TEXT
+);
+CREATE TABLE IF NOT EXISTS detections (
+    id INTEGER PRIMARY KEY,
+    image INTEGER NOT NULL REFERENCES images(id),
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    score REAL NOT NULL,
+    confidence REAL NOT NULL,
+    accepted INTEGER NOT NULL,
+    reasons TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS crops (
+    id INTEGER PRIMARY KEY,
+    detection INTEGER NOT NULL REFERENCES detections(id),
+    path TEXT NOT NULL,
+    variant TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS detections_image ON detections(image);
+CREATE INDEX IF NOT EXISTS crops_detection ON crops(detection);
+";
+
+/// A value bound to a statement parameter
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub enum Value {
+    Int(i64),
+    Real(f64),
+    Text(String),
+    Null,
+}
+
+pub struct ResultsDb {
+    conn: sql::Connection,
+    run: i64,
+}
+
+impl ResultsDb {
+    /// Open or create the database and start a run in it
+    pub fn open(path: &Path, args: &Args) -> Result<Self> {
+        let conn = sql::open(path)?;
+        sql::batch(&conn, SCHEMA)?;
+        let run = sql::execute(
+            &conn,
+            "INSERT INTO runs (started_at_ms, version, input, output) VALUES (?1, ?2, ?3, ?4)",
+            vec![
+                Value::Int(now_ms().parse().unwrap_or(0)),
+                Value::Text(env!("CARGO_PKG_VERSION").to_string()),
+                Value::Text(args.input.to_string_lossy().into_owned()),
+                Value::Text(args.output.to_string_lossy().into_owned()),
+            ],
+        )?;
+        Ok(ResultsDb { conn, run })
+    }
+
+    /// Record an image that went through the detector; returns its row id
+    pub fn image(&self, source: &Path, frame: Option<usize>, (width, height): (u32, u32), detected: usize, retry: bool) -> Result<i64> {
+        sql::execute(
+            &self.conn,
+            "INSERT INTO images (run, source, frame, width, height, detected, retry) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            vec![
+                Value::Int(self.run),
+                Value::Text(source.to_string_lossy().into_owned()),
+                frame.map_or(Value::Null, |f| Value::Int(f as i64)),
+                Value::Int(width as i64),
+                Value::Int(height as i64),
+                Value::Int(detected as i64),
+                Value::Int(retry as i64),
+            ],
+        )
+    }
+
+    /// Record an image that could not be processed
+    pub fn failure(&self, source: &Path, error: &str) -> Result<()> {
+        sql::execute(
+            &self.conn,
+            "INSERT INTO images (run, source, width, height, detected, error) VALUES (?1, ?2, 0, 0, 0, ?3)",
+            vec![
+                Value::Int(self.run),
+                Value::Text(source.to_string_lossy().into_owned()),
+                Value::Text(error.to_string()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record a detection of `image` and the filters it failed; returns its row id
+    pub fn detection(&self, image: i64, face: &Detection, failed: &[&str]) -> Result<i64> {
+        let bbox = &face.bbox;
+        sql::execute(
+            &self.conn,
+            "INSERT INTO detections (image, x, y, width, height, score, confidence, accepted, reasons)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            vec![
+                Value::Int(image),
+                Value::Int(bbox.x as i64),
+                Value::Int(bbox.y as i64),
+                Value::Int(bbox.width as i64),
+                Value::Int(bbox.height as i64),
+                Value::Real(face.score),
+                Value::Real(face.confidence),
+                Value::Int(failed.is_empty() as i64),
+                Value::Text(failed.join(",")),
+            ],
+        )
+    }
+
+    /// Mark an accepted detection as dropped for `reason`
+    pub fn reject(&self, detection: i64, reason: &str) -> Result<()> {
+        sql::execute(
+            &self.conn,
+            "UPDATE detections SET accepted = 0, reasons = ?2 WHERE id = ?1",
+            vec![Value::Int(detection), Value::Text(reason.to_string())],
+        )?;
+        Ok(())
+    }
+
+    /// Record a crop written for a detection
+    pub fn crop(&self, detection: i64, path: &str, variant: &str) -> Result<()> {
+        sql::execute(
+            &self.conn,
+            "INSERT INTO crops (detection, path, variant) VALUES (?1, ?2, ?3)",
+            vec![Value::Int(detection), Value::Text(path.to_string()), Value::Text(variant.to_string())],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+mod sql {
+    use anyhow::Result;
+    use std::path::Path;
+
+    use super::Value;
+
+    /// Never constructed: `open` fails in builds without SQLite
+    pub enum Connection {}
+
+    pub fn open(_path: &Path) -> Result<Connection> {
+        anyhow::bail!("This build has no SQLite support; rebuild with `cargo build --release --features sqlite`")
+    }
+
+    pub fn batch(conn: &Connection, _sql: &str) -> Result<()> {
+        match *conn {}
+    }
+
+    pub fn execute(conn: &Connection, _sql: &str, _values: Vec<Value>) -> Result<i64> {
+        match *conn {}
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sql {
+    use anyhow::{Context, Result};
+    use rusqlite::types::Value as SqlValue;
+    use std::path::Path;
+
+    use super::Value;
+
+    pub struct Connection(rusqlite::Connection);
+
+    pub fn open(path: &Path) -> Result<Connection> {
+        let conn = rusqlite::Connection::open(path)
+            .with_context(|| format!("Failed to open results database {}", path.display()))?;
+        // Rows are a record of the run, not its state, so losing the last few to a power cut is fine
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.execute_batch("PRAGMA synchronous = OFF")?;
+        Ok(Connection(conn))
+    }
+
+    pub fn batch(conn: &Connection, sql: &str) -> Result<()> {
+        conn.0.execute_batch(sql).context("Failed to set up results database")
+    }
+
+    /// Run one statement; returns the id of the last row inserted
+    pub fn execute(conn: &Connection, sql: &str, values: Vec<Value>) -> Result<i64> {
+        let values = values.into_iter().map(|value| match value {
+            Value::Int(n) => SqlValue::Integer(n),
+            Value::Real(x) => SqlValue::Real(x),
+            Value::Text(text) => SqlValue::Text(text),
+            Value::Null => SqlValue::Null,
+        });
+        conn.0
+            .execute(sql, rusqlite::params_from_iter(values))
+            .context("Failed to write to results database")?;
+        Ok(conn.0.last_insert_rowid())
+    }
+}
@@ -0,0 +1,69 @@
+//! Rotation retry for sideways and upside-down sources (`--rotate-retry`)
+//!
+//! Scanned prints are often fed in the wrong way round, and the detectors only
+//! find upright faces. When an image yields no detection at all, it is detected
+//! again turned 90°, 180° and 270° clockwise, and the first turn that finds a
+//! face wins. Its boxes and landmarks are mapped back onto the image as read,
+//! so crops and manifest coordinates stay in source pixels; the manifest's
+//! `rotation` field holds the turn, which is also how far a crop must be
+//! turned clockwise to stand upright.
+
+use anyhow::Result;
+use image::DynamicImage;
+
+use crate::detector::{BBox, Detection, FaceDetectorBackend};
+use crate::landmarks::Point;
+
+/// Clockwise turns tried, in order
+const TURNS: [u16; 3] = [90, 180, 270];
+
+/// Detect `image` at each turn until one finds a face; returns the turn and its faces in source coordinates
+pub fn retry(detector: &mut dyn FaceDetectorBackend, image: &DynamicImage) -> Result<Option<(u16, Vec<Detection>)>> {
+    for turn in TURNS {
+        let turned = match turn {
+            90 => image.rotate90(),
+            180 => image.rotate180(),
+            _ => image.rotate270(),
+        };
+        let mut faces = detector.detect(&turned)?;
+        if faces.is_empty() {
+            continue;
+        }
+        let (width, height) = (image.width(), image.height());
+        for face in &mut faces {
+            face.bbox = unturn_box(face.bbox, turn, width, height);
+            if let Some(points) = &mut face.landmarks {
+                for point in [
+                    &mut points.left_eye,
+                    &mut points.right_eye,
+                    &mut points.nose,
+                    &mut points.mouth_left,
+                    &mut points.mouth_right,
+                ] {
+                    *point = unturn_point(*point, turn, width as f32, height as f32);
+                }
+            }
+        }
+        return Ok(Some((turn, faces)));
+    }
+    Ok(None)
+}
+
+/// Box found in the image turned by `turn` mapped onto the `width` x `height` original
+fn unturn_box(b: BBox, turn: u16, width: u32, height: u32) -> BBox {
+    let (width, height) = (width as i32, height as i32);
+    match turn {
+        90 => BBox { x: b.y, y: height - b.x - b.width as i32, width: b.height, height: b.width },
+        180 => BBox { x: width - b.x - b.width as i32, y: height - b.y - b.height as i32, ..b },
+        _ => BBox { x: width - b.y - b.height as i32, y: b.x, width: b.height, height: b.width },
+    }
+}
+
+/// Point found in the image turned by `turn` mapped onto the `width` x `height` original
+fn unturn_point(p: Point, turn: u16, width: f32, height: f32) -> Point {
+    match turn {
+        90 => Point { x: p.y, y: height - p.x },
+        180 => Point { x: width - p.x, y: height - p.y },
+        _ => Point { x: width - p.y, y: p.x },
+    }
+}
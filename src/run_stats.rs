@@ -0,0 +1,162 @@
+//! Statistics of a run, written to `stats.json` when it ends
+//!
+//! The printed summary gives a few totals; `stats.json` keeps the shape of
+//! what was collected: histograms of face width, detector score and aspect
+//! ratio over the faces saved, faces saved per source, how many detections
+//! each filter turned down, and throughput. Everything covers this run only,
+//! so a resumed run describes what it added. Filter rejections are counted on
+//! the first look at each image; faces dropped later (`exposure`, `pose`,
+//! `duplicate`, `quota`, `limit`) are counted in every pass.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::detector::BBox;
+use crate::say;
+
+pub const STATS_FILE: &str = "stats.json";
+
+/// Bins per histogram
+const BINS: usize = 10;
+
+pub struct RunStats {
+    started: Instant,
+    widths: Vec<f64>,
+    scores: Vec<f64>,
+    aspects: Vec<f64>,
+    per_source: BTreeMap<String, usize>,
+    rejections: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct Histogram {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    bins: Vec<Bin>,
+}
+
+#[derive(Serialize)]
+struct Bin {
+    from: f64,
+    to: f64,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct Throughput {
+    images: usize,
+    errors: usize,
+    faces: usize,
+    seconds: f64,
+    images_per_second: f64,
+    faces_per_hour: f64,
+}
+
+#[derive(Serialize)]
+struct StatsFile<'a> {
+    throughput: Throughput,
+    face_width: Option<Histogram>,
+    score: Option<Histogram>,
+    aspect_ratio: Option<Histogram>,
+    rejections: &'a BTreeMap<String, usize>,
+    faces_per_source: &'a BTreeMap<String, usize>,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        RunStats {
+            started: Instant::now(),
+            widths: Vec::new(),
+            scores: Vec::new(),
+            aspects: Vec::new(),
+            per_source: BTreeMap::new(),
+            rejections: BTreeMap::new(),
+        }
+    }
+}
+
+impl RunStats {
+    /// Count a face saved from `source`
+    pub fn saved(&mut self, source: &Path, bbox: &BBox, score: f64) {
+        self.widths.push(bbox.width as f64);
+        self.scores.push(score);
+        self.aspects.push(bbox.width as f64 / bbox.height.max(1) as f64);
+        *self.per_source.entry(source.display().to_string()).or_default() += 1;
+    }
+
+    /// Count a detection turned down for `reason`
+    pub fn rejected(&mut self, reason: &str) {
+        *self.rejections.entry(reason.to_string()).or_default() += 1;
+    }
+
+    /// Faces saved so far
+    pub fn faces(&self) -> usize {
+        self.widths.len()
+    }
+
+    /// Time since the run started
+    pub fn seconds(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    /// Write `stats.json` into `output`
+    pub fn write(&self, output: &Path, images: usize, errors: usize) -> Result<()> {
+        let seconds = self.seconds();
+        let faces = self.faces();
+        let rate = |n: usize, per: f64| if seconds > 0.0 { n as f64 / seconds * per } else { 0.0 };
+        let file = StatsFile {
+            throughput: Throughput {
+                images,
+                errors,
+                faces,
+                seconds,
+                images_per_second: rate(images, 1.0),
+                faces_per_hour: rate(faces, 3600.0),
+            },
+            face_width: histogram(&self.widths),
+            score: histogram(&self.scores),
+            aspect_ratio: histogram(&self.aspects),
+            rejections: &self.rejections,
+            faces_per_source: &self.per_source,
+        };
+        let path = output.join(STATS_FILE);
+        fs::write(&path, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        say!(StatsWritten, path.display());
+        Ok(())
+    }
+}
+
+/// Equal-width bins between the smallest and largest value
+fn histogram(values: &[f64]) -> Option<Histogram> {
+    if values.is_empty() {
+        return None;
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = (max - min) / BINS as f64;
+    let mut bins: Vec<Bin> = (0..BINS)
+        .map(|i| Bin { from: min + width * i as f64, to: min + width * (i + 1) as f64, count: 0 })
+        .collect();
+    for &value in values {
+        let i = if width > 0.0 { (((value - min) / width) as usize).min(BINS - 1) } else { 0 };
+        bins[i].count += 1;
+    }
+    // All values equal: one bin says it all
+    if width == 0.0 {
+        bins.truncate(1);
+    }
+    Some(Histogram {
+        count: values.len(),
+        min,
+        max,
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+        bins,
+    })
+}
@@ -0,0 +1,59 @@
+//! `export-samples` subcommand: watermarked previews for partners
+//!
+//! Picks an evenly spread subset of crops from a finished run and writes
+//! watermarked copies (plus the matching manifest rows) to a separate folder,
+//! so the production output itself is never touched. Crops keep their path
+//! below the run, and each row's hash is that of the watermarked copy, so
+//! `verify` works on the sample folder too.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::custody::file_sha256;
+use crate::manifest::{self, ManifestWriter, CSV_FILE, MANIFEST_FILE};
+use crate::{say, say_err, watermark};
+
+pub fn run(from: &Path, to: &Path, count: usize, text: &str) -> Result<()> {
+    let records = manifest::read(&from.join(MANIFEST_FILE))?;
+    if records.is_empty() {
+        anyhow::bail!("No crops recorded in {}", from.join(MANIFEST_FILE).display());
+    }
+
+    fs::create_dir_all(to).context("Failed to create sample directory")?;
+    let mut sample_manifest = ManifestWriter::open(to, false, from.join(CSV_FILE).exists())?;
+
+    // Evenly spaced picks cover the whole run instead of just its first images
+    let count = count.min(records.len()).max(1);
+    let step = records.len() as f64 / count as f64;
+    let mut written = 0;
+    for i in 0..count {
+        let record = &records[(i as f64 * step) as usize];
+        let source = from.join(&record.crop);
+        let image = match image::open(&source) {
+            Ok(image) => image,
+            Err(e) => {
+                say_err!(SampleSkipped, source.display(), e);
+                continue;
+            }
+        };
+
+        // Size, tier and split folders hold crops of the same name, so keep them
+        let path = to.join(&record.crop);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create sample directory")?;
+        }
+        watermark::apply(&image, text)
+            .save(&path)
+            .with_context(|| format!("Failed to save sample {}", record.crop))?;
+
+        let mut row = record.clone();
+        row.sha256 = Some(file_sha256(&path)?);
+        sample_manifest.append(&row)?;
+        written += 1;
+    }
+
+    say!(SamplesExported, written, to.display());
+    say!(SampleWatermark, text);
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Pilot runs over part of a corpus (`--sample`, `--sample-fraction`, `--shuffle`)
+//!
+//! Before committing hours to a huge corpus, a run over a random subset shows
+//! what the settings yield. The subset is drawn uniformly from the images
+//! found (after burst selection) and processed in path order unless
+//! `--shuffle` is also given, which randomizes the order of everything taken.
+//! The seed is printed so `--seed` can draw the same subset again, which is
+//! also what a `--resume` of a sampled run needs. Rows of a CSV input list
+//! with a `weight` are drawn in proportion to it (images without one weigh
+//! 1, and weight 0 is never drawn).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::qa::Rng;
+use crate::{say, Args};
+
+/// The images to process out of `paths`, per the sampling and shuffling options
+pub fn apply(mut paths: Vec<PathBuf>, weights: &HashMap<PathBuf, f64>, args: &Args) -> Vec<PathBuf> {
+    let count = match (args.sample, args.sample_fraction) {
+        (Some(n), _) => Some(n.min(paths.len())),
+        (None, Some(fraction)) => Some(((paths.len() as f64 * fraction).round() as usize).clamp(1, paths.len())),
+        (None, None) => None,
+    };
+    if paths.is_empty() || (count.is_none() && !args.shuffle) {
+        return paths;
+    }
+
+    // Directory listing order varies between filesystems; a seed should pick the same images everywhere
+    paths.sort();
+    let (mut rng, seed) = Rng::seeded(args.seed);
+    if let Some(count) = count {
+        let total = paths.len();
+        let mut picked: Vec<usize> = (0..total).collect();
+        if weights.is_empty() {
+            rng.shuffle(&mut picked);
+        } else {
+            // Weighted draw without replacement: the largest keys u^(1/w) win (Efraimidis-Spirakis)
+            let keys: Vec<f64> = paths
+                .iter()
+                .map(|path| {
+                    let weight = weights.get(path).copied().unwrap_or(1.0);
+                    if weight > 0.0 { rng.unit().powf(1.0 / weight) } else { -1.0 }
+                })
+                .collect();
+            picked.sort_by(|&a, &b| keys[b].total_cmp(&keys[a]));
+            picked.retain(|&i| keys[i] >= 0.0);
+        }
+        picked.truncate(count);
+        // Taken images keep their place in the corpus
+        picked.sort_unstable();
+        paths = picked.into_iter().map(|i| paths[i].clone()).collect();
+        say!(Sampled, paths.len(), total, seed);
+    }
+    if args.shuffle {
+        rng.shuffle(&mut paths);
+        if count.is_none() {
+            say!(Shuffled, seed);
+        }
+    }
+    paths
+}
@@ -0,0 +1,60 @@
+//! Splitting one corpus across machines (`--shard-index`, `--shard-count`)
+//!
+//! Each machine runs over the same corpus with its own `--shard-index` and
+//! keeps the images whose path hashes to it. The path is taken relative to
+//! `--input` (or to the input list's folder, or the URL for downloads), so the
+//! corpus may be mounted in a different place on each machine, and an image
+//! stays in its shard when others are added. Burst selection still sees the
+//! whole corpus, so every shard picks the same frames.
+//!
+//! So that the outputs can be pooled, detection ids are interleaved: shard i
+//! of n numbers its faces i+1, i+1+n, i+1+2n, ... Crop names built from them
+//! never clash, and the manifests can simply be concatenated.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::{say, Args};
+
+/// The images of `paths` that belong to this machine's shard
+pub fn select(paths: Vec<PathBuf>, urls: &HashMap<PathBuf, String>, args: &Args) -> Vec<PathBuf> {
+    let Some(count) = args.shard_count else {
+        return paths;
+    };
+    let index = args.shard_index.unwrap_or(0);
+    let root = args.input_list.as_deref().and_then(Path::parent).unwrap_or(&args.input);
+    let total = paths.len();
+    let kept: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|path| {
+            let key = match urls.get(path) {
+                Some(url) => url.clone(),
+                None => key(path.strip_prefix(root).unwrap_or(path)),
+            };
+            shard_of(&key, count) == index
+        })
+        .collect();
+    say!(ShardSelected, index, count, kept.len(), total);
+    kept
+}
+
+/// Detection id of the shard's `n`th face (from 0), distinct from every other shard's
+pub fn detection_id(n: usize, args: &Args) -> usize {
+    match args.shard_count {
+        Some(count) => n * count + args.shard_index.unwrap_or(0) + 1,
+        None => n + 1,
+    }
+}
+
+/// A path with `/` between components whatever the platform
+fn key(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn shard_of(key: &str, count: usize) -> usize {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(head) % count as u64) as usize
+}
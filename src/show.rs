@@ -0,0 +1,176 @@
+//! `show` subcommand: view crops right in the terminal
+//!
+//! For operators on SSH-only boxes: crops from a finished run are drawn as
+//! numbered strips through the kitty graphics protocol or as sixels, with a
+//! caption line per crop below each strip. `auto` picks kitty when the
+//! environment says the terminal speaks it (kitty, WezTerm, Ghostty) and
+//! sixel otherwise.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImage, ImageFormat, Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+
+use crate::manifest::{self, MANIFEST_FILE};
+use crate::qa::Rng;
+use crate::watermark;
+
+/// Crops drawn side by side in one strip
+const STRIP_COLUMNS: usize = 5;
+
+/// Largest base64 payload per kitty escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Protocol {
+    /// kitty when the terminal announces it, sixel otherwise
+    Auto,
+    /// kitty graphics protocol (kitty, WezTerm, Ghostty, Konsole)
+    Kitty,
+    /// DEC sixel graphics (xterm -ti vt340, foot, mlterm, iTerm2, Windows Terminal)
+    Sixel,
+}
+
+impl Protocol {
+    fn resolve(self) -> Protocol {
+        if self != Protocol::Auto {
+            return self;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "WezTerm"
+            || program == "ghostty"
+        {
+            Protocol::Kitty
+        } else {
+            Protocol::Sixel
+        }
+    }
+}
+
+pub fn run(from: &Path, random: Option<usize>, limit: usize, size: u32, protocol: Protocol, seed: Option<u64>) -> Result<()> {
+    let mut records = manifest::read(&from.join(MANIFEST_FILE))?;
+    if records.is_empty() {
+        anyhow::bail!("No crops recorded in {}", from.join(MANIFEST_FILE).display());
+    }
+    let total = records.len();
+    let count = match random {
+        Some(n) => {
+            let (mut rng, seed) = Rng::seeded(seed);
+            rng.shuffle(&mut records);
+            println!("🎲 {} random crops of {} (seed {})", n.min(total), total, seed);
+            n
+        }
+        None => limit,
+    };
+    records.truncate(count);
+
+    let protocol = protocol.resolve();
+    let mut out = io::stdout().lock();
+    for (strip, chunk) in records.chunks(STRIP_COLUMNS).enumerate() {
+        let first = strip * STRIP_COLUMNS;
+        let mut canvas = RgbaImage::from_pixel(chunk.len() as u32 * size, size, Rgba([32, 32, 32, 255]));
+        for (i, record) in chunk.iter().enumerate() {
+            let x = i as u32 * size;
+            match image::open(from.join(&record.crop)) {
+                Ok(crop) => canvas.copy_from(&crop.resize_to_fill(size, size, FilterType::Triangle).to_rgba8(), x, 0)?,
+                Err(e) => eprintln!("  ❌ {}: {}", record.crop, e),
+            }
+            watermark::draw_line(&mut canvas, &(first + i + 1).to_string(), x + 4, 4, 2);
+        }
+
+        let image = DynamicImage::ImageRgba8(canvas);
+        match protocol {
+            Protocol::Kitty => write_kitty(&mut out, &image)?,
+            _ => write_sixel(&mut out, &image)?,
+        }
+        writeln!(out)?;
+        for (i, record) in chunk.iter().enumerate() {
+            writeln!(out, "  {:>3}  {}  score {:.2}", first + i + 1, record.crop, record.score)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// PNG payload in base64 chunks; every chunk but the last carries `m=1`
+fn write_kitty(out: &mut impl Write, image: &DynamicImage) -> Result<()> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .context("Failed to encode preview")?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = if i == 0 { format!("a=T,f=100,m={}", more) } else { format!("m={}", more) };
+        write!(out, "\x1b_G{};", control)?;
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+    Ok(())
+}
+
+/// Sixel image on a 6x6x6 color cube, run-length encoded per six-pixel band
+fn write_sixel(out: &mut impl Write, image: &DynamicImage) -> Result<()> {
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let level = |v: u8| (v as u32 * 5 + 127) / 255;
+    let index: Vec<u32> = rgb
+        .pixels()
+        .map(|p| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]))
+        .collect();
+
+    let mut sixel = format!("\x1bPq\"1;1;{};{}", width, height);
+    for color in 0..216u32 {
+        let percent = |l: u32| l * 100 / 5;
+        let _ = write!(sixel, "#{};2;{};{};{}", color, percent(color / 36), percent(color / 6 % 6), percent(color % 6));
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        // Bit masks of every color present in this band, one per column
+        let mut masks: HashMap<u32, Vec<u8>> = HashMap::new();
+        for dy in 0..rows {
+            for x in 0..width {
+                let color = index[((band + dy) * width + x) as usize];
+                masks.entry(color).or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << dy;
+            }
+        }
+        let mut colors: Vec<_> = masks.into_iter().collect();
+        colors.sort_by_key(|(color, _)| *color);
+        for (color, mask) in colors {
+            let _ = write!(sixel, "#{}", color);
+            push_runs(&mut sixel, &mask);
+            sixel.push('$');
+        }
+        sixel.push('-');
+    }
+    sixel.push_str("\x1b\\");
+    out.write_all(sixel.as_bytes())?;
+    Ok(())
+}
+
+/// Append the sixel characters for `mask`, compressing repeats as `!COUNT`
+fn push_runs(sixel: &mut String, mask: &[u8]) {
+    let mut i = 0;
+    while i < mask.len() {
+        let run = mask[i..].iter().take_while(|&&m| m == mask[i]).count();
+        let c = char::from(63 + mask[i]);
+        if run > 3 {
+            let _ = write!(sixel, "!{}{}", run, c);
+        } else {
+            (0..run).for_each(|_| sixel.push(c));
+        }
+        i += run;
+    }
+}
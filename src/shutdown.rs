@@ -0,0 +1,54 @@
+//! Graceful stop on Ctrl-C and SIGTERM
+//!
+//! The first signal only raises a flag: the run loops stop taking new images,
+//! the ones in progress finish, and the run ends through the normal path, so
+//! the manifest and checkpoint are flushed and the partial summary is printed.
+//! `--resume` picks up the rest. A second signal exits at once. Other
+//! platforms keep the default behavior of Ctrl-C.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::sync::OnceLock;
+
+#[cfg(unix)]
+use crate::i18n::{self, Msg};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Line the handler prints, rendered up front since a handler must not allocate
+#[cfg(unix)]
+static NOTICE: OnceLock<String> = OnceLock::new();
+
+/// Exit status after a second signal, as shells report a SIGINT kill
+#[cfg(unix)]
+const FORCED_EXIT: libc::c_int = 130;
+
+/// Catch SIGINT and SIGTERM for the rest of the run
+pub fn install() {
+    #[cfg(unix)]
+    {
+        if !i18n::quiet() {
+            let _ = NOTICE.set(format!("\n{}\n", i18n::line(Msg::Stopping, &[])));
+        }
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        unsafe {
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
+        }
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_: libc::c_int) {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(FORCED_EXIT) };
+    }
+    if let Some(notice) = NOTICE.get() {
+        unsafe { libc::write(libc::STDERR_FILENO, notice.as_ptr().cast(), notice.len()) };
+    }
+}
+
+/// Whether a stop was asked for; loops check this before taking the next image
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,103 @@
+//! Train/val/test splits (`--split 80/10/10`)
+//!
+//! Each crop is saved below `train/`, `val/` or `test/` in the output
+//! directory and its manifest row records which. The split comes from a hash
+//! of a group key, so it doesn't depend on processing order, and a rerun or a
+//! resumed run sends every face where it went before. All faces of one source
+//! image share a split; with `--split-group label` all faces of one identity
+//! do, so no person shows up in more than one split. The proportions hold over
+//! many groups, not exactly for a handful.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    Train,
+    Val,
+    Test,
+}
+
+impl Split {
+    pub const ALL: [Split; 3] = [Split::Train, Split::Val, Split::Test];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Split::Train => "train",
+            Split::Val => "val",
+            Split::Test => "test",
+        }
+    }
+}
+
+/// What faces must stay together in one split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitGroup {
+    /// Faces from the same source image
+    #[default]
+    Source,
+    /// Faces with the same identity label (`--labels-from-dirs`); unlabelled ones go by source
+    Label,
+}
+
+/// Shares of the train, val and test splits, such as `80/10/10`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SplitRatios {
+    pub train: u32,
+    pub val: u32,
+    pub test: u32,
+}
+
+impl SplitRatios {
+    /// Split for the group `key`
+    pub fn assign(&self, key: &str) -> Split {
+        let digest = Sha256::digest(key.as_bytes());
+        let (train, val, test) = (self.train as u64, self.val as u64, self.test as u64);
+        let slot = u64::from_le_bytes(digest[..8].try_into().expect("digest is 32 bytes")) % (train + val + test);
+        if slot < train {
+            Split::Train
+        } else if slot < train + val {
+            Split::Val
+        } else {
+            Split::Test
+        }
+    }
+}
+
+impl FromStr for SplitRatios {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Option<Vec<u32>> = s.split('/').map(|part| part.trim().parse().ok()).collect();
+        match parts.as_deref() {
+            Some(&[train, val, test]) if (train, val, test) != (0, 0, 0) => Ok(SplitRatios { train, val, test }),
+            _ => Err(format!("'{}' is not a split (use TRAIN/VAL/TEST shares, e.g. 80/10/10)", s)),
+        }
+    }
+}
+
+impl fmt::Display for SplitRatios {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.train, self.val, self.test)
+    }
+}
+
+impl TryFrom<String> for SplitRatios {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SplitRatios> for String {
+    fn from(ratios: SplitRatios) -> Self {
+        ratios.to_string()
+    }
+}
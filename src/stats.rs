@@ -0,0 +1,81 @@
+//! `stats` subcommand: summarize the crops of an existing output directory
+//!
+//! Reads only the manifest (and the sizes of the crop files it lists), so it
+//! works on any finished or interrupted run, including ones copied elsewhere.
+//! For the progress of a run that is still going, see `status`.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::{self, Msg};
+use crate::manifest::{self, MANIFEST_FILE};
+use crate::say;
+
+pub fn run(output_dir: &Path) -> Result<()> {
+    let records = manifest::read(&output_dir.join(MANIFEST_FILE))?;
+    if records.is_empty() {
+        say!(StatsEmpty, output_dir.join(MANIFEST_FILE).display());
+        return Ok(());
+    }
+
+    let sources: HashSet<_> = records.iter().map(|r| &r.source).collect();
+    let detections: HashSet<_> = records.iter().map(|r| (&r.source, r.frame, r.detection_id)).collect();
+    let bytes: u64 = records
+        .iter()
+        .filter_map(|r| fs::metadata(output_dir.join(&r.crop)).ok())
+        .map(|meta| meta.len())
+        .sum();
+    let missing = records.iter().filter(|r| !output_dir.join(&r.crop).exists()).count();
+
+    say!(StatsHeader, output_dir.display());
+    say!(StatsCrops, records.len(), missing, format!("{:.1}", bytes as f64 / 1e6));
+    say!(StatsFaces, detections.len(), sources.len());
+    print_range(&i18n::text(Msg::StatsScore, &[]), records.iter().map(|r| r.score));
+    print_range(&i18n::text(Msg::StatsQuality, &[]), records.iter().filter_map(|r| r.quality));
+    print_range(&i18n::text(Msg::StatsFaceWidth, &[]), records.iter().map(|r| r.bbox.width as f64));
+
+    print_counts(&i18n::text(Msg::StatsVariants, &[]), records.iter().map(|r| Some(r.variant.name().to_string())));
+    print_counts(&i18n::text(Msg::StatsTiers, &[]), records.iter().map(|r| r.tier.map(|t| t.name().to_string())));
+    print_counts(&i18n::text(Msg::StatsFacing, &[]), records.iter().map(|r| r.facing.map(|f| label(&f))));
+    print_counts(&i18n::text(Msg::StatsRelaxation, &[]), records.iter().map(|r| r.relaxation.map(|l| l.to_string())));
+    let axes: HashSet<&String> = records.iter().flat_map(|r| r.tags.keys()).collect();
+    let mut axes: Vec<_> = axes.into_iter().collect();
+    axes.sort();
+    for axis in axes {
+        print_counts(&i18n::text(Msg::StatsTag, &[axis]), records.iter().map(|r| r.tags.get(axis).cloned()));
+    }
+    Ok(())
+}
+
+/// Lowercase name a field serializes to
+fn label<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn print_range(name: &str, values: impl Iterator<Item = f64>) {
+    let values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return;
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    say!(StatsRange, name, format!("{:.2}", mean), format!("{:.2}", min), format!("{:.2}", max));
+}
+
+/// Crops per value, skipped when no crop has the field
+fn print_counts(name: &str, values: impl Iterator<Item = Option<String>>) {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut any = false;
+    for value in values {
+        any |= value.is_some();
+        *counts.entry(value.unwrap_or_else(|| i18n::text(Msg::StatsUnset, &[]))).or_default() += 1;
+    }
+    if !any {
+        return;
+    }
+    let parts: Vec<String> = counts.iter().map(|(value, count)| format!("{} {}", value, count)).collect();
+    say!(StatsCounts, name, parts.join(", "));
+}
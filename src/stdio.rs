@@ -0,0 +1,110 @@
+//! Pipeline mode (`--stdin`): inputs from stdin, one JSON result per input on stdout
+//!
+//! `--stdin` (or `--stdin paths`) reads one image path per line, so the tool
+//! sits behind `find` or `ls`. `--stdin bytes` reads the images themselves,
+//! each as a 4-byte big-endian length and that many bytes, for producers that
+//! never write files; their crops record `stdin` as the source and the image's
+//! position in the stream as `frame`, like `--input-stream`. Each input gets a
+//! line on stdout as soon as it is done, with its face count, crop names or
+//! error, and stdout is flushed after each one. Everything meant for people
+//! goes to stderr.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{decode, decoders, detect_image, extract_faces, process_image, record_outcome, should_stop, BatchStats, Extractor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StdinFormat {
+    /// One image path per line
+    Paths,
+    /// Each image as a 4-byte big-endian length followed by the encoded image
+    Bytes,
+}
+
+#[derive(Serialize)]
+struct Outcome<'a> {
+    source: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<usize>,
+    faces: usize,
+    crops: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Process stdin until it ends or the target is reached
+pub fn run(ex: &mut Extractor, format: StdinFormat) -> Result<BatchStats> {
+    let args = ex.args;
+    let mut input = io::stdin().lock();
+    let mut out = io::stdout().lock();
+    let mut stats = BatchStats::default();
+    let mut index = 0;
+    ex.saved_crops = Some(Vec::new());
+    loop {
+        if should_stop(ex, &mut stats) {
+            break;
+        }
+        let (source, result) = match format {
+            StdinFormat::Paths => {
+                let mut line = String::new();
+                if input.read_line(&mut line).context("Failed to read stdin")? == 0 {
+                    break;
+                }
+                let line = line.trim_end_matches(['\n', '\r']);
+                if line.is_empty() {
+                    continue;
+                }
+                let path = PathBuf::from(line);
+                let result = process_image(ex, &path);
+                (path, result)
+            }
+            StdinFormat::Bytes => {
+                let Some(bytes) = read_image(&mut input)? else { break };
+                ex.frame = Some(index);
+                let source = PathBuf::from("stdin");
+                let result = decoders::registry()
+                    .read()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .decode_bytes(&bytes)
+                    .and_then(|image| {
+                        let enhanced = if args.enhance_scans { decode::enhance_scan(&image) } else { None };
+                        detect_image(args, ex.detector()?, (image, enhanced))
+                    })
+                    .and_then(|detected| extract_faces(ex, &source, detected));
+                (source, result)
+            }
+        };
+
+        let outcome = Outcome {
+            source: &source,
+            frame: ex.frame,
+            faces: *result.as_ref().unwrap_or(&0),
+            crops: ex.saved_crops.replace(Vec::new()).unwrap_or_default(),
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        };
+        serde_json::to_writer(&mut out, &outcome)?;
+        out.write_all(b"\n")?;
+        out.flush().context("Failed to write to stdout")?;
+        record_outcome(ex, &mut stats, index, &source, result);
+        index += 1;
+    }
+    Ok(stats)
+}
+
+/// Next length-prefixed image, or None at the end of the stream
+fn read_image(input: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match input.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e).context("Failed to read stdin"),
+    }
+    let mut bytes = vec![0u8; u32::from_be_bytes(length) as usize];
+    input.read_exact(&mut bytes).context("stdin ended in the middle of an image")?;
+    Ok(Some(bytes))
+}
@@ -0,0 +1,276 @@
+//! Embedded run store (`run.redb`)
+//!
+//! Run metadata, the status of every input file, the run's counters and the
+//! chunk plan of a `--chunk-size` run live in one `redb` database in the
+//! output directory. Progress is committed in
+//! transactions of `--checkpoint-every` images, so a crash loses at most the
+//! batch in flight and the store never holds half a batch. Resuming looks files
+//! up by key instead of replaying a log, which keeps `--resume` and
+//! `--skip-existing` fast on million-file runs, and the `status` subcommand
+//! reads the same tables.
+//!
+//! The database is only held open while a batch is committed or a query runs,
+//! so `status` can inspect a run that is still going.
+
+use anyhow::{Context, Result};
+use redb::{Database, DatabaseError, ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::say;
+
+pub const STORE_FILE: &str = "run.redb";
+
+/// Free-form run metadata, e.g. `started_at_ms`
+const META: TableDefinition<&str, &str> = TableDefinition::new("meta");
+/// Finished input files and the faces each yielded
+const DONE: TableDefinition<&str, u64> = TableDefinition::new("done");
+/// Failed input files and their error; a later success removes the entry
+const FAILED: TableDefinition<&str, &str> = TableDefinition::new("failed");
+const COUNTERS: TableDefinition<&str, u64> = TableDefinition::new("counters");
+/// Shape of a `--chunk-size` plan: `total_images` and `chunk_size`
+const PLAN: TableDefinition<&str, u64> = TableDefinition::new("plan");
+/// Completed chunks and the face count when each finished
+const CHUNKS: TableDefinition<u64, u64> = TableDefinition::new("chunks");
+
+/// Attempts to open a database another process is committing to
+const OPEN_ATTEMPTS: u32 = 50;
+const OPEN_RETRY: Duration = Duration::from_millis(100);
+
+/// Outcome of one input file
+pub enum FileStatus {
+    Done { faces: usize },
+    Failed { error: String },
+}
+
+/// Snapshot of a run for `status`
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub meta: Vec<(String, String)>,
+    pub done: u64,
+    pub failed: Vec<(String, String)>,
+    pub faces_extracted: u64,
+    /// Completed and total chunks of a `--chunk-size` plan
+    pub chunks: Option<(u64, u64)>,
+}
+
+/// Progress through a `--chunk-size` plan
+#[derive(Debug, Default)]
+pub struct PlanProgress {
+    pub total_images: usize,
+    pub chunk_size: usize,
+    pub completed_chunks: Vec<usize>,
+    pub faces_extracted: usize,
+}
+
+pub fn exists(output_dir: &Path) -> bool {
+    output_dir.join(STORE_FILE).exists()
+}
+
+/// Drop the store of a previous run so a fresh run starts empty
+pub fn reset(output_dir: &Path) -> Result<()> {
+    let path = output_dir.join(STORE_FILE);
+    if path.exists() {
+        std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Commit file outcomes, the face count (if given) and metadata in one transaction
+pub fn commit(
+    output_dir: &Path,
+    files: &[(PathBuf, FileStatus)],
+    faces_extracted: Option<usize>,
+    meta: &[(&str, String)],
+) -> Result<()> {
+    let db = open(output_dir)?;
+    let txn = db.begin_write()?;
+    {
+        let mut done = txn.open_table(DONE)?;
+        let mut failed = txn.open_table(FAILED)?;
+        for (path, status) in files {
+            let key = path.to_string_lossy();
+            match status {
+                FileStatus::Done { faces } => {
+                    done.insert(key.as_ref(), *faces as u64)?;
+                    failed.remove(key.as_ref())?;
+                }
+                FileStatus::Failed { error } => {
+                    failed.insert(key.as_ref(), error.as_str())?;
+                }
+            }
+        }
+        let mut counters = txn.open_table(COUNTERS)?;
+        if let Some(faces) = faces_extracted {
+            counters.insert("faces_extracted", faces as u64)?;
+        }
+        let mut table = txn.open_table(META)?;
+        for (key, value) in meta {
+            table.insert(*key, value.as_str())?;
+        }
+        table.insert("updated_at_ms", now_ms().as_str())?;
+    }
+    txn.commit().context("Failed to commit run store")
+}
+
+/// Files finished by earlier runs and the face count they reached
+pub fn progress(output_dir: &Path) -> Result<(Vec<PathBuf>, usize)> {
+    let db = open(output_dir)?;
+    let txn = db.begin_read()?;
+    let mut processed = Vec::new();
+    if let Ok(done) = txn.open_table(DONE) {
+        for entry in done.iter()? {
+            processed.push(PathBuf::from(entry?.0.value()));
+        }
+    }
+    let faces = match txn.open_table(COUNTERS) {
+        Ok(counters) => counters.get("faces_extracted")?.map_or(0, |v| v.value()),
+        Err(_) => 0,
+    };
+    Ok((processed, faces as usize))
+}
+
+/// Files of earlier runs that yielded at least one crop, and the face count reached
+pub fn with_crops(output_dir: &Path) -> Result<(HashSet<PathBuf>, usize)> {
+    let db = open(output_dir)?;
+    let txn = db.begin_read()?;
+    let mut sources = HashSet::new();
+    if let Ok(done) = txn.open_table(DONE) {
+        for entry in done.iter()? {
+            let (path, faces) = entry?;
+            if faces.value() > 0 {
+                sources.insert(PathBuf::from(path.value()));
+            }
+        }
+    }
+    let faces = match txn.open_table(COUNTERS) {
+        Ok(counters) => counters.get("faces_extracted")?.map_or(0, |v| v.value()),
+        Err(_) => 0,
+    };
+    Ok((sources, faces as usize))
+}
+
+/// The chunk plan recorded by an earlier `--chunk-size` run, if any
+pub fn plan(output_dir: &Path) -> Result<Option<PlanProgress>> {
+    let db = open(output_dir)?;
+    let txn = db.begin_read()?;
+    let Ok(plan) = txn.open_table(PLAN) else {
+        return Ok(None);
+    };
+    let (Some(total_images), Some(chunk_size)) = (plan.get("total_images")?, plan.get("chunk_size")?) else {
+        return Ok(None);
+    };
+    let mut progress = PlanProgress {
+        total_images: total_images.value() as usize,
+        chunk_size: chunk_size.value() as usize,
+        ..Default::default()
+    };
+    if let Ok(chunks) = txn.open_table(CHUNKS) {
+        for entry in chunks.iter()? {
+            let (index, faces) = entry?;
+            progress.completed_chunks.push(index.value() as usize);
+            progress.faces_extracted = progress.faces_extracted.max(faces.value() as usize);
+        }
+    }
+    Ok(Some(progress))
+}
+
+/// Record a new chunk plan, forgetting the chunks of any earlier one
+pub fn start_plan(output_dir: &Path, total_images: usize, chunk_size: usize) -> Result<()> {
+    let db = open(output_dir)?;
+    let txn = db.begin_write()?;
+    {
+        let mut plan = txn.open_table(PLAN)?;
+        plan.insert("total_images", total_images as u64)?;
+        plan.insert("chunk_size", chunk_size as u64)?;
+    }
+    txn.delete_table(CHUNKS)?;
+    txn.commit().context("Failed to commit run store")
+}
+
+/// Mark a chunk of the plan complete at `faces_extracted` faces
+pub fn complete_chunk(output_dir: &Path, index: usize, faces_extracted: usize) -> Result<()> {
+    let db = open(output_dir)?;
+    let txn = db.begin_write()?;
+    {
+        let mut chunks = txn.open_table(CHUNKS)?;
+        chunks.insert(index as u64, faces_extracted as u64)?;
+    }
+    txn.commit().context("Failed to commit run store")
+}
+
+pub fn summary(output_dir: &Path) -> Result<Summary> {
+    let db = open(output_dir)?;
+    let txn = db.begin_read()?;
+    let mut summary = Summary::default();
+    if let Ok(meta) = txn.open_table(META) {
+        for entry in meta.iter()? {
+            let (key, value) = entry?;
+            summary.meta.push((key.value().to_string(), value.value().to_string()));
+        }
+    }
+    if let Ok(done) = txn.open_table(DONE) {
+        summary.done = done.len()?;
+    }
+    if let Ok(failed) = txn.open_table(FAILED) {
+        for entry in failed.iter()? {
+            let (path, error) = entry?;
+            summary.failed.push((path.value().to_string(), error.value().to_string()));
+        }
+    }
+    if let Ok(counters) = txn.open_table(COUNTERS) {
+        summary.faces_extracted = counters.get("faces_extracted")?.map_or(0, |v| v.value());
+    }
+    if let Ok(plan) = txn.open_table(PLAN) {
+        if let (Some(total), Some(size)) = (plan.get("total_images")?, plan.get("chunk_size")?) {
+            let completed = txn.open_table(CHUNKS).map_or(Ok(0), |chunks| chunks.len())?;
+            summary.chunks = Some((completed, total.value().div_ceil(size.value().max(1))));
+        }
+    }
+    Ok(summary)
+}
+
+/// Print the state of the run in `output_dir`
+pub fn status(output_dir: &Path, list_failed: bool) -> Result<()> {
+    if !exists(output_dir) {
+        anyhow::bail!("No run store ({}) in {}", STORE_FILE, output_dir.display());
+    }
+    let summary = summary(output_dir)?;
+    say!(StatusHeader, output_dir.display());
+    for (key, value) in &summary.meta {
+        say!(StatusMeta, key, value);
+    }
+    say!(ImagesProcessed, summary.done);
+    say!(StatusFailed, summary.failed.len());
+    say!(FacesExtracted, summary.faces_extracted);
+    if let Some((completed, total)) = summary.chunks {
+        say!(StatusChunks, completed, total);
+    }
+    if list_failed {
+        for (path, error) in &summary.failed {
+            say!(StatusFailure, path, error);
+        }
+    }
+    Ok(())
+}
+
+pub fn now_ms() -> String {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+        .to_string()
+}
+
+/// Open (or create) the store, waiting out another process's commit
+fn open(output_dir: &Path) -> Result<Database> {
+    let path = output_dir.join(STORE_FILE);
+    for _ in 1..OPEN_ATTEMPTS {
+        match Database::create(&path) {
+            Err(DatabaseError::DatabaseAlreadyOpen) => thread::sleep(OPEN_RETRY),
+            result => return result.with_context(|| format!("Failed to open run store {}", path.display())),
+        }
+    }
+    Database::create(&path).with_context(|| format!("Failed to open run store {}", path.display()))
+}
@@ -0,0 +1,102 @@
+//! Live camera input (`--input-stream`)
+//!
+//! `ffmpeg` pulls frames from an RTSP/HTTP URL or a V4L2 device such as
+//! `/dev/video0`, samples them down to `--stream-fps` and pipes them back as
+//! PPM images. Each frame is detected and cropped like a still until
+//! `--target-faces` is reached or the stream ends. Crops record the stream as
+//! their source and the frame's index among the sampled frames.
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, RgbImage};
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::{decode, detect_image, extract_faces, record_outcome, say, should_stop, BatchStats, Extractor};
+
+/// Read frames from `source` until the target is reached or the stream ends
+pub fn run(ex: &mut Extractor, source: &str) -> Result<BatchStats> {
+    let args = ex.args;
+    let mut command = Command::new("ffmpeg");
+    command.args(["-v", "error", "-nostdin"]);
+    if source.starts_with("rtsp://") {
+        // UDP drops packets on busy networks and leaves smeared frames
+        command.args(["-rtsp_transport", "tcp"]);
+    } else if source.starts_with("/dev/video") {
+        command.args(["-f", "v4l2"]);
+    }
+    let mut child = command
+        .args(["-i", source])
+        .args(["-vf", &format!("fps={}", args.stream_fps)])
+        .args(["-f", "image2pipe", "-vcodec", "ppm", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run ffmpeg (it must be installed to read streams)")?;
+    let mut frames = BufReader::new(child.stdout.take().context("ffmpeg has no output pipe")?);
+    say!(StreamOpened, source, args.stream_fps);
+
+    let source_path = PathBuf::from(source);
+    let mut stats = BatchStats::default();
+    let mut index = 0;
+    loop {
+        if should_stop(ex, &mut stats) {
+            break;
+        }
+        let Some(image) = read_ppm(&mut frames)? else { break };
+
+        ex.frame = Some(index);
+        let enhanced = if args.enhance_scans { decode::enhance_scan(&image) } else { None };
+        let result = ex.detector().and_then(|detector| detect_image(args, detector, (image, enhanced)))
+            .and_then(|detected| extract_faces(ex, &source_path, detected));
+        record_outcome(ex, &mut stats, index, &source_path, result);
+        index += 1;
+    }
+
+    // Live sources never end on their own
+    let _ = child.kill();
+    let status = child.wait().context("Failed to wait for ffmpeg")?;
+    if index == 0 && !status.success() {
+        anyhow::bail!("ffmpeg could not read {}", source);
+    }
+    say!(StreamEnded, index);
+    Ok(stats)
+}
+
+/// Next binary PPM (`P6`) image from the pipe, `None` once it closes
+fn read_ppm(reader: &mut impl BufRead) -> Result<Option<DynamicImage>> {
+    let Some(magic) = token(reader)? else { return Ok(None) };
+    anyhow::ensure!(magic == "P6", "Unexpected frame format {} from ffmpeg", magic);
+    let mut header = [0u32; 3];
+    for value in &mut header {
+        *value = token(reader)?
+            .and_then(|t| t.parse().ok())
+            .context("Truncated frame header from ffmpeg")?;
+    }
+    let [width, height, max] = header;
+    anyhow::ensure!(max == 255, "Unsupported frame depth {} from ffmpeg", max);
+
+    let mut pixels = vec![0; width as usize * height as usize * 3];
+    if reader.read_exact(&mut pixels).is_err() {
+        // The pipe closed partway through a frame when ffmpeg was stopped
+        return Ok(None);
+    }
+    let image = RgbImage::from_raw(width, height, pixels).context("Frame size does not match its header")?;
+    Ok(Some(DynamicImage::ImageRgb8(image)))
+}
+
+/// Whitespace-separated header field; consumes the single whitespace byte after it
+fn token(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut token = String::new();
+    for byte in reader.bytes() {
+        let byte = byte.context("Failed to read from ffmpeg")?;
+        if byte.is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            break;
+        }
+        token.push(byte as char);
+    }
+    Ok((!token.is_empty()).then_some(token))
+}
@@ -0,0 +1,207 @@
+//! `tag` subcommand: bulk-edit curator tags on manifest rows
+//!
+//! Rows are picked with `--where`, a small predicate language over the fields
+//! of a manifest row: comparisons `FIELD OP VALUE` (`<`, `<=`, `>`, `>=`, `==`
+//! or `=`, `!=`) and list tests `FIELD in ('a', 'b')`, joined by `&&` and `||`
+//! with `&&` binding tighter. Nested fields use dots (`bbox.width`,
+//! `tags.occlusion`), and a name that is not a manifest field is looked up
+//! among the tags (`license` means `tags.license`). Numbers compare
+//! numerically, everything else as text; a field a row lacks only matches `!=`.
+//!
+//! Tags form a small taxonomy: each key is an axis (`source`, `cond`, `pose`)
+//! holding one value per row, so a crop can carry any number of them, e.g.
+//! `source:cctv`, `cond:night` and `pose:profile`. `export --split-by` lays
+//! a release out along any of these axes.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::custody::SEAL_FILE;
+use crate::manifest;
+use crate::say;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    In,
+}
+
+#[derive(Debug)]
+struct Comparison {
+    field: String,
+    op: Op,
+    /// One value, or the members of an `in` list
+    values: Vec<String>,
+}
+
+/// Parsed `--where` expression: any of the groups matches when all its comparisons do
+#[derive(Debug, Default)]
+pub struct Predicate {
+    any: Vec<Vec<Comparison>>,
+}
+
+impl Predicate {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let any = expr
+            .split("||")
+            .map(|group| group.split("&&").map(Comparison::parse).collect::<Result<Vec<_>>>())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Predicate { any })
+    }
+
+    /// Whether a row, as its JSON object, satisfies the expression; an empty predicate matches everything
+    pub fn matches(&self, row: &Value) -> bool {
+        self.any.is_empty() || self.any.iter().any(|all| all.iter().all(|c| c.matches(row)))
+    }
+}
+
+impl Comparison {
+    fn parse(text: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid condition '{}': expected FIELD OP VALUE, e.g. score<2.5", text.trim());
+
+        let start = text.find(['<', '>', '=', '!']);
+        if let Some(at) = text.find(" in ").filter(|&at| start.is_none_or(|start| at < start)) {
+            return Self::parse_list(&text[..at], &text[at + 4..]);
+        }
+
+        let start = start.ok_or_else(invalid)?;
+        let (op, len) = match &text[start..] {
+            s if s.starts_with("<=") => (Op::Le, 2),
+            s if s.starts_with(">=") => (Op::Ge, 2),
+            s if s.starts_with("==") => (Op::Eq, 2),
+            s if s.starts_with("!=") => (Op::Ne, 2),
+            s if s.starts_with('<') => (Op::Lt, 1),
+            s if s.starts_with('>') => (Op::Gt, 1),
+            s if s.starts_with('=') => (Op::Eq, 1),
+            _ => return Err(invalid()),
+        };
+
+        let field = text[..start].trim();
+        let value = text[start + len..].trim();
+        if field.is_empty() || value.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Comparison {
+            field: field.to_string(),
+            op,
+            values: vec![unquote(value).to_string()],
+        })
+    }
+
+    /// `FIELD in ('a', 'b')`
+    fn parse_list(field: &str, list: &str) -> Result<Self> {
+        let field = field.trim();
+        let items = list
+            .trim()
+            .strip_prefix('(')
+            .and_then(|l| l.strip_suffix(')'))
+            .filter(|_| !field.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid condition '{} in {}': expected FIELD in ('a', 'b')", field, list.trim()))?;
+        let values = items
+            .split(',')
+            .map(|item| unquote(item.trim()).to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+
+        Ok(Comparison {
+            field: field.to_string(),
+            op: Op::In,
+            values,
+        })
+    }
+
+    fn matches(&self, row: &Value) -> bool {
+        let actual = lookup(row, &self.field);
+        if self.op == Op::In {
+            return self.values.iter().any(|value| compare(actual, value) == Some(Ordering::Equal));
+        }
+
+        matches!(
+            (compare(actual, &self.values[0]), self.op),
+            (None, Op::Ne)
+                | (Some(Ordering::Less), Op::Lt | Op::Le | Op::Ne)
+                | (Some(Ordering::Greater), Op::Gt | Op::Ge | Op::Ne)
+                | (Some(Ordering::Equal), Op::Le | Op::Ge | Op::Eq)
+        )
+    }
+}
+
+/// How a row's value compares with a literal; `None` when the row lacks it or a number meets text
+fn compare(actual: Option<&Value>, value: &str) -> Option<Ordering> {
+    match actual? {
+        Value::Null => None,
+        Value::Number(n) => n.as_f64()?.partial_cmp(&value.parse::<f64>().ok()?),
+        Value::String(s) => Some(s.as_str().cmp(value)),
+        other => Some(other.to_string().as_str().cmp(value)),
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|&q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+        .unwrap_or(value)
+}
+
+/// Value of a dotted `field` in a row, falling back to the tag of that name
+pub fn lookup<'a>(row: &'a Value, field: &str) -> Option<&'a Value> {
+    field
+        .split('.')
+        .try_fold(row, |value, key| value.get(key))
+        .or_else(|| row.get("tags")?.get(field))
+}
+
+/// Split a tag written as `AXIS:VALUE` or `KEY=VALUE` at its first `:` or `=`
+pub fn parse(tag: &str) -> Result<(String, String)> {
+    tag.split_once([':', '='])
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .with_context(|| format!("'{}' is not a tag; use AXIS:VALUE or KEY=VALUE", tag))
+}
+
+/// Set `key=value` (or remove `key` when `value` is `None`) on every row matching `filter`
+pub fn run(manifest_path: &Path, key: &str, value: Option<&str>, filter: Option<&str>) -> Result<()> {
+    if key.is_empty() {
+        anyhow::bail!("Tag key must not be empty");
+    }
+    let predicate = filter.map(Predicate::parse).transpose()?.unwrap_or_default();
+
+    let mut records = manifest::read(manifest_path)?;
+    let mut changed = 0;
+    for record in &mut records {
+        let row = serde_json::to_value(&*record).context("Failed to serialize manifest record")?;
+        if !predicate.matches(&row) {
+            continue;
+        }
+        let previous = match value {
+            Some(value) => record.tags.insert(key.to_string(), value.to_string()),
+            None => record.tags.remove(key),
+        };
+        if previous.as_deref() != value {
+            changed += 1;
+        }
+    }
+
+    if changed > 0 {
+        manifest::rewrite(manifest_path, &records)?;
+    }
+    if value.is_some() {
+        say!(Tagged, changed, records.len(), manifest_path.display());
+    } else {
+        say!(Untagged, changed, records.len(), manifest_path.display());
+    }
+
+    let seal = manifest_path.with_file_name(SEAL_FILE);
+    if changed > 0 && seal.exists() {
+        say!(SealStale, seal.display());
+    }
+    Ok(())
+}
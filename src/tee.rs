@@ -0,0 +1,340 @@
+//! Extra outputs fed by the same detection pass (`--tee`)
+//!
+//! Each `--tee KIND:PATH[,key=value...]` adds a sink that receives every crop
+//! the run saves, next to the primary output in `--output`:
+//!
+//! - `crops:DIR` writes another set of crops with its own `format`, `size` and
+//!   `quality`, plus a `manifest.jsonl` naming them.
+//! - `tar:FILE.tar.gz` packs crops (again with their own `format`/`size`) and
+//!   their manifest into one archive for handing over.
+//! - `coco:FILE.json` writes only annotations: every source image and the box
+//!   of each saved face, in COCO detection format.
+//!
+//! Sinks never influence which faces are kept, so one pass can serve several
+//! deliverables instead of re-running detection for each.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::crop::{FaceBox, OutputSize};
+use crate::encode::{self, OutputFormat};
+use crate::manifest::{ManifestRecord, MANIFEST_FILE};
+use crate::Args;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Crops,
+    Tar,
+    Coco,
+}
+
+/// One `--tee` argument
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TeeSpec {
+    spec: String,
+    kind: Kind,
+    path: PathBuf,
+    format: Option<OutputFormat>,
+    size: Option<OutputSize>,
+    quality: Option<u8>,
+}
+
+impl FromStr for TeeSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || format!("'{}' is not a tee output (use KIND:PATH[,key=value...] with KIND crops, tar or coco)", s);
+        let (kind, rest) = s.split_once(':').ok_or_else(usage)?;
+        let kind = match kind.trim() {
+            "crops" => Kind::Crops,
+            "tar" => Kind::Tar,
+            "coco" => Kind::Coco,
+            _ => return Err(usage()),
+        };
+        let mut parts = rest.split(',');
+        let path = PathBuf::from(parts.next().map(str::trim).filter(|p| !p.is_empty()).ok_or_else(usage)?);
+        let mut spec = TeeSpec { spec: s.to_string(), kind, path, format: None, size: None, quality: None };
+
+        for option in parts {
+            let (key, value) = option.split_once('=').ok_or_else(|| format!("Tee option '{}' must be key=value", option))?;
+            let value = value.trim();
+            match key.trim() {
+                "format" if kind != Kind::Coco => {
+                    spec.format = Some(<OutputFormat as ValueEnum>::from_str(value, true).map_err(|_| format!("Unknown tee format '{}'", value))?)
+                }
+                "size" if kind != Kind::Coco => spec.size = Some(value.parse()?),
+                "quality" if kind != Kind::Coco => {
+                    spec.quality = Some(value.parse().ok().filter(|q| (1..=100).contains(q)).ok_or("Tee quality must be 1-100")?)
+                }
+                key => return Err(format!("Tee output {} has no option '{}'", kind.name(), key)),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for TeeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+impl TryFrom<String> for TeeSpec {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<TeeSpec> for String {
+    fn from(spec: TeeSpec) -> Self {
+        spec.spec
+    }
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Crops => "crops",
+            Kind::Tar => "tar",
+            Kind::Coco => "coco",
+        }
+    }
+}
+
+/// Face box of a crop resized to fill the given size, or left at full resolution
+pub type FaceBoxFor<'a> = &'a dyn Fn(Option<(u32, u32)>) -> Option<FaceBox>;
+
+trait Sink {
+    /// Take one saved face: its manifest row and the full-resolution crop
+    fn add(&mut self, record: &ManifestRecord, crop: &DynamicImage, face_box: FaceBoxFor) -> Result<()>;
+
+    /// Write whatever is kept until the end of the run
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// The sinks of all `--tee` outputs
+#[derive(Default)]
+pub struct Tee {
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl Tee {
+    pub fn open(args: &Args) -> Result<Self> {
+        let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+        for spec in &args.tee {
+            let encoding = Encoding {
+                format: spec.format.unwrap_or(args.format),
+                size: spec.size.or(args.output_size),
+                jpeg_quality: spec.quality.unwrap_or(args.jpeg_quality),
+                webp_quality: spec.quality.or(args.webp_quality),
+                filter: args.resize_filter.filter_type(),
+            };
+            let sink: Box<dyn Sink> = match spec.kind {
+                Kind::Crops => Box::new(CropsSink::open(&spec.path, encoding)?),
+                Kind::Tar => Box::new(TarSink::open(&spec.path, encoding)?),
+                Kind::Coco => Box::new(CocoSink::new(&spec.path)),
+            };
+            sinks.push(sink);
+        }
+        Ok(Tee { sinks })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    pub fn add(&mut self, record: &ManifestRecord, crop: &DynamicImage, face_box: FaceBoxFor) -> Result<()> {
+        for sink in &mut self.sinks {
+            sink.add(record, crop, face_box)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        for sink in &mut self.sinks {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// How a sink writes its own copy of each crop
+#[derive(Clone, Copy)]
+struct Encoding {
+    format: OutputFormat,
+    size: Option<OutputSize>,
+    jpeg_quality: u8,
+    webp_quality: Option<u8>,
+    filter: FilterType,
+}
+
+impl Encoding {
+    /// The crop's bytes and its row for this sink's manifest
+    fn apply(&self, record: &ManifestRecord, crop: &DynamicImage, face_box: FaceBoxFor) -> Result<(Vec<u8>, ManifestRecord)> {
+        let crop = match self.size {
+            Some(size) => crop.resize_to_fill(size.width, size.height, self.filter),
+            None => crop.clone(),
+        };
+        let bytes = encode::encode(&crop, self.format, self.jpeg_quality, self.webp_quality)?;
+        let name = Path::new(&record.crop).with_extension(self.format.extension());
+        let row = ManifestRecord {
+            crop: name.to_string_lossy().into_owned(),
+            face_box: face_box(self.size.map(|size| (size.width, size.height))),
+            sha256: Some(hex::encode(Sha256::digest(&bytes))),
+            ..record.clone()
+        };
+        Ok((bytes, row))
+    }
+}
+
+struct CropsSink {
+    dir: PathBuf,
+    encoding: Encoding,
+    manifest: BufWriter<File>,
+}
+
+impl CropsSink {
+    fn open(dir: &Path, encoding: Encoding) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create tee output {}", dir.display()))?;
+        let path = dir.join(MANIFEST_FILE);
+        let manifest = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(CropsSink { dir: dir.to_path_buf(), encoding, manifest: BufWriter::new(manifest) })
+    }
+}
+
+impl Sink for CropsSink {
+    fn add(&mut self, record: &ManifestRecord, crop: &DynamicImage, face_box: FaceBoxFor) -> Result<()> {
+        let (bytes, row) = self.encoding.apply(record, crop, face_box)?;
+        let path = self.dir.join(&row.crop);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        encode::write_atomic(&path, &bytes)?;
+        serde_json::to_writer(&mut self.manifest, &row)?;
+        self.manifest.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.manifest.flush().context("Failed to write tee manifest")
+    }
+}
+
+struct TarSink {
+    path: PathBuf,
+    encoding: Encoding,
+    archive: Option<tar::Builder<GzEncoder<BufWriter<File>>>>,
+    manifest: Vec<u8>,
+}
+
+impl TarSink {
+    fn open(path: &Path, encoding: Encoding) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let archive = tar::Builder::new(GzEncoder::new(BufWriter::new(file), Compression::default()));
+        Ok(TarSink { path: path.to_path_buf(), encoding, archive: Some(archive), manifest: Vec::new() })
+    }
+}
+
+impl Sink for TarSink {
+    fn add(&mut self, record: &ManifestRecord, crop: &DynamicImage, face_box: FaceBoxFor) -> Result<()> {
+        let (bytes, row) = self.encoding.apply(record, crop, face_box)?;
+        let archive = self.archive.as_mut().context("Tee archive already closed")?;
+        append(archive, &row.crop, &bytes)?;
+        serde_json::to_writer(&mut self.manifest, &row)?;
+        self.manifest.push(b'\n');
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Some(mut archive) = self.archive.take() else { return Ok(()) };
+        append(&mut archive, MANIFEST_FILE, &self.manifest)?;
+        archive
+            .into_inner()
+            .and_then(|gz| gz.finish())
+            .and_then(|mut file| file.flush())
+            .with_context(|| format!("Failed to finish {}", self.path.display()))
+    }
+}
+
+fn append(archive: &mut tar::Builder<impl Write>, name: &str, bytes: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, name, bytes)
+        .with_context(|| format!("Failed to add {} to the tee archive", name))
+}
+
+/// COCO detection annotations, written at the end of the run
+struct CocoSink {
+    path: PathBuf,
+    images: Vec<serde_json::Value>,
+    image_ids: HashMap<PathBuf, usize>,
+    annotations: Vec<serde_json::Value>,
+}
+
+impl CocoSink {
+    fn new(path: &Path) -> Self {
+        CocoSink { path: path.to_path_buf(), images: Vec::new(), image_ids: HashMap::new(), annotations: Vec::new() }
+    }
+}
+
+impl Sink for CocoSink {
+    fn add(&mut self, record: &ManifestRecord, _crop: &DynamicImage, _face_box: FaceBoxFor) -> Result<()> {
+        let next_id = self.image_ids.len() + 1;
+        let image_id = *self.image_ids.entry(record.source.clone()).or_insert_with(|| {
+            self.images.push(json!({
+                "id": next_id,
+                "file_name": record.source,
+                "width": record.image_width,
+                "height": record.image_height,
+                "frame": record.frame,
+            }));
+            next_id
+        });
+        let bbox = &record.bbox;
+        self.annotations.push(json!({
+            "id": self.annotations.len() + 1,
+            "image_id": image_id,
+            "category_id": 1,
+            "bbox": [bbox.x, bbox.y, bbox.width, bbox.height],
+            "area": bbox.width * bbox.height,
+            "iscrowd": 0,
+            "score": record.score,
+        }));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let coco = json!({
+            "images": self.images,
+            "annotations": self.annotations,
+            "categories": [{ "id": 1, "name": "face" }],
+        });
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let text = serde_json::to_string_pretty(&coco)?;
+        fs::write(&self.path, text).with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
@@ -0,0 +1,107 @@
+//! Per-image detection timeout (`--per-image-timeout`)
+//!
+//! A pathological image can keep a detector busy for hours, which stalls an
+//! unattended run over scraped data. With a timeout, the detector lives on a
+//! helper thread and each image is handed to it; if no answer comes back in
+//! time, the image fails with an error like any unreadable file and the run
+//! goes on. Threads can't be killed, so the stuck helper is left to finish on
+//! its own and a fresh one, with a freshly loaded detector, takes the next
+//! image. Each detector call is timed on its own, so an image that is also
+//! tried rotated or enhanced gets the limit for every attempt.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::detector::{self, Detection, FaceDetectorBackend};
+use crate::Args;
+
+/// Faces found in one image, with the score floor that applies to them
+type Answer = (Result<Vec<Detection>>, f64);
+
+/// Helper thread owning a detector
+struct Helper {
+    images: Sender<DynamicImage>,
+    answers: Receiver<Answer>,
+}
+
+/// Runs the detector loaded from `args` on a helper thread and gives up on images that take longer than `limit`
+pub struct Timed {
+    args: Args,
+    limit: Duration,
+    helper: Option<Helper>,
+    min_score: f64,
+}
+
+impl Timed {
+    pub fn start(args: &Args, seconds: f64) -> Result<Self> {
+        let mut timed = Timed {
+            args: args.clone(),
+            limit: Duration::from_secs_f64(seconds),
+            helper: None,
+            min_score: 0.0,
+        };
+        // Load up front so a broken model fails the run at startup, not on the first image
+        timed.helper = Some(timed.spawn()?);
+        Ok(timed)
+    }
+
+    fn spawn(&self) -> Result<Helper> {
+        let args = self.args.clone();
+        let (image_tx, image_rx) = mpsc::channel::<DynamicImage>();
+        let (answer_tx, answer_rx) = mpsc::channel::<Answer>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+        thread::Builder::new()
+            .name("detector".to_string())
+            .spawn(move || {
+                let mut detector = match detector::load_uncached(&args) {
+                    Ok(detector) => {
+                        let _ = ready_tx.send(Ok(()));
+                        detector
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                // Ends once the sender is dropped, which is also how an abandoned helper finds out
+                for image in image_rx {
+                    let faces = detector.detect(&image);
+                    if answer_tx.send((faces, detector.min_score())).is_err() {
+                        break;
+                    }
+                }
+            })
+            .context("Failed to start detector thread")?;
+        ready_rx.recv().context("Detector thread stopped while loading")??;
+        Ok(Helper { images: image_tx, answers: answer_rx })
+    }
+}
+
+impl FaceDetectorBackend for Timed {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        let helper = match self.helper.take() {
+            Some(helper) => helper,
+            None => self.spawn()?,
+        };
+        helper.images.send(image.clone()).context("Detector thread stopped")?;
+        match helper.answers.recv_timeout(self.limit) {
+            Ok((faces, min_score)) => {
+                self.min_score = min_score;
+                self.helper = Some(helper);
+                faces
+            }
+            // Dropping the helper here leaves its thread to wind down on its own
+            Err(RecvTimeoutError::Timeout) => {
+                anyhow::bail!("Detection took longer than {} s; skipped", self.limit.as_secs_f64())
+            }
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("Detector thread stopped"),
+        }
+    }
+
+    fn min_score(&self) -> f64 {
+        self.min_score
+    }
+}
@@ -0,0 +1,466 @@
+//! Face tracks across video frames and stabilized sequence export
+//!
+//! With `--export-sequences` every video file under `--input` (decoded with
+//! `ffmpeg`) and every folder of frame images is treated as one clip. Faces are
+//! linked frame to frame by box overlap, short detector dropouts are bridged by
+//! interpolation, and each track's box trajectory is smoothed so the crop window
+//! glides instead of jittering. Crops are written as consecutive frames into one
+//! folder per track, all at the same size. `--export-clips` cuts video clips
+//! from the same tracks (see `clips`). Each video is decoded to frames only
+//! when its turn comes, and the frames are removed once it is tracked, so
+//! temporary space never holds more than one video's frames.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::Ordering;
+use walkdir::WalkDir;
+
+use crate::clips::ClipExporter;
+use crate::custody;
+use crate::encode;
+use crate::detector::BBox;
+use crate::events::Event;
+use crate::manifest::{self, ManifestRecord, Provenance};
+use crate::{collect_images, decode, filter_valid_faces, say, say_err, shutdown, BatchStats, Extractor};
+
+/// Subdirectory of the output directory holding one folder per track
+pub const SEQUENCES_DIR: &str = "sequences";
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "mkv", "avi", "webm", "m4v"];
+
+/// Minimum box overlap for a detection to continue an existing track
+const MIN_IOU: f32 = 0.3;
+
+/// Frames a track may go undetected before it is closed
+const MAX_MISSES: usize = 5;
+
+/// Tracks shorter than this are treated as detector noise and not exported
+const MIN_TRACK_FRAMES: usize = 8;
+
+/// Half-width (in frames) of the moving average applied to the box trajectory
+const SMOOTH_RADIUS: usize = 4;
+
+/// Crop side relative to the face box side, matching the standard padding
+const CONTEXT: f32 = 1.25;
+
+/// One clip: an ordered list of frames from a video or a frame folder
+pub struct Sequence {
+    pub name: String,
+    /// The video file, or the folder the frames came from
+    pub source: PathBuf,
+    pub frames: Vec<PathBuf>,
+    pub fps: Option<f64>,
+    /// Temporary directory holding frames decoded from a video
+    scratch: Option<PathBuf>,
+}
+
+impl Drop for Sequence {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.scratch {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// A clip found under `--input`; a video is decoded into a [`Sequence`] only when it is tracked
+pub enum Clip {
+    Frames(Sequence),
+    Video { path: PathBuf, frames: usize },
+}
+
+impl Clip {
+    fn frames(&self) -> usize {
+        match self {
+            Clip::Frames(sequence) => sequence.frames.len(),
+            Clip::Video { frames, .. } => *frames,
+        }
+    }
+}
+
+/// Square face window as center and side, the form that gets smoothed
+#[derive(Debug, Clone, Copy)]
+pub struct TrackBox {
+    pub cx: f32,
+    pub cy: f32,
+    pub side: f32,
+}
+
+impl TrackBox {
+    fn from_bbox(b: &BBox) -> Self {
+        TrackBox {
+            cx: b.x as f32 + b.width as f32 / 2.0,
+            cy: b.y as f32 + b.height as f32 / 2.0,
+            side: b.width.max(b.height) as f32,
+        }
+    }
+
+    fn lerp(a: TrackBox, b: TrackBox, t: f32) -> Self {
+        TrackBox {
+            cx: a.cx + (b.cx - a.cx) * t,
+            cy: a.cy + (b.cy - a.cy) * t,
+            side: a.side + (b.side - a.side) * t,
+        }
+    }
+}
+
+/// A face followed across frames
+#[derive(Debug)]
+pub struct Track {
+    pub id: usize,
+    /// Detector boxes and scores by frame index, in frame order
+    pub observations: Vec<(usize, BBox, f64)>,
+}
+
+impl Track {
+    pub fn first_frame(&self) -> usize {
+        self.observations[0].0
+    }
+
+    pub fn last_frame(&self) -> usize {
+        self.observations[self.observations.len() - 1].0
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        self.observations.iter().map(|o| o.2).sum::<f64>() / self.observations.len() as f64
+    }
+
+    /// One smoothed box per frame from first to last detection, gaps interpolated
+    pub fn stabilized(&self) -> Vec<(usize, TrackBox)> {
+        let mut dense = Vec::with_capacity(self.last_frame() - self.first_frame() + 1);
+        for pair in self.observations.windows(2) {
+            let (f0, b0) = (pair[0].0, TrackBox::from_bbox(&pair[0].1));
+            let (f1, b1) = (pair[1].0, TrackBox::from_bbox(&pair[1].1));
+            for f in f0..f1 {
+                dense.push((f, TrackBox::lerp(b0, b1, (f - f0) as f32 / (f1 - f0) as f32)));
+            }
+        }
+        let last = &self.observations[self.observations.len() - 1];
+        dense.push((last.0, TrackBox::from_bbox(&last.1)));
+
+        // Centered moving average, window shrinking at the ends
+        (0..dense.len())
+            .map(|i| {
+                let lo = i.saturating_sub(SMOOTH_RADIUS);
+                let hi = (i + SMOOTH_RADIUS + 1).min(dense.len());
+                let n = (hi - lo) as f32;
+                let window = &dense[lo..hi];
+                let smoothed = TrackBox {
+                    cx: window.iter().map(|(_, b)| b.cx).sum::<f32>() / n,
+                    cy: window.iter().map(|(_, b)| b.cy).sum::<f32>() / n,
+                    side: window.iter().map(|(_, b)| b.side).sum::<f32>() / n,
+                };
+                (dense[i].0, smoothed)
+            })
+            .collect()
+    }
+}
+
+/// Track faces in every clip under `--input` and export one crop folder per track
+pub fn run(ex: &mut Extractor) -> Result<BatchStats> {
+    let args = ex.args;
+    let found = collect_clips(&args.input);
+    let total_frames = found.iter().map(Clip::frames).sum();
+    say!(SequencesFound, found.len(), total_frames);
+
+    ex.events.emit(Event::RunStarted {
+        total_images: total_frames,
+        target_faces: args.target_faces,
+    });
+
+    let mut clips = args
+        .export_clips
+        .map(|length| ClipExporter::open(&args.output, length))
+        .transpose()?;
+    if clips.is_some() && found.iter().any(|clip| matches!(clip, Clip::Frames(_))) {
+        say!(ClipsVideoOnly);
+    }
+
+    let mut stats = BatchStats::default();
+    let mut next_track = 1;
+    for clip in found {
+        if shutdown::requested() {
+            stats.interrupted = true;
+            break;
+        }
+        if ex.face_counter.load(Ordering::Relaxed) >= args.target_faces {
+            say!(TargetReached, ex.face_counter.load(Ordering::Relaxed));
+            stats.target_reached = true;
+            break;
+        }
+
+        // Dropped at the end of the iteration, which removes a video's frames before the next is decoded
+        let sequence = match clip {
+            Clip::Frames(sequence) => sequence,
+            Clip::Video { path, .. } => decode_video(&path)?,
+        };
+        match sequence.fps {
+            Some(fps) => say!(SequenceFps, sequence.name, sequence.frames.len(), format!("{:.2}", fps)),
+            None => say!(Sequence, sequence.name, sequence.frames.len()),
+        }
+        let tracks = track_sequence(ex, &sequence, &mut next_track, &mut stats);
+        for track in &tracks {
+            let crops = if args.export_sequences { export_track(ex, &sequence, track)? } else { 0 };
+            let cut = match &mut clips {
+                Some(clips) => clips.export(&sequence, track)?,
+                None => 0,
+            };
+            stats.extracted += crops;
+            say!(
+                TrackDone,
+                track.id,
+                track.first_frame(),
+                track.last_frame(),
+                crops,
+                cut
+            );
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Every video file, plus one sequence per folder that directly contains images
+pub fn collect_clips(input: &Path) -> Vec<Clip> {
+    let mut folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for frame in collect_images(input) {
+        let folder = frame.parent().unwrap_or(input).to_path_buf();
+        folders.entry(folder).or_default().push(frame);
+    }
+
+    let mut clips: Vec<Clip> = folders
+        .into_iter()
+        .map(|(folder, mut frames)| {
+            frames.sort();
+            Clip::Frames(Sequence {
+                name: display_name(&folder),
+                source: folder,
+                frames,
+                fps: None,
+                scratch: None,
+            })
+        })
+        .collect();
+
+    let mut videos: Vec<PathBuf> = WalkDir::new(input)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .collect();
+    videos.sort();
+    for video in videos {
+        let frames = count_frames(&video).unwrap_or(0);
+        clips.push(Clip::Video { path: video, frames });
+    }
+
+    clips
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_stem()
+        .or_else(|| path.file_name())
+        .and_then(|s| s.to_str())
+        .unwrap_or("clip")
+        .to_string()
+}
+
+/// Split a video into PNG frames in a temporary directory using `ffmpeg`
+fn decode_video(video: &Path) -> Result<Sequence> {
+    let name = display_name(video);
+    // Videos in different folders or with different extensions may share a stem
+    let hash = hex::encode(&Sha256::digest(video.to_string_lossy().as_bytes())[..8]);
+    let scratch = std::env::temp_dir().join(format!("face_dataset_generator_{}_{}_{}", std::process::id(), name, hash));
+    fs::create_dir_all(&scratch).context("Failed to create frame directory")?;
+
+    let status = Command::new("ffmpeg")
+        .args(["-v", "error", "-nostdin", "-i"])
+        .arg(video)
+        .args(["-vsync", "0"])
+        .arg(scratch.join("%06d.png"))
+        .status()
+        .context("Failed to run ffmpeg (it must be installed to read video files)")?;
+    if !status.success() {
+        let _ = fs::remove_dir_all(&scratch);
+        anyhow::bail!("ffmpeg could not decode {}", video.display());
+    }
+
+    let mut frames = collect_images(&scratch);
+    frames.sort();
+    Ok(Sequence {
+        name,
+        source: video.to_path_buf(),
+        frames,
+        fps: probe_fps(video),
+        scratch: Some(scratch),
+    })
+}
+
+/// Frames in the first video stream, counted by `ffprobe` without decoding them
+fn count_frames(video: &Path) -> Option<usize> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0", "-count_packets", "-show_entries", "stream=nb_read_packets", "-of", "csv=p=0"])
+        .arg(video)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Frame rate of the first video stream according to `ffprobe`
+fn probe_fps(video: &Path) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0", "-show_entries", "stream=r_frame_rate", "-of", "csv=p=0"])
+        .arg(video)
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (num, den) = text.trim().split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (den > 0.0).then(|| num / den)
+}
+
+/// Link detections frame to frame by greatest overlap, greedily
+fn track_sequence(ex: &mut Extractor, sequence: &Sequence, next_id: &mut usize, stats: &mut BatchStats) -> Vec<Track> {
+    let mut active: Vec<Track> = Vec::new();
+    let mut finished: Vec<Track> = Vec::new();
+
+    for (frame, path) in sequence.frames.iter().enumerate() {
+        let detections = match detect_frame(ex, path) {
+            Ok(detections) => detections,
+            Err(e) => {
+                stats.errors += 1;
+                say_err!(FrameError, frame, e);
+                continue;
+            }
+        };
+        stats.processed += 1;
+
+        let (stale, live): (Vec<Track>, Vec<Track>) = active
+            .into_iter()
+            .partition(|t| frame - t.last_frame() > MAX_MISSES);
+        finished.extend(stale);
+        active = live;
+
+        let mut pairs = Vec::new();
+        for (ti, track) in active.iter().enumerate() {
+            let last = &track.observations[track.observations.len() - 1].1;
+            for (di, (bbox, _)) in detections.iter().enumerate() {
+                let overlap = last.iou(bbox);
+                if overlap >= MIN_IOU {
+                    pairs.push((overlap, ti, di));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut track_taken = vec![false; active.len()];
+        let mut detection_taken = vec![false; detections.len()];
+        for (_, ti, di) in pairs {
+            if track_taken[ti] || detection_taken[di] {
+                continue;
+            }
+            track_taken[ti] = true;
+            detection_taken[di] = true;
+            let (bbox, score) = detections[di];
+            active[ti].observations.push((frame, bbox, score));
+        }
+
+        for (di, &(bbox, score)) in detections.iter().enumerate() {
+            if !detection_taken[di] {
+                active.push(Track {
+                    id: *next_id,
+                    observations: vec![(frame, bbox, score)],
+                });
+                *next_id += 1;
+            }
+        }
+    }
+
+    finished.extend(active);
+    finished.retain(|t| t.last_frame() - t.first_frame() + 1 >= MIN_TRACK_FRAMES);
+    finished.sort_by_key(|t| t.id);
+    finished
+}
+
+fn detect_frame(ex: &mut Extractor, path: &Path) -> Result<Vec<(BBox, f64)>> {
+    let image = decode::open(path, ex.args)?;
+    let detector = ex.detector()?;
+    let faces = detector.detect(&image)?;
+    let min_score = detector.min_score();
+    Ok(filter_valid_faces(ex.args, &faces, &image, min_score)
+        .into_iter()
+        .map(|f| (f.bbox, f.score))
+        .collect())
+}
+
+/// Crop the stabilized window from every frame of `track`, all scaled to one size
+fn export_track(ex: &mut Extractor, sequence: &Sequence, track: &Track) -> Result<usize> {
+    let args = ex.args;
+    let boxes = track.stabilized();
+
+    let mut sides: Vec<f32> = boxes.iter().map(|(_, b)| b.side * CONTEXT).collect();
+    sides.sort_by(|a, b| a.total_cmp(b));
+    let out_side = sides[sides.len() / 2].round().max(1.0) as u32;
+
+    let folder = format!("{}_track_{:04}", sequence.name, track.id);
+    let dir = args.output.join(SEQUENCES_DIR).join(&folder);
+    fs::create_dir_all(&dir).context("Failed to create track directory")?;
+
+    let score = track.mean_score();
+    let mut written = 0;
+    for (frame, window) in boxes {
+        let image = decode::open(&sequence.frames[frame], args)?;
+        let (width, height) = (image.width() as f32, image.height() as f32);
+
+        // Shift rather than shrink at the frame edges so every crop stays square
+        let side = (window.side * CONTEXT).min(width).min(height);
+        let x = (window.cx - side / 2.0).clamp(0.0, width - side);
+        let y = (window.cy - side / 2.0).clamp(0.0, height - side);
+        let crop = image
+            .crop_imm(x as u32, y as u32, side as u32, side as u32)
+            .resize_exact(out_side, out_side, args.resize_filter.filter_type());
+
+        let name = format!("{:06}.{}", frame, args.format.extension());
+        encode::save(&crop, &dir.join(&name), args, None).context("Failed to save track frame")?;
+        let sha256 = custody::file_sha256(&dir.join(&name))?;
+
+        let detection_id = ex.face_counter.fetch_add(1, Ordering::Relaxed) + 1;
+        let record = ManifestRecord {
+            crop: format!("{}/{}/{}", SEQUENCES_DIR, folder, name),
+            source: sequence.source.clone(),
+            detection_id,
+            bbox: BBox {
+                x: (window.cx - window.side / 2.0) as i32,
+                y: (window.cy - window.side / 2.0) as i32,
+                width: window.side as u32,
+                height: window.side as u32,
+            },
+            score,
+            image_width: Some(width as u32),
+            image_height: Some(height as u32),
+            track: Some(track.id),
+            frame: Some(frame),
+            sha256: Some(sha256),
+            ..Default::default()
+        };
+        if args.sidecars {
+            let mut provenance = Provenance::new(args);
+            provenance.preprocessing = vec![
+                "stabilized track window".to_string(),
+                format!("resized to {}x{}", out_side, out_side),
+            ];
+            manifest::write_sidecar(&args.output, &record, &provenance)?;
+        }
+        ex.manifest.append(&record)?;
+        written += 1;
+    }
+
+    Ok(written)
+}
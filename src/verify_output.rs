@@ -0,0 +1,127 @@
+//! `verify-output` subcommand: find and remove truncated files in --output
+//!
+//! Crops are written through a `.tmp` file and renamed, but output from older
+//! versions, or a machine that lost power mid-write, can still hold leftover
+//! `.tmp` files and images cut short. Every JPEG, PNG and WebP file is checked
+//! for the marker its format ends with (or, for WebP, the length its header
+//! announces), which catches truncation without decoding anything. Broken
+//! files are removed with their sidecars; `--dry-run` only lists them.
+//! Downloaded inputs are left alone.
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::encode::PARTIAL_SUFFIX;
+use crate::manifest::{self, MANIFEST_FILE};
+use crate::i18n::{self, Msg};
+use crate::remote::DOWNLOADS_DIR;
+use crate::{say, say_err};
+
+const PNG_END: [u8; 12] = [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82];
+
+pub fn run(output_dir: &Path, dry_run: bool) -> Result<()> {
+    let downloads = output_dir.join(DOWNLOADS_DIR);
+    let mut checked = 0;
+    let mut broken: Vec<(PathBuf, Msg)> = Vec::new();
+    for entry in WalkDir::new(output_dir).into_iter().filter_entry(|e| e.path() != downloads) {
+        let entry = entry.with_context(|| format!("Failed to scan {}", output_dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.into_path();
+        if path.to_string_lossy().ends_with(PARTIAL_SUFFIX) {
+            broken.push((path, Msg::OutputUnfinished));
+            continue;
+        }
+        let Some(complete) = check(&path) else { continue };
+        checked += 1;
+        match complete {
+            Ok(true) => {}
+            Ok(false) => broken.push((path, Msg::OutputTruncated)),
+            Err(e) => {
+                say_err!(OutputUncheckable, path.display(), format!("{:#}", e));
+            }
+        }
+    }
+
+    for (path, reason) in &broken {
+        say!(OutputBroken, path.display(), i18n::text(*reason, &[]));
+    }
+    if dry_run {
+        say!(OutputWouldRemove, broken.len(), checked);
+        return Ok(());
+    }
+    if broken.is_empty() {
+        say!(OutputComplete, checked, output_dir.display());
+        return Ok(());
+    }
+    for (path, _) in &broken {
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        let sidecar = path.with_extension("json");
+        if sidecar.exists() {
+            fs::remove_file(&sidecar).with_context(|| format!("Failed to remove {}", sidecar.display()))?;
+        }
+    }
+    say!(OutputRemoved, broken.len(), checked);
+
+    // Rows are only written after their crop, so a listed crop that was cut short points at damage from elsewhere
+    let manifest_path = output_dir.join(MANIFEST_FILE);
+    if manifest_path.exists() {
+        let removed: HashSet<&Path> = broken.iter().map(|(path, _)| path.as_path()).collect();
+        let listed = manifest::read(&manifest_path)?
+            .iter()
+            .filter(|record| removed.contains(output_dir.join(&record.crop).as_path()))
+            .count();
+        if listed > 0 {
+            say!(OutputRowsOrphaned, listed);
+        }
+    }
+    Ok(())
+}
+
+/// Whether an image file ends where its format says it should; None for files that aren't images
+fn check(path: &Path) -> Option<Result<bool>> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let ends = match extension.as_str() {
+        "jpg" | "jpeg" => jpeg_complete,
+        "png" => png_complete,
+        "webp" => webp_complete,
+        _ => return None,
+    };
+    Some(File::open(path).map_err(anyhow::Error::from).and_then(|mut file| ends(&mut file)))
+}
+
+/// The last `n` bytes of `file` (fewer if it is shorter)
+fn tail(file: &mut File, n: u64) -> Result<Vec<u8>> {
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(n)))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A JPEG ends with the EOI marker, possibly followed by padding
+fn jpeg_complete(file: &mut File) -> Result<bool> {
+    let tail = tail(file, 64)?;
+    let end = tail.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    Ok(tail[..end].ends_with(&[0xFF, 0xD9]))
+}
+
+/// A PNG ends with its IEND chunk
+fn png_complete(file: &mut File) -> Result<bool> {
+    Ok(tail(file, PNG_END.len() as u64)? == PNG_END)
+}
+
+/// A WebP file is exactly as long as its RIFF header says
+fn webp_complete(file: &mut File) -> Result<bool> {
+    let mut header = [0u8; 8];
+    if file.read_exact(&mut header).is_err() || &header[..4] != b"RIFF" {
+        return Ok(false);
+    }
+    let announced = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64 + 8;
+    Ok(file.metadata()?.len() >= announced)
+}
@@ -0,0 +1,71 @@
+//! `visualize` subcommand: every detection drawn onto copies of the inputs
+//!
+//! The batch counterpart of `detect-one --save`. Each input image is decoded
+//! and detected as a run would with the same flags, and a copy is written with
+//! accepted faces boxed in green (labelled with their score) and rejected ones
+//! in red, labelled with the filters they failed (`score`, `coverage`,
+//! `aspect`, `size`, `exposure`, `pose`). Images without any detection are
+//! copied too, so "no faces found" can be told apart from "faces filtered out".
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::detect_one::{all_checks, annotate};
+use crate::detector::{self, BBox};
+use crate::{annotations, collect_images, decode_image, detect_image, say, say_err, Args};
+
+pub fn run(args: &Args, to: &Path) -> Result<()> {
+    let paths = collect_images(&args.input);
+    if paths.is_empty() {
+        anyhow::bail!("No images found in {}", args.input.display());
+    }
+    let mut detector = detector::load(args)?;
+    fs::create_dir_all(to).with_context(|| format!("Failed to create {}", to.display()))?;
+
+    let (mut kept, mut rejected) = (0, 0);
+    let mut reasons: BTreeMap<&str, usize> = BTreeMap::new();
+    for source in &paths {
+        let detected = match decode_image(args, source).and_then(|decoded| detect_image(args, detector.as_mut(), decoded)) {
+            Ok(detected) => detected,
+            Err(e) => {
+                say_err!(VisualizeFailed, source.display(), format!("{:#}", e));
+                continue;
+            }
+        };
+        let image = &detected.image;
+        let gray = image.to_luma8();
+
+        let mut labels: Vec<(BBox, bool, String)> = Vec::new();
+        for face in &detected.faces {
+            let failed: Vec<&str> = all_checks(args, face, image, &gray, detected.min_score)
+                .into_iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.reason)
+                .collect();
+            let label = if failed.is_empty() {
+                kept += 1;
+                format!("{:.1}", face.score)
+            } else {
+                rejected += 1;
+                for reason in &failed {
+                    *reasons.entry(reason).or_default() += 1;
+                }
+                failed.join(",")
+            };
+            labels.push((face.bbox, failed.is_empty(), label));
+        }
+
+        let target = to.join(format!("{}.jpg", annotations::flat_name(&args.input, source)));
+        annotate(image, &labels)
+            .save(&target)
+            .with_context(|| format!("Failed to save {}", target.display()))?;
+    }
+
+    say!(VisualizeDone, paths.len(), to.display(), kept, rejected);
+    for (reason, count) in &reasons {
+        say!(VisualizeReason, reason, count);
+    }
+    Ok(())
+}
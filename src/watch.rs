@@ -0,0 +1,89 @@
+//! Hot-folder ingestion (`--watch`)
+//!
+//! After the images already under `--input` are processed, the directory is
+//! watched with `notify` and every new image is cropped as soon as it has
+//! settled, i.e. no write touched it for `SETTLE`, so half-copied files are
+//! not decoded. Crops and manifest rows are written as each image finishes,
+//! and the checkpoint records every processed file, so a stopped watch can be
+//! picked up again with `--resume --watch`. Watching ends when
+//! `--target-faces` is reached or the process is interrupted.
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::{decoders, process_image, record_outcome, say, should_stop, shutdown, yield_to_load, BatchStats, Extractor};
+
+/// Quiet time after the last write before a new file is read
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// How often pending files are checked when no events arrive
+const POLL: Duration = Duration::from_millis(250);
+
+/// Process images appearing under `--input` until the target is reached; `known` were handled already
+pub fn run(ex: &mut Extractor, stats: &mut BatchStats, known: &[PathBuf]) -> Result<()> {
+    if ex.face_counter.load(Ordering::Relaxed) >= ex.args.target_faces {
+        return Ok(());
+    }
+    let input = &ex.args.input;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start the file watcher")?;
+    watcher
+        .watch(input, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", input.display()))?;
+    say!(Watching, input.display());
+
+    // Crops written below a watched input must not be picked up as new images
+    let output = fs::canonicalize(&ex.args.output).unwrap_or_else(|_| ex.args.output.clone());
+
+    let mut seen: HashSet<PathBuf> = known.iter().cloned().collect();
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    let mut index = stats.processed + stats.errors;
+    loop {
+        if shutdown::requested() {
+            stats.interrupted = true;
+            break;
+        }
+        match rx.recv_timeout(POLL) {
+            Ok(Ok(event)) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths.into_iter().filter(|path| !seen.contains(path)) {
+                    pending.insert(path, Instant::now());
+                }
+            }
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(e)) => eprintln!("⚠️  Watch error: {}", e),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, last_write)| last_write.elapsed() >= SETTLE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready.sort();
+        for path in ready {
+            pending.remove(&path);
+            let in_output = fs::canonicalize(&path).is_ok_and(|path| path.starts_with(&output));
+            let recognized = path.is_file() && decoders::registry().read().unwrap_or_else(|e| e.into_inner()).recognizes(&path);
+            if in_output || !recognized {
+                continue;
+            }
+            seen.insert(path.clone());
+
+            yield_to_load(ex);
+            say!(NewImage, index + 1, path.display());
+            let result = process_image(ex, &path);
+            record_outcome(ex, stats, index, &path, result);
+            index += 1;
+            if should_stop(ex, stats) {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
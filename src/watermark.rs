@@ -0,0 +1,175 @@
+//! Visible watermarks for partner-shared sample crops
+//!
+//! Text is rendered with a built-in 5x7 bitmap font (uppercase ASCII, digits and
+//! common punctuation) so no font file has to ship with the binary. The text is
+//! drawn white with a dark outline on a translucent band across the lower third,
+//! wrapping onto several lines when the crop is narrow.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+const GLYPH_W: u32 = 5;
+const GLYPH_H: u32 = 7;
+
+/// Bitmap rows, most significant of the low 5 bits is the leftmost pixel
+const FONT: &[(char, [u8; 7])] = &[
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (' ', [0, 0, 0, 0, 0, 0, 0]),
+    ('-', [0, 0, 0, 0b11111, 0, 0, 0]),
+    ('_', [0, 0, 0, 0, 0, 0, 0b11111]),
+    ('.', [0, 0, 0, 0, 0, 0b01100, 0b01100]),
+    (',', [0, 0, 0, 0, 0b01100, 0b00100, 0b01000]),
+    (':', [0, 0b01100, 0b01100, 0, 0b01100, 0b01100, 0]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0, 0b00100]),
+    ('/', [0, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('\'', [0b00100, 0b00100, 0b01000, 0, 0, 0, 0]),
+    ('&', [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101]),
+];
+
+fn glyph(c: char) -> [u8; 7] {
+    let c = c.to_ascii_uppercase();
+    FONT.iter()
+        .find(|(g, _)| *g == c)
+        .or_else(|| FONT.iter().find(|(g, _)| *g == '?'))
+        .map(|(_, rows)| *rows)
+        .unwrap_or_default()
+}
+
+/// Stamp `text` onto a copy of `image`
+pub fn apply(image: &DynamicImage, text: &str) -> DynamicImage {
+    let mut canvas = image.to_rgba8();
+    let (width, height) = canvas.dimensions();
+
+    // Largest integer scale where at least 8 characters fit per line, never below 1
+    let advance = GLYPH_W + 1;
+    let scale = (width / (advance * 8 + 2)).clamp(1, 4);
+    let chars_per_line = ((width.saturating_sub(2 * scale)) / (advance * scale)).max(1) as usize;
+    let lines = wrap(text, chars_per_line);
+
+    let line_h = (GLYPH_H + 2) * scale;
+    let block_h = line_h * lines.len() as u32 + scale;
+    let top = height.saturating_sub(block_h + height / 10);
+
+    darken_band(&mut canvas, top, block_h);
+    for (i, line) in lines.iter().enumerate() {
+        let line_w = line.chars().count() as u32 * advance * scale;
+        let x = width.saturating_sub(line_w) / 2;
+        let y = top + scale + i as u32 * line_h;
+        draw_line(&mut canvas, line, x, y, scale);
+    }
+
+    // Samples are shared as JPEG, which has no alpha channel
+    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+}
+
+/// Greedy word wrap; words longer than a line are hard-split
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        while word.chars().count() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            let head: String = word.chars().take(width).collect();
+            word = word.chars().skip(width).collect();
+            lines.push(head);
+        }
+        if current.is_empty() {
+            current = word;
+        } else if current.chars().count() + 1 + word.chars().count() <= width {
+            current.push(' ');
+            current.push_str(&word);
+        } else {
+            lines.push(std::mem::replace(&mut current, word));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn darken_band(canvas: &mut RgbaImage, top: u32, band_h: u32) {
+    let bottom = (top + band_h).min(canvas.height());
+    for y in top..bottom {
+        for x in 0..canvas.width() {
+            let p = canvas.get_pixel_mut(x, y);
+            for c in 0..3 {
+                p[c] = (p[c] as u16 * 2 / 5) as u8;
+            }
+        }
+    }
+}
+
+/// Draw `line` white on a dark outline with its top-left corner at (`x`, `y`)
+pub fn draw_line(canvas: &mut RgbaImage, line: &str, x: u32, y: u32, scale: u32) {
+    let white = Rgba([255, 255, 255, 255]);
+    let dark = Rgba([0, 0, 0, 255]);
+    for (i, c) in line.chars().enumerate() {
+        let gx = x + i as u32 * (GLYPH_W + 1) * scale;
+        let rows = glyph(c);
+        // Outline pass first, then the glyph itself on top
+        for (color, offset) in [(dark, 1i32), (white, 0)] {
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..GLYPH_W {
+                    if bits & (1 << (GLYPH_W - 1 - col)) == 0 {
+                        continue;
+                    }
+                    let px = (gx + col * scale) as i32;
+                    let py = (y + row as u32 * scale) as i32;
+                    fill(canvas, px - offset, py - offset, scale + 2 * offset as u32, color);
+                }
+            }
+        }
+    }
+}
+
+fn fill(canvas: &mut RgbaImage, x: i32, y: i32, size: u32, color: Rgba<u8>) {
+    for dy in 0..size as i32 {
+        for dx in 0..size as i32 {
+            let (px, py) = (x + dx, y + dy);
+            if px >= 0 && py >= 0 && (px as u32) < canvas.width() && (py as u32) < canvas.height() {
+                canvas.put_pixel(px as u32, py as u32, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Native YOLOv8-face inference through `tract` (`--backend yolov8`)
+//!
+//! Frames are letterboxed to the network's 640x640 input, the raw prediction
+//! grid is decoded into boxes (and, for face exports, five keypoints) in
+//! source-image coordinates, and overlapping boxes are merged with greedy
+//! non-maximum suppression.
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use std::path::{Path, PathBuf};
+use tract_onnx::prelude::*;
+
+use crate::detector::{BBox, Detection, FaceDetectorBackend, Landmarks};
+use crate::landmarks::Point;
+use crate::{model, Args};
+
+/// Square network input side
+const INPUT_SIDE: u32 = 640;
+
+/// Padding value used by the Ultralytics letterbox
+const LETTERBOX_FILL: f32 = 114.0 / 255.0;
+
+/// Face exports append five (x, y, visibility) keypoints after box and confidence
+const KEYPOINTS_START: usize = 5;
+const KEYPOINT_ATTRS: usize = KEYPOINTS_START + 5 * 3;
+
+/// Boxes overlapping a stronger box by more than this are suppressed
+const NMS_IOU: f32 = 0.45;
+
+/// Where `ensure_yolo_model` keeps the downloaded network
+const MODEL_DIR: &str = "./models";
+
+pub struct YoloV8 {
+    model: TypedRunnableModel<TypedModel>,
+    confidence: f64,
+    min_face_size: u32,
+}
+
+impl YoloV8 {
+    /// Load `--model` if it is an `.onnx` file, otherwise the downloaded `yolov8n-face.onnx`
+    pub fn load(args: &Args) -> Result<Self> {
+        let path: PathBuf = if args.model.extension().is_some_and(|e| e == "onnx") {
+            model::verify_checksum(&args.model, args.skip_checksum)?;
+            args.model.clone()
+        } else {
+            model::ensure_yolo_model(Path::new(MODEL_DIR), args.skip_checksum)?
+        };
+
+        let model = tract_onnx::onnx()
+            .model_for_path(&path)
+            .and_then(|m| m.with_input_fact(0, f32::fact([1, 3, INPUT_SIDE as usize, INPUT_SIDE as usize]).into()))
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| format!("Failed to load YOLOv8 model {}", path.display()))?;
+
+        Ok(YoloV8 {
+            model,
+            confidence: args.yolo_confidence,
+            min_face_size: args.min_face_size,
+        })
+    }
+}
+
+impl FaceDetectorBackend for YoloV8 {
+    fn detect(&mut self, image: &DynamicImage) -> Result<Vec<Detection>> {
+        let (width, height) = image.dimensions();
+        let letterbox = Letterbox::new(width, height);
+        let input = letterbox.tensor(image);
+
+        let outputs = self
+            .model
+            .run(tvec!(input.into()))
+            .map_err(|e| anyhow::anyhow!("YOLOv8 inference failed: {}", e))?;
+        let predictions = outputs[0]
+            .to_array_view::<f32>()
+            .map_err(|e| anyhow::anyhow!("Unexpected YOLOv8 output: {}", e))?;
+        let shape = predictions.shape().to_vec();
+        anyhow::ensure!(shape.len() == 3 && shape[0] == 1, "Unexpected YOLOv8 output shape {:?}", shape);
+
+        // Exports disagree on whether predictions are [1, attrs, anchors] or [1, anchors, attrs]
+        let attrs_first = shape[1] < shape[2];
+        let (attrs, anchors) = if attrs_first { (shape[1], shape[2]) } else { (shape[2], shape[1]) };
+        anyhow::ensure!(attrs >= 5, "YOLOv8 output has only {} values per anchor", attrs);
+        let at = |attr: usize, anchor: usize| {
+            if attrs_first {
+                predictions[[0, attr, anchor]]
+            } else {
+                predictions[[0, anchor, attr]]
+            }
+        };
+
+        let mut candidates = Vec::new();
+        for anchor in 0..anchors {
+            let confidence = at(4, anchor) as f64;
+            if confidence < self.confidence {
+                continue;
+            }
+            let (cx, cy, w, h) = (at(0, anchor), at(1, anchor), at(2, anchor), at(3, anchor));
+            let bbox = letterbox.to_source(cx - w / 2.0, cy - h / 2.0, w, h);
+            if bbox.width < self.min_face_size || bbox.height < self.min_face_size {
+                continue;
+            }
+            let landmarks = (attrs >= KEYPOINT_ATTRS).then(|| {
+                let point = |k: usize| {
+                    let offset = KEYPOINTS_START + 3 * k;
+                    let (x, y) = letterbox.point_to_source(at(offset, anchor), at(offset + 1, anchor));
+                    Point { x, y }
+                };
+                let (mut left_eye, mut right_eye) = (point(0), point(1));
+                if left_eye.x > right_eye.x {
+                    std::mem::swap(&mut left_eye, &mut right_eye);
+                }
+                Landmarks {
+                    left_eye,
+                    right_eye,
+                    nose: point(2),
+                    mouth_left: point(3),
+                    mouth_right: point(4),
+                }
+            });
+            candidates.push(Detection {
+                bbox,
+                score: confidence,
+                confidence,
+                landmarks,
+            });
+        }
+
+        Ok(non_max_suppression(candidates))
+    }
+
+    fn min_score(&self) -> f64 {
+        self.confidence
+    }
+}
+
+/// Scale-and-pad transform between the source image and the network input
+struct Letterbox {
+    scale: f32,
+    pad_x: f32,
+    pad_y: f32,
+    width: u32,
+    height: u32,
+}
+
+impl Letterbox {
+    fn new(width: u32, height: u32) -> Self {
+        let scale = (INPUT_SIDE as f32 / width as f32).min(INPUT_SIDE as f32 / height as f32);
+        let scaled_w = (width as f32 * scale).round();
+        let scaled_h = (height as f32 * scale).round();
+        Letterbox {
+            scale,
+            pad_x: ((INPUT_SIDE as f32 - scaled_w) / 2.0).floor(),
+            pad_y: ((INPUT_SIDE as f32 - scaled_h) / 2.0).floor(),
+            width,
+            height,
+        }
+    }
+
+    /// NCHW float tensor in 0-1 with the image centered on a gray canvas
+    fn tensor(&self, image: &DynamicImage) -> Tensor {
+        let scaled_w = ((self.width as f32 * self.scale).round() as u32).max(1);
+        let scaled_h = ((self.height as f32 * self.scale).round() as u32).max(1);
+        let resized = image.resize_exact(scaled_w, scaled_h, FilterType::Triangle).to_rgb8();
+        let (pad_x, pad_y) = (self.pad_x as u32, self.pad_y as u32);
+
+        let side = INPUT_SIDE as usize;
+        tract_ndarray::Array4::from_shape_fn((1, 3, side, side), |(_, c, y, x)| {
+            let (x, y) = (x as u32, y as u32);
+            if x < pad_x || y < pad_y || x >= pad_x + scaled_w || y >= pad_y + scaled_h {
+                LETTERBOX_FILL
+            } else {
+                resized.get_pixel(x - pad_x, y - pad_y)[c] as f32 / 255.0
+            }
+        })
+        .into()
+    }
+
+    /// Map a network-space point back onto the source image
+    fn point_to_source(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.pad_x) / self.scale, (y - self.pad_y) / self.scale)
+    }
+
+    /// Map a network-space box back onto the source image, clipped to its bounds
+    fn to_source(&self, x: f32, y: f32, w: f32, h: f32) -> BBox {
+        let x0 = ((x - self.pad_x) / self.scale).clamp(0.0, self.width as f32);
+        let y0 = ((y - self.pad_y) / self.scale).clamp(0.0, self.height as f32);
+        let x1 = ((x + w - self.pad_x) / self.scale).clamp(0.0, self.width as f32);
+        let y1 = ((y + h - self.pad_y) / self.scale).clamp(0.0, self.height as f32);
+        BBox {
+            x: x0 as i32,
+            y: y0 as i32,
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+        }
+    }
+}
+
+/// Greedy NMS: keep the strongest box, drop everything overlapping it, repeat
+fn non_max_suppression(mut candidates: Vec<Detection>) -> Vec<Detection> {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Detection> = Vec::new();
+    for candidate in candidates {
+        if kept.iter().all(|k| k.bbox.iou(&candidate.bbox) <= NMS_IOU) {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
@@ -286,3 +286,25 @@ fn test_production_simulation() {
     assert!(face_count > 0 || stdout.contains("No images found"), 
            "Should extract faces or handle gracefully");
 }
+
+/// Test the doctor self-check used by field support
+#[test]
+fn test_doctor_self_check() {
+    println!("🩺 DOCTOR SELF-CHECK");
+    println!("====================");
+
+    let temp_dir = TempDir::new().unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("doctor")
+        .arg("--output").arg(temp_dir.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "Doctor should pass with the bundled model");
+    assert!(stdout.contains("model load"), "Should report model loading");
+    assert!(stdout.contains("throughput"), "Should report baseline throughput");
+    assert!(stdout.contains("All checks passed"), "Should print an overall verdict");
+}
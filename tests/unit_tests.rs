@@ -4,6 +4,7 @@
 //! using Test-Driven Development methodology.
 
 use std::fs;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use std::process::Command;
 
@@ -213,3 +214,953 @@ fn test_concurrent_safety() {
     
     println!("✅ Concurrent execution safe");
 }
+
+/// Test chunked execution planning
+#[test]
+fn test_chunk_plan_only() {
+    println!("🗺️ CHUNK PLAN TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("corpus");
+    let output_dir = temp_dir.path().join("output");
+    fs::create_dir_all(&input_dir).unwrap();
+
+    for i in 0..5 {
+        fs::write(input_dir.join(format!("img_{}.jpg", i)), encoded(image::ImageFormat::Jpeg)).unwrap();
+    }
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg(&input_dir)
+        .arg("--output").arg(&output_dir)
+        .arg("--chunk-size").arg("2")
+        .arg("--plan-only")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "Plan-only run should succeed");
+    assert!(stdout.contains("Execution plan: 3 chunks"), "Should split 5 images into 3 chunks");
+    assert!(!output_dir.join("plan_checkpoint.json").exists(), "Plan-only should not checkpoint");
+
+    let status = Command::new("./target/release/face_dataset_generator")
+        .arg("--output").arg(&output_dir)
+        .arg("status")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    assert!(!stdout.contains("Chunks completed"), "Plan-only should not record a plan in the run store");
+}
+
+/// Bytes of a small gray image in `format`
+fn encoded(format: image::ImageFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image::DynamicImage::new_luma8(64, 64)
+        .write_to(&mut std::io::Cursor::new(&mut bytes), format)
+        .unwrap();
+    bytes
+}
+
+/// Test that inputs are recognized by content rather than by extension
+#[test]
+fn test_inputs_recognized_by_content() {
+    println!("🔎 DECODER REGISTRY TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("corpus");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("misnamed.jpg"), encoded(image::ImageFormat::Png)).unwrap();
+    fs::write(input_dir.join("notes.jpg"), b"not an image at all").unwrap();
+    fs::write(input_dir.join("scan.dat"), encoded(image::ImageFormat::Jpeg)).unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg(&input_dir)
+        .arg("--output").arg(temp_dir.path().join("output"))
+        .arg("--chunk-size").arg("10")
+        .arg("--plan-only")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Plan-only run should succeed");
+    assert!(
+        stdout.contains("Found 2 images to process"),
+        "PNG bytes named .jpg and JPEG bytes named .dat should be found, the text file skipped: {}",
+        stdout
+    );
+}
+
+/// Test the interactive init wizard with scripted answers
+#[test]
+fn test_init_wizard_writes_config() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    println!("🧙 INIT WIZARD TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let mut child = Command::new("./target/release/face_dataset_generator")
+        .arg("init").arg(&config_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // CCTV corpus, detection goal, 250 faces
+    child.stdin.take().unwrap().write_all(b"2\n2\n250\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "Wizard should complete");
+
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("min_face_size = 24"), "CCTV corpora should search for small faces");
+    assert!(config.contains("target_faces = 250"), "Should record the requested target");
+}
+
+/// Test that malformed alpha background colors are rejected up front
+#[test]
+fn test_alpha_background_validation() {
+    println!("🎨 ALPHA BACKGROUND TESTING");
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--alpha-background").arg("not-a-color")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success(), "Should reject an invalid color");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not a color"), "Should explain the accepted formats");
+}
+
+/// Test that clip lengths must carry a usable duration
+#[test]
+fn test_export_clips_length_validation() {
+    println!("🎬 CLIP LENGTH TESTING");
+
+    for bad in ["0s", "fast", "-2s"] {
+        let output = Command::new("./target/release/face_dataset_generator")
+            .arg("--export-clips").arg(bad)
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success(), "Should reject clip length {}", bad);
+    }
+}
+
+/// Test that translated output keeps its stable message codes
+#[test]
+fn test_localized_output_with_codes() {
+    println!("🌐 LOCALIZED OUTPUT TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("empty");
+    fs::create_dir_all(&input_dir).unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg(&input_dir)
+        .arg("--output").arg(temp_dir.path().join("output"))
+        .arg("--lang").arg("es")
+        .arg("--message-codes")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "Should run with a translated catalog");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[FDG-104] No se encontraron imágenes"), "Should print the Spanish message with its code");
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--lang").arg("xx")
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "Should reject an unknown language");
+}
+
+/// Test that verify catches a crop altered after the manifest was written
+#[test]
+fn test_verify_detects_tampering() {
+    println!("🔏 VERIFY TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let run_dir = temp_dir.path().join("run");
+    fs::create_dir_all(&run_dir).unwrap();
+    fs::write(run_dir.join("face.jpg"), b"original").unwrap();
+    let record = r#"{"crop":"face.jpg","source":"a.jpg","detection_id":1,"bbox":{"x":0,"y":0,"width":1,"height":1},"score":3.0,"sha256":"0682c5f2076f099c34cfdd15a9e063849ed437a49677e6fcc5b4198c76575be5"}"#;
+    fs::write(run_dir.join("manifest.jsonl"), format!("{}\n", record)).unwrap();
+
+    let verify = |strict: bool| {
+        let mut command = Command::new("./target/release/face_dataset_generator");
+        command.arg("verify").arg("--manifest").arg(run_dir.join("manifest.jsonl"));
+        if strict {
+            command.arg("--strict");
+        }
+        command.output().unwrap()
+    };
+
+    assert!(verify(false).status.success(), "Intact crops should verify");
+    assert!(!verify(true).status.success(), "Strict mode should require a seal");
+
+    fs::write(run_dir.join("face.jpg"), b"altered").unwrap();
+    let output = verify(false);
+    assert!(!output.status.success(), "An altered crop should fail verification");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("hash mismatch"));
+}
+
+/// Test that the parallel pipeline produces a normal run and rejects bad job counts
+#[test]
+fn test_parallel_jobs() {
+    println!("🧵 PARALLEL JOBS TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    for jobs in ["2", "auto"] {
+        let output = Command::new("./target/release/face_dataset_generator")
+            .arg("--input").arg("images")
+            .arg("--output").arg(temp_dir.path().join(jobs))
+            .arg("--target-faces").arg("5")
+            .arg("--jobs").arg(jobs)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "Should run with --jobs {}", jobs);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("complete"));
+    }
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--jobs").arg("0")
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "Should reject zero workers");
+}
+
+/// Test that a corrupted model.bin is refused before detection starts
+#[test]
+fn test_model_checksum_mismatch() {
+    println!("🔐 MODEL CHECKSUM TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let model = temp_dir.path().join("model.bin");
+    fs::write(&model, b"truncated download").unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path().join("output"))
+        .arg("--model").arg(&model)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success(), "Should refuse a corrupted model");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Checksum mismatch"), "Should name the checksum failure");
+}
+
+/// Test that --resume skips checkpointed images and continues the face count
+#[test]
+fn test_resume_from_checkpoint() {
+    println!("⏯️  RESUME TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let run = |target: &str, resume: bool| {
+        let mut command = Command::new("./target/release/face_dataset_generator");
+        command
+            .arg("--input").arg("images")
+            .arg("--output").arg(temp_dir.path())
+            .arg("--target-faces").arg(target)
+            .arg("--checkpoint-every").arg("1");
+        if resume {
+            command.arg("--resume");
+        }
+        command.output().unwrap()
+    };
+
+    let first = run("1", false);
+    assert!(first.status.success(), "First run should succeed");
+    assert!(temp_dir.path().join("run.redb").exists(), "Should write the run store");
+
+    let resumed = run("1000", true);
+    assert!(resumed.status.success(), "Resumed run should succeed");
+    let stdout = String::from_utf8_lossy(&resumed.stdout);
+    assert!(stdout.contains("Resuming"), "Should report the checkpoint it resumed from");
+}
+
+/// Test that regress finds no lost detections when compared against its own run
+#[test]
+fn test_regress_against_same_settings() {
+    println!("🔬 REGRESSION TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_dir = temp_dir.path().join("output");
+    let run = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(&output_dir)
+        .arg("--target-faces").arg("5")
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "Baseline run should succeed");
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("regress")
+        .arg("--baseline").arg(output_dir.join("manifest.jsonl"))
+        .arg("--gallery").arg(temp_dir.path().join("gallery"))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "Regress should succeed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Detections lost: 0"), "Same settings should lose no detections");
+    assert!(temp_dir.path().join("gallery/index.html").exists(), "Should write the gallery index");
+}
+
+/// Test that --annotations yolo writes normalized label files and a dataset.yaml
+#[test]
+fn test_yolo_label_export() {
+    println!("🏷️  YOLO LABEL TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("5")
+        .arg("--annotations").arg("yolo")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --annotations yolo");
+
+    let yolo = temp_dir.path().join("yolo");
+    assert!(yolo.join("dataset.yaml").exists(), "Should write dataset.yaml");
+    for entry in fs::read_dir(yolo.join("labels")).unwrap() {
+        let labels = fs::read_to_string(entry.unwrap().path()).unwrap();
+        for line in labels.lines() {
+            let values: Vec<f64> = line.split_whitespace().skip(1).map(|v| v.parse().unwrap()).collect();
+            assert_eq!(values.len(), 4, "Label lines should be class cx cy w h");
+            assert!(values.iter().all(|v| (0.0..=1.0).contains(v)), "Coordinates should be normalized");
+        }
+    }
+}
+
+/// Test that --align writes 112x112 ArcFace crops
+#[test]
+fn test_align_to_arcface_template() {
+    println!("📐 ALIGNMENT TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("3")
+        .arg("--align")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --align");
+
+    let crops: Vec<_> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.to_string_lossy().ends_with("_arcface.jpg"))
+        .collect();
+    assert!(!crops.is_empty(), "Should save arcface crops");
+    for crop in crops {
+        let (width, height) = image::image_dimensions(&crop).unwrap();
+        assert_eq!((width, height), (112, 112), "Aligned crops should match the template size");
+    }
+}
+
+/// Test that --output-size gives every crop the same resolution
+#[test]
+fn test_fixed_output_size() {
+    println!("📏 OUTPUT SIZE TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("3")
+        .arg("--output-size").arg("96x120")
+        .arg("--resize-filter").arg("bilinear")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --output-size");
+
+    for entry in fs::read_dir(temp_dir.path()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "jpg") {
+            assert_eq!(image::image_dimensions(&path).unwrap(), (96, 120), "Crops should be 96x120");
+        }
+    }
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--output-size").arg("96")
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "Should reject a size without a height");
+}
+
+/// Test that every padded crop records where the face sits inside it
+#[test]
+fn test_face_box_in_manifest() {
+    println!("🔲 FACE BOX TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("3")
+        .arg("--output-size").arg("96x96")
+        .arg("--manifest-csv")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --output-size");
+
+    let manifest = fs::read_to_string(temp_dir.path().join("manifest.jsonl")).unwrap();
+    assert!(!manifest.is_empty(), "Should record crops");
+    for line in manifest.lines() {
+        assert!(line.contains("\"face_box\":{\"cx\":"), "Every standard crop should have a face box");
+    }
+
+    let csv = fs::read_to_string(temp_dir.path().join("manifest.csv")).unwrap();
+    assert!(csv.lines().next().unwrap().contains("face_cx,face_cy,face_width,face_height"), "CSV should mirror the face box");
+}
+
+/// Test that export splits tagged crops into one archive per license
+#[test]
+fn test_license_export() {
+    println!("📦 LICENSE EXPORT TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let run_dir = temp_dir.path().join("run");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(&run_dir)
+        .arg("--target-faces").arg("4")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Extraction should succeed");
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("tag").arg("add").arg("license=cc-by")
+        .arg("--where").arg("detection_id<=2")
+        .arg("--manifest").arg(run_dir.join("manifest.jsonl"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Tagging should succeed");
+
+    let release = temp_dir.path().join("release");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("export")
+        .arg("--from").arg(&run_dir)
+        .arg("--to").arg(&release)
+        .arg("--where").arg("license in ('cc-by', 'internal')")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Export should succeed");
+    assert!(release.join("cc-by.tar.gz").exists(), "Should write the cc-by archive");
+    assert!(!release.join("unspecified.tar.gz").exists(), "Untagged crops should be filtered out");
+}
+
+/// Test that --heatmap writes the heatmap image and the per-third breakdown
+#[test]
+fn test_face_heatmap() {
+    println!("🗺️ HEATMAP TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("3")
+        .arg("--heatmap")
+        .arg("--message-codes")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --heatmap");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[FDG-500]"), "Report should include the face positions");
+    assert_eq!(stdout.matches("[FDG-501]").count(), 3, "Breakdown should have one line per third");
+    assert_eq!(image::image_dimensions(temp_dir.path().join("heatmap.png")).unwrap(), (256, 256));
+}
+
+/// Test that --dedup writes duplicates.json with the kept face first in each cluster
+#[test]
+fn test_duplicate_clusters() {
+    println!("👯 DUPLICATE CLUSTERS TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("5")
+        .arg("--dedup")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --dedup");
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp_dir.path().join("duplicates.json")).unwrap()).unwrap();
+    assert_eq!(report["max_distance"], 6);
+    let mut dropped = 0;
+    for cluster in report["clusters"].as_array().unwrap() {
+        let members = cluster["members"].as_array().unwrap();
+        assert_eq!(members[0]["kept"], true, "Representative should come first");
+        assert!(!members[0]["crops"].as_array().unwrap().is_empty());
+        assert!(members[1..].iter().all(|m| m["kept"] == false && m["distance"].as_u64().unwrap() <= 6));
+        dropped += members.len() - 1;
+    }
+    assert_eq!(report["dropped"], dropped);
+}
+
+/// Test that detect-one lists every check for a face a run kept and saves the annotated image
+#[test]
+fn test_detect_one_verdicts() {
+    println!("🔎 DETECT-ONE TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let run_dir = temp_dir.path().join("run");
+    let run = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(&run_dir)
+        .arg("--target-faces").arg("1")
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "Run should succeed");
+    let manifest = fs::read_to_string(run_dir.join("manifest.jsonl")).unwrap();
+    let row: serde_json::Value = serde_json::from_str(manifest.lines().next().unwrap()).unwrap();
+    let source = row["source"].as_str().unwrap();
+
+    let annotated = temp_dir.path().join("annotated.png");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("detect-one").arg(source)
+        .arg("--save").arg(&annotated)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "detect-one should succeed");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("➡️  kept"), "The face the run cropped should be kept");
+    assert!(stdout.matches("✅").count() >= 4, "Every check should be listed");
+    assert!(annotated.exists(), "Annotated image should be saved");
+}
+
+/// Test that routing every image to the small-image detector still extracts faces
+#[test]
+fn test_small_image_routing() {
+    println!("🔀 DETECTOR ROUTING TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("3")
+        .arg("--small-backend").arg("rustface")
+        .arg("--small-image-side").arg("100000")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should run with --small-backend");
+
+    let manifest = fs::read_to_string(temp_dir.path().join("manifest.jsonl")).unwrap();
+    assert_eq!(manifest.lines().count(), 3, "Routed detector should reach the target");
+}
+
+/// Test that --dump-config prints the merged settings and loads back as a config
+#[test]
+fn test_dump_config_round_trip() {
+    println!("⚙️  DUMP CONFIG TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let config = temp_dir.path().join("pipeline.toml");
+    fs::write(&config, "max_face_ratio = 0.3\npadding = 0.2\n").unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--config").arg(&config)
+        .arg("--padding").arg("0.25")
+        .arg("--dump-config")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Should dump the configuration");
+
+    let dumped = String::from_utf8_lossy(&output.stdout);
+    assert!(dumped.contains("max_face_ratio = 0.3"), "Config file values should be dumped");
+    assert!(dumped.contains("padding = 0.25"), "Command-line flags should win over the config file");
+
+    let effective = temp_dir.path().join("effective.toml");
+    fs::write(&effective, dumped.as_bytes()).unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--config").arg(&effective)
+        .arg("--dump-config")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Dumped settings should load as a config");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("padding = 0.25"));
+}
+
+/// Test that status summarizes a run from its run store
+#[test]
+fn test_status_from_run_store() {
+    println!("📋 RUN STATUS TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let run = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("2")
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "Run should succeed");
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--output").arg(temp_dir.path())
+        .arg("status")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Status should read the run store");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("started_at_ms"), "Should show run metadata");
+    assert!(stdout.contains("Faces extracted: 2"), "Should show the face counter");
+}
+
+/// Test that detect writes face boxes without cropping, with run flags after the subcommand
+#[test]
+fn test_detect_subcommand_writes_boxes_only() {
+    println!("🔎 DETECT SUBCOMMAND TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("detect")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Detect should succeed");
+
+    let detections = fs::read_to_string(temp_dir.path().join("detections.jsonl")).unwrap();
+    assert!(detections.lines().count() > 0, "Should write one line per image");
+    assert!(detections.contains("\"bbox\""), "Should record face boxes");
+    assert!(!temp_dir.path().join("manifest.jsonl").exists(), "Should not crop anything");
+}
+
+/// Test that --save-rejected keeps filtered-out faces with their rejection reason
+#[test]
+fn test_save_rejected_records_reasons() {
+    println!("🚫 REJECTED FACES TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let rejected = temp_dir.path().join("rejected");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path().join("faces"))
+        .arg("--max-aspect").arg("0.9")
+        .arg("--save-rejected").arg(&rejected)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Run should succeed");
+
+    let manifest = fs::read_to_string(rejected.join("manifest.jsonl")).unwrap();
+    assert!(manifest.lines().count() > 0, "Square faces should fail --max-aspect 0.9");
+    assert!(manifest.contains("\"rejected\":\"aspect\""), "Manifest should name the failed filter");
+    let named = fs::read_dir(&rejected)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .any(|e| e.file_name().to_string_lossy().ends_with("_aspect.jpg"));
+    assert!(named, "Filenames should carry the rejection reason");
+}
+
+/// Test that images inside a tar archive are read without extracting it
+#[test]
+fn test_tar_archive_input() {
+    println!("📦 ARCHIVE INPUT TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let archive = temp_dir.path().join("images.tar");
+    let mut builder = tar::Builder::new(fs::File::create(&archive).unwrap());
+    builder.append_dir_all("photos", "images").unwrap();
+    builder.into_inner().unwrap();
+
+    let faces = temp_dir.path().join("faces");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg(&archive)
+        .arg("--output").arg(&faces)
+        .arg("--target-faces").arg("3")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Run on an archive should succeed");
+
+    let manifest = fs::read_to_string(faces.join("manifest.jsonl")).unwrap();
+    assert!(manifest.lines().count() > 0, "Faces should be found inside the archive");
+    assert!(manifest.contains("images.tar/photos/"), "Sources should name the archive member");
+    assert!(!temp_dir.path().join("photos").exists(), "Nothing should be extracted");
+}
+
+/// Test that --stdin reads paths from stdin and prints one JSON result per path
+#[test]
+fn test_stdin_paths_json_results() {
+    println!("🔌 STDIN PIPELINE TESTING");
+
+    let images: Vec<String> = fs::read_dir("images")
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().display().to_string())
+        .filter(|p| p.ends_with(".jpg") || p.ends_with(".png"))
+        .take(3)
+        .collect();
+    assert!(!images.is_empty(), "Test images should exist");
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut child = Command::new("./target/release/face_dataset_generator")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--stdin")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(&mut child.stdin.take().unwrap(), format!("{}\n", images.join("\n")).as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "Run should succeed");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), images.len(), "One result line per input");
+    for line in stdout.lines() {
+        let result: serde_json::Value = serde_json::from_str(line).expect("Each line should be JSON");
+        assert!(result["faces"].is_u64(), "Results should carry a face count");
+        assert!(result["crops"].is_array(), "Results should list crop names");
+    }
+}
+
+/// Test that verify-output removes truncated crops and unfinished writes but keeps complete ones
+#[test]
+fn test_verify_output_removes_truncated_crops() {
+    println!("🧹 VERIFY OUTPUT TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let run = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path())
+        .arg("--target-faces").arg("2")
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "Run should succeed");
+
+    let crops: Vec<_> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "jpg"))
+        .collect();
+    assert!(!crops.is_empty(), "Run should write crops");
+    assert!(!crops.iter().any(|p| p.to_string_lossy().ends_with(".tmp")), "No temporary files should remain");
+
+    let truncated = temp_dir.path().join("truncated.jpg");
+    fs::write(&truncated, &fs::read(&crops[0]).unwrap()[..100]).unwrap();
+    let partial = temp_dir.path().join("face_999999.jpg.tmp");
+    fs::write(&partial, b"partial").unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--output").arg(temp_dir.path())
+        .arg("verify-output")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "verify-output should succeed");
+    assert!(!truncated.exists(), "Truncated crop should be removed");
+    assert!(!partial.exists(), "Leftover .tmp file should be removed");
+    assert!(crops.iter().all(|p| p.exists()), "Complete crops should be kept");
+}
+
+/// A JPEG from the test image folder
+fn sample_image() -> PathBuf {
+    fs::read_dir("images")
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.extension().is_some_and(|ext| ext == "jpg"))
+        .expect("Test images should exist")
+}
+
+/// Test that --name-by-hash saves identical faces from copied images only once
+#[test]
+fn test_name_by_hash_skips_exact_duplicates() {
+    println!("#️⃣ CONTENT HASH NAMES TESTING");
+
+    let image = sample_image();
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("input");
+    fs::create_dir_all(&input).unwrap();
+    fs::copy(&image, input.join("a.jpg")).unwrap();
+    fs::copy(&image, input.join("b.jpg")).unwrap();
+
+    let faces = temp_dir.path().join("faces");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&faces)
+        .arg("--keep-all-burst")
+        .arg("--name-by-hash")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Run should succeed");
+
+    let manifest = fs::read_to_string(faces.join("manifest.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = manifest.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert!(!records.is_empty(), "Faces should be found");
+    assert!(records.iter().all(|r| r["source"] == records[0]["source"]), "The copy's faces should be skipped as duplicates");
+    let crops = fs::read_dir(&faces)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "jpg"))
+        .count();
+    assert_eq!(crops, records.len(), "Each crop should be written once");
+}
+
+/// Test that an ensemble of two identical backends requiring agreement keeps the same faces as one
+#[test]
+fn test_ensemble_agreement_matches_single_backend() {
+    println!("🤝 ENSEMBLE DETECTION TESTING");
+
+    let image = sample_image();
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("input");
+    fs::create_dir_all(&input).unwrap();
+    fs::copy(&image, input.join("photo.jpg")).unwrap();
+
+    let run = |name: &str, extra: &[&str]| {
+        let faces = temp_dir.path().join(name);
+        let output = Command::new("./target/release/face_dataset_generator")
+            .arg("--input").arg(&input)
+            .arg("--output").arg(&faces)
+            .arg("--keep-all-burst")
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "Run should succeed");
+        let manifest = fs::read_to_string(faces.join("manifest.jsonl")).unwrap_or_default();
+        (manifest.lines().count(), String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let (single, _) = run("single", &[]);
+    let (ensemble, stdout) = run("ensemble", &["--ensemble", "rustface", "--ensemble-votes", "2"]);
+    assert_eq!(ensemble, single, "Two copies of one detector should agree on every face");
+    assert!(stdout.contains("Ensemble contributions"), "Summary should report per-backend contributions");
+}
+
+/// Test that contact sheets index every saved face
+#[test]
+fn test_contact_sheet_indexes_every_face() {
+    println!("🗂️ CONTACT SHEET TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let faces = temp_dir.path().join("faces");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input").arg("images")
+        .arg("--output").arg(&faces)
+        .arg("--keep-all-burst")
+        .arg("--contact-sheet").arg("2x2")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Run should succeed");
+
+    let manifest = fs::read_to_string(faces.join("manifest.jsonl")).unwrap_or_default();
+    let index = fs::read_to_string(faces.join("contact_sheets").join("index.csv")).unwrap();
+    assert_eq!(index.lines().count() - 1, manifest.lines().count(), "Every saved face should be on a sheet");
+    let sheets = fs::read_dir(faces.join("contact_sheets"))
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "png"))
+        .count();
+    assert_eq!(sheets, manifest.lines().count().div_ceil(4), "Sheets should hold four faces each");
+}
+
+/// Test the exit status of runs with failed images and of strict runs short of the target
+#[test]
+fn test_exit_status_for_failures_and_shortfall() {
+    println!("🚦 EXIT STATUS TESTING");
+
+    let image = sample_image();
+    let temp_dir = TempDir::new().unwrap();
+    let broken = temp_dir.path().join("broken");
+    fs::create_dir_all(&broken).unwrap();
+    let bytes = fs::read(&image).unwrap();
+    fs::write(broken.join("cut.jpg"), &bytes[..bytes.len() / 8]).unwrap();
+
+    let run = |input: &Path, name: &str, extra: &[&str]| {
+        Command::new("./target/release/face_dataset_generator")
+            .arg("--input").arg(input)
+            .arg("--output").arg(temp_dir.path().join(name))
+            .arg("--quiet")
+            .args(extra)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(run(&broken, "broken", &[]), Some(2), "A run where every image failed should exit with 2");
+    assert_eq!(run(Path::new("images"), "lenient", &["--target-faces", "100000"]), Some(0), "A shortfall is fine without --strict");
+    assert_eq!(run(Path::new("images"), "strict", &["--target-faces", "100000", "--strict"]), Some(3), "A strict run short of the target should exit with 3");
+}
+
+/// Test that --strict is accepted after the extract subcommand
+#[test]
+fn test_strict_after_extract_subcommand() {
+    println!("🚦 EXTRACT --STRICT TESTING");
+
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("extract")
+        .arg("--input").arg("images")
+        .arg("--output").arg(temp_dir.path().join("faces"))
+        .arg("--target-faces").arg("100000")
+        .arg("--strict")
+        .arg("--quiet")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3), "extract --strict short of the target should exit with 3: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+/// Test that a CSV input list labels crops from its label column
+#[test]
+fn test_csv_input_list_labels() {
+    println!("🏷️ CSV INPUT LIST TESTING");
+
+    let image = sample_image();
+    let temp_dir = TempDir::new().unwrap();
+    let list = temp_dir.path().join("list.csv");
+    let absolute = fs::canonicalize(&image).unwrap();
+    fs::write(&list, format!("path,label\n{},alice\n", absolute.display())).unwrap();
+
+    let faces = temp_dir.path().join("faces");
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input-list").arg(&list)
+        .arg("--output").arg(&faces)
+        .arg("--keep-all-burst")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Run should succeed");
+
+    let manifest = fs::read_to_string(faces.join("manifest.jsonl")).expect("Run should write a manifest");
+    assert!(manifest.lines().next().is_some(), "Run should save at least one crop");
+    for line in manifest.lines() {
+        assert!(line.contains("\"label\":\"alice\""), "Crops should carry the CSV label: {}", line);
+    }
+}
+
+/// Test that --sample reports the images actually drawn when weight 0 leaves too few rows
+#[test]
+fn test_weighted_sample_reports_drawn_count() {
+    println!("🎲 WEIGHTED SAMPLE TESTING");
+
+    let image = sample_image();
+    let temp_dir = TempDir::new().unwrap();
+    let mut list = String::from("path,weight\n");
+    for (name, weight) in [("a.jpg", 1), ("b.jpg", 0), ("c.jpg", 0)] {
+        fs::copy(&image, temp_dir.path().join(name)).unwrap();
+        list.push_str(&format!("{},{}\n", name, weight));
+    }
+    fs::write(temp_dir.path().join("list.csv"), list).unwrap();
+
+    let output = Command::new("./target/release/face_dataset_generator")
+        .arg("--input-list").arg(temp_dir.path().join("list.csv"))
+        .arg("--output").arg(temp_dir.path().join("faces"))
+        .arg("--keep-all-burst")
+        .arg("--sample").arg("2")
+        .arg("--seed").arg("7")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Run should succeed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Sampled 1 of 3 images"), "Only the row with a weight can be drawn: {}", stdout);
+}